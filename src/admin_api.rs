@@ -0,0 +1,458 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+JSON API администратора на сервере вебхука. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+// Все запросы начинаются с /api/ и передают токен в заголовке Authorization: Bearer.
+// Тела запросов и ответы в JSON, ошибки в виде {"error": "описание"}
+
+use teloxide::prelude::*;
+use std::{collections::HashMap, sync::Arc};
+use chrono::NaiveTime;
+use serde_json::{json, Value};
+use warp::{Filter, http::StatusCode, reply, path::Tail};
+
+use crate::database as db;
+use crate::settings;
+use crate::config;
+use crate::outbox;
+use crate::notify;
+use crate::basket;
+use crate::language::{self as lang, Res::*};
+
+// Наибольший размер тела запроса
+const BODY_LIMIT: usize = 64 * 1024;
+
+// Наибольшее количество записей в списке
+const MAX_LIMIT: i64 = 1000;
+
+type Response = reply::WithStatus<reply::Json>;
+
+// Успешный ответ или ответ с ошибкой, второй возвращается оператором ?
+type Result = std::result::Result<Response, Response>;
+
+fn respond(code: StatusCode, value: Value) -> Response {
+   reply::with_status(reply::json(&value), code)
+}
+
+fn error(code: StatusCode, message: &str) -> Response {
+   respond(code, json!({"error": message}))
+}
+
+fn db_error() -> Response {
+   error(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+}
+
+// Отказ из-за неверного токена, превращается в ответ 401 в самом API
+#[derive(Debug)]
+struct Unauthorized;
+
+impl warp::reject::Reject for Unauthorized {}
+
+// Маршруты API, без токена в настройках API отключено и отвечает 404.
+// Токен проверяется до чтения тела, а тело ограничено по размеру
+pub fn routes(bot: Arc<Bot>, token: Option<String>) -> impl Filter<Extract = (Response,), Error = warp::Rejection> + Clone {
+   warp::path("api")
+      .and(warp::method())
+      .and(warp::path::tail())
+      .and(warp::header::optional::<String>("authorization"))
+      .and_then(move |method: warp::http::Method, tail: Tail, auth: Option<String>| {
+         let token = token.clone();
+         async move {
+            let token = token.ok_or_else(warp::reject::not_found)?;
+
+            // Запросы без правильного токена отклоняем
+            if auth.as_deref() != Some(format!("Bearer {}", token).as_str()) {
+               log::warn!("Admin API request with wrong token: {} /api/{}", method, tail.as_str());
+               return Err(warp::reject::custom(Unauthorized));
+            }
+            Ok((method, tail))
+         }
+      })
+      .untuple_one()
+      .and(warp::query::<HashMap<String, String>>())
+      .and(body())
+      .and_then(move |method: warp::http::Method, tail: Tail, query: HashMap<String, String>, body: warp::hyper::body::Bytes| {
+         let bot = bot.clone();
+         async move {
+            let res = route(&bot, method.as_str(), tail.as_str(), &query, &body).await;
+            Ok::<_, warp::Rejection>(res.unwrap_or_else(|e| e))
+         }
+      })
+      .recover(|rejection: warp::Rejection| async move {
+         if rejection.find::<Unauthorized>().is_some() {
+            Ok(error(StatusCode::UNAUTHORIZED, "wrong or missing token"))
+         } else {
+            Err(rejection)
+         }
+      })
+      .unify()
+}
+
+// Тело не больше BODY_LIMIT. Запрос без Content-Length и Transfer-Encoding
+// (обычный GET) тела не имеет, а с Transfer-Encoding без длины отклоняется
+fn body() -> impl Filter<Extract = (warp::hyper::body::Bytes,), Error = warp::Rejection> + Clone {
+   let limited = warp::body::content_length_limit(BODY_LIMIT as u64)
+      .and(warp::body::bytes());
+   let empty = warp::header::headers_cloned()
+      .and_then(|headers: warp::http::HeaderMap| async move {
+         if headers.contains_key("content-length") || headers.contains_key("transfer-encoding") {
+            Err(warp::reject::not_found())
+         } else {
+            Ok(warp::hyper::body::Bytes::new())
+         }
+      });
+   limited.or(empty).unify()
+}
+
+// Разбор пути и вызов обработчика
+async fn route(bot: &Arc<Bot>, method: &str, path: &str, query: &HashMap<String, String>, body: &[u8]) -> Result {
+   // Тело нужно только для изменений, пустое равносильно пустому объекту
+   let body = if body.is_empty() {json!({})} else {
+      match serde_json::from_slice::<Value>(body) {
+         Ok(value) if value.is_object() => value,
+         _ => return Err(error(StatusCode::BAD_REQUEST, "request body must be a JSON object")),
+      }
+   };
+
+   let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+   match (method, parts.as_slice()) {
+      ("GET", ["restaurants"]) => rest_list().await,
+      ("GET", ["restaurants", rest_num]) => restaurant(id(rest_num)?).await,
+      ("PATCH", ["restaurants", rest_num]) => rest_edit(bot, id(rest_num)?, &body).await,
+      ("PATCH", ["restaurants", rest_num, "groups", group_num]) => group_edit(bot, id(rest_num)?, id(group_num)?, &body).await,
+      ("PATCH", ["restaurants", rest_num, "groups", group_num, "dishes", dish_num]) => dish_edit(bot, id(rest_num)?, id(group_num)?, id(dish_num)?, &body).await,
+      ("POST", ["caterers", user_id]) => caterer_register(id(user_id)?).await,
+      ("DELETE", ["caterers", user_id]) => caterer_hold(id(user_id)?).await,
+      ("GET", ["users"]) => user_list(query_num(query, "limit", 100, MAX_LIMIT)?, query_num(query, "offset", 0, i64::MAX)?).await,
+      ("GET", ["tickets"]) => ticket_list(query).await,
+      ("GET", ["tickets", ticket_id]) => ticket_info(id(ticket_id)?).await,
+      ("POST", ["tickets", ticket_id, "close"]) => ticket_close(bot, id(ticket_id)?).await,
+      _ => Err(error(StatusCode::NOT_FOUND, "unknown method or path")),
+   }
+}
+
+// ============================================================================
+// [Arguments]
+// ============================================================================
+fn id(s: &str) -> std::result::Result<i32, Response> {
+   s.parse().map_err(|_| error(StatusCode::BAD_REQUEST, &format!("'{}' is not an integer identifier", s)))
+}
+
+fn query_num(query: &HashMap<String, String>, key: &str, default: i64, max: i64) -> std::result::Result<i64, Response> {
+   match query.get(key) {
+      None => Ok(default),
+      Some(s) => match s.parse::<i64>() {
+         Ok(n) if (0..=max).contains(&n) => Ok(n),
+         _ => Err(error(StatusCode::BAD_REQUEST, &format!("{} must be an integer from 0 to {}", key, max))),
+      }
+   }
+}
+
+fn field_str(body: &Value, key: &str) -> std::result::Result<Option<String>, Response> {
+   match body.get(key) {
+      None => Ok(None),
+      Some(Value::String(s)) if !s.trim().is_empty() => Ok(Some(s.trim().to_string())),
+      _ => Err(error(StatusCode::BAD_REQUEST, &format!("{} must be a non-empty string", key))),
+   }
+}
+
+fn field_bool(body: &Value, key: &str) -> std::result::Result<Option<bool>, Response> {
+   match body.get(key) {
+      None => Ok(None),
+      Some(Value::Bool(b)) => Ok(Some(*b)),
+      _ => Err(error(StatusCode::BAD_REQUEST, &format!("{} must be true or false", key))),
+   }
+}
+
+fn field_int(body: &Value, key: &str, min: i32, max: i32) -> std::result::Result<Option<i32>, Response> {
+   match body.get(key) {
+      None => Ok(None),
+      Some(v) => match v.as_i64() {
+         Some(n) if n >= i64::from(min) && n <= i64::from(max) => Ok(Some(n as i32)),
+         _ => Err(error(StatusCode::BAD_REQUEST, &format!("{} must be an integer from {} to {}", key, min, max))),
+      }
+   }
+}
+
+fn field_time(body: &Value, key: &str) -> std::result::Result<Option<NaiveTime>, Response> {
+   match field_str(body, key)? {
+      None => Ok(None),
+      Some(s) => NaiveTime::parse_from_str(&s, "%H:%M").map(Some)
+         .map_err(|_| error(StatusCode::BAD_REQUEST, &format!("{} must be a time like 09:30", key))),
+   }
+}
+
+// Часовой пояс IANA или смещение в часах, null возвращает пояс по-умолчанию
+fn field_time_zone(body: &Value, key: &str) -> std::result::Result<Option<Option<String>>, Response> {
+   match body.get(key) {
+      None => Ok(None),
+      Some(Value::Null) => Ok(Some(None)),
+      Some(Value::String(s)) => match config::parse_time_zone(s) {
+         Some(tz) => Ok(Some(Some(tz.name().to_string()))),
+         None => Err(error(StatusCode::BAD_REQUEST, &format!("{} must be a time zone like Asia/Ho_Chi_Minh", key))),
+      }
+      _ => Err(error(StatusCode::BAD_REQUEST, &format!("{} must be a time zone or null", key))),
+   }
+}
+
+// Результат изменения в БД
+fn check(res: bool) -> std::result::Result<(), Response> {
+   if res {Ok(())} else {Err(db_error())}
+}
+
+// ============================================================================
+// [JSON]
+// ============================================================================
+fn time_json(time: NaiveTime) -> String {
+   time.format("%H:%M").to_string()
+}
+
+fn rest_json(rest: &db::Restaurant) -> Value {
+   json!({
+      "num": rest.num,
+      "user_id": rest.user_id,
+      "title": rest.title,
+      "info": rest.info,
+      "active": rest.active,
+      "enabled": rest.enabled,
+      "opening_time": time_json(rest.opening_time),
+      "closing_time": time_json(rest.closing_time),
+      "payment": rest.payment_token.is_some(),
+      "location": rest.location.map(|(latitude, longitude)| json!({"latitude": latitude, "longitude": longitude})),
+      "time_zone": rest.time_zone,
+   })
+}
+
+fn group_json(group: &db::Group) -> Value {
+   json!({
+      "num": group.num,
+      "title": group.title,
+      "info": group.info,
+      "active": group.active,
+      "cat_id": group.cat_id,
+      "opening_time": time_json(group.opening_time),
+      "closing_time": time_json(group.closing_time),
+   })
+}
+
+fn dish_json(dish: &db::Dish) -> Value {
+   json!({
+      "num": dish.num,
+      "group_num": dish.group_num,
+      "title": dish.title,
+      "info": dish.info,
+      "active": dish.active,
+      "price": dish.price,
+   })
+}
+
+fn ticket_json(ticket: &db::Ticket) -> Value {
+   json!({
+      "ticket_id": ticket.ticket_id,
+      "eater_id": ticket.eater_id,
+      "caterer_id": ticket.caterer_id,
+      "stage": ticket.stage,
+      "closed": ticket.stage >= 5,
+      "order_time": ticket.order_time.map(|t| t.format("%Y-%m-%dT%H:%M").to_string()),
+      "payment_required": ticket.payment_required,
+      "paid": ticket.paid,
+      "promo_code": ticket.promo_code,
+      "discount": ticket.discount,
+   })
+}
+
+// ============================================================================
+// [Restaurants]
+// ============================================================================
+async fn rest_list() -> Result {
+   let list = db::rest_list(db::RestListBy::All).await.unwrap_or_default();
+   Ok(respond(StatusCode::OK, Value::Array(list.iter().map(rest_json).collect())))
+}
+
+// Ресторан вместе с группами и блюдами
+async fn restaurant(rest_num: i32) -> Result {
+   let rest = db::restaurant(db::RestBy::Num(rest_num)).await
+      .ok_or_else(|| error(StatusCode::NOT_FOUND, "restaurant not found"))?;
+
+   let mut groups = Vec::new();
+   for group in db::group_list(db::GroupListBy::All(rest_num)).await.unwrap_or_default() {
+      let dishes = db::dish_list(db::DishesBy::All(rest_num, group.num)).await.unwrap_or_default();
+      let mut value = group_json(&group);
+      value["dishes"] = Value::Array(dishes.iter().map(dish_json).collect());
+      groups.push(value);
+   }
+
+   let mut value = rest_json(&rest);
+   value["groups"] = Value::Array(groups);
+   Ok(respond(StatusCode::OK, value))
+}
+
+async fn rest_edit(bot: &Arc<Bot>, rest_num: i32, body: &Value) -> Result {
+   // Сначала проверяем все поля, чтобы не применить изменения частично
+   let title = field_str(body, "title")?;
+   let info = field_str(body, "info")?;
+   let active = field_bool(body, "active")?;
+   let time_zone = field_time_zone(body, "time_zone")?;
+   db::restaurant(db::RestBy::Num(rest_num)).await
+      .ok_or_else(|| error(StatusCode::NOT_FOUND, "restaurant not found"))?;
+
+   // Все поля меняются одним запросом, пояс в нём ещё проверяется по pg_timezone_names
+   check(db::rest_edit(rest_num, title, info, active, time_zone).await)?;
+
+   // Заведение могло открыться для ждущих уведомления
+   tokio::spawn(notify::fire(Arc::clone(bot)));
+
+   settings::log(&format!("API: изменён ресторан {}", rest_num)).await;
+   restaurant(rest_num).await
+}
+
+async fn group_edit(bot: &Arc<Bot>, rest_num: i32, group_num: i32, body: &Value) -> Result {
+   let title = field_str(body, "title")?;
+   let info = field_str(body, "info")?;
+   let active = field_bool(body, "active")?;
+   let cat_id = field_int(body, "cat_id", 1, 4)?;
+   let opening_time = field_time(body, "opening_time")?;
+   let closing_time = field_time(body, "closing_time")?;
+   let group = db::group(rest_num, group_num).await
+      .ok_or_else(|| error(StatusCode::NOT_FOUND, "group not found"))?;
+
+   if let Some(title) = title {check(db::rest_group_edit_title(rest_num, group_num, title).await)?;}
+   if let Some(info) = info {check(db::rest_group_edit_info(rest_num, group_num, info).await)?;}
+   if active == Some(!group.active) {check(db::rest_group_toggle(rest_num, group_num).await)?;}
+   if let Some(cat_id) = cat_id {check(db::rest_group_edit_category(rest_num, group_num, cat_id).await)?;}
+   if opening_time.is_some() || closing_time.is_some() {
+      let opening_time = opening_time.unwrap_or(group.opening_time);
+      let closing_time = closing_time.unwrap_or(group.closing_time);
+      check(db::rest_group_edit_time(rest_num, group_num, opening_time, closing_time).await)?;
+   }
+   tokio::spawn(notify::fire(Arc::clone(bot)));
+
+   settings::log(&format!("API: изменена группа {} ресторана {}", group_num, rest_num)).await;
+   let group = db::group(rest_num, group_num).await.ok_or_else(db_error)?;
+   Ok(respond(StatusCode::OK, group_json(&group)))
+}
+
+async fn dish_edit(bot: &Arc<Bot>, rest_num: i32, group_num: i32, dish_num: i32, body: &Value) -> Result {
+   let title = field_str(body, "title")?;
+   let info = field_str(body, "info")?;
+   let active = field_bool(body, "active")?;
+   let price = field_int(body, "price", 0, i32::MAX)?;
+   let dish = db::dish(db::DishBy::All(rest_num, group_num, dish_num)).await
+      .ok_or_else(|| error(StatusCode::NOT_FOUND, "dish not found"))?;
+
+   if let Some(title) = title {check(db::rest_dish_edit_title(rest_num, group_num, dish_num, title).await)?;}
+   if let Some(info) = info {check(db::rest_dish_edit_info(rest_num, group_num, dish_num, info).await)?;}
+   if active == Some(!dish.active) {check(db::rest_dish_toggle(rest_num, group_num, dish_num).await)?;}
+   if let Some(price) = price {check(db::rest_dish_edit_price(rest_num, group_num, dish_num, price).await)?;}
+   tokio::spawn(notify::fire(Arc::clone(bot)));
+
+   settings::log(&format!("API: изменено блюдо {} группы {} ресторана {}", dish_num, group_num, rest_num)).await;
+   let dish = db::dish(db::DishBy::All(rest_num, group_num, dish_num)).await.ok_or_else(db_error)?;
+   Ok(respond(StatusCode::OK, dish_json(&dish)))
+}
+
+// ============================================================================
+// [Caterers and users]
+// ============================================================================
+async fn caterer_register(user_id: i32) -> Result {
+   check(db::register_caterer(lang::lang_of(user_id).await, user_id).await)?;
+   settings::log(&format!("API: регистрация ресторатора {}", user_id)).await;
+   let rest = db::restaurant(db::RestBy::Id(user_id)).await.ok_or_else(db_error)?;
+   Ok(respond(StatusCode::OK, rest_json(&rest)))
+}
+
+async fn caterer_hold(user_id: i32) -> Result {
+   // Ресторатора без ресторана приостановить нельзя
+   db::restaurant(db::RestBy::Id(user_id)).await
+      .ok_or_else(|| error(StatusCode::NOT_FOUND, "caterer not found"))?;
+
+   check(db::hold_caterer(user_id).await)?;
+   settings::log(&format!("API: приостановлен доступ ресторатора {}", user_id)).await;
+   let rest = db::restaurant(db::RestBy::Id(user_id)).await.ok_or_else(db_error)?;
+   Ok(respond(StatusCode::OK, rest_json(&rest)))
+}
+
+async fn user_list(limit: i64, offset: i64) -> Result {
+   let list = db::user_list(limit, offset).await.ok_or_else(db_error)?;
+   let list = list.iter().map(|u| json!({
+      "user_id": u.user_id,
+      "name": u.name,
+      "contact": u.contact,
+      "address": u.address,
+      "last_seen": u.last_seen.format("%Y-%m-%dT%H:%M:%S").to_string(),
+      "language": u.language,
+   })).collect();
+   Ok(respond(StatusCode::OK, Value::Array(list)))
+}
+
+// ============================================================================
+// [Tickets]
+// ============================================================================
+// Последние заказы, по-умолчанию только незавершённые, с state=all все
+async fn ticket_list(query: &HashMap<String, String>) -> Result {
+   let open_only = match query.get("state").map(String::as_str) {
+      None | Some("open") => true,
+      Some("all") => false,
+      _ => return Err(error(StatusCode::BAD_REQUEST, "state must be open or all")),
+   };
+   let limit = query_num(query, "limit", 100, MAX_LIMIT)?;
+
+   let list = db::ticket_list_by(db::TicketListBy::Recent(open_only, limit)).await.unwrap_or_default();
+   Ok(respond(StatusCode::OK, Value::Array(list.iter().map(ticket_json).collect())))
+}
+
+// Заказ вместе с составом
+async fn ticket_info(ticket_id: i32) -> Result {
+   let ticket = db::ticket(db::TicketBy::TicketId(ticket_id)).await
+      .ok_or_else(|| error(StatusCode::NOT_FOUND, "ticket not found"))?;
+
+   let items = db::ticket_items(ticket_id, ticket.eater_id).await.unwrap_or_default();
+   let items = items.iter().map(|i| json!({
+      "rest_num": i.rest_num,
+      "group_num": i.group_num,
+      "dish_num": i.dish_num,
+      "title": i.title,
+      "price": i.price,
+      "amount": i.amount,
+   })).collect();
+
+   let mut value = ticket_json(&ticket);
+   value["items"] = Value::Array(items);
+   Ok(respond(StatusCode::OK, value))
+}
+
+// Принудительно отменяет незавершённый заказ и сообщает об этом обеим сторонам
+async fn ticket_close(bot: &Arc<Bot>, ticket_id: i32) -> Result {
+   let ticket = db::ticket(db::TicketBy::TicketId(ticket_id)).await
+      .ok_or_else(|| error(StatusCode::NOT_FOUND, "ticket not found"))?;
+
+   if ticket.stage >= 5 {
+      return Err(error(StatusCode::CONFLICT, "ticket is already closed"));
+   }
+   check(db::basket_edit_stage(ticket_id, 6).await)?;
+
+   // Сообщения со статусом у обеих сторон показывают закрытый заказ
+   if let Some(ticket) = db::ticket(db::TicketBy::TicketId(ticket_id)).await {
+      basket::update_status_messages(bot, &ticket).await;
+   }
+
+   // Текст на языке получателя, возврат оплаты выполняется вне бота
+   let text = |lang: &str| {
+      let s = lang::tf(lang, TicketClosedByAdmin, &[&ticket_id]);
+      if ticket.paid {
+         lang::tf(lang, TicketCancelledPaid, &[&s, &settings::admin_contact_info()])
+      } else {s}
+   };
+   for user_id in [ticket.eater_id, ticket.caterer_id].iter() {
+      let lang = lang::lang_of(*user_id).await;
+      outbox::send_critical(bot, i64::from(*user_id), &text(lang), None).await;
+   }
+   settings::log(&format!("API: {}", text(lang::DEFAULT))).await;
+
+   ticket_info(ticket_id).await
+}
@@ -11,12 +11,23 @@ use teloxide::{
    prelude::*,
    types::{ChatId, InlineKeyboardMarkup, CallbackQuery, ChatOrInlineMessage, ParseMode, },
 };
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Datelike};
 use std::sync::Arc;
 
 use crate::commands as cmd;
 use crate::database as db;
 use crate::eater;
 use crate::settings;
+use crate::shutdown;
+use crate::outbox;
+use crate::payment;
+use crate::language::{self as lang, Res::*};
+
+// За сколько минут до заказанного времени напоминать ресторатору
+const REMIND_BEFORE_MINUTES: i32 = 30;
+
+// Насколько дней вперёд можно сделать предварительный заказ
+const MAX_DAYS_AHEAD: i64 = 7;
 
 // Вид отображаемого заказа
 #[derive(Copy, Clone)]
@@ -28,50 +39,62 @@ pub enum InfoFor {
 
 // Показывает список заказов для user_id
 pub async fn next_with_info(cx: cmd::Cx<i32>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
    // Извлечём параметры
    let user_id = cx.dialogue;
    
    // Получаем информацию из БД
-   match db::basket_contents(user_id).await {
+   match db::basket_contents(lang, user_id).await {
       None => {
          // Отображаем информацию и кнопки меню
-         cx.answer("Корзина пуста")
-         .reply_markup(cmd::Basket::bottom_markup())
+         cx.answer(lang::t(lang, BasketEmpty))
+         .reply_markup(cmd::Basket::bottom_markup(lang))
          .disable_notification(true)
          .send()
          .await?;
       }
       Some(baskets) => {
+         // Контактные данные едока
+         let info = db::user_basket_info(user_id).await;
+
+         // При доставке адрес можно выбрать прямо при оформлении
+         let addresses = match &info {
+            Some(info) if !info.pickup => db::address_list(user_id).await.unwrap_or_default(),
+            _ => Vec::new(),
+         };
+
          // Отдельными сообщениями выводим рестораны
          for basket in baskets.baskets {
 
             let rest_id = basket.rest_id;
 
             // Текст сообщения о корзине
-            let s = make_basket_message_text(&Some(basket));
+            let s = make_basket_message_text(lang, &Some(basket));
 
             // Отправляем сообщение
             cx.answer(s)
             .parse_mode(ParseMode::HTML)
-            .reply_markup(cmd::Basket::inline_markup_send(rest_id))
+            .reply_markup(cmd::Basket::inline_markup_send(lang, rest_id, &addresses))
             .disable_notification(true)
             .send()
             .await?;
          }
 
          // Контактные данные едока
-         let eater_info = if let Some(info) = db::user_basket_info(user_id).await {
-            let method = if info.pickup {String::from("самовывоз")} else {String::from("курьером по адресу")};
-            format!("Ваше имя: {} /edit_name\nКонтакт: {} /edit_contact\nАдрес: {} /edit_address\nМетод доставки: {} /toggle\n\n", info.name, info.contact, info.address_label(), method)
+         let eater_info = if let Some(info) = info {
+            let method = lang::t(lang, if info.pickup {BasketPickup} else {BasketCourier});
+            let promo = info.promo_code.clone().unwrap_or_else(|| lang::t(lang, No));
+            lang::tf(lang, BasketEaterInfo, &[&info.name, &info.contact, &info.address_label(lang), &method, &info.order_time_label(lang), &promo])
          } else {
-            String::from("Информации о пользователе нет\n\n")
+            lang::t(lang, BasketNoUserInfo)
          };
 
          // Выводим информацию о пользователе, общий итог и инструкцию
-         let s = format!("{}<b>Общая сумма заказа {}</b>", eater_info, settings::price_with_unit(baskets.grand_total));
+         let s = lang::tf(lang, BasketGrandTotal, &[&eater_info, &settings::price_with_unit(baskets.grand_total)]);
          cx.answer(s)
          .parse_mode(ParseMode::HTML)
-         .reply_markup(cmd::Basket::bottom_markup())
+         .reply_markup(cmd::Basket::bottom_markup(lang))
          .disable_notification(true)
          .send()
          .await?;
@@ -113,11 +136,11 @@ async fn show_tickets(bot: Arc<Bot>, chat: ChatId, user_id: i32, show: InfoFor)
 }
 
 // Отправляет сообщение с информацией о заказе, ожидающем обработки другой стороной
-async fn send_message_for(bot: Arc<Bot>, chat: ChatId, show: InfoFor, ticket: &db::Ticket) -> Result<Message, RequestError> {
+pub async fn send_message_for(bot: Arc<Bot>, chat: ChatId, show: InfoFor, ticket: &db::Ticket) -> Result<Message, RequestError> {
    // Исходные данные - сообщение с заказом, со статусом и новое сообщение со статусом
    let (order_msg_id, status_msg_id, (text, markup_opt)) = match show {
-      InfoFor::Eater => (ticket.eater_order_msg_id, ticket.eater_status_msg_id, make_message_for_eater(ticket).await),      // собственное сообщение с заказом
-      InfoFor::Caterer => (ticket.caterer_order_msg_id, ticket.caterer_status_msg_id, make_message_for_caterer(ticket).await),// сообщение с заказом от едока
+      InfoFor::Eater => (Some(ticket.eater_order_msg_id), ticket.eater_status_msg_id, make_message_for_eater(ticket).await),// собственное сообщение с заказом
+      InfoFor::Caterer => (ticket.caterer_reply_to(), ticket.caterer_status_msg_id, make_message_for_caterer(ticket).await),// сообщение с заказом от едока
    };
 
    // Если ранее уже было сообщение со статусом, его нужно удалить
@@ -128,31 +151,56 @@ async fn send_message_for(bot: Arc<Bot>, chat: ChatId, show: InfoFor, ticket: &d
    }
 
    // Отправляем стадию выполнения с цитированием заказа
+   let request = bot.send_message(chat.clone(), text);
+   let request = match order_msg_id {
+      Some(message_id) => request.reply_to_message_id(message_id),
+      None => request,
+   };
    let res = if let Some(markup) = markup_opt {
-      bot.send_message(chat, text)
-      .reply_to_message_id(order_msg_id)
-      .reply_markup(markup)
-      .send()
-      .await?
+      outbox::send(&chat, &request.reply_markup(markup)).await?
    } else {
-      bot.send_message(chat, text)
-      .reply_to_message_id(order_msg_id)
-      .send()
-      .await?
+      outbox::send(&chat, &request).await?
    };
    
    // Сохраним ссылку на новое сообщение со статусом
    let (eater_status, caterer_status) = match show {
-      InfoFor::Eater => (res.id, ticket.caterer_status_msg_id.unwrap_or_default()),
-      InfoFor::Caterer => (ticket.eater_status_msg_id.unwrap_or_default(), res.id), 
+      InfoFor::Eater => (Some(res.id), None),
+      InfoFor::Caterer => (None, Some(res.id)),
    };
    db::ticket_save_status_msg(ticket.ticket_id, eater_status, caterer_status).await;
 
    Ok(res)
 }
 
+// Обновляет сообщения со статусом заказа у обеих сторон
+pub async fn update_status_messages(bot: &Arc<Bot>, ticket: &db::Ticket) {
+   for show in [InfoFor::Eater, InfoFor::Caterer].iter() {
+      let (chat_id, status_msg_id, (text, markup)) = match show {
+         InfoFor::Eater => (ticket.eater_id, ticket.eater_status_msg_id, make_message_for_eater(ticket).await),
+         InfoFor::Caterer => (ticket.caterer_id, ticket.caterer_status_msg_id, make_message_for_caterer(ticket).await),
+      };
+
+      if let Some(message_id) = status_msg_id {
+         let chat_message = ChatOrInlineMessage::Chat {
+            chat_id: ChatId::Id(i64::from(chat_id)),
+            message_id,
+         };
+         let res = if let Some(markup) = markup {
+            bot.edit_message_text(chat_message, text).reply_markup(markup).send().await
+         } else {
+            bot.edit_message_text(chat_message, text).send().await
+         };
+         if let Err(e) = res {
+            settings::log(&format!("Error update_status_messages({}): {}", ticket.ticket_id, e)).await;
+         }
+      }
+   }
+}
+
 // Формирует сообщение с заказом для показа едоку
 pub async fn make_message_for_eater(ticket: &db::Ticket) -> (String, Option<InlineKeyboardMarkup>) {
+   let lang = lang::lang_of(ticket.eater_id).await;
+
 
    // Название ресторана
    let rest_name = match db::restaurant(db::RestBy::Id(ticket.caterer_id)).await {
@@ -161,34 +209,70 @@ pub async fn make_message_for_eater(ticket: &db::Ticket) -> (String, Option<Inli
    };
    
    // Текст сообщения со стадией выполнения 
-   let stage = db::stage_to_str(ticket.stage);
-   let s = format!("{}. Для отправки сообщения к '{}', например, с уточнением времени, нажмите на ссылку /snd{}", stage, rest_name, ticket.caterer_id);
-   (s, make_markup(ticket, InfoFor::Eater))
+   let stage = db::stage_to_str(lang, ticket.stage);
+   let s = lang::tf(lang, TicketForEater, &[&order_time_header(lang, ticket), &payment_header(lang, ticket), &stage, &rest_name, &ticket.caterer_id]);
+   let s = format!("{}{}", s, promo_footer(lang, ticket));
+   (s, make_markup(lang, ticket, InfoFor::Eater))
 }
 
 // Формирует сообщение с заказом для показа ресторатору
 pub async fn make_message_for_caterer(ticket: &db::Ticket) -> (String, Option<InlineKeyboardMarkup>) {
+   let lang = lang::lang_of(ticket.caterer_id).await;
+
+
    // Текст сообщения
-   let eater_name = db::user_name_by_id(ticket.eater_id).await;
-   let stage1 = db::stage_to_str(ticket.stage);
-   let stage2 = db::stage_to_str(ticket.stage + 1);
-   let s = format!("Заказ вам от {} в '{}'. Для отправки заказчику сообщения, например, с уточнением времени, нажмите на ссылку /snd{}\nДля изменения статуса на '{}' нажмите кнопку 'Далее'", eater_name, stage1, ticket.eater_id, stage2);
-   (s, make_markup(ticket, InfoFor::Caterer))
+   let eater_name = db::user_name_by_id(lang, ticket.eater_id).await;
+   let stage1 = db::stage_to_str(lang, ticket.stage);
+   let stage2 = db::stage_to_str(lang, ticket.stage + 1);
+   let s = lang::tf(lang, TicketForCaterer, &[&order_time_header(lang, ticket), &payment_header(lang, ticket), &eater_name, &stage1, &ticket.eater_id, &stage2, &lang::t(lang, BtnNext)]);
+   let s = format!("{}{}", s, promo_footer(lang, ticket));
+   (s, make_markup(lang, ticket, InfoFor::Caterer))
+}
+
+// Строка с заказанным временем для начала сообщения, чтобы её было хорошо видно
+fn order_time_header(lang: &str, ticket: &db::Ticket) -> String {
+   if ticket.order_time.is_some() {
+      lang::tf(lang, OrderTimeHeader, &[&ticket.order_time_label(lang)])
+   } else {
+      String::default()
+   }
+}
+
+// Строка с состоянием оплаты для заказов, оплачиваемых через бота
+fn payment_header(lang: &str, ticket: &db::Ticket) -> String {
+   if ticket.is_awaiting_payment() {
+      lang::t(lang, TicketAwaitingPayment)
+   } else if ticket.paid {
+      lang::t(lang, TicketPaid)
+   } else {
+      String::default()
+   }
 }
 
-pub fn make_markup(ticket: &db::Ticket, show: InfoFor) -> Option<InlineKeyboardMarkup> {
+// Строка о скидке по промокоду, зафиксированной при оформлении заказа
+fn promo_footer(lang: &str, ticket: &db::Ticket) -> String {
+   match &ticket.promo_code {
+      Some(code) => lang::tf(lang, TicketPromo, &[code, &settings::price_with_unit(ticket.discount)]),
+      None => String::default(),
+   }
+}
+
+pub fn make_markup(lang: &str, ticket: &db::Ticket, show: InfoFor) -> Option<InlineKeyboardMarkup> {
    match show {
       InfoFor::Eater => {
          match ticket.stage {
-            1 | 2 | 3 => Some(cmd::Basket::inline_markup_message_cancel(ticket.ticket_id)),
-            4 => Some(cmd::Basket::inline_markup_message_confirm(ticket.ticket_id)),
+            1 if ticket.is_awaiting_payment() => Some(cmd::Basket::inline_markup_message_pay(lang, ticket.ticket_id)),
+            1 | 2 | 3 => Some(cmd::Basket::inline_markup_message_cancel(lang, ticket.ticket_id)),
+            4 => Some(cmd::Basket::inline_markup_message_confirm(lang, ticket.ticket_id)),
             _ => None,
          }
       }
       InfoFor::Caterer => {
          match ticket.stage {
-            1 | 2 | 3 => Some(cmd::Basket::inline_markup_message_next(ticket.ticket_id)),
-            4 => Some(cmd::Basket::inline_markup_message_cancel(ticket.ticket_id)),
+            // До оплаты ресторатор может только отменить заказ
+            1 if ticket.is_awaiting_payment() => Some(cmd::Basket::inline_markup_message_cancel(lang, ticket.ticket_id)),
+            1 | 2 | 3 => Some(cmd::Basket::inline_markup_message_next(lang, ticket.ticket_id)),
+            4 => Some(cmd::Basket::inline_markup_message_cancel(lang, ticket.ticket_id)),
             _ => None,
          }
       }
@@ -196,9 +280,9 @@ pub fn make_markup(ticket: &db::Ticket, show: InfoFor) -> Option<InlineKeyboardM
 }
 
 // Формирует сообщение с собственной корзиной
-pub fn make_basket_message_text(basket: &Option<db::Basket>) -> String {
+pub fn make_basket_message_text(lang: &str, basket: &Option<db::Basket>) -> String {
    match basket {
-      None => String::from("корзина пуста"),
+      None => lang::t(lang, BasketEmptyLower),
       Some(basket) => {
          // Заголовок с информацией о ресторане
          let mut s = String::default();
@@ -208,8 +292,17 @@ pub fn make_basket_message_text(basket: &Option<db::Basket>) -> String {
             s.push_str(&format!("\n{}", dish))
          }
 
-         // Итоговая стоимость и информация о ресторане
-         s.push_str(&format!("\nВсего: {}\n\n{}", settings::price_with_unit(basket.total), basket.restaurant));
+         // Итоговая стоимость с учётом промокода
+         s.push_str(&lang::tf(lang, BasketTotal, &[&settings::price_with_unit(basket.total)]));
+         match &basket.promo {
+            Some(db::PromoResult {code, discount, error: None}) => s.push_str(&lang::tf(lang, BasketPromoApplied,
+               &[code, &settings::price_with_unit(*discount), &settings::price_with_unit(basket.total - discount)])),
+            Some(db::PromoResult {code, error: Some(e), ..}) => s.push_str(&lang::tf(lang, BasketPromoRejected, &[code, e])),
+            None => (),
+         }
+
+         // Информация о ресторане
+         s.push_str(&format!("\n\n{}", basket.restaurant));
          s
       }
    }
@@ -217,8 +310,9 @@ pub fn make_basket_message_text(basket: &Option<db::Basket>) -> String {
 
 // Показывает сообщение об ошибке/отмене без повторного вывода информации
 async fn next_with_cancel(cx: cmd::Cx<i32>, text: &str) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
    cx.answer(text)
-   .reply_markup(cmd::Basket::bottom_markup())
+   .reply_markup(cmd::Basket::bottom_markup(lang))
    .disable_notification(true)
    .send()
    .await?;
@@ -232,13 +326,15 @@ async fn next_with_cancel(cx: cmd::Cx<i32>, text: &str) -> cmd::Res {
 
 // Обработчик команд
 pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
    // Извлечём параметры
    let user_id = cx.dialogue;
 
    // Разбираем команду.
    match cx.update.text() {
       None => {
-         next_with_cancel(cx, "Текстовое сообщение, пожалуйста!").await
+         next_with_cancel(cx, &lang::t(lang, TextPlease)).await
       }
       Some(command) => {
          match cmd::Basket::from(command) {
@@ -257,13 +353,13 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
             // Неизвестная команда
             cmd::Basket::UnknownCommand => {
                // Сохраним текущее состояние для возврата
-               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::BasketMode(user_id), m : cmd::Basket::bottom_markup()});
+               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::BasketMode(user_id), m : cmd::Basket::bottom_markup(lang)});
 
                // Возможно это общая команда
                if let Some(res) = eater::handle_common_commands(DialogueDispatcherHandlerCx::new(cx.bot.clone(), cx.update.clone(), ()), command, origin).await {return res;}
                else {
                   let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
-                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, user_id), "Вы в меню корзина: неизвестная команда").await
+                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, user_id), &lang::t(lang, BasketUnknownCommand)).await
                }
             }
 
@@ -279,7 +375,7 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
                   next_with_info(DialogueDispatcherHandlerCx::new(bot, update, user_id)).await
                } else {
                   let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
-                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, user_id), "Вы в меню корзина: ошибка очистки корзины").await
+                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, user_id), &lang::t(lang, BasketClearError)).await
                }
             }
 
@@ -298,7 +394,7 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
                   }
                   _ => {
                      let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
-                     next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, user_id), "Вы в меню корзина: ошибка удаления блюда").await
+                     next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, user_id), &lang::t(lang, BasketDeleteError)).await
                   }
                }
             }
@@ -306,7 +402,7 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
             // Редактировать имя
             cmd::Basket::EditName => {
                // Отправляем приглашение ввести строку со слешем в меню для отмены
-               cx.answer(format!("Отправьте ваше имя (/ для отмены)"))
+               cx.answer(lang::t(lang, BasketEnterName))
                .reply_markup(cmd::Caterer::slash_markup())
                .disable_notification(true)
                .send()
@@ -319,7 +415,7 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
             // Редактировать контакт
             cmd::Basket::EditContact => {
                // Отправляем приглашение ввести строку со слешем в меню для отмены
-               cx.answer(format!("Если хотите дать возможность ресторатору связаться с вами напрямую, укажите ник или телефон (/ для отмены)"))
+               cx.answer(lang::t(lang, BasketEnterContact))
                .reply_markup(cmd::Caterer::slash_markup())
                .disable_notification(true)
                .send()
@@ -331,9 +427,23 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
 
             // Редактировать адрес
             cmd::Basket::EditAddress => {
+               // Покажем адресную книгу, если в ней уже что-то есть
+               let addresses = db::address_list(user_id).await.unwrap_or_default();
+               let s = if addresses.is_empty() {
+                  lang::t(lang, BasketEnterAddress)
+               } else {
+                  let book: String = addresses.iter()
+                  .map(|address| {
+                     let set = if address.is_default {String::from("⭐")} else {format!("/addr{}", address.id)};
+                     format!("{} {} /deladdr{}\n", address.text(lang), set, address.id)
+                  })
+                  .collect();
+                  format!("{}\n{}", lang::tf(lang, BasketAddressBook, &[&book]), lang::t(lang, BasketEnterAddress))
+               };
+
                // Отправляем приглашение ввести строку со слешем в меню для отмены
-               cx.answer(format!("Введите адрес для доставки или укажите точку на карте (/ для отмены). Также вы можете отправить произвольную точку или даже транслировать её изменение, для этого нажмите скрепку 📎 и выберите геопозицию."))
-               .reply_markup(cmd::Basket::address_markup())
+               cx.answer(s)
+               .reply_markup(cmd::Basket::address_markup(lang))
                .disable_notification(true)
                .send()
                .await?;
@@ -342,11 +452,73 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
                next(cmd::Dialogue::BasketEditAddress(user_id))
             }
 
+            // Сделать адрес адресом по умолчанию
+            cmd::Basket::SetAddress(address_id) => {
+               if db::address(user_id, address_id).await.is_some() && db::address_set_default(user_id, address_id).await {
+                  next_with_info(cx).await
+               } else {
+                  next_with_cancel(cx, &lang::tf(lang, BasketAddressNotFound, &[&address_id])).await
+               }
+            }
+
+            // Удалить адрес из адресной книги
+            cmd::Basket::RemoveAddress(address_id) => {
+               if db::address_remove(user_id, address_id).await {
+                  next_with_info(cx).await
+               } else {
+                  next_with_cancel(cx, &lang::tf(lang, BasketAddressNotFound, &[&address_id])).await
+               }
+            }
+
             // Переключить способ доставки
             cmd::Basket::TogglePickup => {
                db::basket_toggle_pickup(user_id).await;
                next_with_info(cx).await
             }
+
+            // История заказов
+            cmd::Basket::History => {
+               next_with_cancel(cx, &history_text(lang, user_id).await).await
+            }
+
+            // Повторить заказ из истории
+            cmd::Basket::Repeat(ticket_id) => {
+               let s = repeat_order(lang, user_id, ticket_id).await;
+               cx.answer(s)
+               .reply_markup(cmd::Basket::bottom_markup(lang))
+               .disable_notification(true)
+               .send()
+               .await?;
+
+               // Отображаем изменённую корзину
+               next_with_info(cx).await
+            }
+
+            // Редактировать желаемое время заказа
+            cmd::Basket::EditTime => {
+               // Отправляем приглашение ввести строку со слешем в меню для отмены
+               cx.answer(lang::t(lang, BasketEnterTime))
+               .reply_markup(cmd::Caterer::slash_markup())
+               .disable_notification(true)
+               .send()
+               .await?;
+
+               // Переходим в режим ввода
+               next(cmd::Dialogue::BasketEditTime(user_id))
+            }
+
+            // Ввести промокод
+            cmd::Basket::EditPromo => {
+               // Отправляем приглашение ввести строку со слешем в меню для отмены
+               cx.answer(lang::t(lang, BasketEnterPromo))
+               .reply_markup(cmd::Caterer::slash_markup())
+               .disable_notification(true)
+               .send()
+               .await?;
+
+               // Переходим в режим ввода
+               next(cmd::Dialogue::BasketEditPromo(user_id))
+            }
          }
       }
    }
@@ -354,6 +526,8 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
 
 // Изменить имя едока
 pub async fn edit_name_mode(cx: cmd::Cx<i32>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
    // Извлечём параметры
    let user_id = cx.dialogue;
         
@@ -369,11 +543,11 @@ pub async fn edit_name_mode(cx: cmd::Cx<i32>) -> cmd::Res {
             next_with_info(cx).await
          } else {
             // Сообщим об ошибке
-            next_with_cancel(cx, &format!("Ошибка edit_name_mode({})", user_id)).await
+            next_with_cancel(cx, &lang::tf(lang, ErrorCall, &[&format!("edit_name_mode({})", user_id)])).await
          }
       } else {
          // Сообщим об отмене
-         next_with_cancel(cx, "Отмена ввода имени").await
+         next_with_cancel(cx, &lang::t(lang, BasketCancelName)).await
       }
    } else {
       next(cmd::Dialogue::BasketMode(user_id))
@@ -382,6 +556,8 @@ pub async fn edit_name_mode(cx: cmd::Cx<i32>) -> cmd::Res {
 
 // Изменить контакт едока
 pub async fn edit_contact_mode(cx: cmd::Cx<i32>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
    // Извлечём параметры
    let user_id = cx.dialogue;
         
@@ -397,73 +573,371 @@ pub async fn edit_contact_mode(cx: cmd::Cx<i32>) -> cmd::Res {
             next_with_info(cx).await
          } else {
             // Сообщим об ошибке
-            next_with_cancel(cx, &format!("Ошибка edit_contact_mode({})", user_id)).await
+            next_with_cancel(cx, &lang::tf(lang, ErrorCall, &[&format!("edit_contact_mode({})", user_id)])).await
          }
       } else {
          // Сообщим об отмене
-         next_with_cancel(cx, "Отмена ввода контакта").await
+         next_with_cancel(cx, &lang::t(lang, BasketCancelContact)).await
       }
    } else {
       next(cmd::Dialogue::BasketMode(user_id))
    }
 }
 
-// Изменить адрес едока
+// Добавить адрес едока
 pub async fn edit_address_mode(cx: cmd::Cx<i32>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
    // Извлечём параметры
    let user_id = cx.dialogue;
         
    // Ожидаем либо текстовое сообщение, либо локацию
    let option_text = cx.update.text();
    let option_location = cx.update.location();
-   let message_id = cx.update.id;
 
-   // Проверяем на текстовое сообщение
-   if let Some(text) = option_text {
+   // Новый адрес либо текстом, либо точкой на карте с сохранением координат
+   let address_id = if let Some(text) = option_text {
+      // Удалим из строки слеши
+      let s = cmd::remove_slash(text).await;
+
+      // Если строка пустая, сообщим об отмене
+      if s.is_empty() {
+         return next_with_cancel(cx, &lang::t(lang, BasketCancelAddress)).await;
+      }
+      db::address_add(user_id, &s, None).await
+   } else if let Some(location) = option_location {
+      let s = format!("{:.5}, {:.5}", location.latitude, location.longitude);
+      db::address_add(user_id, &s, Some((location.latitude, location.longitude))).await
+   } else {
+      // Сообщим об отмене
+      return next_with_cancel(cx, &lang::t(lang, BasketCancelLocation)).await;
+   };
+
+   match address_id {
+      Some(address_id) => {
+         // Предложим подписать адрес
+         cx.answer(lang::t(lang, BasketEnterAddressLabel))
+         .reply_markup(cmd::Caterer::slash_markup())
+         .disable_notification(true)
+         .send()
+         .await?;
+
+         next(cmd::Dialogue::BasketEditAddressLabel(user_id, address_id))
+      }
+      // Сообщим об ошибке
+      None => next_with_cancel(cx, &lang::tf(lang, ErrorCall, &[&format!("edit_address_mode({})", user_id)])).await,
+   }
+}
+
+// Подписать только что добавленный адрес
+pub async fn edit_address_label_mode(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
+   // Извлечём параметры
+   let (user_id, address_id) = cx.dialogue;
+
+   if let Some(text) = cx.update.text() {
+      // Удалим из строки слеши, пустая строка оставляет адрес без метки
+      let s = cmd::remove_slash(text).await;
+      if !s.is_empty() && !db::address_edit_label(user_id, address_id, &s).await {
+         let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
+         return next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, user_id), &lang::tf(lang, ErrorCall, &[&format!("edit_address_label_mode({})", user_id)])).await;
+      }
+   }
+
+   // Покажем изменённую информацию
+   let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
+   next_with_info(DialogueDispatcherHandlerCx::new(bot, update, user_id)).await
+}
+
+// Формирует текст с историей заказов
+async fn history_text(lang: &str, user_id: i32) -> String {
+   match db::ticket_history(user_id).await {
+      None => lang::t(lang, HistoryEmpty),
+      Some(tickets) => {
+         let mut s = lang::t(lang, HistoryTitle);
+         for ticket in tickets {
+            // Краткий состав заказа
+            let dishes: String = db::ticket_items(ticket.ticket_id, user_id).await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|item| format!("   {}\n", lang::tf(lang, ItemAmount, &[&item.title, &item.amount])))
+            .collect();
+
+            s.push_str(&lang::tf(lang, HistoryTicket, &[&ticket.ticket_id, &ticket.created_label(), &ticket.rest_title, &settings::price_with_unit(ticket.total),
+               &db::stage_to_str(lang, ticket.stage), &dishes, &ticket.ticket_id]));
+         }
+         s
+      }
+   }
+}
+
+// Добавляет в корзину блюда из ранее сделанного заказа и возвращает отчёт
+async fn repeat_order(lang: &str, user_id: i32, ticket_id: i32) -> String {
+   let items = match db::ticket_items(ticket_id, user_id).await {
+      Some(items) => items,
+      None => return lang::tf(lang, RepeatNotFound, &[&ticket_id]),
+   };
+
+   let mut added = Vec::<String>::new();
+   let mut skipped = Vec::<String>::new();
+   let mut changed = Vec::<String>::new();
+
+   for item in items {
+      // Недоступные сейчас блюда пропускаем
+      if !item.available {
+         skipped.push(format!("   {}", item.title));
+         continue;
+      }
+
+      if db::basket_add_amount(item.rest_num, item.group_num, item.dish_num, user_id, item.amount).await {
+         added.push(format!("   {}", lang::tf(lang, ItemAmount, &[&item.title, &item.amount])));
+
+         // Предупредим об изменении цены
+         if let Some(price) = item.current_price {
+            if price != item.price {
+               changed.push(format!("   {}", lang::tf(lang, RepeatPrice, &[&item.title, &settings::price_with_unit(item.price), &settings::price_with_unit(price)])));
+            }
+         }
+      } else {
+         skipped.push(format!("   {}", item.title));
+      }
+   }
+
+   let mut s = if added.is_empty() {
+      lang::tf(lang, RepeatNothing, &[&ticket_id])
+   } else {
+      lang::tf(lang, RepeatAdded, &[&ticket_id, &added.join("\n")])
+   };
+   if !added.is_empty() && !skipped.is_empty() {
+      s.push_str(&lang::tf(lang, RepeatSkipped, &[&skipped.join("\n")]));
+   }
+   if !changed.is_empty() {
+      s.push_str(&lang::tf(lang, RepeatChanged, &[&changed.join("\n")]));
+   }
+   s
+}
+
+// Изменить желаемое время заказа
+pub async fn edit_time_mode(cx: cmd::Cx<i32>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
+   // Извлечём параметры
+   let user_id = cx.dialogue;
+        
+   if let Some(text) = cx.update.text() {
       // Удалим из строки слеши
       let s = cmd::remove_slash(text).await;
 
       // Если строка не пустая, продолжим
       if !s.is_empty() {
-         // Сохраним новое значение в БД
-         if db::basket_edit_address(user_id, s).await {
-            // Покажем изменённую информацию
-            next_with_info(cx).await
-         } else {
-            // Сообщим об ошибке
-            next_with_cancel(cx, &format!("Ошибка edit_address_mode({})", user_id)).await
+         // Время вводится по часовому поясу заведения, как и проверяется при отправке заказа
+         let now = match db::basket_restaurant(user_id).await {
+            Some(rest) => rest.now(),
+            None => settings::current_date_time(),
+         };
+
+         // Попытаемся распознать время
+         match parse_order_time(lang, &s, now) {
+            Ok(order_time) => {
+               // Сохраним новое значение в БД
+               if db::basket_edit_order_time(user_id, order_time).await {
+                  // Покажем изменённую информацию
+                  next_with_info(cx).await
+               } else {
+                  // Сообщим об ошибке
+                  next_with_cancel(cx, &lang::tf(lang, ErrorCall, &[&format!("edit_time_mode({})", user_id)])).await
+               }
+            }
+            Err(e) => next_with_cancel(cx, &e).await,
          }
       } else {
          // Сообщим об отмене
-         next_with_cancel(cx, "Отмена ввода адреса").await
+         next_with_cancel(cx, &lang::t(lang, CancelTime)).await
       }
    } else {
-      // Проверяем на геометку
-      if let Some(_location) = option_location {
-         // Сохраним код сообщения
-         if db::basket_edit_address(user_id, format!("Location{}", message_id)).await {
+      next(cmd::Dialogue::BasketMode(user_id))
+   }
+}
+
+// Ввести или удалить промокод
+pub async fn edit_promo_mode(cx: cmd::Cx<i32>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
+   // Извлечём параметры
+   let user_id = cx.dialogue;
+        
+   if let Some(text) = cx.update.text() {
+      // Удалим из строки слеши
+      let s = cmd::remove_slash(text).await;
+
+      // Если строка не пустая, продолжим
+      if !s.is_empty() {
+         // Промокоды хранятся в верхнем регистре
+         let code = s.trim().to_uppercase();
+         let code = if code == "0" {None} else {Some(code)};
+
+         // Сообщим сразу, если такого промокода нет или он просрочен, остальные условия видны в корзине
+         if let Some(code) = &code {
+            match db::promo_code(code).await {
+               None => return next_with_cancel(cx, &lang::tf(lang, PromoCodeNotFound, &[code])).await,
+               Some(promo) => if promo.is_expired() {
+                  return next_with_cancel(cx, &lang::tf(lang, PromoCodeExpired, &[code])).await;
+               }
+            }
+         }
+
+         // Сохраним новое значение в БД
+         if db::basket_edit_promo_code(user_id, code).await {
             // Покажем изменённую информацию
             next_with_info(cx).await
          } else {
             // Сообщим об ошибке
-            next_with_cancel(cx, &format!("Ошибка basket_edit_address2({})", user_id)).await
+            next_with_cancel(cx, &lang::tf(lang, ErrorCall, &[&format!("edit_promo_mode({})", user_id)])).await
          }
       } else {
          // Сообщим об отмене
-         next_with_cancel(cx, "Отмена, ожидался либо текст либо геометка").await
-      } 
+         next_with_cancel(cx, &lang::t(lang, BasketCancelPromo)).await
+      }
+   } else {
+      next(cmd::Dialogue::BasketMode(user_id))
+   }
+}
+
+// Распознаёт время заказа в виде "0", "ЧЧ:ММ" или "ЧЧ:ММ ДД.ММ"
+fn parse_order_time(lang: &str, s: &str, now: NaiveDateTime) -> Result<Option<NaiveDateTime>, String> {
+   let s = s.trim();
+
+   // Ноль означает "как можно скорее"
+   if s == "0" {
+      return Ok(None);
+   }
+
+   // Время обязательно, дата нет
+   let err = || lang::tf(lang, OrderTimeError, &[&s]);
+   let mut parts = s.split_whitespace();
+   let time = parts.next()
+   .and_then(|part| NaiveTime::parse_from_str(part, "%H:%M").ok())
+   .ok_or_else(err)?;
+
+   let res = match parts.next() {
+      None => {
+         // Если время сегодня уже прошло, значит заказ на завтра
+         let res = now.date().and_time(time);
+         if res > now {res} else {res + Duration::days(1)}
+      }
+      Some(part) => {
+         // День и месяц, год текущий или следующий, если дата уже прошла
+         let mut date_parts = part.split('.');
+         let day = date_parts.next().and_then(|d| d.parse::<u32>().ok()).ok_or_else(err)?;
+         let month = date_parts.next().and_then(|m| m.parse::<u32>().ok()).ok_or_else(err)?;
+         let res = NaiveDate::from_ymd_opt(now.year(), month, day)
+         .ok_or_else(err)?
+         .and_time(time);
+         if res > now {res}
+         else {
+            NaiveDate::from_ymd_opt(now.year() + 1, month, day)
+            .ok_or_else(err)?
+            .and_time(time)
+         }
+      }
+   };
+
+   // Слишком далёкие заказы не принимаем
+   if res - now > Duration::days(MAX_DAYS_AHEAD) {
+      return Err(lang::tf(lang, OrderTooFar, &[&MAX_DAYS_AHEAD]));
+   }
+
+   Ok(Some(res))
+}
+
+// Проверяет, что ресторан сможет выполнить заказ к нужному времени, иначе возвращает пояснение для едока
+async fn check_order_time(lang: &str, rest: &db::Restaurant, user_id: i32, order_time: Option<NaiveDateTime>) -> Result<(), String> {
+   // Время заказа задано по часовому поясу заведения
+   let now = rest.now();
+
+   // Время, к которому будет выполняться заказ
+   let target = match order_time {
+      Some(t) => {
+         if t <= now {
+            return Err(lang::tf(lang, OrderTimePassed, &[&db::order_time_to_str(lang, order_time)]));
+         }
+         t
+      }
+      None => now,
+   };
+
+   // Заведение должно работать
+   if !rest.active || !rest.enabled {
+      return Err(lang::tf(lang, RestNotAccepting, &[&rest.title]));
+   }
+
+   // Каждая группа блюд из корзины должна быть доступна в это время
+   let closed: Vec<String> = db::basket_groups(user_id, rest.num).await
+   .unwrap_or_default()
+   .into_iter()
+   .filter(|group| !group.is_open_at(target.time()))
+   .map(|group| format!("   {} ({}-{})", group.title, db::str_time(group.opening_time), db::str_time(group.closing_time)))
+   .collect();
+
+   if closed.is_empty() {
+      Ok(())
+   } else {
+      let when = if order_time.is_some() {lang::tf(lang, OrderAt, &[&db::order_time_to_str(lang, order_time)])} else {lang::t(lang, OrderNow)};
+      Err(lang::tf(lang, OrderImpossible, &[&when, &closed.join("\n")]))
    }
 }
 
+// Доставляет ресторатору новый заказ из очереди важных сообщений: текст с точкой на карте,
+// пересланное сообщение с заказом и статус. Выполненные шаги отмечаются в БД и при повторе пропускаются
+pub async fn deliver_order(bot: &Arc<Bot>, msg: &db::OutboxMessage, ticket_id: i32) -> ResponseResult<()> {
+   let to = ChatId::Id(msg.chat_id);
+
+   if !msg.text.is_empty() {
+      let sent = outbox::send(&to, &bot.send_message(to.clone(), msg.text.as_str())).await?;
+      if let Some((latitude, longitude)) = msg.location {
+         if let Err(e) = outbox::send(&to, &bot.send_location(to.clone(), latitude as f32, longitude as f32)).await {
+            settings::log(&format!("Error basket::deliver_order({}) location: {}", ticket_id, e)).await;
+         }
+      }
+
+      // Без пересылки заказом ресторатора служит сам текст
+      if msg.forward.is_none() {
+         db::ticket_save_caterer_order_msg(ticket_id, sent.id).await;
+      }
+      db::outbox_text_sent(msg.msg_id).await;
+   }
+
+   // Заказ мог измениться, пока сообщение ждало повтора
+   let mut ticket = match db::ticket(db::TicketBy::TicketId(ticket_id)).await {
+      Some(ticket) => ticket,
+      None => return Ok(()),
+   };
+
+   if let (Some((from, message_id)), None) = (msg.forward, ticket.caterer_reply_to()) {
+      let forwarded = outbox::send(&to, &bot.forward_message(to.clone(), ChatId::Id(from), message_id)).await?;
+      db::ticket_save_caterer_order_msg(ticket_id, forwarded.id).await;
+      ticket.caterer_order_msg_id = forwarded.id;
+   }
+
+   if ticket.caterer_status_msg_id.is_none() {
+      send_message_for(bot.clone(), to, InfoFor::Caterer, &ticket).await?;
+   }
+   Ok(())
+}
+
 // Отправляет сообщение ресторатору с корзиной пользователя
-pub async fn send_basket(cx: &DispatcherHandlerCx<CallbackQuery>, rest_id: i32, user_id: i32, message_id: i32) -> bool {
-   // Откуда и куда
+// address_id - выбранный при оформлении адрес или 0 для адреса по умолчанию
+pub async fn send_basket(cx: &DispatcherHandlerCx<CallbackQuery>, rest_id: i32, user_id: i32, message_id: i32, address_id: i32) -> bool {
+   let lang = lang::user_lang(Some(&cx.update.from)).await;
+
+
+   // Чат едока, ресторатору заказ доставит очередь важных сообщений
    let from = ChatId::Id(i64::from(user_id));
-   let to = ChatId::Id(i64::from(rest_id));
 
    // Если у ресторана недействительный айди, предложим пользователю отправить заказ самостоятельно
    if rest_id < 9999 {
-      let msg = String::from("Заведение пока не подключено к боту, пожалуйста скопируйте ваш заказ отправьте по указанным контактным данным напрямую, после чего можно очистить корзину");
+      let msg = lang::t(lang, SendNotConnected);
       let res = cx.bot.send_message(from.clone(), msg)
       .reply_to_message_id(message_id)
       .send().await;
@@ -478,8 +952,9 @@ pub async fn send_basket(cx: &DispatcherHandlerCx<CallbackQuery>, rest_id: i32,
    let basket_info = db::user_basket_info(user_id).await;
    if basket_info.is_none() {
       // Этот код никогда не должен выполниться
-      let msg = String::from("send_basket: Информации о пользователе нет, нажмите кнопку 'В начало', выберите блюдо заново");
-      settings::log(&msg).await;
+      let msg = |lang: &str| lang::tf(lang, SendNoUserInfo, &[&lang::t(lang, BtnHome)]);
+      settings::log(&format!("send_basket: {}", msg(lang::DEFAULT))).await;
+      let msg = msg(lang);
       let res = cx.bot.send_message(from.clone(), msg).send().await;
       if let Err(e) = res {
          let msg = format!("basket::send_basket 2(): {}", e);
@@ -489,16 +964,18 @@ pub async fn send_basket(cx: &DispatcherHandlerCx<CallbackQuery>, rest_id: i32,
    }
 
    // Проверка выше гарантирует отсутствие паники на unwrap()
-   let basket_info = basket_info.unwrap();
+   let mut basket_info = basket_info.unwrap();
 
-   // Сообщение с геолокацией, если есть
-   let location_message = basket_info.address_message_id();
-
-      // Если не самовывоз, то проверим контактную информацию
+   // Если не самовывоз, то нужен адрес из адресной книги
    if !basket_info.pickup {
-      // Если адрес слишком короткий, выходим с сообщением
-      if basket_info.address.len() < 3 {
-         let msg = String::from("Пожалуйста, введите адрес, нажав /edit_address или переключитесь на самовывоз, нажав /toggle\nЭта информация будет сохранена для последующих заказов, при необходимости вы всегда сможете её изменить");
+      // Адрес, выбранный при оформлении, заменяет адрес по умолчанию
+      if address_id != 0 {
+         basket_info.address = db::address(user_id, address_id).await;
+      }
+
+      // Если адреса нет, выходим с сообщением
+      if basket_info.address.is_none() {
+         let msg = lang::t(lang, SendEnterAddress);
          let res = cx.bot.send_message(from.clone(), msg).send().await;
          if let Err(e) = res {
             let msg = format!("basket::send_basket 3(): {}", e);
@@ -506,45 +983,26 @@ pub async fn send_basket(cx: &DispatcherHandlerCx<CallbackQuery>, rest_id: i32,
          }
          return false;
       } 
+   }
 
-      // Если задано местоположение на карте, надо проверить, что сообщение с геолокацией ещё доступно
-      if basket_info.is_geolocation() {
-         // Заготовим текст сообщения с ошибкой заранее
-         let err_message = String::from("Недоступно сообщение с геопозицией, пожалуйста укажите адрес ещё раз, нажав /edit_address");
+   // Начнём с запроса информации о ресторане-получателе
+   match db::restaurant(db::RestBy::Id(rest_id)).await {
+      Some(rest) => {
 
-         // Код сообщения
-         if let Some(msg_id) = location_message {
-            // Отправим сообщение самому едоку для контроля и проверки, что нет ошибки
-            let res = cx.bot.forward_message(to.clone(), from.clone(), msg_id).send().await;
+         // Проверим, что заведение успеет выполнить заказ к нужному времени
+         if let Err(msg) = check_order_time(lang, &rest, user_id, basket_info.order_time).await {
+            let res = cx.bot.send_message(from.clone(), msg)
+            .reply_to_message_id(message_id)
+            .send().await;
             if let Err(e) = res {
-               let err_message = format!("{}\n<i>{}</i>", err_message, e);
-               let res = cx.bot.send_message(from.clone(), err_message)
-               .parse_mode(ParseMode::HTML)
-               .send().await;
-               if let Err(e) = res {
-                  let msg = format!("basket::send_basket 4(): {}", e);
-                  settings::log(&msg).await;
-               }
-               return false;
-            }
-         } else {
-            // Этот код никогда не должен выполниться
-            let res = cx.bot.send_message(from.clone(), err_message).send().await;
-            if let Err(e) = res {
-               let msg = format!("basket::send_basket 5(): {}", e);
+               let msg = format!("basket::send_basket 6(): {}", e);
                settings::log(&msg).await;
             }
             return false;
          }
-      }
-   }
-
-   // Начнём с запроса информации о ресторане-получателе
-   match db::restaurant(db::RestBy::Id(rest_id)).await {
-      Some(rest) => {
 
          // Заново сгенерируем текст исходного сообщения уже без команд /del в тексте, чтобы пересылать его
-         let basket_with_no_commands = db::basket_content(user_id, rest.num, rest_id, &rest.title, &rest.info, true).await;
+         let basket_with_no_commands = db::basket_content(lang, user_id, rest.num, rest_id, &rest.title, &rest.info, true).await;
 
          // Ссылка на исправляемое сообщение
          let original_message = ChatOrInlineMessage::Chat {
@@ -553,57 +1011,52 @@ pub async fn send_basket(cx: &DispatcherHandlerCx<CallbackQuery>, rest_id: i32,
          };
 
          // Исправим исходное сообщение на новый текст, чтобы исчезли команды и кнопка "оформить"
-         if let Err(e) = cx.bot.edit_message_text(original_message, make_basket_message_text(&basket_with_no_commands)).send().await {
+         if let Err(e) = cx.bot.edit_message_text(original_message, make_basket_message_text(lang, &basket_with_no_commands)).send().await {
             let s = format!("Error send_basket edit_message_text(): {}", e);
             settings::log(&s).await;
          }
          
          // Информация о едоке
-         let method = if basket_info.pickup {String::from("Cамовывоз")} else {format!("Курьером по адресу {}", basket_info.address_label())};
-         let eater_info = format!("Заказ от {}\nКонтакт: {}\n{}", basket_info.name, basket_info.contact, method);
-
-         // Отправим сообщение с контактными данными (геолокация уже отправлена выше)
-         settings::log_and_notify(&eater_info).await;
-         match cx.bot.send_message(to.clone(), eater_info).send().await {
-            Ok(_) => {
-               // Пересылаем сообщение с заказом
-               settings::log_forward(from.clone(), message_id).await;
-               match cx.bot.forward_message(to.clone(), from.clone(), message_id).send().await {
-                  Ok(new_message) => {
-
-                     // Переместим заказ из корзины в обработку
-                     if db::order_to_ticket(user_id, rest_id, message_id, new_message.id).await {
-
-                        // Прочитаем только что записанный тикет из базы
-                        let ticket = db::ticket(db::TicketBy::EaterAndCatererId(user_id, rest_id)).await;
-                        if ticket.is_none() {
-                           return false;
-                        }
-                        let ticket = ticket.unwrap();
-
-                        // Отправим сообщение едоку, уже со статусом заказа
-                        let eater_msg = send_message_for(cx.bot.clone(), from, InfoFor::Eater, &ticket).await;
-                        if let Err(e) = eater_msg {
-                           settings::log(&format!("Error send_basket({}, {}, {}), send_messages_for_eater: {}", user_id, rest_id, message_id, e)).await;
-                           return false;
-                        }
-
-                        // И то же самое для ресторатора
-                        let caterer_msg = send_message_for(cx.bot.clone(), to, InfoFor::Caterer, &ticket).await;
-                        if let Err(e) = caterer_msg {
-                           settings::log(&format!("Error send_basket({}, {}, {}), send_messages_for_caterer: {}", user_id, rest_id, message_id, e)).await;
-                           return false;
-                        }
-
-                        // Все операции прошли успешно, сохраним ссылки на сообщения со статусом для возможности их редактирования
-                        return db::ticket_save_status_msg(ticket.ticket_id, eater_msg.unwrap().id, caterer_msg.unwrap().id).await;
-                     }
-                  }
-                  Err(err) =>  { settings::log(&format!("Error send_basket({}, {}, {}): {}", user_id, rest_id, message_id, err)).await;}
-               }
-            }
-            Err(err) =>  { settings::log(&format!("Error send_basket announcement({}, {}, {}): {}", user_id, rest_id, message_id, err)).await;}
+         let eater_info = |lang: &str| {
+            let method = if basket_info.pickup {lang::t(lang, OrderPickup)} else {lang::tf(lang, OrderCourier, &[&basket_info.address_label(lang)])};
+            let eater_info = lang::tf(lang, OrderFrom, &[&basket_info.name, &basket_info.contact, &method]);
+            if basket_info.order_time.is_some() {
+               format!("{}{}", lang::tf(lang, OrderTimeHeader, &[&basket_info.order_time_label(lang)]), eater_info)
+            } else {eater_info}
+         };
+
+         // Переместим заказ из корзины в обработку, сообщение с заказом ресторатору ещё предстоит доставить
+         let promo = basket_with_no_commands.as_ref().and_then(|basket| basket.promo.as_ref());
+         if !db::order_to_ticket(user_id, rest_id, message_id, 0, promo).await {
+            return false;
+         }
+
+         // Прочитаем только что записанный тикет из базы
+         let ticket = match db::ticket(db::TicketBy::EaterAndCatererId(user_id, rest_id)).await {
+            Some(ticket) => ticket,
+            None => return false,
+         };
+
+         // Контактные данные на языке ресторатора, точку на карте по сохранённым координатам и сам заказ
+         // отправим через очередь важных сообщений, при неудаче они будут доставлены позже
+         settings::log_and_notify(&eater_info(lang::DEFAULT)).await;
+         settings::log_forward(from.clone(), message_id).await;
+         let location = if basket_info.pickup {None} else {basket_info.address.as_ref().and_then(|address| address.location)};
+         if !outbox::send_order(&cx.bot, &ticket, eater_info(lang::lang_of(rest_id).await), Some((i64::from(user_id), message_id)), location).await {
+            settings::log(&format!("Error send_basket({}, {}, {}): caterer notification postponed", user_id, rest_id, message_id)).await;
+         }
+
+         // Отправим сообщение едоку, уже со статусом заказа
+         if let Err(e) = send_message_for(cx.bot.clone(), from, InfoFor::Eater, &ticket).await {
+            settings::log(&format!("Error send_basket({}, {}, {}), send_messages_for_eater: {}", user_id, rest_id, message_id, e)).await;
+            return false;
+         }
+
+         // Если заведение принимает оплату через бота, выставим счёт
+         if ticket.payment_required {
+            return payment::send_invoice(&cx.bot, &ticket).await;
          }
+         return true;
       }
       None => {
          let s = format!("Error send_basket none info");
@@ -616,3 +1069,28 @@ pub async fn send_basket(cx: &DispatcherHandlerCx<CallbackQuery>, rest_id: i32,
    false
 }
 
+// Периодически напоминает рестораторам о приближении времени предварительных заказов
+pub async fn remind_loop(bot: Arc<Bot>) {
+   let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+   loop {
+      interval.tick().await;
+      let _work = shutdown::work();
+
+      // Заказы, время которых наступит в ближайшее время
+      if let Some(tickets) = db::ticket_list_to_remind(REMIND_BEFORE_MINUTES).await {
+         for ticket in tickets {
+            // Отметим заранее, чтобы при ошибке отправки не напоминать бесконечно
+            if !db::ticket_set_reminded(ticket.ticket_id).await {
+               continue;
+            }
+
+            let lang = lang::lang_of(ticket.caterer_id).await;
+            let eater_name = db::user_name_by_id(lang, ticket.eater_id).await;
+            let s = lang::tf(lang, Reminder, &[&eater_name, &ticket.order_time_label(lang)]);
+            if !outbox::send_critical(&bot, i64::from(ticket.caterer_id), &s, ticket.caterer_reply_to()).await {
+               settings::log(&format!("Error remind_loop({}): postponed", ticket.ticket_id)).await;
+            }
+         }
+      }
+   }
+}
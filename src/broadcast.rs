@@ -0,0 +1,272 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Подписка на новости заведения и рассылка подписчикам. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+use teloxide::{
+   prelude::*,
+   types::{CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, InputFile, ReplyMarkup},
+};
+use chrono::NaiveDateTime;
+use std::{sync::Arc, time::Duration};
+
+use crate::callback;
+use crate::commands as cmd;
+use crate::database as db;
+use crate::language::{self as lang, Res::*};
+use crate::outbox;
+use crate::settings;
+use crate::shutdown;
+
+// Подпись к фото не длиннее 1024 символов, оставляем место для строки с отпиской
+const MAX_TEXT_LEN: usize = 900;
+
+// Сколько подписчиков читать из БД за раз
+const BATCH: i64 = 100;
+
+// Пауза между сообщениями рассылки, чтобы в общей очереди оставалось место для ответов пользователям
+const DELAY: Duration = Duration::from_millis(100);
+
+// Надпись кнопки-переключателя
+fn bell(subscribed: bool) -> &'static str {
+   if subscribed {"🔔"} else {"🔕"}
+}
+
+// Формат даты рассылки
+fn date(time: NaiveDateTime) -> String {
+   time.format("%d.%m.%Y %H:%M").to_string()
+}
+
+// Инлайн-кнопка подписки на заведение с текущим состоянием
+pub async fn rest_button(user_id: i32, rest_num: i32) -> InlineKeyboardButton {
+   let subscribed = db::is_subscribed(user_id, rest_num).await;
+   InlineKeyboardButton::callback(bell(subscribed).to_string(), format!("sub{}", db::make_key_3_int(rest_num, 0, 0)))
+}
+
+// Строка с командой /sub для режима со ссылками
+pub async fn rest_line(lang: &str, user_id: i32, rest_num: i32) -> String {
+   let subscribed = db::is_subscribed(user_id, rest_num).await;
+   lang::t(lang, if subscribed {RestSubscribeOn} else {RestSubscribeOff})
+}
+
+// Ответ пользователю о результате переключения
+pub fn result_text(lang: &str, subscribed: Option<bool>) -> String {
+   match subscribed {
+      Some(true) => lang::t(lang, CallbackSubscribeOn),
+      Some(false) => lang::t(lang, CallbackSubscribeOff),
+      None => db::is_success(lang, false),
+   }
+}
+
+// Переключает подписку по команде /sub
+pub async fn toggle_rest(lang: &str, user_id: i32, rest_num: i32) -> String {
+   result_text(lang, db::subscription_toggle(user_id, rest_num).await)
+}
+
+// Переключает по инлайн-кнопке и меняет её надпись
+pub async fn toggle(cx: &DispatcherHandlerCx<CallbackQuery>, rest_num: i32) -> Option<bool> {
+   let subscribed = db::subscription_toggle(cx.update.from.id, rest_num).await?;
+
+   let data = format!("sub{}", db::make_key_3_int(rest_num, 0, 0));
+   callback::update_button_text(cx, &data, bell(subscribed)).await;
+
+   Some(subscribed)
+}
+
+// Отписка по ссылке или кнопке из рассылки
+pub async fn unsubscribe(lang: &str, user_id: i32, rest_num: i32) -> String {
+   result_text(lang, if db::unsubscribe(user_id, rest_num).await {Some(false)} else {None})
+}
+
+// Подписчики и последняя рассылка для информации о заведении
+pub async fn caterer_info(lang: &str, rest_num: i32) -> String {
+   let s = lang::tf(lang, CatSubscribers, &[&db::subscriber_count(rest_num).await]);
+   match db::broadcast_last(rest_num).await {
+      Some(b) => {
+         let key = if b.finished.is_some() {CatBroadcastLast} else {CatBroadcastRunning};
+         format!("{}\n{}", s, lang::tf(lang, key, &[&date(b.created), &b.delivered, &b.failed]))
+      }
+      None => s,
+   }
+}
+
+// Время, раньше которого заведение не может сделать новую рассылку
+async fn next_allowed(rest_num: i32) -> Option<NaiveDateTime> {
+   // Администратор мог задать для заведения свою частоту
+   let hours = db::rest_broadcast_hours(rest_num).await.unwrap_or_else(settings::broadcast_interval);
+   if hours == 0 {
+      return None;
+   }
+
+   let last = db::broadcast_last(rest_num).await?;
+   let allowed = last.created + chrono::Duration::hours(hours as i64);
+   if allowed > settings::current_date_time() {Some(allowed)} else {None}
+}
+
+// Команда /Broadcast, проверяет ограничения и запрашивает текст рассылки
+pub async fn start(cx: cmd::Cx<i32>) -> cmd::Res {
+   let rest_num = cx.dialogue;
+   let lang = lang::user_lang(cx.update.from()).await;
+
+   let count = db::subscriber_count(rest_num).await;
+   let refusal = if count == 0 {
+      Some(lang::t(lang, CatBroadcastNoSubscribers))
+   } else {
+      next_allowed(rest_num).await.map(|allowed| lang::tf(lang, CatBroadcastTooOften, &[&date(allowed)]))
+   };
+
+   if let Some(s) = refusal {
+      cx.answer(s)
+      .reply_markup(cmd::Caterer::main_menu_markup(lang))
+      .disable_notification(true)
+      .send()
+      .await?;
+
+      // Остаёмся в режиме главного меню ресторатора
+      return next(cmd::Dialogue::CatererMode(rest_num));
+   }
+
+   // Отправляем приглашение ввести строку со слешем в меню для отмены
+   cx.answer(lang::tf(lang, CatBroadcastEnter, &[&count]))
+   .reply_markup(cmd::Caterer::slash_markup())
+   .disable_notification(true)
+   .send()
+   .await?;
+
+   // Переходим в режим ввода рассылки
+   next(cmd::Dialogue::CatBroadcast(rest_num))
+}
+
+// Ввод текста или фото с подписью для рассылки
+pub async fn enter_mode(cx: cmd::Cx<i32>) -> cmd::Res {
+   let rest_num = cx.dialogue;
+   let lang = lang::user_lang(cx.update.from()).await;
+
+   // Текст сообщения либо подпись к фото, для фото берём самый крупный размер
+   let image_id = cx.update.photo().and_then(|sizes| sizes.last()).map(|size| size.file_id.clone());
+   let text = cmd::remove_slash(cx.update.text().or_else(|| cx.update.caption()).unwrap_or_default()).await;
+
+   let s = if text.is_empty() && image_id.is_none() {
+      lang::t(lang, CatBroadcastCancel)
+   } else if text.chars().count() > MAX_TEXT_LEN {
+      // Даём возможность прислать текст покороче
+      cx.answer(lang::tf(lang, CatBroadcastTooLong, &[&MAX_TEXT_LEN]))
+      .reply_markup(cmd::Caterer::slash_markup())
+      .disable_notification(true)
+      .send()
+      .await?;
+      return next(cmd::Dialogue::CatBroadcast(rest_num));
+   } else {
+      let user_id = cx.update.from().unwrap().id;
+      match db::broadcast_add(rest_num, user_id, &text, image_id.as_deref()).await {
+         Some(broadcast_id) => {
+            tokio::spawn(deliver(Arc::clone(&cx.bot), broadcast_id));
+            lang::tf(lang, CatBroadcastStarted, &[&db::subscriber_count(rest_num).await])
+         }
+         None => lang::tf(lang, ErrorCall, &[&format!("broadcast_add({})", rest_num)]),
+      }
+   };
+
+   cx.answer(s)
+   .reply_markup(cmd::Caterer::main_menu_markup(lang))
+   .disable_notification(true)
+   .send()
+   .await?;
+
+   // Возвращаемся в главное меню ресторатора
+   next(cmd::Dialogue::CatererMode(rest_num))
+}
+
+// Отправляет рассылку одному подписчику на его языке
+async fn send(bot: &Arc<Bot>, broadcast: &db::Broadcast, title: &str, user_id: i32) -> bool {
+   let lang = lang::lang_of(user_id).await;
+   let footer = lang::tf(lang, BroadcastFooter, &[&title, &broadcast.rest_num]);
+   let text = if broadcast.text.is_empty() {footer} else {format!("{}\n\n{}", broadcast.text, footer)};
+
+   let button = InlineKeyboardButton::callback(lang::t(lang, BroadcastUnsubscribe), format!("uns{}", db::make_key_3_int(broadcast.rest_num, 0, 0)));
+   let markup = InlineKeyboardMarkup::default().append_row(vec![button]);
+
+   let chat = ChatId::Id(user_id as i64);
+   let res = match &broadcast.image_id {
+      Some(image_id) => {
+         let request = bot.send_photo(chat.clone(), InputFile::file_id(image_id.clone()))
+         .caption(text)
+         .reply_markup(ReplyMarkup::InlineKeyboardMarkup(markup));
+         outbox::send(&chat, &request).await.map(|_| ())
+      }
+      None => {
+         let request = bot.send_message(chat.clone(), text)
+         .reply_markup(markup)
+         .disable_web_page_preview(true);
+         outbox::send(&chat, &request).await.map(|_| ())
+      }
+   };
+
+   match res {
+      Ok(_) => true,
+      Err(e) => {
+         log::warn!("Broadcast {} to {} failed: {}", broadcast.id, user_id, e);
+         false
+      }
+   }
+}
+
+// Рассылает сообщение подписчикам, начиная с места остановки, и сообщает автору итог
+async fn deliver(bot: Arc<Bot>, broadcast_id: i32) {
+   let broadcast = match db::broadcast(broadcast_id).await {
+      Some(broadcast) => broadcast,
+      None => return,
+   };
+   let title = match db::restaurant(db::RestBy::Num(broadcast.rest_num)).await {
+      Some(rest) => rest.title,
+      None => String::default(),
+   };
+
+   let mut cursor = broadcast.cursor;
+   loop {
+      // При ошибке БД рассылка продолжится после перезапуска
+      let list = match db::subscriber_list(broadcast.rest_num, cursor, BATCH).await {
+         Some(list) => list,
+         None => return,
+      };
+      if list.is_empty() {
+         break;
+      }
+
+      for user_id in list {
+         // Остаток разошлём после перезапуска
+         if shutdown::is_requested() {
+            return;
+         }
+
+         {
+            let _work = shutdown::work();
+            let delivered = send(&bot, &broadcast, &title, user_id).await;
+            db::broadcast_progress(broadcast_id, user_id, delivered).await;
+         }
+         cursor = user_id;
+         tokio::time::delay_for(DELAY).await;
+      }
+   }
+
+   // Отчёт автору рассылки
+   db::broadcast_finish(broadcast_id).await;
+   if let Some(b) = db::broadcast(broadcast_id).await {
+      let lang = lang::lang_of(b.user_id).await;
+      let s = lang::tf(lang, CatBroadcastDone, &[&date(b.created), &b.delivered, &b.failed]);
+      outbox::send_critical(&bot, b.user_id as i64, &s, None).await;
+   }
+}
+
+// Продолжает рассылки, прерванные перезапуском бота
+pub async fn resume(bot: Arc<Bot>) {
+   if let Some(list) = db::broadcast_unfinished().await {
+      for broadcast_id in list {
+         tokio::spawn(deliver(Arc::clone(&bot), broadcast_id));
+      }
+   }
+}
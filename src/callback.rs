@@ -10,7 +10,7 @@ Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
 use teloxide::{
    prelude::*, 
    types::{CallbackQuery, ChatOrInlineMessage, ChatId, InlineKeyboardButton,
-      InlineKeyboardMarkup,
+      InlineKeyboardButtonKind, InlineKeyboardMarkup,
    },
 };
 
@@ -22,24 +22,45 @@ use crate::eat_group;
 use crate::eat_group_now;
 use crate::eat_dish;
 use crate::basket;
+use crate::review;
+use crate::favorites;
+use crate::broadcast;
+use crate::notify;
+use crate::group_basket;
+use crate::payment;
 use crate::settings;
+use crate::metrics;
+use crate::language::{self as lang, Res::*};
 
 #[derive(Copy, Clone)]
 enum CallbackCommand {
     Add(i32, i32, i32), // rest_num, group_num, dish_num
     Remove(i32, i32, i32), // rest_num, group_num, dish_num
-    GroupsByRestaurantAndCategory(i32, i32), // rest_num, cat_id
-    ReturnToCategory(i32), // cat_id
+    GroupsByRestaurantAndCategory(i32, i32, i32), // rest_num, cat_id, page
+    ReturnToCategory(i32, i32), // cat_id, page
     Dishes(i32, i32, i32),  // rest_num, group_num, cat_id или 0 для автоопределения
-    ReturnToGroups(i32, i32), // rest_num, cat_id
+    DishesPage(i32, i32, i32),  // rest_num, group_num, page
+    ReturnToGroups(i32, i32, i32), // rest_num, cat_id, page
     Dish(i32, i32, i32),  // rest_num, group_num, dish_num
     ReturnToDishes(i32, i32, i32),  // rest_num, group_num, cat_id или 0 для автоопределения
-    GroupsByRestaurantNow(i32), // rest_num
-    ReturnToRestaurantsNow,
-    SendBasket(i32), // rest_id
+    GroupsByRestaurantNow(i32, i32), // rest_num, page
+    ReturnToRestaurantsNow(i32), // page
+    SendBasket(i32, i32), // rest_id, address_id
    //  BasketMessageToCaterer(i32), // rest_id
     BasketCancel(i32), // ticket_id
     BasketNext(i32), // ticket_id
+    BasketPay(i32), // ticket_id
+    Rate(i32, i32), // ticket_id, rating
+    Favorite(i32, i32, i32), // rest_num, group_num, dish_num
+    FavoritesPage(i32), // page
+    Subscribe(i32), // rest_num
+    Unsubscribe(i32), // rest_num
+    Notify(i32, i32, i32), // rest_num, group_num, dish_num
+    GroupAdd(i32, i32, i32), // rest_num, group_num, dish_num
+    GroupClear(i32), // rest_num
+    GroupPage(i32, i32), // rest_num, page
+    GroupSubmit(i32), // rest_num
+    GroupCancel(i32), // rest_num
     UnknownCommand,
 }
 
@@ -52,18 +73,31 @@ impl CallbackCommand {
             match input.get(..3).unwrap_or_default() {
                "add" => CallbackCommand::Add(first, second, third),
                "del" => CallbackCommand::Remove(first, second, third),
-               "grc" => CallbackCommand::GroupsByRestaurantAndCategory(first, second),
-               "rca" => CallbackCommand::ReturnToCategory(first),
+               "grc" => CallbackCommand::GroupsByRestaurantAndCategory(first, second, third),
+               "rca" => CallbackCommand::ReturnToCategory(first, second),
                "drg" => CallbackCommand::Dishes(first, second, third),
-               "rrg" => CallbackCommand::ReturnToGroups(first, second),
+               "dpg" => CallbackCommand::DishesPage(first, second, third),
+               "rrg" => CallbackCommand::ReturnToGroups(first, second, third),
                "dis" => CallbackCommand::Dish(first, second, third),
                "rrd" => CallbackCommand::ReturnToDishes(first, second, third),
-               "rng" => CallbackCommand::GroupsByRestaurantNow(first),
-               "rno" => CallbackCommand::ReturnToRestaurantsNow,
-               "bas" => CallbackCommand::SendBasket(first),
+               "rng" => CallbackCommand::GroupsByRestaurantNow(first, second),
+               "rno" => CallbackCommand::ReturnToRestaurantsNow(first),
+               "bas" => CallbackCommand::SendBasket(first, second),
                // "bse" => CallbackCommand::BasketMessageToCaterer(first),
                "bca" => CallbackCommand::BasketCancel(first),
                "bne" => CallbackCommand::BasketNext(first),
+               "bpa" => CallbackCommand::BasketPay(first),
+               "rat" => CallbackCommand::Rate(first, second),
+               "fav" => CallbackCommand::Favorite(first, second, third),
+               "fvp" => CallbackCommand::FavoritesPage(first),
+               "sub" => CallbackCommand::Subscribe(first),
+               "uns" => CallbackCommand::Unsubscribe(first),
+               "ntf" => CallbackCommand::Notify(first, second, third),
+               "gad" => CallbackCommand::GroupAdd(first, second, third),
+               "gcl" => CallbackCommand::GroupClear(first),
+               "gpg" => CallbackCommand::GroupPage(first, second),
+               "gsn" => CallbackCommand::GroupSubmit(first),
+               "gcn" => CallbackCommand::GroupCancel(first),
                _ => CallbackCommand::UnknownCommand,
             }
          }
@@ -84,37 +118,52 @@ pub async fn handle_message(cx: DispatcherHandlerCx<CallbackQuery>) {
       Some(data) => {
          // Код едока
          let user_id = query.from.id;
+         let lang = lang::user_lang(Some(&query.from)).await;
 
          // Идентифицируем и исполним команду
          match CallbackCommand::from(&data) {
             CallbackCommand::UnknownCommand => { settings::log(&format!("UnknownCommand {}", &data)).await; format!("UnknownCommand {}", &data)}
-            CallbackCommand::Add(rest_num, group_num, dish_num) => format!("Добавить {}: {}", db::make_key_3_int(rest_num, group_num, dish_num), db::is_success(add_dish(&cx, rest_num, group_num, dish_num, user_id).await)),
-            CallbackCommand::Remove(rest_num, group_num, dish_num) => format!("Удалить {}: {}", db::make_key_3_int(rest_num, group_num, dish_num), db::is_success(remove_dish(&cx, rest_num, group_num, dish_num, user_id).await)),
-            CallbackCommand::GroupsByRestaurantAndCategory(rest_num, cat_id) => 
-               format!("Группы '{}' {}", db::id_to_category(cat_id), db::is_success(eat_group::show_inline_interface(&cx, cat_id, rest_num).await)),
-            CallbackCommand::ReturnToCategory(cat_id) => 
-               format!("Возврат к '{}' {}", db::id_to_category(cat_id), db::is_success(eat_rest::show_inline_interface(&cx, cat_id).await)),
+            CallbackCommand::Add(rest_num, group_num, dish_num) => lang::tf(lang, CallbackAdd, &[&db::make_key_3_int(rest_num, group_num, dish_num), &db::is_success(lang, add_dish(&cx, rest_num, group_num, dish_num, user_id).await)]),
+            CallbackCommand::Remove(rest_num, group_num, dish_num) => lang::tf(lang, CallbackRemove, &[&db::make_key_3_int(rest_num, group_num, dish_num), &db::is_success(lang, remove_dish(&cx, rest_num, group_num, dish_num, user_id).await)]),
+            CallbackCommand::GroupsByRestaurantAndCategory(rest_num, cat_id, page) => 
+               lang::tf(lang, CallbackGroups, &[&db::id_to_category(lang, cat_id), &db::is_success(lang, eat_group::show_inline_interface(&cx, cat_id, rest_num, page).await)]),
+            CallbackCommand::ReturnToCategory(cat_id, page) => 
+               lang::tf(lang, CallbackReturnTo, &[&db::id_to_category(lang, cat_id), &db::is_success(lang, eat_rest::show_inline_interface(&cx, cat_id, page).await)]),
             CallbackCommand::Dishes(rest_num, group_num, cat_id) => 
-               format!("Блюда {}:{} {}", rest_num, group_num, db::is_success(eat_dish::show_inline_interface(&cx, cat_id, rest_num, group_num).await)),
-            CallbackCommand::ReturnToGroups(rest_num, cat_id) => 
-               format!("Группы '{}' {}", db::id_to_category(cat_id), db::is_success(eat_group::show_inline_interface(&cx, cat_id, rest_num).await)),
+               lang::tf(lang, CallbackDishes, &[&rest_num, &group_num, &db::is_success(lang, eat_dish::show_inline_interface(&cx, cat_id, rest_num, group_num, 1).await)]),
+            CallbackCommand::DishesPage(rest_num, group_num, page) => 
+               lang::tf(lang, CallbackDishes, &[&rest_num, &group_num, &db::is_success(lang, eat_dish::show_inline_interface(&cx, 0, rest_num, group_num, page).await)]),
+            CallbackCommand::ReturnToGroups(rest_num, cat_id, page) => 
+               lang::tf(lang, CallbackGroups, &[&db::id_to_category(lang, cat_id), &db::is_success(lang, eat_group::show_inline_interface(&cx, cat_id, rest_num, page).await)]),
             CallbackCommand::Dish(rest_num, group_num, dish_num) =>
-               format!("Блюдо '{}': {}", db::make_key_3_int(rest_num, group_num, dish_num), db::is_success(eat_dish::show_dish(eat_dish::DishMode::CallbackInline(&cx, rest_num, group_num, dish_num)).await.is_ok())),
+               lang::tf(lang, CallbackDish, &[&db::make_key_3_int(rest_num, group_num, dish_num), &db::is_success(lang, eat_dish::show_dish(eat_dish::DishMode::CallbackInline(&cx, rest_num, group_num, dish_num)).await.is_ok())]),
             CallbackCommand::ReturnToDishes(rest_num, group_num, cat_id) =>
-               format!("Блюда {}:{} {}", rest_num, group_num, db::is_success(eat_dish::show_inline_interface(&cx, cat_id, rest_num, group_num).await)),
-            CallbackCommand::GroupsByRestaurantNow(rest_num) => 
-               format!("Работающие: {}", db::is_success(eat_group_now::show_inline_interface(&cx, rest_num).await)),
-            CallbackCommand::ReturnToRestaurantsNow => 
-               format!("Работающие: {}", db::is_success(eat_rest_now::show_inline_interface(&cx).await)),
-            CallbackCommand::SendBasket(rest_id) => {
+               lang::tf(lang, CallbackDishes, &[&rest_num, &group_num, &db::is_success(lang, eat_dish::show_inline_interface(&cx, cat_id, rest_num, group_num, 1).await)]),
+            CallbackCommand::GroupsByRestaurantNow(rest_num, page) => 
+               lang::tf(lang, CallbackOpenNow, &[&db::is_success(lang, eat_group_now::show_inline_interface(&cx, rest_num, page).await)]),
+            CallbackCommand::ReturnToRestaurantsNow(page) => 
+               lang::tf(lang, CallbackOpenNow, &[&db::is_success(lang, eat_rest_now::show_inline_interface(&cx, page).await)]),
+            CallbackCommand::SendBasket(rest_id, address_id) => {
                let res = match query.message.clone() {
-                  Some(message) => basket::send_basket(&cx, rest_id, user_id, message.id).await,
+                  Some(message) => basket::send_basket(&cx, rest_id, user_id, message.id, address_id).await,
                   None => false,
                };
-               format!("Отправка: {}", db::is_success(res))
+               lang::tf(lang, CallbackSend, &[&db::is_success(lang, res)])
             }
-            CallbackCommand::BasketCancel(ticket_id) => format!("{}", db::is_success(cancel_ticket(&cx, user_id, ticket_id).await)),
-            CallbackCommand::BasketNext(ticket_id) => format!("{}", db::is_success(process_ticket(&cx, user_id, ticket_id).await)),
+            CallbackCommand::BasketCancel(ticket_id) => db::is_success(lang, cancel_ticket(&cx, user_id, ticket_id).await),
+            CallbackCommand::BasketNext(ticket_id) => db::is_success(lang, process_ticket(&cx, user_id, ticket_id).await),
+            CallbackCommand::BasketPay(ticket_id) => db::is_success(lang, payment::resend_invoice(&cx.bot, user_id, ticket_id).await),
+            CallbackCommand::Rate(ticket_id, rating) => lang::tf(lang, CallbackRate, &[&rating, &db::is_success(lang, review::rate(&cx, user_id, ticket_id, rating).await)]),
+            CallbackCommand::Favorite(rest_num, group_num, dish_num) => favorites::result_text(lang, favorites::toggle(&cx, rest_num, group_num, dish_num).await),
+            CallbackCommand::FavoritesPage(page) => db::is_success(lang, favorites::page(&cx, page).await),
+            CallbackCommand::Subscribe(rest_num) => broadcast::result_text(lang, broadcast::toggle(&cx, rest_num).await),
+            CallbackCommand::Unsubscribe(rest_num) => broadcast::unsubscribe(lang, user_id, rest_num).await,
+            CallbackCommand::Notify(rest_num, group_num, dish_num) => notify::request(lang, user_id, rest_num, group_num, dish_num).await,
+            CallbackCommand::GroupAdd(rest_num, group_num, dish_num) => group_basket::add(&cx, rest_num, group_num, dish_num).await,
+            CallbackCommand::GroupClear(rest_num) => group_basket::clear(&cx, rest_num).await,
+            CallbackCommand::GroupPage(rest_num, page) => group_basket::page(&cx, rest_num, page).await,
+            CallbackCommand::GroupSubmit(rest_num) => group_basket::submit(&cx, rest_num).await,
+            CallbackCommand::GroupCancel(rest_num) => group_basket::cancel(&cx, rest_num).await,
          }
       }
    };
@@ -124,7 +173,10 @@ pub async fn handle_message(cx: DispatcherHandlerCx<CallbackQuery>) {
       .text(&msg)
       .send()
       .await {
-         Err(_) => log::info!("Error handle_message {}", &msg),
+         Err(_) => {
+            metrics::telegram_error("answer_callback_query");
+            log::info!("Error handle_message {}", &msg)
+         }
          _ => (),
    }
 }
@@ -164,20 +216,50 @@ async fn remove_dish(cx: &DispatcherHandlerCx<CallbackQuery>, rest_num: i32, gro
 }
 
 
+// Меняет надпись кнопки с указанными данными под сообщением, остальные кнопки не трогает
+pub async fn update_button_text(cx: &DispatcherHandlerCx<CallbackQuery>, data: &str, text: &str) {
+   let message = match cx.update.message.as_ref() {
+      Some(message) => message,
+      None => return,
+   };
+
+   if let Some(markup) = message.reply_markup() {
+      let mut markup = markup.clone();
+      for button in markup.inline_keyboard.iter_mut().flatten() {
+         if matches!(&button.kind, InlineKeyboardButtonKind::CallbackData(d) if d == data) {
+            button.text = text.to_string();
+         }
+      }
+
+      let chat_message = ChatOrInlineMessage::Chat {
+         chat_id: ChatId::Id(message.chat_id()),
+         message_id: message.id,
+      };
+      if let Err(e) = cx.bot.edit_message_reply_markup(chat_message)
+      .reply_markup(markup)
+      .send()
+      .await {
+         settings::log(&format!("Error update_button_text {}: {}", data, e)).await;
+      }
+   }
+}
+
 // Обновляет инлайн-клавиатуру для правки количества
 async fn update_keyboard(cx: &DispatcherHandlerCx<CallbackQuery>, rest_num: i32, group_num: i32, dish_num: i32, new_amount: i32) -> bool {
    let message = cx.update.message.as_ref().unwrap();
 
    // Запросим настройку интерфейса для пользователя
    let compact = db::user_compact_interface(Some(&cx.update.from)).await;
+   let lang = lang::user_lang(Some(&cx.update.from)).await;
 
-   // Кнопка назад нужна только в режиме с инлайн-кнопками
+   // Звёздочка избранного всегда, кнопка назад нужна только в режиме с инлайн-кнопками
+   let inline_keyboard = cmd::EaterDish::inline_markup(&db::make_key_3_int(rest_num, group_num, dish_num), new_amount)
+   .append_to_row(favorites::dish_button(cx.update.from.id, rest_num, group_num, dish_num).await, 0);
    let inline_keyboard = if !compact {
-      let button_back = InlineKeyboardButton::callback(String::from("В меню"), format!("rrd{}", db::make_key_3_int(rest_num, group_num, 0)));
-      cmd::EaterDish::inline_markup(&db::make_key_3_int(rest_num, group_num, dish_num), new_amount)
-      .append_to_row(button_back, 0)}
+      let button_back = InlineKeyboardButton::callback(lang::t(lang, EatToMenu), format!("rrd{}", db::make_key_3_int(rest_num, group_num, 0)));
+      inline_keyboard.append_to_row(button_back, 0)}
    else {
-      cmd::EaterDish::inline_markup(&db::make_key_3_int(rest_num, group_num, dish_num), new_amount)
+      inline_keyboard
    };
 
    let chat_message = ChatOrInlineMessage::Chat {
@@ -214,6 +296,7 @@ pub async fn edit_message(cx: &DispatcherHandlerCx<CallbackQuery>, chat_id: Chat
    };
 
    if let Err(e) = res {
+      metrics::telegram_error("edit_message");
       settings::log(&format!("Error callback::message_with_quote: {}", e)).await;
    }
 }
@@ -226,26 +309,32 @@ async fn cancel_ticket(cx: &DispatcherHandlerCx<CallbackQuery>, user_id: i32, ti
       // Информация о тикете
       if let Some(ticket) = db::ticket(db::TicketBy::TicketId(ticket_id)).await {
 
-         // Проверим, что ссылки на сообщения со статусом заказа есть в базе
-         if ticket.eater_status_msg_id.is_none() || ticket.caterer_status_msg_id.is_none() {
-            settings::log(&format!("cancel_ticket({}, {}) status_msg_id is none", user_id, ticket_id)).await;
-            return false;
-         }
+         // Подготовим текст сообщения на языке получателя
+         let text = |lang: &str| {
+            let s = lang::t(lang, if user_id == ticket.caterer_id {TicketCancelledByCaterer} else {TicketCancelledByEater});
 
-         // Подготовим текст сообщения
-         let s = if user_id == ticket.caterer_id {"заведения"} else {"клиента"};
-         let s = format!("Заказ отменён по инициативе {}", s);
+            // Возврат оплаченного через бота заказа выполняется вне бота
+            if ticket.paid {
+               lang::tf(lang, TicketCancelledPaid, &[&s, &settings::admin_contact_info()])
+            } else {s}
+         };
 
          // Отредактируем сообщение у едока
-         let markup = basket::make_markup(&ticket, basket::InfoFor::Eater);
-         edit_message(cx, ChatId::Id(i64::from(ticket.eater_id)), ticket.eater_status_msg_id.unwrap(), &s, markup).await;
+         if let Some(message_id) = ticket.eater_status_msg_id {
+            let lang = lang::lang_of(ticket.eater_id).await;
+            let markup = basket::make_markup(lang, &ticket, basket::InfoFor::Eater);
+            edit_message(cx, ChatId::Id(i64::from(ticket.eater_id)), message_id, &text(lang), markup).await;
+         }
 
-         // Отредактируем сообщение у ресторатора
-         let markup = basket::make_markup(&ticket, basket::InfoFor::Caterer);
-         edit_message(cx, ChatId::Id(i64::from(ticket.caterer_id)), ticket.caterer_status_msg_id.unwrap(), &s, markup).await;
+         // Отредактируем сообщение у ресторатора. Если заказ ему ещё не доставлен, статус придёт вместе с заказом
+         if let Some(message_id) = ticket.caterer_status_msg_id {
+            let lang = lang::lang_of(ticket.caterer_id).await;
+            let markup = basket::make_markup(lang, &ticket, basket::InfoFor::Caterer);
+            edit_message(cx, ChatId::Id(i64::from(ticket.caterer_id)), message_id, &text(lang), markup).await;
+         }
 
          // Два сообщения в служебный чат - об отмене и сам отменённый заказ
-         settings::log(&s).await;
+         settings::log(&text(lang::DEFAULT)).await;
          let eater_chat = ChatId::Id(i64::from(ticket.eater_id));
          settings::log_forward(eater_chat, ticket.eater_order_msg_id).await;
 
@@ -263,23 +352,23 @@ async fn process_ticket(cx: &DispatcherHandlerCx<CallbackQuery>, user_id: i32, t
       // Информация о тикете
       if let Some(ticket) = db::ticket(db::TicketBy::TicketId(ticket_id)).await {
 
-         // Проверим, что ссылки на сообщения со статусом заказа есть в базе
-         if ticket.eater_status_msg_id.is_none() || ticket.caterer_status_msg_id.is_none() {
-            settings::log(&format!("process_ticket({}, {}) status_msg_id is none", user_id, ticket_id)).await;
-            return false;
-         }
-
          // Новый статус заказа
          let status = db::basket_stage(ticket_id).await;
 
          // Отредактируем сообщение у едока
-         let s = format!("Статус заказа изменён на '{}'", db::stage_to_str(status));
-         let markup = basket::make_markup(&ticket, basket::InfoFor::Eater);
-         edit_message(cx, ChatId::Id(i64::from(ticket.eater_id)), ticket.eater_status_msg_id.unwrap(), &s, markup).await;
+         let text = |lang: &str| lang::tf(lang, TicketStatusChanged, &[&db::stage_to_str(lang, status)]);
+         if let Some(message_id) = ticket.eater_status_msg_id {
+            let lang = lang::lang_of(ticket.eater_id).await;
+            let markup = basket::make_markup(lang, &ticket, basket::InfoFor::Eater);
+            edit_message(cx, ChatId::Id(i64::from(ticket.eater_id)), message_id, &text(lang), markup).await;
+         }
 
          // Отредактируем сообщение у ресторатора
-         let markup = basket::make_markup(&ticket, basket::InfoFor::Caterer);
-         edit_message(cx, ChatId::Id(i64::from(ticket.caterer_id)), ticket.caterer_status_msg_id.unwrap(), &s, markup).await;
+         if let Some(message_id) = ticket.caterer_status_msg_id {
+            let lang = lang::lang_of(ticket.caterer_id).await;
+            let markup = basket::make_markup(lang, &ticket, basket::InfoFor::Caterer);
+            edit_message(cx, ChatId::Id(i64::from(ticket.caterer_id)), message_id, &text(lang), markup).await;
+         }
 
          // Если заказ завершён едоком, то дополнительные действия
          if status == 5 {
@@ -288,6 +377,9 @@ async fn process_ticket(cx: &DispatcherHandlerCx<CallbackQuery>, user_id: i32, t
 
             settings::log(&format!("Заказ завершён {}", user_id)).await;
             settings::log_forward(eater_chat, ticket.eater_order_msg_id).await;
+
+            // Предложим едоку оценить заказ
+            review::ask_rating(&cx.bot, &ticket).await;
          }
 
          return true;
@@ -12,6 +12,7 @@ use teloxide::{
     prelude::*, 
     types::{InputFile, ReplyMarkup, InputMedia,},
 };
+use std::sync::Arc;
 
 
 use crate::commands as cmd;
@@ -19,13 +20,16 @@ use crate::database as db;
 use crate::eater;
 use crate::caterer;
 use crate::dish;
+use crate::notify;
 use crate::settings;
-use crate::language as lang;
+use crate::language::{self as lang, Res::*};
 
 
 // Показывает информацию о группе 
 //
 pub async fn next_with_info(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
    // Извлечём параметры
    let (rest_num, group_num) = cx.dialogue;
    
@@ -33,13 +37,12 @@ pub async fn next_with_info(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
    let info = match db::group(rest_num, group_num).await {
       Some(group) => {
          // Сформируем информацию о группе
-         let group_info = String::from(format!("Название: {} /EditTitle\nДоп.инфо: {} /EditInfo\nКатегория: {} /EditCat\nСтатус: {} /Toggle\nВремя: {}-{} /EditTime\nУдалить группу /Remove\nНовое блюдо /AddDish\nСообщение для рекламы /Promote",
-            group.title, group.info, db::id_to_category(group.cat_id), db::active_to_str(group.active), group.opening_time.format("%H:%M"), group.closing_time.format("%H:%M")));
+         let group_info = lang::tf(lang, CatGroupInfo, &[&group.title, &group.info, &db::id_to_category(lang, group.cat_id), &db::active_to_str(lang, group.active), &group.opening_time.format("%H:%M"), &group.closing_time.format("%H:%M")]);
 
          // Получим информацию о блюдах из БД
          let dishes_info = match db::dish_list(db::DishesBy::All(rest_num, group_num)).await {
             None => {
-               String::from(lang::t("ru", lang::Res::CatGroupsEmpty))
+               lang::t(lang, CatGroupsEmpty)
             }
             Some(dishes) => {
                // Сформируем строку вида: Мясо по-французски 120₽ /EdDi2
@@ -50,12 +53,12 @@ pub async fn next_with_info(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
          // Итоговое описание группы с блюдами
          String::from(format!("{}\n{}", group_info, dishes_info))
       },
-      None => String::from(lang::t("ru", lang::Res::CatGroupsEmpty))
+      None => lang::t(lang, CatGroupsEmpty)
    };
 
    // Отображаем информацию о группе и оставляем кнопки главного меню
    cx.answer(format!("\n{}", info))
-   .reply_markup(cmd::Caterer::main_menu_markup())
+   .reply_markup(cmd::Caterer::main_menu_markup(lang))
    .disable_notification(true)
    .send()
    .await?;
@@ -65,8 +68,9 @@ pub async fn next_with_info(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
 }
 
 async fn next_with_cancel(cx: cmd::Cx<(i32, i32)>, text: &str) -> cmd::Res {
+    let lang = lang::user_lang(cx.update.from()).await;
     cx.answer(text)
-    .reply_markup(cmd::Caterer::main_menu_markup())
+    .reply_markup(cmd::Caterer::main_menu_markup(lang))
     .disable_notification(true)
     .send()
     .await?;
@@ -81,6 +85,8 @@ async fn next_with_cancel(cx: cmd::Cx<(i32, i32)>, text: &str) -> cmd::Res {
 
 // Режим редактирования у ресторана rest_id группы group_id
 pub async fn handle_commands(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
      
    // Извлечём параметры
    let (rest_id, group_id) = cx.dialogue;
@@ -89,7 +95,7 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
    match cx.update.text() {
       None => {
          let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
-         next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, (rest_id, group_id)), "Текстовое сообщение, пожалуйста!").await
+         next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, (rest_id, group_id)), &lang::t(lang, TextPlease)).await
       }
       Some(command) => {
          match cmd::CatGroup::from(rest_id, group_id, command) {
@@ -111,7 +117,7 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
             cmd::CatGroup::EditTitle(rest_id, group_id) => {
 
                // Отправляем приглашение ввести строку со слешем в меню для отмены
-               cx.answer(format!("Введите название (/ для отмены)"))
+               cx.answer(lang::t(lang, EnterTitle))
                .reply_markup(cmd::Caterer::slash_markup())
                .disable_notification(true)
                .send()
@@ -125,7 +131,7 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
             cmd::CatGroup::EditInfo(rest_id, group_id) => {
 
                // Отправляем приглашение ввести строку со слешем в меню для отмены
-               cx.answer(format!("Введите пояснения для группы"))
+               cx.answer(lang::t(lang, CatGroupEnterInfo))
                .reply_markup(cmd::Caterer::slash_markup())
                .disable_notification(true)
                .send()
@@ -140,6 +146,9 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
                // Запрос доп.данных не требуется, сразу переключаем активность
                db::rest_group_toggle(rest_id, group_id).await;
 
+               // Возможно, кто-то ждёт открытия группы
+               tokio::spawn(notify::fire(Arc::clone(&cx.bot)));
+
                // Покажем изменённую информацию
                let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
                next_with_info(DialogueDispatcherHandlerCx::new(bot, update, (rest_id, group_id))).await
@@ -149,8 +158,8 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
             cmd::CatGroup::EditCategory(rest_id, group_id) => {
 
                // Отправляем приглашение ввести строку с категориями в меню для выбора
-               cx.answer(format!("Выберите категорию"))
-               .reply_markup(cmd::CatGroup::category_markup())
+               cx.answer(lang::t(lang, CatGroupSelectCategory))
+               .reply_markup(cmd::CatGroup::category_markup(lang))
                .disable_notification(true)
                .send()
                .await?;
@@ -163,7 +172,7 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
             cmd::CatGroup::EditTime(rest_id, group_id) => {
 
                // Отправляем приглашение ввести строку с категориями в меню для выбора
-               cx.answer(format!("Введите время доступности категории"))
+               cx.answer(lang::t(lang, CatGroupEnterTime))
                .reply_markup(cmd::Caterer::slash_markup())
                .disable_notification(true)
                .send()
@@ -181,7 +190,7 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
                   let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
                   caterer::next_with_info(DialogueDispatcherHandlerCx::new(bot, update, rest_id), false).await
                } else {
-                  next_with_cancel(cx, "Ошибка удаления группы, возможно в ней остались блюда (удалите или перенесите сначала их)").await
+                  next_with_cancel(cx, &lang::t(lang, CatGroupRemoveError)).await
                }
             }
 
@@ -189,7 +198,7 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
             cmd::CatGroup::AddDish(rest_id, group_id) => {
 
                // Отправляем приглашение ввести строку со слешем в меню для отмены
-               cx.answer(format!("Введите название блюда (/ для отмены)"))
+               cx.answer(lang::t(lang, CatGroupEnterDish))
                .reply_markup(cmd::Caterer::slash_markup())
                .disable_notification(true)
                .send()
@@ -238,11 +247,11 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
                      };
                      (info, photos)
                   },
-                  None => (String::from(lang::t("ru", lang::Res::CatGroupsEmpty)), None)
+                  None => (lang::t(lang, CatGroupsEmpty), None)
                };
 
                // Добавляем гиперссылку
-               let info = format!("{}\n{}{}", info, settings::link(), db::make_key_3_int(rest_id, group_id, 0));
+               let info = settings::with_link(info, &db::make_key_3_int(rest_id, group_id, 0));
 
                // Отображаем информацию, либо с одной картинкой, либо с группой
                if let Some(mut photo_iter) = photos_opt {
@@ -250,7 +259,7 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
                   match cnt {
                      0 => {
                         cx.answer(info)
-                        .reply_markup(cmd::Caterer::main_menu_markup())
+                        .reply_markup(cmd::Caterer::main_menu_markup(lang))
                         .disable_notification(true)
                         .disable_web_page_preview(true)
                         .send()
@@ -264,7 +273,7 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
                         // Отправляем картинку и текст как комментарий
                         cx.answer_photo(image)
                         .caption(info)
-                        .reply_markup(ReplyMarkup::ReplyKeyboardMarkup(cmd::Caterer::main_menu_markup()))
+                        .reply_markup(ReplyMarkup::ReplyKeyboardMarkup(cmd::Caterer::main_menu_markup(lang)))
                         .disable_notification(true)
                         .send()
                         .await?;
@@ -278,7 +287,7 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
 
                         // Отправляем информацию о ресторане и кнопки
                         cx.answer(info)
-                        .reply_markup(cmd::Caterer::main_menu_markup())
+                        .reply_markup(cmd::Caterer::main_menu_markup(lang))
                         .disable_notification(true)
                         .disable_web_page_preview(true)
                         .send()
@@ -288,7 +297,7 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
                } else {
                   // Если ни одного блюда в группе нет, выведем просто описание группы
                   cx.answer(info)
-                  .reply_markup(cmd::Caterer::main_menu_markup())
+                  .reply_markup(cmd::Caterer::main_menu_markup(lang))
                   .disable_notification(true)
                   .disable_web_page_preview(true)
                   .send()
@@ -302,13 +311,13 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
             // Ошибочная команда
             cmd::CatGroup::UnknownCommand => {
                // Сохраним текущее состояние для возврата
-               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::CatEditGroup(rest_id, group_id), m : cmd::Caterer::main_menu_markup()});
+               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::CatEditGroup(rest_id, group_id), m : cmd::Caterer::main_menu_markup(lang)});
 
                // Возможно это общая команда
                if let Some(res) = eater::handle_common_commands(DialogueDispatcherHandlerCx::new(cx.bot.clone(), cx.update.clone(), ()), command, origin).await {return res;}
                else {
                   let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
-                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, (rest_id, group_id)), "Вы в меню группы: неизвестная команда").await
+                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, (rest_id, group_id)), &lang::t(lang, CatGroupUnknownCommand)).await
                }
             }
          }
@@ -319,6 +328,7 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
 // Изменение названия группы rest_id, group_id
 //
 pub async fn edit_title_mode(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
+    let lang = lang::user_lang(cx.update.from()).await;
     
     if let Some(text) = cx.update.text() {
         // Удалим из строки слеши
@@ -337,17 +347,18 @@ pub async fn edit_title_mode(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
 
         } else {
             // Сообщим об отмене
-            next_with_cancel(cx, "Отмена ввода названия").await
+            next_with_cancel(cx, &lang::t(lang, CancelTitle)).await
         }
     } else {
       // Сообщим об отмене
-      next_with_cancel(cx, "Отмена ввода названия").await
+      next_with_cancel(cx, &lang::t(lang, CancelTitle)).await
     }
 }
 
 // Изменение описания группы rest_id, group_id
 //
 pub async fn edit_info_mode(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
+    let lang = lang::user_lang(cx.update.from()).await;
     if let Some(text) = cx.update.text() {
         // Удалим из строки слеши
         let s = cmd::remove_slash(text).await;
@@ -365,16 +376,17 @@ pub async fn edit_info_mode(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
 
         } else {
             // Сообщим об отмене
-            next_with_cancel(cx, "Отмена ввода описания").await
+            next_with_cancel(cx, &lang::t(lang, CancelInfo)).await
         }
     } else {
       // Сообщим об отмене
-      next_with_cancel(cx, "Отмена ввода описания").await
+      next_with_cancel(cx, &lang::t(lang, CancelInfo)).await
     }
 }
 
 // Изменение категории группы rest_id, group_id
 pub async fn edit_category_mode(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
+    let lang = lang::user_lang(cx.update.from()).await;
     if let Some(text) = cx.update.text() {
         // Попытаемся преобразовать ответ пользователя в код категории
         let cat_id = db::category_to_id(text);
@@ -392,17 +404,18 @@ pub async fn edit_category_mode(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
 
         } else {
             // Сообщим об отмене
-            next_with_cancel(cx, "Неизвестная категория, отмена изменения").await
+            next_with_cancel(cx, &lang::t(lang, UnknownCategoryCancel)).await
         }
     } else {
       // Сообщим об отмене
-      next_with_cancel(cx, "Отмена изменения категории").await
+      next_with_cancel(cx, &lang::t(lang, CatGroupCancelCategory)).await
     }
 }
 
 // Изменение времени доступности группы rest_id, group_id
 //
 pub async fn edit_time_mode(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
+    let lang = lang::user_lang(cx.update.from()).await;
     if let Some(text) = cx.update.text() {
         // Удалим из строки слеши
         let s = cmd::remove_slash(text).await;
@@ -430,14 +443,14 @@ pub async fn edit_time_mode(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
             }
         
             // Сообщим об ошибке
-            next_with_cancel(cx, "Ошибка распознавания, д.б. ЧЧ:ММ-ЧЧ-ММ").await
+            next_with_cancel(cx, &lang::t(lang, CatGroupTimeError)).await
         } else {
             // Сообщим об отмене
-            next_with_cancel(cx, "Отмена ввода времени").await
+            next_with_cancel(cx, &lang::t(lang, CancelTime)).await
         }
     } else {
       // Сообщим об отмене
-      next_with_cancel(cx, "Отмена ввода времени").await
+      next_with_cancel(cx, &lang::t(lang, CancelTime)).await
     }
 }
 
@@ -445,6 +458,7 @@ pub async fn edit_time_mode(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
 // Добавление нового блюда
 //
 pub async fn add_dish_mode(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
+    let lang = lang::user_lang(cx.update.from()).await;
     if let Some(text) = cx.update.text() {
         // Удалим из строки слеши
         let s = cmd::remove_slash(text).await;
@@ -464,15 +478,15 @@ pub async fn add_dish_mode(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
                next_with_info(cx).await
             } else {
                // Сообщим об ошибке
-               next_with_cancel(cx, &format!("Ошибка add_dish_mode({}, {})", rest_id, group_id)).await
+               next_with_cancel(cx, &lang::tf(lang, ErrorCall, &[&format!("add_dish_mode({}, {})", rest_id, group_id)])).await
             }
         } else {
             // Сообщим об отмене
-            next_with_cancel(cx, "Отмена ввода названия").await
+            next_with_cancel(cx, &lang::t(lang, CancelTitle)).await
         }
     } else {
       // Сообщим об отмене
-      next_with_cancel(cx, "Отмена ввода названия").await
+      next_with_cancel(cx, &lang::t(lang, CancelTitle)).await
     }
 }
 
@@ -12,24 +12,30 @@ use teloxide::{
     types::{InputFile, ReplyMarkup, ParseMode,
     },
 };
+use std::sync::Arc;
 
 
 use crate::commands as cmd;
 use crate::database as db;
 use crate::eater;
 use crate::cat_group;
+use crate::broadcast;
+use crate::notify;
 use crate::settings;
+use crate::language::{self as lang, Res::*};
 
 // Показывает информацию о ресторане 
 //
 pub async fn next_with_info(cx: cmd::Cx<i32>, show_welcome: bool) -> cmd::Res {
-   async fn hint(image_id : &Option<String>) -> String {
+   async fn hint(lang: &str, image_id : &Option<String>) -> String {
       match image_id {
          Some(_) => String::default(),
-         _ => String::from("\nИзначально всё заполнено значениями по-умолчанию, отредактируйте их."),
+         _ => lang::t(lang, CatDefaultsHint),
       }
    }
 
+   let lang = lang::user_lang(cx.update.from()).await;
+
    // Номер ресторана
    let rest_num = cx.dialogue;
 
@@ -40,7 +46,7 @@ pub async fn next_with_info(cx: cmd::Cx<i32>, show_welcome: bool) -> cmd::Res {
          // Дополним, при необходимости, приветствием
          let welcome_msg = if show_welcome {
             // Добавляем подсказку только если ещё нет картинки
-            format!("Добро пожаловать в режим ввода меню!{}\nUser Id={}, {}\n", hint(&rest.image_id).await, cx.update.from().unwrap().id, rest_num)
+            lang::tf(lang, CatWelcome, &[&hint(lang, &rest.image_id).await, &cx.update.from().unwrap().id, &rest_num])
          } else {
             String::default()
          };
@@ -50,14 +56,18 @@ pub async fn next_with_info(cx: cmd::Cx<i32>, show_welcome: bool) -> cmd::Res {
             None => String::default(),
             Some(groups) => {
                // Сформируем строку вида "название /ссылка\n"
-               groups.into_iter().map(|group| (format!("   {} /EdGr{}\n", group.title_with_time(rest.opening_time, rest.closing_time), group.num))).collect()
+               groups.into_iter().map(|group| (format!("   {} /EdGr{}\n", group.title_with_time(lang, rest.opening_time, rest.closing_time), group.num))).collect()
             }
          };
 
          // Итоговая информация
-         let info = format!("Название: {} /EditTitle\nОписание: {} /EditInfo\nСтатус: {} /Toggle\nЗагрузить фото /EditImg\nСообщение для рекламы /Promote\nГруппы и время работы (добавить новую /AddGroup):\n{}",
-            rest.title, rest.info, db::active_to_str(rest.active), groups_desc);
-         let info = format!("{}{}", welcome_msg, info);
+         let info = lang::tf(lang, CatInfo, &[&rest.title, &rest.info, &db::active_to_str(lang, rest.active), &groups_desc]);
+         let location = match rest.location {
+            Some((latitude, longitude)) => lang::tf(lang, CatLocation, &[&format!("{:.5}, {:.5}", latitude, longitude)]),
+            None => lang::t(lang, CatNoLocation),
+         };
+         let time_zone = lang::tf(lang, CatTimeZone, &[&settings::time_zone(rest.time_zone.as_deref()).name()]);
+         let info = format!("{}{}\n{}\n{}\n{}", welcome_msg, info, location, time_zone, broadcast::caterer_info(lang, rest_num).await);
 
          // Отправляем описание пользователю, если есть картинка, то отправим описание как комментарий к ней
          if let Some(image_id) = rest.image_id {
@@ -67,21 +77,24 @@ pub async fn next_with_info(cx: cmd::Cx<i32>, show_welcome: bool) -> cmd::Res {
             // Отправляем картинку и текст как комментарий
             cx.answer_photo(image)
             .caption(info)
-            .reply_markup(ReplyMarkup::ReplyKeyboardMarkup(cmd::Caterer::main_menu_markup()))
+            .reply_markup(ReplyMarkup::ReplyKeyboardMarkup(cmd::Caterer::main_menu_markup(lang)))
             .disable_notification(true)
             .send()
             .await?;
          } else {
             cx.answer(info)
-            .reply_markup(cmd::Caterer::main_menu_markup())
+            .reply_markup(cmd::Caterer::main_menu_markup(lang))
             .disable_notification(true)
             .send()
             .await?;
          }
       }
       None => {
-         let s = format!("Ошибка caterer::next_with_info({}) none info", rest_num);
-         settings::log(&s).await;
+         settings::log(&format!("Error caterer::next_with_info({}) none info", rest_num)).await;
+         cx.answer(lang::tf(lang, ErrorCall, &[&format!("next_with_info({})", rest_num)]))
+         .reply_markup(cmd::Caterer::main_menu_markup(lang))
+         .send()
+         .await?;
       }
    }
 
@@ -90,8 +103,9 @@ pub async fn next_with_info(cx: cmd::Cx<i32>, show_welcome: bool) -> cmd::Res {
 }
 
 async fn next_with_cancel(cx: cmd::Cx<i32>, text: &str) -> cmd::Res {
+    let lang = lang::user_lang(cx.update.from()).await;
     cx.answer(text)
-    .reply_markup(cmd::Caterer::main_menu_markup())
+    .reply_markup(cmd::Caterer::main_menu_markup(lang))
     .disable_notification(true)
     .send()
     .await?;
@@ -108,12 +122,19 @@ async fn next_with_cancel(cx: cmd::Cx<i32>, text: &str) -> cmd::Res {
 pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
    // Код ресторана
    let rest_id = cx.dialogue;
+   let lang = lang::user_lang(cx.update.from()).await;
 
    // Разбираем команду.
    match cx.update.text() {
       None => {
          let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
-         next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, rest_id), "Текстовое сообщение, пожалуйста!").await
+
+         // Геопозиция заведения для поиска поблизости
+         if let Some(location) = update.location() {
+            db::rest_edit_location(rest_id, location.latitude, location.longitude).await;
+            return next_with_info(DialogueDispatcherHandlerCx::new(bot, update, rest_id), false).await;
+         }
+         next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, rest_id), &lang::t(lang, TextPlease)).await
       }
       Some(command) => {
          match cmd::Caterer::from(rest_id, command) {
@@ -135,19 +156,19 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
             cmd::Caterer::TransferOwnership(rest_id, user_id) => {
                // Проверим права
                if settings::is_admin(cx.update.from()) {
-                  let res = db::is_success(db::transfer_ownership(rest_id, user_id).await);
+                  let res = db::is_success(lang, db::transfer_ownership(rest_id, user_id).await);
                   let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
-                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, rest_id), &format!("Передача управления новому ресторатору {}: {}", user_id, res)).await
+                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, rest_id), &lang::tf(lang, CatTransfer, &[&user_id, &res])).await
                } else {
                   let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
-                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, rest_id), "Недостаточно прав").await
+                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, rest_id), &lang::t(lang, GearNoRights)).await
                }
             }
 
             // Изменение названия ресторана
             cmd::Caterer::EditTitle(rest_id) => {
                // Отправляем приглашение ввести строку со слешем в меню для отмены
-               cx.answer(format!("Введите название (/ для отмены)"))
+               cx.answer(lang::t(lang, EnterTitle))
                .reply_markup(cmd::Caterer::slash_markup())
                .disable_notification(true)
                .send()
@@ -160,7 +181,7 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
             // Изменение информации о ресторане
             cmd::Caterer::EditInfo(rest_id) => {
                // Отправляем приглашение ввести строку со слешем в меню для отмены
-               cx.answer(format!("Введите описание (адрес, контакты)"))
+               cx.answer(lang::t(lang, CatEnterRestInfo))
                .reply_markup(cmd::Caterer::slash_markup())
                .disable_notification(true)
                .send()
@@ -175,6 +196,9 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
                // Запрос доп.данных не требуется, сразу переключаем активность
                db::rest_toggle(rest_id).await;
 
+               // Возможно, кто-то ждёт открытия заведения
+               tokio::spawn(notify::fire(Arc::clone(&cx.bot)));
+
                // Покажем изменённую информацию
                let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
                next_with_info(DialogueDispatcherHandlerCx::new(bot, update, rest_id), false).await
@@ -184,8 +208,8 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
             cmd::Caterer::EditImage(rest_id) => {
 
                // Отправляем приглашение ввести строку с категориями в меню для выбора
-               cx.answer(format!("Загрузите картинку"))
-               .reply_markup(cmd::Caterer::main_menu_markup())
+               cx.answer(lang::t(lang, CatUploadImage))
+               .reply_markup(cmd::Caterer::main_menu_markup(lang))
                .disable_notification(true)
                .send()
                .await?;
@@ -199,10 +223,10 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
                // Информация о ресторане
                if let Some(rest) = db::restaurant(db::RestBy::Num(rest_num)).await {
                   // Сформируем информацию о ресторане
-                  let info = format!("<b>{}</b>\n{}\nОсновное время работы: {}-{}", rest.title, rest.info, db::str_time(rest.opening_time), db::str_time(rest.closing_time));
+                  let info = lang::tf(lang, CatPromoteRest, &[&rest.title, &rest.info, &db::str_time(rest.opening_time), &db::str_time(rest.closing_time)]);
 
                   // Добавляем гиперссылку
-                  let info = format!("{}\n{}{}", info, settings::link(), db::make_key_3_int(rest_id, 0, 0));
+                  let info = settings::with_link(info, &db::make_key_3_int(rest_id, 0, 0));
 
                   // Отображаем информацию, либо с картинкой, либо только текст
                   if let Some(image_id) = rest.image_id {
@@ -212,14 +236,14 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
                      // Отправляем картинку и текст как комментарий
                      cx.answer_photo(image)
                      .caption(info)
-                     .reply_markup(ReplyMarkup::ReplyKeyboardMarkup(cmd::Caterer::main_menu_markup()))
+                     .reply_markup(ReplyMarkup::ReplyKeyboardMarkup(cmd::Caterer::main_menu_markup(lang)))
                      .parse_mode(ParseMode::HTML)
                      .disable_notification(true)
                      .send()
                      .await?;
                   } else {
                      cx.answer(info)
-                     .reply_markup(cmd::Caterer::main_menu_markup())
+                     .reply_markup(cmd::Caterer::main_menu_markup(lang))
                      .parse_mode(ParseMode::HTML)
                      .disable_notification(true)
                      .disable_web_page_preview(true)
@@ -232,6 +256,12 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
                next(cmd::Dialogue::CatererMode(rest_id))
             }
 
+            // Рассылка подписчикам
+            cmd::Caterer::Broadcast(rest_id) => {
+               let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
+               broadcast::start(DialogueDispatcherHandlerCx::new(bot, update, rest_id)).await
+            }
+
             // Команда редактирования групп ресторана
             cmd::Caterer::EditGroup(rest_id, group_id) => {
                // Отображаем информацию о группе и переходим в режим её редактирования
@@ -243,7 +273,7 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
             cmd::Caterer::AddGroup(rest_id) => {
 
                // Отправляем приглашение ввести строку со слешем в меню для отмены
-               cx.answer(format!("Введите название (/ для отмены)"))
+               cx.answer(lang::t(lang, EnterTitle))
                .reply_markup(cmd::Caterer::slash_markup())
                .disable_notification(true)
                .send()
@@ -255,14 +285,14 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
 
             cmd::Caterer::UnknownCommand => {
                // Сохраним текущее состояние для возврата
-               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::CatererMode(rest_id), m : cmd::Caterer::main_menu_markup()});
+               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::CatererMode(rest_id), m : cmd::Caterer::main_menu_markup(lang)});
 
                // Возможно это общая команда
                if let Some(res) = eater::handle_common_commands(DialogueDispatcherHandlerCx::new(cx.bot.clone(), cx.update.clone(), ()), command, origin).await {return res;}
                else {
                   let s = String::from(command);
                   let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
-                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, rest_id), &format!("Вы в меню ресторатора: неизвестная команда '{}'", s)).await
+                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, rest_id), &lang::tf(lang, CatUnknownCommand, &[&s])).await
                }
             }
          }
@@ -275,6 +305,7 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
 pub async fn edit_rest_title_mode(cx: cmd::Cx<i32>) -> cmd::Res {
    // Код ресторана
    let rest_id = cx.dialogue;
+   let lang = lang::user_lang(cx.update.from()).await;
         
    if let Some(text) = cx.update.text() {
       // Удалим из строки слеши
@@ -288,11 +319,11 @@ pub async fn edit_rest_title_mode(cx: cmd::Cx<i32>) -> cmd::Res {
             next_with_info(cx, false).await
          } else {
             // Сообщим об ошибке
-            next_with_cancel(cx, &format!("Ошибка rest_edit_title({})", rest_id)).await
+            next_with_cancel(cx, &lang::tf(lang, ErrorCall, &[&format!("rest_edit_title({})", rest_id)])).await
          }
       } else {
          // Сообщим об отмене
-         next_with_cancel(cx, "Отмена ввода названия").await
+         next_with_cancel(cx, &lang::t(lang, CancelTitle)).await
       }
    } else {
       next(cmd::Dialogue::CatererMode(rest_id))
@@ -304,6 +335,7 @@ pub async fn edit_rest_title_mode(cx: cmd::Cx<i32>) -> cmd::Res {
 pub async fn edit_rest_info_mode(cx: cmd::Cx<i32>) -> cmd::Res {
    // Код ресторана
    let rest_id = cx.dialogue;
+   let lang = lang::user_lang(cx.update.from()).await;
 
    if let Some(text) = cx.update.text() {
       // Удалим из строки слеши
@@ -317,11 +349,11 @@ pub async fn edit_rest_info_mode(cx: cmd::Cx<i32>) -> cmd::Res {
             next_with_info(cx, false).await
          } else {
             // Сообщим об ошибке
-            next_with_cancel(cx, &format!("Ошибка edit_rest_info_mode({})", rest_id)).await
+            next_with_cancel(cx, &lang::tf(lang, ErrorCall, &[&format!("edit_rest_info_mode({})", rest_id)])).await
          }
       } else {
          // Сообщим об отмене
-         next_with_cancel(cx, "Отмена ввода описания").await
+         next_with_cancel(cx, &lang::t(lang, CancelInfo)).await
       }
    } else {
       next(cmd::Dialogue::CatererMode(rest_id))
@@ -350,6 +382,7 @@ pub async fn edit_rest_image_mode(cx: cmd::Cx<i32>) -> cmd::Res {
 pub async fn add_rest_group(cx: cmd::Cx<i32>) -> cmd::Res {
    // Код ресторана
    let rest_id = cx.dialogue;
+   let lang = lang::user_lang(cx.update.from()).await;
 
    if let Some(text) = cx.update.text() {
       // Удалим из строки слеши
@@ -363,11 +396,11 @@ pub async fn add_rest_group(cx: cmd::Cx<i32>) -> cmd::Res {
             next_with_info(cx, false).await
          } else {
             // Сообщим об ошибке
-            next_with_cancel(cx, &format!("Ошибка rest_add_group({})", rest_id)).await
+            next_with_cancel(cx, &lang::tf(lang, ErrorCall, &[&format!("rest_add_group({})", rest_id)])).await
          }
       } else {
          // Сообщим об отмене
-         next_with_cancel(cx, "Отмена добавления группы").await
+         next_with_cancel(cx, &lang::t(lang, CatCancelAddGroup)).await
       }
    } else {
       next(cmd::Dialogue::CatererMode(rest_id))
@@ -10,12 +10,15 @@ Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
 use teloxide::{
    prelude::*, 
    types::{KeyboardButton, ReplyKeyboardMarkup, InlineKeyboardMarkup, 
-      InlineKeyboardButton, ReplyMarkup, InputFile, ButtonRequest, 
+      InlineKeyboardButton, ReplyMarkup, InputFile, ButtonRequest, ChatId, 
    },
 };
 
 use crate::database as db;
 use crate::settings;
+use crate::outbox;
+use crate::language::{self as lang, Res::*};
+use crate::registry::{self, Menu, Value};
 
 // ============================================================================
 // [Common]
@@ -37,6 +40,7 @@ pub enum Dialogue {
    CatEditRestImage(i32), // rest_id
    CatEditGroup(i32, i32), // rest_id, group_id
    CatAddGroup(i32), // rest_id
+   CatBroadcast(i32), // rest_id
    CatEditGroupTitle(i32, i32), // rest_id, group_id (cat_group)
    CatEditGroupInfo(i32, i32), // rest_id, group_id (cat_group)
    CatEditGroupCategory(i32, i32), // rest_id, group_id (cat_group)
@@ -52,10 +56,60 @@ pub enum Dialogue {
    BasketEditName(i32), // user_id
    BasketEditContact(i32), // user_id
    BasketEditAddress(i32), // user_id
+   BasketEditAddressLabel(i32, i32), // user_id, address_id
+   BasketEditTime(i32), // user_id
+   BasketEditPromo(i32), // user_id
    MessageToCaterer(i32, i32, Box<DialogueState>), // user_id, caterer_id, previous mode
+   ReviewComment(i32, i32, Box<DialogueState>), // user_id, review_id, previous mode
+   ReviewReply(i32, i32, Box<DialogueState>), // user_id, review_id, previous mode
    GearMode,
 }
 
+impl Dialogue {
+   // Название состояния для метрик
+   pub fn name(&self) -> &'static str {
+      match self {
+         Dialogue::Start => "Start",
+         Dialogue::UserMode => "UserMode",
+         Dialogue::UserModeEditCatImage(..) => "UserModeEditCatImage",
+         Dialogue::EatRestSelectionMode(..) => "EatRestSelectionMode",
+         Dialogue::EatRestGroupSelectionMode(..) => "EatRestGroupSelectionMode",
+         Dialogue::EatRestGroupDishSelectionMode(..) => "EatRestGroupDishSelectionMode",
+         Dialogue::EatRestNowSelectionMode => "EatRestNowSelectionMode",
+         Dialogue::EatRestGroupNowSelectionMode(..) => "EatRestGroupNowSelectionMode",
+         Dialogue::CatererMode(..) => "CatererMode",
+         Dialogue::CatEditRestTitle(..) => "CatEditRestTitle",
+         Dialogue::CatEditRestInfo(..) => "CatEditRestInfo",
+         Dialogue::CatEditRestImage(..) => "CatEditRestImage",
+         Dialogue::CatEditGroup(..) => "CatEditGroup",
+         Dialogue::CatAddGroup(..) => "CatAddGroup",
+         Dialogue::CatBroadcast(..) => "CatBroadcast",
+         Dialogue::CatEditGroupTitle(..) => "CatEditGroupTitle",
+         Dialogue::CatEditGroupInfo(..) => "CatEditGroupInfo",
+         Dialogue::CatEditGroupCategory(..) => "CatEditGroupCategory",
+         Dialogue::CatEditGroupTime(..) => "CatEditGroupTime",
+         Dialogue::CatAddDish(..) => "CatAddDish",
+         Dialogue::CatEditDish(..) => "CatEditDish",
+         Dialogue::CatEditDishTitle(..) => "CatEditDishTitle",
+         Dialogue::CatEditDishInfo(..) => "CatEditDishInfo",
+         Dialogue::CatEditDishGroup(..) => "CatEditDishGroup",
+         Dialogue::CatEditDishPrice(..) => "CatEditDishPrice",
+         Dialogue::CatEditDishImage(..) => "CatEditDishImage",
+         Dialogue::BasketMode(..) => "BasketMode",
+         Dialogue::BasketEditName(..) => "BasketEditName",
+         Dialogue::BasketEditContact(..) => "BasketEditContact",
+         Dialogue::BasketEditAddress(..) => "BasketEditAddress",
+         Dialogue::BasketEditAddressLabel(..) => "BasketEditAddressLabel",
+         Dialogue::BasketEditTime(..) => "BasketEditTime",
+         Dialogue::BasketEditPromo(..) => "BasketEditPromo",
+         Dialogue::MessageToCaterer(..) => "MessageToCaterer",
+         Dialogue::ReviewComment(..) => "ReviewComment",
+         Dialogue::ReviewReply(..) => "ReviewReply",
+         Dialogue::GearMode => "GearMode",
+      }
+   }
+}
+
 pub type Cx<State> = DialogueDispatcherHandlerCx<Message, State>;
 pub type Res = ResponseResult<DialogueStage<Dialogue>>;
 
@@ -74,28 +128,31 @@ pub enum Common {
    StartArgs(i32, i32, i32), // rest_num, group_num, dish_num
    SendMessage(i32), // caterer_id
    Goto(i32, i32, i32),   // rest_num, group_num, dish_num
+   Reviews(i32),  // rest_num
+   ReviewComment(i32), // review_id
+   ReviewReply(i32), // review_id
+   ReviewHide(i32), // review_id
+   Unsubscribe(i32), // rest_num
+   Notify(i32, i32, i32), // rest_num, group_num, dish_num
+   Help,
    UnknownCommand,
 }
 
 impl Common {
    pub fn from(input: &str) -> Common {
-      match input {
-         "/start" => Common::Start,
-         _ => {
-            // Ищем среди команд с аргументами
-            if input.get(..4).unwrap_or_default() == "/snd" {
-               let r_part = input.get(4..).unwrap_or_default();
-               Common::SendMessage(r_part.parse().unwrap_or_default())
-            } else if input.get(..7).unwrap_or_default() == "/start " {
-               let r_part = input.get(7..).unwrap_or_default();
-               if let Ok((first, second, third)) = db::parse_key_3_int(r_part) {Common::StartArgs(first, second, third)}
-               else {Common::UnknownCommand}
-            } else if input.get(..5).unwrap_or_default() == "/goto" {
-               let r_part = input.get(5..).unwrap_or_default();
-               if let Ok((first, second, third)) = db::parse_key_3_int(r_part) {Common::Goto(first, second, third)}
-               else {Common::UnknownCommand}
-            } else {Common::UnknownCommand}
-         }
+      match registry::parse(Menu::Common, input) {
+         Ok(("/start", Value::Key3(first, second, third))) => Common::StartArgs(first, second, third),
+         Ok(("/start", _)) => Common::Start,
+         Ok(("/help", _)) => Common::Help,
+         Ok(("/goto", Value::Key3(first, second, third))) => Common::Goto(first, second, third),
+         Ok(("/snd", Value::Num(caterer_id))) => Common::SendMessage(caterer_id),
+         Ok(("/rvl", Value::Num(id))) => Common::Reviews(id),
+         Ok(("/rvc", Value::Num(id))) => Common::ReviewComment(id),
+         Ok(("/rvr", Value::Num(id))) => Common::ReviewReply(id),
+         Ok(("/rvh", Value::Num(id))) => Common::ReviewHide(id),
+         Ok(("/unsub", Value::Num(rest_num))) => Common::Unsubscribe(rest_num),
+         Ok(("/ntf", Value::Key3(first, second, third))) => Common::Notify(first, second, third),
+         _ => Common::UnknownCommand,
       }
    }
 }
@@ -110,6 +167,8 @@ pub enum User {
     Category(i32),   // cat_id 
     OpenedNow,
     Basket,
+    Favorites(i32), // номер страницы
+    NearMe,
     UnknownCommand,
     ChatId,
     Gear,
@@ -117,42 +176,72 @@ pub enum User {
 
 impl User {
    pub fn from(input: &str) -> User {
-      match input {
-         // Сначала проверим на цельные команды.
-         "Соки воды" => User::Category(1),
-         "Еда" => User::Category(2),
-         "Напитки" => User::Category(3),
-         "Развлечения" => User::Category(4),
-         "Сейчас" => User::OpenedNow,
-         "🛒Корзина" => User::Basket,
-         "⚙" => User::Gear,
+      // Сначала проверим на цельные команды на любом из языков.
+      match (input, lang::button(input)) {
+         (_, Some(BtnJuice)) => User::Category(1),
+         (_, Some(BtnFood)) => User::Category(2),
+         (_, Some(BtnDrinks)) => User::Category(3),
+         (_, Some(BtnFun)) => User::Category(4),
+         (_, Some(BtnNow)) => User::OpenedNow,
+         (_, Some(BtnBasket)) => User::Basket,
+         (_, Some(BtnFavorites)) => User::Favorites(1),
+         (_, Some(BtnNearMe)) => User::NearMe,
+         ("⚙", _) => User::Gear,
          _ => {
-            // Ищем среди команд с цифровыми суффиксами - аргументами
-            match input.get(..5).unwrap_or_default() {
-               "/chat" => User::ChatId, // правее может быть имя бота, игнорируем это.
+            // Ищем среди команд меню, правее /chat может быть имя бота
+            match registry::parse(Menu::User, input) {
+               Ok(("/chat", _)) => User::ChatId,
+               Ok(("/favs", Value::Num(page))) => User::Favorites(page),
+               Ok(("/favs", _)) => User::Favorites(1),
                _ => User::UnknownCommand,
             }
          }
       }
    }
 
-    pub fn main_menu_markup() -> ReplyKeyboardMarkup {
+    pub fn main_menu_markup(lang: &str) -> ReplyKeyboardMarkup {
       ReplyKeyboardMarkup::default()
          .append_row(vec![
-            // KeyboardButton::new("Соки воды"),
-            KeyboardButton::new("Еда"),
-            KeyboardButton::new("Напитки"),
-            KeyboardButton::new("Развлечения"),
+            // KeyboardButton::new(lang::t(lang, BtnJuice)),
+            KeyboardButton::new(lang::t(lang, BtnFood)),
+            KeyboardButton::new(lang::t(lang, BtnDrinks)),
+            KeyboardButton::new(lang::t(lang, BtnFun)),
+            KeyboardButton::new(lang::t(lang, BtnNearMe))
+            .request(ButtonRequest::Location),
          ])
          .append_row(vec![
-            KeyboardButton::new("🛒Корзина"),
-            KeyboardButton::new("Сейчас"),
+            KeyboardButton::new(lang::t(lang, BtnBasket)),
+            KeyboardButton::new(lang::t(lang, BtnNow)),
+            KeyboardButton::new(lang::t(lang, BtnFavorites)),
             KeyboardButton::new("⚙"),
          ])
          .resize_keyboard(true)
    }
 }
 
+// ============================================================================
+// [Group chat]
+// ============================================================================
+#[derive(Copy, Clone, PartialEq)]
+pub enum GroupChat {
+   ChatId,
+   Lunch(Option<i32>), // rest_num, без номера список заведений
+   UnknownCommand,
+}
+
+impl GroupChat {
+   pub fn from(input: &str) -> GroupChat {
+      // В группах к команде добавляется имя бота через @
+      let input = input.split('@').next().unwrap_or_default();
+      match registry::parse(Menu::Chat, input) {
+         Ok(("/chat", _)) => GroupChat::ChatId,
+         Ok(("/lunch", Value::Num(rest_num))) => GroupChat::Lunch(Some(rest_num)),
+         Ok(("/lunch", _)) => GroupChat::Lunch(None),
+         _ => GroupChat::UnknownCommand,
+      }
+   }
+}
+
 // ============================================================================
 // [Restaurant owner main menu]
 // ============================================================================
@@ -182,37 +271,40 @@ pub enum Caterer {
    TransferOwnership(i32, i32), // rest_id, user_id
    // Рекламировать
    Promote(i32), // rest_id
+   // Рассылка подписчикам
+   Broadcast(i32), // rest_id
 }
 
 impl Caterer {
 
    pub fn from(rest_id: i32, input: &str) -> Caterer {
-      match input {
+      match (input, lang::button(input)) {
          // Сначала проверим на цельные команды.
-         "Главная" => Caterer::Main(rest_id),
-         "Выход" => Caterer::Exit,
-         "/EditTitle" => Caterer::EditTitle(rest_id),
-         "/EditInfo" => Caterer::EditInfo(rest_id),
-         "/Toggle" => Caterer::TogglePause(rest_id),
-         "/EditImg" => Caterer::EditImage(rest_id),
-         "/AddGroup" => Caterer::AddGroup(rest_id),
-         "/Promote" => Caterer::Promote(rest_id),
+         (_, Some(BtnMain)) => Caterer::Main(rest_id),
+         (_, Some(BtnExit)) => Caterer::Exit,
          _ => {
-               // Ищем среди команд с цифровыми суффиксами - аргументами
-               match input.get(..5).unwrap_or_default() {
-                  "/EdGr" => Caterer::EditGroup(rest_id, input.get(5..).unwrap_or_default().parse().unwrap_or_default()),
-                  "/move" => Caterer::TransferOwnership(rest_id, input.get(5..).unwrap_or_default().parse().unwrap_or_default()),
-                  _ => Caterer::UnknownCommand,
-               }
+            // Ищем среди команд меню
+            match registry::parse(Menu::Caterer, input) {
+               Ok(("/EditTitle", _)) => Caterer::EditTitle(rest_id),
+               Ok(("/EditInfo", _)) => Caterer::EditInfo(rest_id),
+               Ok(("/Toggle", _)) => Caterer::TogglePause(rest_id),
+               Ok(("/EditImg", _)) => Caterer::EditImage(rest_id),
+               Ok(("/AddGroup", _)) => Caterer::AddGroup(rest_id),
+               Ok(("/Promote", _)) => Caterer::Promote(rest_id),
+               Ok(("/Broadcast", _)) => Caterer::Broadcast(rest_id),
+               Ok(("/EdGr", Value::Num(group_id))) => Caterer::EditGroup(rest_id, group_id),
+               Ok(("/move", Value::Num(user_id))) => Caterer::TransferOwnership(rest_id, user_id),
+               _ => Caterer::UnknownCommand,
+            }
          }
       }
    }
 
-   pub fn main_menu_markup() -> ReplyKeyboardMarkup {
+   pub fn main_menu_markup(lang: &str) -> ReplyKeyboardMarkup {
       ReplyKeyboardMarkup::default()
          .append_row(vec![
-               KeyboardButton::new("Главная"),
-               KeyboardButton::new("Выход"),
+               KeyboardButton::new(lang::t(lang, BtnMain)),
+               KeyboardButton::new(lang::t(lang, BtnExit)),
          ])
          .resize_keyboard(true)
          //.one_time_keyboard(true)
@@ -244,12 +336,11 @@ pub async fn send_text<T>(cx: &Cx<()>, text: &str, markup: T)
 where
    T: Into<ReplyMarkup>,
 {
-   let res = cx.answer(text)
+   let request = cx.answer(text)
    .reply_markup(markup)
    .disable_notification(true)
-   .disable_web_page_preview(true)
-   .send()
-   .await;
+   .disable_web_page_preview(true);
+   let res = outbox::send(&ChatId::Id(cx.chat_id()), &request).await;
 
    // Если не удалось отправить, выведем ошибку в лог
    if let Err(err) = res {
@@ -260,11 +351,10 @@ where
 // Отправляет текстовое сообщение
 pub async fn send_text_without_markup(cx: &Cx<()>, text: &str) 
 {
-   let res = cx.answer(text)
+   let request = cx.answer(text)
    .disable_notification(true)
-   .disable_web_page_preview(true)
-   .send()
-   .await;
+   .disable_web_page_preview(true);
+   let res = outbox::send(&ChatId::Id(cx.chat_id()), &request).await;
 
    // Если не удалось отправить, выведем ошибку в лог
    if let Err(err) = res {
@@ -277,12 +367,11 @@ pub async fn send_text_without_markup(cx: &Cx<()>, text: &str)
 pub async fn send_photo(cx: &Cx<()>, text: &str, markup: ReplyMarkup, image_id : String) 
 {
    // Отправляем картинку и текст как комментарий
-   let res = cx.answer_photo(InputFile::file_id(image_id))
+   let request = cx.answer_photo(InputFile::file_id(image_id))
       .caption(text)
       .reply_markup(markup)
-      .disable_notification(true)
-      .send()
-      .await;
+      .disable_notification(true);
+   let res = outbox::send(&ChatId::Id(cx.chat_id()), &request).await;
 
    // Если не удалось отправить, выведем ошибку в лог
    if let Err(err) = res {
@@ -323,35 +412,35 @@ pub enum CatGroup {
 impl CatGroup {
 
     pub fn from(rest_id: i32, group_id: i32, input: &str) -> CatGroup {
-        match input {
+        match (input, lang::button(input)) {
             // Сначала проверим на цельные команды.
-            "Главная" => CatGroup::Main(rest_id),
-            "Выход" => CatGroup::Exit,
-            "/EditTitle" => CatGroup::EditTitle(rest_id, group_id),
-            "/EditInfo" => CatGroup::EditInfo(rest_id, group_id),
-            "/Toggle" => CatGroup::TogglePause(rest_id, group_id),
-            "/EditCat" => CatGroup::EditCategory(rest_id, group_id),
-            "/EditTime" => CatGroup::EditTime(rest_id, group_id),
-            "/Remove" => CatGroup::RemoveGroup(rest_id, group_id),
-            "/AddDish" => CatGroup::AddDish(rest_id, group_id),
-            "/Promote" => CatGroup::Promote(rest_id, group_id),
+            (_, Some(BtnMain)) => CatGroup::Main(rest_id),
+            (_, Some(BtnExit)) => CatGroup::Exit,
             _ => {
-                // Ищем среди команд с цифровыми суффиксами - аргументами
-                match input.get(..5).unwrap_or_default() {
-                    "/EdDi" => CatGroup::EditDish(rest_id, group_id, input.get(5..).unwrap_or_default().parse().unwrap_or_default()),
+                // Ищем среди команд меню
+                match registry::parse(Menu::CatGroup, input) {
+                    Ok(("/EditTitle", _)) => CatGroup::EditTitle(rest_id, group_id),
+                    Ok(("/EditInfo", _)) => CatGroup::EditInfo(rest_id, group_id),
+                    Ok(("/Toggle", _)) => CatGroup::TogglePause(rest_id, group_id),
+                    Ok(("/EditCat", _)) => CatGroup::EditCategory(rest_id, group_id),
+                    Ok(("/EditTime", _)) => CatGroup::EditTime(rest_id, group_id),
+                    Ok(("/Remove", _)) => CatGroup::RemoveGroup(rest_id, group_id),
+                    Ok(("/AddDish", _)) => CatGroup::AddDish(rest_id, group_id),
+                    Ok(("/Promote", _)) => CatGroup::Promote(rest_id, group_id),
+                    Ok(("/EdDi", Value::Num(dish_id))) => CatGroup::EditDish(rest_id, group_id, dish_id),
                     _ => CatGroup::UnknownCommand,
                 }
             }
         }
     }
 
-    pub fn category_markup() -> ReplyKeyboardMarkup {
+    pub fn category_markup(lang: &str) -> ReplyKeyboardMarkup {
         ReplyKeyboardMarkup::default()
             .append_row(vec![
-               //  KeyboardButton::new("Соки воды"),
-                KeyboardButton::new("Еда"),
-                KeyboardButton::new("Напитки"),
-                KeyboardButton::new("Развлечения"),
+               //  KeyboardButton::new(lang::t(lang, BtnJuice)),
+                KeyboardButton::new(lang::t(lang, BtnFood)),
+                KeyboardButton::new(lang::t(lang, BtnDrinks)),
+                KeyboardButton::new(lang::t(lang, BtnFun)),
             ])
             .resize_keyboard(true)
     }
@@ -388,19 +477,24 @@ pub enum CatDish {
 impl CatDish {
 
     pub fn from(rest_id: i32, group_id: i32, dish_id: i32, input: &str) -> CatDish {
-        match input {
+        match (input, lang::button(input)) {
             // Сначала проверим на цельные команды.
-            "Главная" => CatDish::Main(rest_id),
-            "Выход" => CatDish::Exit,
-            "/EditTitle" => CatDish::EditTitle(rest_id, group_id, dish_id),
-            "/EditInfo" => CatDish::EditInfo(rest_id, group_id, dish_id),
-            "/Toggle" => CatDish::TogglePause(rest_id, group_id, dish_id),
-            "/EditGroup" => CatDish::EditGroup(rest_id, group_id, dish_id),
-            "/EditPrice" => CatDish::EditPrice(rest_id, group_id, dish_id),
-            "/EditImg" => CatDish::EditImage(rest_id, group_id, dish_id),
-            "/Remove" => CatDish::Remove(rest_id, group_id, dish_id),
-            "/Promote" => CatDish::Promote(rest_id, group_id, dish_id),
-            _ => CatDish::UnknownCommand,
+            (_, Some(BtnMain)) => CatDish::Main(rest_id),
+            (_, Some(BtnExit)) => CatDish::Exit,
+            _ => {
+                // Ищем среди команд меню
+                match registry::parse(Menu::CatDish, input) {
+                    Ok(("/EditTitle", _)) => CatDish::EditTitle(rest_id, group_id, dish_id),
+                    Ok(("/EditInfo", _)) => CatDish::EditInfo(rest_id, group_id, dish_id),
+                    Ok(("/Toggle", _)) => CatDish::TogglePause(rest_id, group_id, dish_id),
+                    Ok(("/EditGroup", _)) => CatDish::EditGroup(rest_id, group_id, dish_id),
+                    Ok(("/EditPrice", _)) => CatDish::EditPrice(rest_id, group_id, dish_id),
+                    Ok(("/EditImg", _)) => CatDish::EditImage(rest_id, group_id, dish_id),
+                    Ok(("/Remove", _)) => CatDish::Remove(rest_id, group_id, dish_id),
+                    Ok(("/Promote", _)) => CatDish::Promote(rest_id, group_id, dish_id),
+                    _ => CatDish::UnknownCommand,
+                }
+            }
         }
     }
 }
@@ -415,29 +509,31 @@ pub enum EaterRest {
    Main,
    UnknownCommand,
    Restaurant(i32),   // cat_id 
+   Page(i32),   // номер страницы списка
 }
 
 impl EaterRest {
    pub fn from(input: &str) -> EaterRest {
-      match input {
+      match (input, lang::button(input)) {
          // Сначала проверим на цельные команды.
-         "🛒" => EaterRest::Basket,
-         "В начало" => EaterRest::Main,
+         ("🛒", _) => EaterRest::Basket,
+         (_, Some(BtnHome)) => EaterRest::Main,
          _ => {
-             // Ищем среди команд с цифровыми суффиксами - аргументами
-             match input.get(..5).unwrap_or_default() {
-                 "/rest" => EaterRest::Restaurant(input.get(5..).unwrap_or_default().parse().unwrap_or_default()),
+             // Ищем среди команд меню
+             match registry::parse(Menu::EaterRest, input) {
+                 Ok(("/rest", Value::Num(num))) => EaterRest::Restaurant(num),
+                 Ok(("/page", Value::Num(num))) => EaterRest::Page(num),
                  _ => EaterRest::UnknownCommand,
              }
          }
      }
    }
 
-   pub fn markup() -> ReplyKeyboardMarkup {
+   pub fn markup(lang: &str) -> ReplyKeyboardMarkup {
       ReplyKeyboardMarkup::default()
           .append_row(vec![
             KeyboardButton::new("🛒"),
-            KeyboardButton::new("В начало"),
+            KeyboardButton::new(lang::t(lang, BtnHome)),
           ])
           .resize_keyboard(true)
   }
@@ -453,31 +549,37 @@ pub enum EaterGroup {
    Return,
    UnknownCommand,
    Group(i32),   // cat_id 
+   Favorite,     // добавить заведение в избранное или убрать
+   Subscribe,    // подписаться на новости заведения или отписаться
+   Page(i32),    // номер страницы списка
 }
 
 impl EaterGroup {
    pub fn from(input: &str) -> EaterGroup {
-      match input {
+      match (input, lang::button(input)) {
          // Сначала проверим на цельные команды.
-         "🛒" => EaterGroup::Basket,
-         "В начало" => EaterGroup::Main,
-         "⏪⏪Назад" => EaterGroup::Return,
+         ("🛒", _) => EaterGroup::Basket,
+         (_, Some(BtnHome)) => EaterGroup::Main,
+         (_, Some(BtnBackToRest)) => EaterGroup::Return,
          _ => {
-             // Ищем среди команд с цифровыми суффиксами - аргументами
-             match input.get(..5).unwrap_or_default() {
-                 "/grou" => EaterGroup::Group(input.get(5..).unwrap_or_default().parse().unwrap_or_default()),
+             // Ищем среди команд меню
+             match registry::parse(Menu::EaterGroup, input) {
+                 Ok(("/grou", Value::Num(num))) => EaterGroup::Group(num),
+                 Ok(("/fav", _)) => EaterGroup::Favorite,
+                 Ok(("/sub", _)) => EaterGroup::Subscribe,
+                 Ok(("/page", Value::Num(num))) => EaterGroup::Page(num),
                  _ => EaterGroup::UnknownCommand,
              }
          }
      }
    }
 
-   pub fn markup() -> ReplyKeyboardMarkup {
+   pub fn markup(lang: &str) -> ReplyKeyboardMarkup {
       ReplyKeyboardMarkup::default()
          .append_row(vec![
             KeyboardButton::new("🛒"),
-            KeyboardButton::new("В начало"),
-            KeyboardButton::new("⏪⏪Назад"),
+            KeyboardButton::new(lang::t(lang, BtnHome)),
+            KeyboardButton::new(lang::t(lang, BtnBackToRest)),
          ])
          .resize_keyboard(true)
   }
@@ -493,31 +595,33 @@ pub enum EaterDish {
    Return,
    UnknownCommand,
    Dish(i32),   // group_id
+   Page(i32),   // номер страницы списка
 }
 
 impl EaterDish {
    pub fn from(input: &str) -> EaterDish {
-      match input {
+      match (input, lang::button(input)) {
          // Сначала проверим на цельные команды.
-         "🛒" => EaterDish::Basket,
-         "В начало" => EaterDish::Main,
-         "⏪Назад" => EaterDish::Return,
+         ("🛒", _) => EaterDish::Basket,
+         (_, Some(BtnHome)) => EaterDish::Main,
+         (_, Some(BtnBack)) => EaterDish::Return,
          _ => {
-             // Ищем среди команд с цифровыми суффиксами - аргументами
-             match input.get(..5).unwrap_or_default() {
-                 "/dish" => EaterDish::Dish(input.get(5..).unwrap_or_default().parse().unwrap_or_default()),
+             // Ищем среди команд меню
+             match registry::parse(Menu::EaterDish, input) {
+                 Ok(("/dish", Value::Num(num))) => EaterDish::Dish(num),
+                 Ok(("/page", Value::Num(num))) => EaterDish::Page(num),
                  _ => EaterDish::UnknownCommand,
              }
          }
      }
    }
 
-   pub fn markup() -> ReplyKeyboardMarkup {
+   pub fn markup(lang: &str) -> ReplyKeyboardMarkup {
       ReplyKeyboardMarkup::default()
       .append_row(vec![
          KeyboardButton::new("🛒"),
-         KeyboardButton::new("В начало"),
-         KeyboardButton::new("⏪Назад"),
+         KeyboardButton::new(lang::t(lang, BtnHome)),
+         KeyboardButton::new(lang::t(lang, BtnBack)),
       ])
       .resize_keyboard(true)
    }
@@ -554,30 +658,36 @@ pub enum Basket {
    EditName,
    EditContact,
    EditAddress,
+   SetAddress(i32), // address_id
+   RemoveAddress(i32), // address_id
    TogglePickup,
+   EditTime,
+   EditPromo,
+   History,
+   Repeat(i32), // ticket_id
 }
 
 impl Basket {
    pub fn from(input: &str) -> Basket {
-      match input {
-         "В начало" => Basket::Main,
-         "⟳ Обновить" => Basket::Refresh,
-         "Очистить" => Basket::Clear,
-         "/edit_name" => Basket::EditName,
-         "/edit_contact" => Basket::EditContact,
-         "/edit_address" => Basket::EditAddress,
-         "/toggle" => Basket::TogglePickup,
+      match (input, lang::button(input)) {
+         (_, Some(BtnHome)) => Basket::Main,
+         (_, Some(BtnRefresh)) => Basket::Refresh,
+         (_, Some(BtnClear)) => Basket::Clear,
+         (_, Some(BtnHistory)) => Basket::History,
          _ => {
-            // Ищем среди команд с аргументами
-            let r_part = input.get(4..).unwrap_or_default();
-            match input.get(..4).unwrap_or_default() {
-               "/del" => {
-                  // Попытаемся извлечь аргументы
-                  match db::parse_key_3_int(r_part) {
-                     Ok((rest_num, group_num, dish_num)) => Basket::Delete(rest_num, group_num, dish_num),
-                     _ => Basket::UnknownCommand,
-                  }
-               }
+            // Ищем среди команд меню
+            match registry::parse(Menu::Basket, input) {
+               Ok(("/edit_name", _)) => Basket::EditName,
+               Ok(("/edit_contact", _)) => Basket::EditContact,
+               Ok(("/edit_address", _)) => Basket::EditAddress,
+               // Коды адресов начинаются с единицы
+               Ok(("/addr", Value::Num(address_id))) if address_id > 0 => Basket::SetAddress(address_id),
+               Ok(("/deladdr", Value::Num(address_id))) if address_id > 0 => Basket::RemoveAddress(address_id),
+               Ok(("/toggle", _)) => Basket::TogglePickup,
+               Ok(("/edit_time", _)) => Basket::EditTime,
+               Ok(("/edit_promo", _)) => Basket::EditPromo,
+               Ok(("/del", Value::Key3(rest_num, group_num, dish_num))) => Basket::Delete(rest_num, group_num, dish_num),
+               Ok(("/rep", Value::Num(ticket_id))) => Basket::Repeat(ticket_id),
                _ => Basket::UnknownCommand,
             }
          }
@@ -585,70 +695,100 @@ impl Basket {
    }
 
    // Кнопки для меню снизу
-   pub fn bottom_markup() -> ReplyKeyboardMarkup {
+   pub fn bottom_markup(lang: &str) -> ReplyKeyboardMarkup {
       ReplyKeyboardMarkup::default()
       .append_row(vec![
-         KeyboardButton::new("В начало"),
-         KeyboardButton::new("⟳ Обновить"),
-         KeyboardButton::new("Очистить"),
+         KeyboardButton::new(lang::t(lang, BtnHome)),
+         KeyboardButton::new(lang::t(lang, BtnRefresh)),
+         KeyboardButton::new(lang::t(lang, BtnClear)),
+         KeyboardButton::new(lang::t(lang, BtnHistory)),
       ])
       .resize_keyboard(true)
    }
 
-   pub fn address_markup() -> ReplyKeyboardMarkup {
+   pub fn address_markup(lang: &str) -> ReplyKeyboardMarkup {
       ReplyKeyboardMarkup::default()
          .append_row(vec![
-            KeyboardButton::new("Геопозиция")
+            KeyboardButton::new(lang::t(lang, BtnLocation))
             .request(ButtonRequest::Location),
             KeyboardButton::new("/"),
          ])
          .resize_keyboard(true)
    }
 
-   // Меню при отправке нового заказа из корзины
-   pub fn inline_markup_send(rest_id: i32) -> InlineKeyboardMarkup {
-      // Колбек команда
-      let data = format!("bas{}", db::make_key_3_int(rest_id, 0, 0));
+   // Меню при отправке нового заказа из корзины, при нескольких адресах кнопка для каждого
+   pub fn inline_markup_send(lang: &str, rest_id: i32, addresses: &[db::Address]) -> InlineKeyboardMarkup {
+      if addresses.len() < 2 {
+         // Колбек команда, 0 означает адрес по умолчанию
+         let data = format!("bas{}", db::make_key_3_int(rest_id, 0, 0));
 
-      let button = InlineKeyboardButton::callback(String::from("Оформить через бота"), data);
+         let button = InlineKeyboardButton::callback(lang::t(lang, BtnOrderViaBot), data);
 
-      InlineKeyboardMarkup::default()
-      .append_row(vec![button])
+         InlineKeyboardMarkup::default()
+         .append_row(vec![button])
+      } else {
+         addresses.iter()
+         .fold(InlineKeyboardMarkup::default(), |markup, address| {
+            let data = format!("bas{}", db::make_key_3_int(rest_id, address.id, 0));
+            let button = InlineKeyboardButton::callback(lang::tf(lang, BtnOrderTo, &[&address.title(lang)]), data);
+            markup.append_row(vec![button])
+         })
+      }
    }
 
    // Меню едока для заказов в обработке
-   pub fn inline_markup_message_cancel(ticket_id: i32) -> InlineKeyboardMarkup {
+   pub fn inline_markup_message_cancel(lang: &str, ticket_id: i32) -> InlineKeyboardMarkup {
       // Аргументы для колбек команды
       let args = db::make_key_3_int(ticket_id, 0, 0);
       // let button1 = InlineKeyboardButton::callback(String::from("Написать"), format!("bse{}", args));
-      let button2 = InlineKeyboardButton::callback(String::from("Отмена заказа"), format!("bca{}", args));
+      let button2 = InlineKeyboardButton::callback(lang::t(lang, BtnCancelOrder), format!("bca{}", args));
 
       InlineKeyboardMarkup::default()
       .append_row(vec![button2])
    }
 
+   // Меню едока для заказа, ожидающего оплаты, счёт можно запросить повторно
+   pub fn inline_markup_message_pay(lang: &str, ticket_id: i32) -> InlineKeyboardMarkup {
+      // Аргументы для колбек команды
+      let args = db::make_key_3_int(ticket_id, 0, 0);
+      let button1 = InlineKeyboardButton::callback(lang::t(lang, BtnCancelOrder), format!("bca{}", args));
+      let button2 = InlineKeyboardButton::callback(lang::t(lang, BtnPay), format!("bpa{}", args));
+
+      InlineKeyboardMarkup::default()
+      .append_row(vec![button1, button2])
+   }
+
    // Меню едока для заказов на последней стадии
-   pub fn inline_markup_message_confirm(ticket_id: i32) -> InlineKeyboardMarkup {
+   pub fn inline_markup_message_confirm(lang: &str, ticket_id: i32) -> InlineKeyboardMarkup {
       // Аргументы для колбек команды
       let args = db::make_key_3_int(ticket_id, 0, 0);
-      let button1 = InlineKeyboardButton::callback(String::from("Отмена заказа"), format!("bca{}", args));
-      let button2 = InlineKeyboardButton::callback(String::from("Подтвердить"), format!("bne{}", args));
+      let button1 = InlineKeyboardButton::callback(lang::t(lang, BtnCancelOrder), format!("bca{}", args));
+      let button2 = InlineKeyboardButton::callback(lang::t(lang, BtnConfirm), format!("bne{}", args));
 
       InlineKeyboardMarkup::default()
       .append_row(vec![button1, button2])
    }
 
    // Меню ресторатора для заказов в обработке
-   pub fn inline_markup_message_next(ticket_id: i32) -> InlineKeyboardMarkup {
+   pub fn inline_markup_message_next(lang: &str, ticket_id: i32) -> InlineKeyboardMarkup {
       // Аргументы для колбек команды
       let args = db::make_key_3_int(ticket_id, 0, 0);
-      let button1 = InlineKeyboardButton::callback(String::from("Отмена заказа"), format!("bca{}", args));
-      let button2 = InlineKeyboardButton::callback(String::from("Далее"), format!("bne{}", args));
+      let button1 = InlineKeyboardButton::callback(lang::t(lang, BtnCancelOrder), format!("bca{}", args));
+      let button2 = InlineKeyboardButton::callback(lang::t(lang, BtnNext), format!("bne{}", args));
 
       InlineKeyboardMarkup::default()
       .append_row(vec![button1, button2])
    }
 
+   // Оценка завершённого заказа
+   pub fn inline_markup_rate(ticket_id: i32) -> InlineKeyboardMarkup {
+      let buttons = (1..=5).map(|rating| InlineKeyboardButton::callback(format!("{}⭐", rating), format!("rat{}", db::make_key_3_int(ticket_id, rating, 0))))
+      .collect();
+
+      InlineKeyboardMarkup::default()
+      .append_row(buttons)
+   }
+
    
 }
 
@@ -660,26 +800,50 @@ pub enum Gear {
    Main,
    UnknownCommand,
    CatererMode,
+   SudoList(i32), // номер страницы
    ToggleInterface,
    RegisterCaterer(i32), // user_id
    HoldCaterer(i32), // user_id
    Sudo(i32), // rest_num
-   List,
+   List(i32), // номер страницы
+   PaymentToken(i32), // rest_num, сам токен через пробел
+   BroadcastHours(i32, Option<i32>), // rest_num, часов между рассылками
+   TimeZone(i32), // rest_num, часовой пояс через пробел
+   PromoList,
+   PromoSave, // параметры промокода через пробел
+   PromoRemove, // код через пробел
+   Languages,
+   SetLanguage(&'static str), // язык из /lang_xx
 }
 
 impl Gear {
    pub fn from(input: &str) -> Gear {
-      match input {
-         "В начало" => Gear::Main,
-         "Добавить меню" => Gear::CatererMode,
-         "/toggle" => Gear::ToggleInterface,
-         "/list" => Gear::List,
+      match (input, lang::button(input)) {
+         (_, Some(BtnHome)) => Gear::Main,
+         (_, Some(BtnAddMenu)) => Gear::CatererMode,
          _ => {
-            // Ищем среди команд с цифровыми суффиксами - аргументами
-            match input.get(..5).unwrap_or_default() {
-               "/regi" => Gear::RegisterCaterer(input.get(5..).unwrap_or_default().parse().unwrap_or_default()),
-               "/hold" => Gear::HoldCaterer(input.get(5..).unwrap_or_default().parse().unwrap_or_default()),
-               "/sudo" => Gear::Sudo(input.get(5..).unwrap_or_default().parse().unwrap_or_default()),
+            // Ищем среди команд меню
+            match registry::parse(Menu::Gear, input) {
+               Ok(("/toggle", _)) => Gear::ToggleInterface,
+               Ok(("/list", Value::Num(page))) => Gear::List(page),
+               Ok(("/list", _)) => Gear::List(1),
+               Ok(("/rests", Value::Num(page))) => Gear::SudoList(page),
+               Ok(("/rests", _)) => Gear::SudoList(1),
+               Ok(("/promos", _)) => Gear::PromoList,
+               Ok(("/lang", _)) => Gear::Languages,
+               Ok(("/lang_", Value::Lang(lang))) => Gear::SetLanguage(lang),
+               Ok(("/regi", Value::Num(user_id))) => Gear::RegisterCaterer(user_id),
+               Ok(("/hold", Value::Num(user_id))) => Gear::HoldCaterer(user_id),
+               Ok(("/sudo", Value::Num(rest_num))) => Gear::Sudo(rest_num),
+               Ok(("/paym", Value::NumText(rest_num, _))) => Gear::PaymentToken(rest_num),
+               Ok(("/bcast", Value::NumText(rest_num, None))) => Gear::BroadcastHours(rest_num, None),
+               Ok(("/bcast", Value::NumText(rest_num, Some(hours)))) => match hours.parse() {
+                  Ok(hours) if hours >= 0 => Gear::BroadcastHours(rest_num, Some(hours)),
+                  _ => Gear::UnknownCommand,
+               }
+               Ok(("/tz", Value::NumText(rest_num, _))) => Gear::TimeZone(rest_num),
+               Ok(("/promo", _)) => Gear::PromoSave,
+               Ok(("/unpromo", _)) => Gear::PromoRemove,
                _ => Gear::UnknownCommand,
             }
          }
@@ -687,11 +851,11 @@ impl Gear {
    }
 
    // Кнопки для меню снизу
-   pub fn bottom_markup() -> ReplyKeyboardMarkup {
+   pub fn bottom_markup(lang: &str) -> ReplyKeyboardMarkup {
       ReplyKeyboardMarkup::default()
       .append_row(vec![
-         KeyboardButton::new("В начало"),
-         KeyboardButton::new("Добавить меню"),
+         KeyboardButton::new(lang::t(lang, BtnHome)),
+         KeyboardButton::new(lang::t(lang, BtnAddMenu)),
       ])
       .resize_keyboard(true)
    }
@@ -0,0 +1,498 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Конфигурация из файла TOML с переопределением переменными окружения. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+use std::{collections::HashMap, env, fs};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+// Файл конфигурации по-умолчанию, если не указан в CONFIG_FILE
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+// Часовой пояс по имени из базы IANA, например Asia/Ho_Chi_Minh, или прежним
+// способом целым смещением в часах от -12 до 14, которое соответствует поясу Etc/GMT
+pub fn parse_time_zone(s: &str) -> Option<Tz> {
+   let s = s.trim();
+   match s.parse::<i32>() {
+      // У поясов Etc/GMT знак обратный: Etc/GMT-7 это UTC+7
+      Ok(n) if (-12..=14).contains(&n) => format!("Etc/GMT{:+}", -n).parse().ok(),
+      Ok(_) => None,
+      Err(_) => s.parse().ok(),
+   }
+}
+
+// Строка из символов, допустимых и в пути URL, и в секрете вебхука Telegram
+fn is_url_token(s: &str) -> bool {
+   s.len() <= 256 && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+// Способ получения обновлений
+#[derive(Copy, Clone, PartialEq)]
+pub enum UpdateMode {
+   Webhook,
+   Polling,
+}
+
+// Настройки бота
+pub struct Config {
+   pub token: String,
+   pub database_url: String,
+   pub log_group_id: Option<i64>,
+   pub contact_info: String,
+   pub admins: Vec<i32>,
+   pub price_unit: String,
+   pub time_zone: Tz,   // часовой пояс по-умолчанию, у заведения может быть свой
+   pub default_image_id: String,
+   pub payment_currency: String,
+   pub payment_multiplier: i32,
+   pub page_size: usize,    // строк в списке со ссылками
+   pub page_buttons: usize, // инлайн-кнопок в списке
+   pub broadcast_interval: i32, // часов между рассылками заведения, 0 без ограничения
+   pub resend_interval: u64,    // секунд между попытками дослать важные сообщения
+   pub update_mode: UpdateMode,
+   pub host: String,
+   pub port: u16,
+   pub webhook_path: Option<String>,
+   pub webhook_secret: Option<String>,
+   pub telegram_api_proxy: Option<String>,
+   pub telegram_api_proxy_ca: Option<Vec<u8>>, // дополнительный корневой сертификат для прокси, PEM
+   pub admin_api_token: Option<String>, // без токена API администратора отключено
+}
+
+// Число или строка, например часовой пояс 7 или "Asia/Ho_Chi_Minh"
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Scalar {
+   Int(i64),
+   Str(String),
+}
+
+impl std::fmt::Display for Scalar {
+   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+      match self {
+         Scalar::Int(n) => write!(f, "{}", n),
+         Scalar::Str(s) => write!(f, "{}", s),
+      }
+   }
+}
+
+// Содержимое файла конфигурации, проверка значений общая с переменными окружения
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct FileConfig {
+   token: Option<String>,
+   database_url: Option<String>,
+   log_group_id: Option<i64>,
+   contact_info: Option<String>,
+   admins: Option<Vec<i64>>,
+   price_unit: Option<String>,
+   time_zone: Option<Scalar>,
+   default_image_id: Option<String>,
+   payment_currency: Option<String>,
+   payment_multiplier: Option<i64>,
+   page_size: Option<i64>,
+   page_buttons: Option<i64>,
+   broadcast_interval: Option<i64>,
+   resend_interval: Option<i64>,
+   update_mode: Option<String>,
+   host: Option<String>,
+   port: Option<i64>,
+   webhook_path: Option<String>,
+   webhook_secret: Option<String>,
+   telegram_api_proxy: Option<String>,
+   telegram_api_proxy_ca: Option<String>,
+   admin_api_token: Option<String>,
+   #[serde(flatten)]
+   unknown: HashMap<String, toml::Value>,
+}
+
+impl FileConfig {
+   // Значения в виде строк, как если бы они пришли из переменных окружения
+   fn into_values(self) -> HashMap<&'static str, String> {
+      let numbers = |list: Vec<i64>| list.iter().map(i64::to_string).collect::<Vec<String>>().join(",");
+      let values = vec![
+         ("token", self.token),
+         ("database_url", self.database_url),
+         ("log_group_id", self.log_group_id.map(|n| n.to_string())),
+         ("contact_info", self.contact_info),
+         ("admins", self.admins.map(numbers)),
+         ("price_unit", self.price_unit),
+         ("time_zone", self.time_zone.map(|v| v.to_string())),
+         ("default_image_id", self.default_image_id),
+         ("payment_currency", self.payment_currency),
+         ("payment_multiplier", self.payment_multiplier.map(|n| n.to_string())),
+         ("page_size", self.page_size.map(|n| n.to_string())),
+         ("page_buttons", self.page_buttons.map(|n| n.to_string())),
+         ("broadcast_interval", self.broadcast_interval.map(|n| n.to_string())),
+         ("resend_interval", self.resend_interval.map(|n| n.to_string())),
+         ("update_mode", self.update_mode),
+         ("host", self.host),
+         ("port", self.port.map(|n| n.to_string())),
+         ("webhook_path", self.webhook_path),
+         ("webhook_secret", self.webhook_secret),
+         ("telegram_api_proxy", self.telegram_api_proxy),
+         ("telegram_api_proxy_ca", self.telegram_api_proxy_ca),
+         ("admin_api_token", self.admin_api_token),
+      ];
+      values.into_iter().filter_map(|(key, value)| value.map(|value| (key, value))).collect()
+   }
+}
+
+// Разбор файла, ошибки TOML и неизвестные ключи попадают в общий отчёт
+fn parse_file(path: &str, text: &str, problems: &mut Vec<String>, warnings: &mut Vec<String>) -> HashMap<&'static str, String> {
+   match toml::from_str::<FileConfig>(text) {
+      Ok(file) => {
+         let mut unknown: Vec<&String> = file.unknown.keys().collect();
+         unknown.sort();
+         warnings.extend(unknown.into_iter().map(|key| format!("unknown key '{}' in config file", key)));
+         file.into_values()
+      }
+      Err(e) => {
+         problems.push(format!("config file {}: {}", path, e));
+         HashMap::new()
+      }
+   }
+}
+
+// Собирает значения из файла и окружения, накапливая все проблемы для общего отчёта
+struct Loader {
+   file: HashMap<&'static str, String>,
+   errors: Vec<String>,
+   warnings: Vec<String>,
+}
+
+impl Loader {
+   // Значение из переменной окружения, если она задана, иначе из файла. Пустое значение считается отсутствующим
+   fn raw(&mut self, key: &str, env_name: &str) -> Option<String> {
+      let value = match env::var(env_name) {
+         Ok(s) => Some(s),
+         Err(_) => self.file.remove(key),
+      };
+      value.filter(|s| !s.trim().is_empty())
+   }
+
+   fn required(&mut self, key: &str, env_name: &str) -> String {
+      match self.raw(key, env_name) {
+         Some(s) => s,
+         None => {
+            self.errors.push(format!("{} ({}) is required", key, env_name));
+            String::default()
+         }
+      }
+   }
+
+   fn optional(&mut self, key: &str, env_name: &str, what: &str) -> String {
+      match self.raw(key, env_name) {
+         Some(s) => s,
+         None => {
+            self.warnings.push(format!("{} ({}) is not set, {}", key, env_name, what));
+            String::default()
+         }
+      }
+   }
+
+   // Размер страницы от 1 до 100, если не задан, то значение по-умолчанию
+   fn page(&mut self, key: &str, env_name: &str, default: usize) -> usize {
+      match self.raw(key, env_name) {
+         Some(s) => match self.number::<usize>(key, env_name, &s) {
+            Some(n) if (1..=100).contains(&n) => n,
+            Some(n) => {
+               self.errors.push(format!("{} ({}) must be from 1 to 100, got {}", key, env_name, n));
+               default
+            }
+            None => default,
+         }
+         None => default,
+      }
+   }
+
+   fn number<T: std::str::FromStr>(&mut self, key: &str, env_name: &str, s: &str) -> Option<T> {
+      match s.trim().parse::<T>() {
+         Ok(n) => Some(n),
+         Err(_) => {
+            self.errors.push(format!("{} ({}) must be integer, got '{}'", key, env_name, s));
+            None
+         }
+      }
+   }
+}
+
+impl Config {
+   // Загружает настройки и возвращает их либо полный список ошибок.
+   // Предупреждения о необязательных настройках возвращаются вместе с конфигурацией
+   pub fn load() -> Result<(Self, Vec<String>), Vec<String>> {
+      let mut problems = Vec::new();
+      let mut warnings = Vec::new();
+
+      // Файл необязателен, если его имя не указано явно
+      let file = match env::var("CONFIG_FILE") {
+         Ok(path) => match fs::read_to_string(&path) {
+            Ok(text) => parse_file(&path, &text, &mut problems, &mut warnings),
+            Err(e) => {
+               problems.push(format!("cannot read config file {}: {}", path, e));
+               HashMap::new()
+            }
+         }
+         Err(_) => match fs::read_to_string(DEFAULT_CONFIG_FILE) {
+            Ok(text) => parse_file(DEFAULT_CONFIG_FILE, &text, &mut problems, &mut warnings),
+            Err(_) => HashMap::new(),
+         }
+      };
+
+      let mut l = Loader {file, errors: problems, warnings};
+
+      let token = l.required("token", "TELOXIDE_TOKEN");
+
+      let database_url = l.required("database_url", "DATABASE_URL");
+      if !database_url.is_empty() && database_url.parse::<tokio_postgres::Config>().is_err() {
+         l.errors.push(String::from("database_url (DATABASE_URL) is not a valid connection string"));
+      }
+
+      let log_group_id = match l.raw("log_group_id", "LOG_GROUP_ID") {
+         Some(s) => l.number("log_group_id", "LOG_GROUP_ID", &s),
+         None => {
+            l.warnings.push(String::from("log_group_id (LOG_GROUP_ID) is not set, no service chat"));
+            None
+         }
+      };
+
+      let contact_info = l.optional("contact_info", "CONTACT_INFO", "caterers will not see how to contact admins");
+
+      // Администраторы списком, а также по старым переменным TELEGRAM_ADMIN_ID1..3
+      let mut admins = Vec::new();
+      if let Some(s) = l.raw("admins", "TELEGRAM_ADMINS") {
+         for item in s.split(',').filter(|item| !item.trim().is_empty()) {
+            if let Some(n) = l.number("admins", "TELEGRAM_ADMINS", item) {
+               admins.push(n);
+            }
+         }
+      }
+      for env_name in &["TELEGRAM_ADMIN_ID1", "TELEGRAM_ADMIN_ID2", "TELEGRAM_ADMIN_ID3"] {
+         if let Some(s) = env::var(env_name).ok().filter(|s| !s.trim().is_empty()) {
+            if let Some(n) = l.number("admins", env_name, &s) {
+               admins.push(n);
+            }
+         }
+      }
+      admins.sort_unstable();
+      admins.dedup();
+      if admins.is_empty() {
+         l.warnings.push(String::from("admins (TELEGRAM_ADMINS) is not set, nobody can manage restaurants"));
+      }
+
+      let price_unit = l.optional("price_unit", "PRICE_UNIT", "prices will be shown without unit");
+
+      let time_zone = match l.raw("time_zone", "TIME_ZONE") {
+         Some(s) => match parse_time_zone(&s) {
+            Some(tz) => tz,
+            None => {
+               l.errors.push(format!("time_zone (TIME_ZONE) must be IANA name like Asia/Ho_Chi_Minh or hours from -12 to 14, got '{}'", s));
+               Tz::UTC
+            }
+         }
+         None => {
+            l.errors.push(String::from("time_zone (TIME_ZONE) is required, for example Asia/Ho_Chi_Minh or 7 for UTC+7"));
+            Tz::UTC
+         }
+      };
+
+      let default_image_id = l.optional("default_image_id", "DEFAULT_IMAGE_ID", "inline interface will have no pictures for dishes without photo");
+
+      let payment_currency = l.raw("payment_currency", "PAYMENT_CURRENCY").unwrap_or_else(|| String::from("VND")).to_uppercase();
+      if payment_currency.len() != 3 || !payment_currency.chars().all(|c| c.is_ascii_alphabetic()) {
+         l.errors.push(format!("payment_currency (PAYMENT_CURRENCY) must be 3-letter ISO 4217 code, got '{}'", payment_currency));
+      }
+
+      let payment_multiplier = match l.raw("payment_multiplier", "PAYMENT_MULTIPLIER") {
+         Some(s) => match l.number::<i32>("payment_multiplier", "PAYMENT_MULTIPLIER", &s) {
+            Some(n) if n > 0 => n,
+            Some(n) => {
+               l.errors.push(format!("payment_multiplier (PAYMENT_MULTIPLIER) must be positive, got {}", n));
+               1
+            }
+            None => 1,
+         }
+         None => 1,
+      };
+
+      // Размеры страниц длинных списков. Telegram принимает не более 100 инлайн-кнопок и 4096 символов в сообщении
+      let page_size = l.page("page_size", "PAGE_SIZE", 30);
+      let page_buttons = l.page("page_buttons", "PAGE_BUTTONS", 20);
+
+      // Как часто заведение может делать рассылку подписчикам, администратор может изменить для отдельного заведения
+      let broadcast_interval = match l.raw("broadcast_interval", "BROADCAST_INTERVAL") {
+         Some(s) => match l.number::<i32>("broadcast_interval", "BROADCAST_INTERVAL", &s) {
+            Some(n) if n >= 0 => n,
+            Some(n) => {
+               l.errors.push(format!("broadcast_interval (BROADCAST_INTERVAL) must not be negative, got {}", n));
+               24
+            }
+            None => 24,
+         }
+         None => 24,
+      };
+
+      // Как часто досылать важные сообщения, которые не удалось доставить сразу
+      let resend_interval = match l.raw("resend_interval", "RESEND_INTERVAL") {
+         Some(s) => match l.number::<u64>("resend_interval", "RESEND_INTERVAL", &s) {
+            Some(n) if n > 0 => n,
+            Some(n) => {
+               l.errors.push(format!("resend_interval (RESEND_INTERVAL) must be positive, got {}", n));
+               60
+            }
+            None => 60,
+         }
+         None => 60,
+      };
+
+      let update_mode = match l.raw("update_mode", "UPDATE_MODE").as_deref() {
+         None | Some("webhook") => UpdateMode::Webhook,
+         Some("polling") => UpdateMode::Polling,
+         Some(s) => {
+            l.errors.push(format!("update_mode (UPDATE_MODE) must be webhook or polling, got '{}'", s));
+            UpdateMode::Webhook
+         }
+      };
+
+      // Адрес и порт нужны только для вебхука
+      let (host, port) = if update_mode == UpdateMode::Webhook {
+         let host = l.required("host", "HOST");
+         let port = l.required("port", "PORT");
+         let port = if port.is_empty() {0} else {l.number("port", "PORT", &port).unwrap_or_default()};
+         (host, port)
+      } else {
+         (String::default(), 0)
+      };
+
+      let webhook_path = l.raw("webhook_path", "WEBHOOK_PATH");
+      if let Some(path) = &webhook_path {
+         if !is_url_token(path) {
+            l.errors.push(String::from("webhook_path (WEBHOOK_PATH) may contain only A-Z, a-z, 0-9, _ and - and be up to 256 characters"));
+         }
+      }
+      let webhook_secret = l.raw("webhook_secret", "WEBHOOK_SECRET");
+      if let Some(secret) = &webhook_secret {
+         if !is_url_token(secret) {
+            l.errors.push(String::from("webhook_secret (WEBHOOK_SECRET) may contain only A-Z, a-z, 0-9, _ and - and be up to 256 characters"));
+         }
+      }
+
+      let telegram_api_proxy = l.raw("telegram_api_proxy", "TELEGRAM_API_PROXY");
+      if let Some(proxy) = &telegram_api_proxy {
+         if reqwest::Proxy::all(proxy).is_err() {
+            l.errors.push(format!("telegram_api_proxy (TELEGRAM_API_PROXY) is not a valid URL: {}", proxy));
+         }
+      }
+
+      // Сертификаты проверяются всегда, для локальной замены можно только добавить её сертификат
+      let telegram_api_proxy_ca = match l.raw("telegram_api_proxy_ca", "TELEGRAM_API_PROXY_CA") {
+         Some(path) => match std::fs::read(&path) {
+            Ok(pem) if reqwest::Certificate::from_pem(&pem).is_ok() => Some(pem),
+            Ok(_) => {
+               l.errors.push(format!("telegram_api_proxy_ca (TELEGRAM_API_PROXY_CA) is not a PEM certificate: {}", path));
+               None
+            }
+            Err(e) => {
+               l.errors.push(format!("telegram_api_proxy_ca (TELEGRAM_API_PROXY_CA) cannot be read from {}: {}", path, e));
+               None
+            }
+         }
+         None => None,
+      };
+      if telegram_api_proxy_ca.is_some() && telegram_api_proxy.is_none() {
+         l.warnings.push(String::from("telegram_api_proxy_ca (TELEGRAM_API_PROXY_CA) is ignored without telegram_api_proxy"));
+      }
+
+      // API администратора работает на сервере вебхука
+      let admin_api_token = l.raw("admin_api_token", "ADMIN_API_TOKEN");
+      match &admin_api_token {
+         Some(token) if token.len() < 16 || !token.chars().all(|c| c.is_ascii_graphic()) => {
+            l.errors.push(String::from("admin_api_token (ADMIN_API_TOKEN) must be at least 16 printable ASCII characters without spaces"));
+         }
+         Some(_) if update_mode == UpdateMode::Polling => {
+            l.warnings.push(String::from("admin_api_token (ADMIN_API_TOKEN) is set, but admin API works only in webhook mode"));
+         }
+         _ => (),
+      }
+
+      if !l.errors.is_empty() {
+         return Err(l.errors);
+      }
+
+      Ok((Config {
+         token,
+         database_url,
+         log_group_id,
+         contact_info,
+         admins,
+         price_unit,
+         time_zone,
+         default_image_id,
+         payment_currency,
+         payment_multiplier,
+         page_size,
+         page_buttons,
+         broadcast_interval,
+         resend_interval,
+         update_mode,
+         host,
+         port,
+         webhook_path,
+         webhook_secret,
+         telegram_api_proxy,
+         telegram_api_proxy_ca,
+         admin_api_token,
+      }, l.warnings))
+   }
+}
+
+// Общий отчёт о проблемах конфигурации
+pub fn report(title: &str, problems: &[String]) -> String {
+   let list: String = problems.iter().map(|p| format!("\n- {}", p)).collect();
+   format!("{}:{}", title, list)
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   #[test]
+   fn parses_time_zones() {
+      assert_eq!(parse_time_zone("7"), Some(Tz::Etc__GMTMinus7));
+      assert_eq!(parse_time_zone("-3"), Some(Tz::Etc__GMTPlus3));
+      assert_eq!(parse_time_zone("0"), Some(Tz::Etc__GMTPlus0));
+      assert_eq!(parse_time_zone("Asia/Ho_Chi_Minh"), Some(Tz::Asia__Ho_Chi_Minh));
+      assert_eq!(parse_time_zone(" Asia/Kolkata "), Some(Tz::Asia__Kolkata));
+      assert_eq!(parse_time_zone("15"), None);
+      assert_eq!(parse_time_zone("Mars/Olympus"), None);
+   }
+
+   #[test]
+   fn checks_url_tokens() {
+      assert!(is_url_token("bot_Hook-1"));
+      assert!(!is_url_token("/"));
+      assert!(!is_url_token("a/b"));
+      assert!(!is_url_token(&"a".repeat(257)));
+   }
+
+   #[test]
+   fn parses_example_file() {
+      let (mut problems, mut warnings) = (Vec::new(), Vec::new());
+      let values = parse_file("config.example.toml", include_str!("../config.example.toml"), &mut problems, &mut warnings);
+      assert!(problems.is_empty() && warnings.is_empty(), "{:?} {:?}", problems, warnings);
+      assert_eq!(values["admins"], "40966,40967");
+      assert_eq!(values["time_zone"], "Asia/Ho_Chi_Minh");
+      assert_eq!(values["port"], "443");
+
+      parse_file("bad.toml", "port = \"443\"\nextra = 1", &mut problems, &mut warnings);
+      assert_eq!(problems.len(), 1, "{:?}", problems);
+      let values = parse_file("zone.toml", "time_zone = 7\nextra = 1", &mut problems, &mut warnings);
+      assert_eq!(values["time_zone"], "7");
+      assert_eq!(warnings, vec![String::from("unknown key 'extra' in config file")]);
+   }
+}
@@ -7,7 +7,7 @@ http://www.gnu.org/licenses/gpl-3.0.html
 Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
 =============================================================================== */
 
-use chrono::{NaiveTime, Timelike};
+use chrono::{NaiveDateTime, NaiveTime, Timelike};
 use once_cell::sync::{OnceCell};
 use text_io::try_scan;
 use teloxide::{
@@ -15,18 +15,47 @@ use teloxide::{
 };
 use tokio_postgres::{Row, types::ToSql, };
 use deadpool_postgres::{Pool, Client};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
 use std::sync::RwLock;
 
 use crate::settings;
+use crate::metrics;
+use crate::language::{self as lang, Res::*};
 
 // Пул клиентов БД
 pub static DB: OnceCell<Pool> = OnceCell::new();
 
+// Попытки соединения с БД при запуске
+const DB_RETRY_ATTEMPTS: u32 = 8;
+const DB_RETRY_FIRST_DELAY_SECS: u64 = 1;
+const DB_RETRY_MAX_DELAY_SECS: u64 = 30;
+
 // Картинки по-умолчанию для категорий блюд
 type CatImageList = HashMap<i32, String>;
 pub static CI: OnceCell<RwLock<CatImageList>> = OnceCell::new();
 
+// ============================================================================
+// [Time zones]
+// ============================================================================
+// Часовой пояс по-умолчанию для заведений без своего пояса, передаётся в запросы параметром.
+// Пояса заведений проверяются по pg_timezone_names при сохранении, поэтому PostgreSQL их знает
+fn default_zone() -> String {
+   settings::time_zone(None).name().to_string()
+}
+
+// Выражение SQL с текущим временем в поясе zone или в поясе по-умолчанию из параметра default_zone
+fn local_now(default_zone: &str, zone: &str) -> String {
+   format!("(now() AT TIME ZONE COALESCE({z}, {d}::TEXT))", d = default_zone, z = zone)
+}
+
+// Условие работы группы g сейчас по времени её заведения, в том числе через полночь
+fn group_opened_now(default_zone: &str) -> String {
+   let now = format!("{}::TIME", local_now(default_zone, "(SELECT z.time_zone FROM restaurants z WHERE z.rest_num = g.rest_num)"));
+   format!("g.active = TRUE AND (({now} BETWEEN g.opening_time AND g.closing_time)
+   OR (g.opening_time > g.closing_time AND ({now} >= g.opening_time OR {now} <= g.closing_time)))", now = now)
+}
+
 // ============================================================================
 // [Restaurants table]
 // ============================================================================
@@ -42,6 +71,9 @@ pub struct Restaurant {
    pub image_id: Option<String>,
    pub opening_time: NaiveTime,
    pub closing_time: NaiveTime,
+   pub payment_token: Option<String>,
+   pub location: Option<(f64, f64)>, // широта и долгота
+   pub time_zone: Option<String>,    // часовой пояс IANA, None для пояса по-умолчанию
 }
 
 impl Restaurant {
@@ -56,9 +88,20 @@ impl Restaurant {
          image_id: row.get(6),
          opening_time: row.get(7),
          closing_time: row.get(8),
+         payment_token: row.get(9),
+         location: match (row.get(10), row.get(11)) {
+            (Some(latitude), Some(longitude)) => Some((latitude, longitude)),
+            _ => None,
+         },
+         time_zone: row.get(12),
       }
    }
 
+   // Текущее время в часовом поясе заведения
+   pub fn now(&self) -> NaiveDateTime {
+      settings::zone_date_time(self.time_zone.as_deref())
+   }
+
    // Возвращает собственную картинку или картинку по-умолчанию
    pub fn image_or_default(&self) -> String {
       if let Some(id) = self.image_id.clone() {id}
@@ -76,7 +119,10 @@ pub enum RestBy {
 pub enum RestListBy {
    All,              // все рестораны
    Category(i32),    // активные, с группами в указанной категории
-   Time(NaiveTime),  // активные, с группами, работающими в указанное время
+   OpenNow,          // активные, с группами, работающими сейчас по времени заведения
+   WithMenu,         // активные, с активными группами независимо от времени работы
+   Find(String),     // активные, с шаблоном в названии или описании
+   Located,          // активные, с заданной геопозицией
 }
 
 // Список ресторанов
@@ -90,20 +136,30 @@ pub async fn rest_list(by: RestListBy) -> Option<RestList> {
    // Выберем нужный текст запроса
    let statement_text =  match by {
       RestListBy::All =>
-         "SELECT r.user_id, r.title, r.info, r.active, r.enabled, r.rest_num, r.image_id, r.opening_time, r.closing_time FROM restaurants AS r
+         "SELECT r.user_id, r.title, r.info, r.active, r.enabled, r.rest_num, r.image_id, r.opening_time, r.closing_time, r.payment_token, r.latitude, r.longitude, r.time_zone FROM restaurants AS r
          ORDER BY rest_num",
       RestListBy::Category(_cat_id) =>
-         "SELECT r.user_id, r.title, r.info, r.active, r.enabled, r.rest_num, r.image_id, r.opening_time, r.closing_time FROM restaurants AS r 
+         "SELECT r.user_id, r.title, r.info, r.active, r.enabled, r.rest_num, r.image_id, r.opening_time, r.closing_time, r.payment_token, r.latitude, r.longitude, r.time_zone FROM restaurants AS r 
             INNER JOIN (SELECT DISTINCT rest_num FROM groups WHERE cat_id=$1::INTEGER AND active = TRUE) g ON r.rest_num = g.rest_num 
             WHERE r.active = TRUE",
-      RestListBy::Time(_time) =>
-         "SELECT r.user_id, r.title, r.info, r.active, r.enabled, r.rest_num, r.image_id, r.opening_time, r.closing_time FROM restaurants AS r 
-            INNER JOIN (SELECT DISTINCT rest_num FROM groups WHERE active = TRUE AND 
-            ($1::TIME BETWEEN opening_time AND closing_time) OR (opening_time > closing_time AND $1::TIME > opening_time)) g ON r.rest_num = g.rest_num WHERE r.active = TRUE",
+      RestListBy::OpenNow =>
+         "SELECT r.user_id, r.title, r.info, r.active, r.enabled, r.rest_num, r.image_id, r.opening_time, r.closing_time, r.payment_token, r.latitude, r.longitude, r.time_zone FROM restaurants AS r 
+            WHERE r.active = TRUE AND EXISTS (SELECT 1 FROM groups g WHERE g.rest_num = r.rest_num AND {opened})",
+      RestListBy::WithMenu =>
+         "SELECT r.user_id, r.title, r.info, r.active, r.enabled, r.rest_num, r.image_id, r.opening_time, r.closing_time, r.payment_token, r.latitude, r.longitude, r.time_zone FROM restaurants AS r 
+            WHERE r.active = TRUE AND EXISTS (SELECT 1 FROM groups g WHERE g.rest_num = r.rest_num AND g.active = TRUE)
+            ORDER BY rest_num",
+      RestListBy::Find(_) =>
+         "SELECT r.user_id, r.title, r.info, r.active, r.enabled, r.rest_num, r.image_id, r.opening_time, r.closing_time, r.payment_token, r.latitude, r.longitude, r.time_zone FROM restaurants AS r
+            WHERE r.active = TRUE AND r.enabled = TRUE AND (UPPER(r.title) LIKE UPPER($1::VARCHAR(100)) OR UPPER(r.info) LIKE UPPER($1::VARCHAR(100)))
+            ORDER BY rest_num",
+      RestListBy::Located =>
+         "SELECT r.user_id, r.title, r.info, r.active, r.enabled, r.rest_num, r.image_id, r.opening_time, r.closing_time, r.payment_token, r.latitude, r.longitude, r.time_zone FROM restaurants AS r
+            WHERE r.active = TRUE AND r.enabled = TRUE AND r.latitude IS NOT NULL AND r.longitude IS NOT NULL",
    };
 
    // Подготовим нужный запрос с кешем благодаря пулу
-   let statement = client.prepare(statement_text).await;
+   let statement = client.prepare(&statement_text.replace("{opened}", &group_opened_now("$1"))).await;
 
    // Если запрос подготовлен успешно, выполняем его
    match statement {
@@ -111,7 +167,10 @@ pub async fn rest_list(by: RestListBy) -> Option<RestList> {
          let rows = match by {
             RestListBy::All => client.query(&stmt, &[]).await,
             RestListBy::Category(cat_id) => client.query(&stmt, &[&cat_id]).await,
-            RestListBy::Time(time) => client.query(&stmt, &[&time]).await,
+            RestListBy::OpenNow => client.query(&stmt, &[&default_zone()]).await,
+            RestListBy::WithMenu => client.query(&stmt, &[]).await,
+            RestListBy::Find(text) => client.query(&stmt, &[&text]).await,
+            RestListBy::Located => client.query(&stmt, &[]).await,
          };
 
          // Возвращаем результат
@@ -139,9 +198,9 @@ pub async fn restaurant(by: RestBy) -> Option<Restaurant> {
 
    // Подготовим нужный запрос с кешем благодаря пулу
    let statement = match by {
-      RestBy::Id(_user_id) => client.prepare("SELECT user_id, title, info, active, enabled, rest_num, image_id, opening_time, closing_time FROM restaurants
+      RestBy::Id(_user_id) => client.prepare("SELECT user_id, title, info, active, enabled, rest_num, image_id, opening_time, closing_time, payment_token, latitude, longitude, time_zone FROM restaurants
          WHERE user_id=$1::INTEGER"),
-      RestBy::Num(_rest_num) => client.prepare("SELECT user_id, title, info, active, enabled, rest_num, image_id, opening_time, closing_time FROM restaurants
+      RestBy::Num(_rest_num) => client.prepare("SELECT user_id, title, info, active, enabled, rest_num, image_id, opening_time, closing_time, payment_token, latitude, longitude, time_zone FROM restaurants
          WHERE rest_num=$1::INTEGER"),
    }.await;
 
@@ -205,20 +264,60 @@ pub async fn rest_edit_image(rest_num: i32, image_id: &String) -> bool {
    execute_one("UPDATE restaurants SET image_id = $1::VARCHAR(512) WHERE rest_num=$2::INTEGER", &[&image_id, &rest_num]).await
 }
 
+// Изменяет геопозицию ресторана
+pub async fn rest_edit_location(rest_num: i32, latitude: f64, longitude: f64) -> bool {
+   execute_one("UPDATE restaurants SET latitude = $1::FLOAT8, longitude = $2::FLOAT8 WHERE rest_num=$3::INTEGER", &[&latitude, &longitude, &rest_num]).await
+}
+
+// Текущее время в часовом поясе ресторана
+pub async fn rest_now(rest_num: i32) -> NaiveDateTime {
+   let time_zone: Option<String> = match db_client().await {
+      Some(client) => match client.query("SELECT time_zone FROM restaurants WHERE rest_num=$1::INTEGER", &[&rest_num]).await {
+         Ok(data) => data.first().and_then(|row| row.get(0)),
+         Err(e) => {
+            settings::log(&format!("db::rest_now({}): {}", rest_num, e)).await;
+            None
+         }
+      }
+      None => None,
+   };
+   settings::zone_date_time(time_zone.as_deref())
+}
+
+// Задаёт часовой пояс ресторана, пустое значение возвращает пояс по-умолчанию
+pub async fn rest_edit_time_zone(rest_num: i32, time_zone: Option<&str>) -> bool {
+   // Время работы считается в запросах, поэтому пояс должен быть известен и PostgreSQL
+   execute_one("UPDATE restaurants SET time_zone = $1::VARCHAR(64) WHERE rest_num=$2::INTEGER
+      AND ($1::VARCHAR(64) IS NULL OR EXISTS (SELECT 1 FROM pg_timezone_names WHERE name = $1::VARCHAR(64)))", &[&time_zone, &rest_num]).await
+}
+
+// Изменяет сразу несколько полей ресторана одним запросом, чтобы не применить их частично.
+// Пояс меняется, только если time_zone задан, None в нём сбрасывает пояс на общий
+pub async fn rest_edit(rest_num: i32, title: Option<String>, info: Option<String>, active: Option<bool>, time_zone: Option<Option<String>>) -> bool {
+   let set_zone = time_zone.is_some();
+   let time_zone = time_zone.flatten();
+   execute_one("UPDATE restaurants SET title = COALESCE($2::VARCHAR(100), title), info = COALESCE($3::VARCHAR, info),
+      active = COALESCE($4::BOOLEAN, active), time_zone = CASE WHEN $5::BOOLEAN THEN $6::VARCHAR(64) ELSE time_zone END
+      WHERE rest_num=$1::INTEGER
+      AND (NOT $5::BOOLEAN OR $6::VARCHAR(64) IS NULL OR EXISTS (SELECT 1 FROM pg_timezone_names WHERE name = $6::VARCHAR(64)))",
+      &[&rest_num, &title, &info, &active, &set_zone, &time_zone]).await
+}
+
 // Изменяет владельца ресторана
 pub async fn transfer_ownership(rest_num: i32, new_user_id: i32) -> bool {
    execute_one("UPDATE restaurants SET user_id = $1::INTEGER WHERE rest_num=$2::INTEGER", &[&new_user_id, &rest_num]).await
 }
 
-// Регистрация или разблокировка ресторатора
-pub async fn register_caterer(user_id: i32) -> bool {
+// Регистрация или разблокировка ресторатора, название и описание нового заведения на языке ресторатора
+pub async fn register_caterer(lang: &str, user_id: i32) -> bool {
    // Попробуем разблокировать пользователя
    if execute_one_no_error("UPDATE restaurants SET enabled = TRUE WHERE user_id=$1::INTEGER", &[&user_id]).await {
       return true;
    }
 
    // Cоздадим новую запись
-   execute_one("INSERT INTO restaurants (user_id, title, info, active, enabled, opening_time, closing_time) VALUES ($1::INTEGER, 'Мяу', 'Наш адрес 00NDC, доставка @nick, +84123', FALSE, TRUE, '07:00', '23:00')", &[&user_id])
+   execute_one("INSERT INTO restaurants (user_id, title, info, active, enabled, opening_time, closing_time) VALUES ($1::INTEGER, $2::VARCHAR(100), $3::VARCHAR, FALSE, TRUE, '07:00', '23:00')",
+      &[&user_id, &lang::t(lang, NewRestTitle), &lang::t(lang, NewRestInfo)])
    .await
 }
 
@@ -227,6 +326,11 @@ pub async fn hold_caterer(user_id: i32) -> bool {
    execute_one("UPDATE restaurants SET enabled = FALSE, active = FALSE WHERE user_id=$1::INTEGER", &[&user_id]).await
 }
 
+// Задаёт или сбрасывает токен платёжного провайдера ресторана
+pub async fn rest_edit_payment_token(rest_num: i32, token: Option<String>) -> bool {
+   execute_one("UPDATE restaurants SET payment_token = $1::VARCHAR(512) WHERE rest_num=$2::INTEGER", &[&token, &rest_num]).await
+}
+
 // Проверяет существование таблиц
 pub async fn is_tables_exist() -> bool {
    // Получаем клиента БД
@@ -244,6 +348,71 @@ pub async fn is_tables_exist() -> bool {
    }
 }
 
+// Состояние пула соединений для проверки живости (размер, максимум, свободно), None если БД ещё не открыта
+pub fn pool_status() -> Option<(usize, usize, isize)> {
+   DB.get().map(|pool| {
+      let status = pool.status();
+      (status.size, status.max_size, status.available)
+   })
+}
+
+// Проверяет готовность БД выполнять запросы, без записи в служебный чат, чтобы частые проверки его не засоряли
+pub async fn is_ready() -> bool {
+   let pool = match DB.get() {
+      Some(pool) => pool,
+      None => return false,
+   };
+
+   let check = async {
+      let client = pool.get().await.map_err(|e| e.to_string())?;
+      client.query_one("SELECT 1", &[]).await.map_err(|e| e.to_string())
+   };
+
+   match tokio::time::timeout(std::time::Duration::from_secs(5), check).await {
+      Ok(Ok(_)) => true,
+      Ok(Err(e)) => {
+         log::warn!("Database is not ready: {}", e);
+         false
+      }
+      Err(_) => {
+         log::warn!("Database is not ready: timeout");
+         false
+      }
+   }
+}
+
+// Ждёт доступности БД при запуске, повторяя попытки с нарастающей паузой
+pub async fn wait_for_connection() -> bool {
+   let mut delay = DB_RETRY_FIRST_DELAY_SECS;
+   for attempt in 1..=DB_RETRY_ATTEMPTS {
+      match DB.get().unwrap().get().await {
+         Ok(_) => return true,
+         Err(e) => {
+            log::warn!("Database connection attempt {} of {} failed: {}", attempt, DB_RETRY_ATTEMPTS, e);
+            if attempt < DB_RETRY_ATTEMPTS {
+               tokio::time::delay_for(std::time::Duration::from_secs(delay)).await;
+               delay = (delay * 2).min(DB_RETRY_MAX_DELAY_SECS);
+            }
+         }
+      }
+   }
+   false
+}
+
+// Закрывает соединения пула, когда вся работа с БД завершена
+pub async fn close_pool() {
+   if let Some(pool) = DB.get() {
+      let (size, _, _) = pool_status().unwrap_or_default();
+      for _ in 0..size {
+         // Свободное соединение изымается из пула и закрывается
+         match pool.try_get().await {
+            Ok(client) => drop(Client::take(client)),
+            Err(_) => break,
+         }
+      }
+   }
+}
+
 // Создаёт новые таблицы
 pub async fn create_tables() -> bool {
    // Получаем клиента БД
@@ -262,7 +431,12 @@ pub async fn create_tables() -> bool {
          rest_num       SERIAL,
          image_id       VARCHAR(512),
          opening_time   TIME           NOT NULL,    
-         closing_time   TIME           NOT NULL);
+         closing_time   TIME           NOT NULL,
+         payment_token  VARCHAR(512),
+         latitude       FLOAT8,
+         longitude      FLOAT8,
+         broadcast_hours INTEGER,
+         time_zone      VARCHAR(64));
 
       CREATE TABLE groups (
          PRIMARY KEY (rest_num, group_num),
@@ -294,7 +468,10 @@ pub async fn create_tables() -> bool {
          address        VARCHAR(100)   NOT NULL,
          last_seen      TIMESTAMP      NOT NULL,
          compact        BOOLEAN        NOT NULL,
-         pickup         BOOLEAN        NOT NULL);
+         pickup         BOOLEAN        NOT NULL,
+         order_time     TIMESTAMP,
+         promo_code     VARCHAR(32),
+         language       VARCHAR(5));
 
       CREATE TABLE orders (
          PRIMARY KEY (user_id, rest_num, group_num, dish_num),
@@ -318,7 +495,126 @@ pub async fn create_tables() -> bool {
          caterer_msg_id INTEGER        NOT NULL,
          stage          INTEGER        NOT NULL,
          eater_status_msg_id     INTEGER,
-         caterer_status_msg_id   INTEGER);")
+         caterer_status_msg_id   INTEGER,
+         order_time     TIMESTAMP,
+         reminded       BOOLEAN        NOT NULL DEFAULT FALSE,
+         created        TIMESTAMP,
+         payment_required  BOOLEAN     NOT NULL DEFAULT FALSE,
+         paid           BOOLEAN        NOT NULL DEFAULT FALSE,
+         charge_id      VARCHAR(256),
+         promo_code     VARCHAR(32),
+         discount       INTEGER        NOT NULL DEFAULT 0);
+
+      CREATE TABLE ticket_items (
+         PRIMARY KEY (ticket_id, rest_num, group_num, dish_num),
+         ticket_id      INTEGER        NOT NULL,
+         rest_num       INTEGER        NOT NULL,
+         group_num      INTEGER        NOT NULL,
+         dish_num       INTEGER        NOT NULL,
+         title          VARCHAR(100)   NOT NULL,
+         price          INTEGER        NOT NULL,
+         amount         INTEGER        NOT NULL);
+
+      CREATE TABLE reviews (
+         PRIMARY KEY (review_id),
+         review_id      SERIAL         NOT NULL,
+         ticket_id      INTEGER        NOT NULL UNIQUE,
+         rest_num       INTEGER        NOT NULL,
+         eater_id       INTEGER        NOT NULL,
+         rating         INTEGER        NOT NULL,
+         comment        VARCHAR(512),
+         reply          VARCHAR(512),
+         hidden         BOOLEAN        NOT NULL DEFAULT FALSE,
+         created        TIMESTAMP      NOT NULL);
+
+      CREATE TABLE promo_codes (
+         PRIMARY KEY (code),
+         code           VARCHAR(32)    NOT NULL,
+         rest_num       INTEGER        NOT NULL,
+         percent        BOOLEAN        NOT NULL,
+         value          INTEGER        NOT NULL,
+         min_total      INTEGER        NOT NULL,
+         expires        TIMESTAMP,
+         max_uses       INTEGER        NOT NULL,
+         max_uses_per_user INTEGER     NOT NULL,
+         group_nums     INTEGER[]);
+
+      CREATE TABLE promo_uses (
+         PRIMARY KEY (ticket_id),
+         ticket_id      INTEGER        NOT NULL,
+         code           VARCHAR(32)    NOT NULL,
+         user_id        INTEGER        NOT NULL);
+
+      CREATE TABLE outbox (
+         PRIMARY KEY (msg_id),
+         msg_id         SERIAL,
+         chat_id        BIGINT         NOT NULL,
+         text           TEXT           NOT NULL,
+         reply_to       INTEGER,
+         attempts       INTEGER        NOT NULL DEFAULT 0,
+         created        TIMESTAMPTZ    NOT NULL DEFAULT now(),
+         ticket_id      INTEGER,
+         forward_from   BIGINT,
+         forward_msg_id INTEGER,
+         latitude       FLOAT8,
+         longitude      FLOAT8);
+
+      CREATE TABLE favorites (
+         PRIMARY KEY (user_id, rest_num, group_num, dish_num),
+         user_id        INTEGER        NOT NULL,
+         rest_num       INTEGER        NOT NULL,
+         group_num      INTEGER        NOT NULL,
+         dish_num       INTEGER        NOT NULL);
+
+      CREATE TABLE subscriptions (
+         PRIMARY KEY (user_id, rest_num),
+         user_id        INTEGER        NOT NULL,
+         rest_num       INTEGER        NOT NULL);
+
+      CREATE TABLE broadcasts (
+         PRIMARY KEY (broadcast_id),
+         broadcast_id   SERIAL,
+         rest_num       INTEGER        NOT NULL,
+         user_id        INTEGER        NOT NULL,
+         text           TEXT           NOT NULL,
+         image_id       VARCHAR(512),
+         created        TIMESTAMP      NOT NULL,
+         finished       TIMESTAMP,
+         cursor         INTEGER        NOT NULL DEFAULT 0,
+         delivered      INTEGER        NOT NULL DEFAULT 0,
+         failed         INTEGER        NOT NULL DEFAULT 0);
+      CREATE TABLE notifications (
+         PRIMARY KEY (user_id, rest_num, group_num, dish_num),
+         user_id        INTEGER        NOT NULL,
+         rest_num       INTEGER        NOT NULL,
+         group_num      INTEGER        NOT NULL,
+         dish_num       INTEGER        NOT NULL,
+         created        TIMESTAMP      NOT NULL);
+      CREATE TABLE group_baskets (
+         PRIMARY KEY (chat_id),
+         chat_id        BIGINT         NOT NULL,
+         rest_num       INTEGER        NOT NULL,
+         initiator      INTEGER        NOT NULL,
+         initiator_name VARCHAR(100)   NOT NULL,
+         message_id     INTEGER,
+         created        TIMESTAMP      NOT NULL);
+      CREATE TABLE group_orders (
+         PRIMARY KEY (chat_id, user_id, group_num, dish_num),
+         chat_id        BIGINT         NOT NULL,
+         user_id        INTEGER        NOT NULL,
+         user_name      VARCHAR(100)   NOT NULL,
+         group_num      INTEGER        NOT NULL,
+         dish_num       INTEGER        NOT NULL,
+         amount         INTEGER        NOT NULL);
+      CREATE TABLE addresses (
+         PRIMARY KEY (address_id),
+         address_id     SERIAL,
+         user_id        INTEGER        NOT NULL,
+         label          VARCHAR(32)    NOT NULL,
+         address        VARCHAR(100)   NOT NULL,
+         latitude       FLOAT8,
+         longitude      FLOAT8,
+         is_default     BOOLEAN        NOT NULL);")
    .await;
       
    match query {
@@ -330,6 +626,172 @@ pub async fn create_tables() -> bool {
    }
 }
 
+// Дополняет таблицы, созданные предыдущими версиями, новыми полями
+pub async fn update_tables() -> bool {
+   // Получаем клиента БД
+   let client = db_client().await;
+   if client.is_none() {return false;}
+
+   // Каждая команда повторяема, существующие поля не затрагиваются
+   let query = client.unwrap()
+   .batch_execute(&format!("ALTER TABLE users ADD COLUMN IF NOT EXISTS order_time TIMESTAMP;
+
+      ALTER TABLE tickets ADD COLUMN IF NOT EXISTS order_time TIMESTAMP;
+      ALTER TABLE tickets ADD COLUMN IF NOT EXISTS reminded BOOLEAN NOT NULL DEFAULT FALSE;
+      ALTER TABLE tickets ADD COLUMN IF NOT EXISTS created TIMESTAMP;
+      ALTER TABLE tickets ADD COLUMN IF NOT EXISTS payment_required BOOLEAN NOT NULL DEFAULT FALSE;
+      ALTER TABLE tickets ADD COLUMN IF NOT EXISTS paid BOOLEAN NOT NULL DEFAULT FALSE;
+      ALTER TABLE tickets ADD COLUMN IF NOT EXISTS charge_id VARCHAR(256);
+      ALTER TABLE restaurants ADD COLUMN IF NOT EXISTS payment_token VARCHAR(512);
+      ALTER TABLE users ADD COLUMN IF NOT EXISTS promo_code VARCHAR(32);
+      ALTER TABLE tickets ADD COLUMN IF NOT EXISTS promo_code VARCHAR(32);
+      ALTER TABLE tickets ADD COLUMN IF NOT EXISTS discount INTEGER NOT NULL DEFAULT 0;
+      ALTER TABLE users ADD COLUMN IF NOT EXISTS language VARCHAR(5);
+      ALTER TABLE restaurants ADD COLUMN IF NOT EXISTS latitude FLOAT8;
+      ALTER TABLE restaurants ADD COLUMN IF NOT EXISTS longitude FLOAT8;
+      ALTER TABLE restaurants ADD COLUMN IF NOT EXISTS broadcast_hours INTEGER;
+      ALTER TABLE restaurants ADD COLUMN IF NOT EXISTS time_zone VARCHAR(64);
+
+      CREATE TABLE IF NOT EXISTS ticket_items (
+         PRIMARY KEY (ticket_id, rest_num, group_num, dish_num),
+         ticket_id      INTEGER        NOT NULL,
+         rest_num       INTEGER        NOT NULL,
+         group_num      INTEGER        NOT NULL,
+         dish_num       INTEGER        NOT NULL,
+         title          VARCHAR(100)   NOT NULL,
+         price          INTEGER        NOT NULL,
+         amount         INTEGER        NOT NULL);
+
+      CREATE TABLE IF NOT EXISTS reviews (
+         PRIMARY KEY (review_id),
+         review_id      SERIAL         NOT NULL,
+         ticket_id      INTEGER        NOT NULL UNIQUE,
+         rest_num       INTEGER        NOT NULL,
+         eater_id       INTEGER        NOT NULL,
+         rating         INTEGER        NOT NULL,
+         comment        VARCHAR(512),
+         reply          VARCHAR(512),
+         hidden         BOOLEAN        NOT NULL DEFAULT FALSE,
+         created        TIMESTAMP      NOT NULL);
+
+      CREATE TABLE IF NOT EXISTS promo_codes (
+         PRIMARY KEY (code),
+         code           VARCHAR(32)    NOT NULL,
+         rest_num       INTEGER        NOT NULL,
+         percent        BOOLEAN        NOT NULL,
+         value          INTEGER        NOT NULL,
+         min_total      INTEGER        NOT NULL,
+         expires        TIMESTAMP,
+         max_uses       INTEGER        NOT NULL,
+         max_uses_per_user INTEGER     NOT NULL,
+         group_nums     INTEGER[]);
+
+      CREATE TABLE IF NOT EXISTS promo_uses (
+         PRIMARY KEY (ticket_id),
+         ticket_id      INTEGER        NOT NULL,
+         code           VARCHAR(32)    NOT NULL,
+         user_id        INTEGER        NOT NULL);
+
+      CREATE TABLE IF NOT EXISTS outbox (
+         PRIMARY KEY (msg_id),
+         msg_id         SERIAL,
+         chat_id        BIGINT         NOT NULL,
+         text           TEXT           NOT NULL,
+         reply_to       INTEGER,
+         attempts       INTEGER        NOT NULL DEFAULT 0,
+         created        TIMESTAMPTZ    NOT NULL DEFAULT now(),
+         ticket_id      INTEGER,
+         forward_from   BIGINT,
+         forward_msg_id INTEGER,
+         latitude       FLOAT8,
+         longitude      FLOAT8);
+      ALTER TABLE outbox ADD COLUMN IF NOT EXISTS ticket_id INTEGER;
+      ALTER TABLE outbox ADD COLUMN IF NOT EXISTS forward_from BIGINT;
+      ALTER TABLE outbox ADD COLUMN IF NOT EXISTS forward_msg_id INTEGER;
+      ALTER TABLE outbox ADD COLUMN IF NOT EXISTS latitude FLOAT8;
+      ALTER TABLE outbox ADD COLUMN IF NOT EXISTS longitude FLOAT8;
+
+      -- Прежние версии хранили время создания по поясу бота без указания пояса
+      DO $$ BEGIN
+         IF EXISTS (SELECT 1 FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = 'outbox' AND column_name = 'created' AND data_type = 'timestamp without time zone') THEN
+            ALTER TABLE outbox ALTER COLUMN created TYPE TIMESTAMPTZ USING created AT TIME ZONE '{zone}';
+            ALTER TABLE outbox ALTER COLUMN created SET DEFAULT now();
+         END IF;
+      END $$;
+
+      CREATE TABLE IF NOT EXISTS favorites (
+         PRIMARY KEY (user_id, rest_num, group_num, dish_num),
+         user_id        INTEGER        NOT NULL,
+         rest_num       INTEGER        NOT NULL,
+         group_num      INTEGER        NOT NULL,
+         dish_num       INTEGER        NOT NULL);
+
+      CREATE TABLE IF NOT EXISTS subscriptions (
+         PRIMARY KEY (user_id, rest_num),
+         user_id        INTEGER        NOT NULL,
+         rest_num       INTEGER        NOT NULL);
+
+      CREATE TABLE IF NOT EXISTS broadcasts (
+         PRIMARY KEY (broadcast_id),
+         broadcast_id   SERIAL,
+         rest_num       INTEGER        NOT NULL,
+         user_id        INTEGER        NOT NULL,
+         text           TEXT           NOT NULL,
+         image_id       VARCHAR(512),
+         created        TIMESTAMP      NOT NULL,
+         finished       TIMESTAMP,
+         cursor         INTEGER        NOT NULL DEFAULT 0,
+         delivered      INTEGER        NOT NULL DEFAULT 0,
+         failed         INTEGER        NOT NULL DEFAULT 0);
+      CREATE TABLE IF NOT EXISTS notifications (
+         PRIMARY KEY (user_id, rest_num, group_num, dish_num),
+         user_id        INTEGER        NOT NULL,
+         rest_num       INTEGER        NOT NULL,
+         group_num      INTEGER        NOT NULL,
+         dish_num       INTEGER        NOT NULL,
+         created        TIMESTAMP      NOT NULL);
+      CREATE TABLE IF NOT EXISTS group_baskets (
+         PRIMARY KEY (chat_id),
+         chat_id        BIGINT         NOT NULL,
+         rest_num       INTEGER        NOT NULL,
+         initiator      INTEGER        NOT NULL,
+         initiator_name VARCHAR(100)   NOT NULL,
+         message_id     INTEGER,
+         created        TIMESTAMP      NOT NULL);
+      CREATE TABLE IF NOT EXISTS group_orders (
+         PRIMARY KEY (chat_id, user_id, group_num, dish_num),
+         chat_id        BIGINT         NOT NULL,
+         user_id        INTEGER        NOT NULL,
+         user_name      VARCHAR(100)   NOT NULL,
+         group_num      INTEGER        NOT NULL,
+         dish_num       INTEGER        NOT NULL,
+         amount         INTEGER        NOT NULL);
+      CREATE TABLE IF NOT EXISTS addresses (
+         PRIMARY KEY (address_id),
+         address_id     SERIAL,
+         user_id        INTEGER        NOT NULL,
+         label          VARCHAR(32)    NOT NULL,
+         address        VARCHAR(100)   NOT NULL,
+         latitude       FLOAT8,
+         longitude      FLOAT8,
+         is_default     BOOLEAN        NOT NULL);
+
+      -- Прежний единственный текстовый адрес переносится в адресную книгу, геопозиции по id сообщения не переносятся
+      INSERT INTO addresses (user_id, label, address, is_default)
+         SELECT user_id, '', address, TRUE FROM users
+         WHERE length(address) >= 3 AND address NOT LIKE 'Location%';
+      UPDATE users SET address = '-' WHERE address <> '-';", zone = default_zone()))
+   .await;
+
+   match query {
+      Ok(_) => true,
+      Err(e) => {
+         settings::log(&format!("Error update_tables: {}", e)).await;
+         false
+      }
+   }
+}
+
 // Обновляет время работы ресторана на основании времени, заданного в группах
 pub async fn rest_edit_time(rest_num: i32) -> bool {
    // Определяем самое частое время открытия и закрытия групп и записываем его как время ресторана
@@ -370,17 +832,17 @@ impl Group {
    }
 
    // Строка со временем работы группы, исключая время по-умолчанию для краткости
-   fn work_time(&self, def_opening_time: NaiveTime, def_closing_time: NaiveTime) -> String {
+   fn work_time(&self, lang: &str, def_opening_time: NaiveTime, def_closing_time: NaiveTime) -> String {
       // Четыре варианта отображения времени
       if self.opening_time != def_opening_time && self.closing_time != def_closing_time {
          // Показываем и время начала и время конца
          format!(" ({}-{})", str_time(self.opening_time), str_time(self.closing_time))
       } else if self.opening_time != def_opening_time && self.closing_time == def_closing_time {
          // Показываем время начала
-         format!(" (c {})", str_time(self.opening_time))
+         lang::tf(lang, GroupFrom, &[&str_time(self.opening_time)])
       } else if self.opening_time == def_opening_time && self.closing_time != def_closing_time {
          // Показываем время конца
-         format!(" (до {})", str_time(self.closing_time))
+         lang::tf(lang, GroupUntil, &[&str_time(self.closing_time)])
       } else {
          // Не показываем время
          String::default()
@@ -388,8 +850,23 @@ impl Group {
    }
 
    // Возвращает название вместе со временем работы
-   pub fn title_with_time(&self, def_opening_time: NaiveTime, def_closing_time: NaiveTime) -> String {
-      format!("{}{}", self.title, self.work_time(def_opening_time, def_closing_time))
+   pub fn title_with_time(&self, lang: &str, def_opening_time: NaiveTime, def_closing_time: NaiveTime) -> String {
+      format!("{}{}", self.title, self.work_time(lang, def_opening_time, def_closing_time))
+   }
+
+   // Возвращает истину, если группа доступна в указанное время
+   pub fn is_open_at(&self, time: NaiveTime) -> bool {
+      self.active && is_time_in(time, self.opening_time, self.closing_time)
+   }
+
+   // Состояние работы группы в указанное время
+   pub fn status(&self, time: NaiveTime) -> WorkStatus {
+      WorkStatus::of(time, self.opening_time, self.closing_time)
+   }
+
+   // Возвращает название вместе с состоянием работы для едока
+   pub fn title_with_status(&self, lang: &str, time: NaiveTime) -> String {
+      format!("{} · {}", self.title, self.status(time).to_str(lang))
    }
 }
 
@@ -397,7 +874,7 @@ impl Group {
 pub enum GroupListBy {
    All(i32),               // все группы ресторана с указанным номером
    Category(i32, i32),     // активные, по номеру ресторана и категории
-   Time(i32, NaiveTime),   // активные, по номеру ресторана и группами, работающими в указанное время
+   Active(i32),            // активные, по номеру ресторана
 }
 
 // Список групп
@@ -416,9 +893,10 @@ pub async fn group_list(by: GroupListBy) -> Option<GroupList> {
       GroupListBy::Category(_rest_num, _cat_id) =>
          "SELECT g.rest_num, g.group_num, g.title, g.info, g.active, g.cat_id, g.opening_time, g.closing_time FROM groups as g
          WHERE active = TRUE AND rest_num=$1::INTEGER AND cat_id=$2::INTEGER",
-      GroupListBy::Time(_rest_num, _time) =>
+      GroupListBy::Active(_rest_num) =>
          "SELECT g.rest_num, g.group_num, g.title, g.info, g.active, g.cat_id, g.opening_time, g.closing_time FROM groups as g
-         WHERE active = TRUE AND rest_num=$1::INTEGER AND (($2::TIME BETWEEN opening_time AND closing_time) OR (opening_time > closing_time AND $2::TIME > opening_time))",
+         WHERE active = TRUE AND rest_num=$1::INTEGER
+         ORDER BY group_num",
    };
 
    // Подготовим нужный запрос с кешем благодаря пулу
@@ -430,7 +908,7 @@ pub async fn group_list(by: GroupListBy) -> Option<GroupList> {
          let rows = match by {
             GroupListBy::All(rest_num) => client.query(&stmt, &[&rest_num]).await,
             GroupListBy::Category(rest_num, cat_id) => client.query(&stmt, &[&rest_num, &cat_id]).await,
-            GroupListBy::Time(rest_num, time) => client.query(&stmt, &[&rest_num, &time]).await,
+            GroupListBy::Active(rest_num) => client.query(&stmt, &[&rest_num]).await,
          };
 
          // Возвращаем результат
@@ -628,7 +1106,7 @@ impl Dish {
    }
 
    // Возвращает описание для едока
-   pub fn info_for_eater(&self) -> String {
+   pub fn info_for_eater(&self, lang: &str) -> String {
       // Если описание слишком короткое, не выводим его
       let info_str = if self.info.len() < 3 {
          String::default()
@@ -637,16 +1115,15 @@ impl Dish {
       };
 
       // Если цена нулевая, не выводим её
-      let price_str = if self.price > 0 {format!("Цена: {}", settings::price_with_unit(self.price))}
+      let price_str = if self.price > 0 {lang::tf(lang, DishPrice, &[&settings::price_with_unit(self.price)])}
       else {String::default()};
 
       format!("<b>{}</b>\n<i>{}</i>{}", self.title, info_str, price_str)
    }
 
    // Возвращает описание для ресторатора
-   pub fn info_for_caterer(&self) -> String {
-      format!("Название: {} /EditTitle\nДоп.инфо: {} /EditInfo\nГруппа: {} /EditGroup\nСтатус: {} /Toggle\nЦена: {} /EditPrice\nЗагрузить фото /EditImg\nУдалить блюдо /Remove\nСообщение для рекламы /Promote",
-      self.title, self.info, self.group_num, active_to_str(self.active), settings::price_with_unit(self.price))
+   pub fn info_for_caterer(&self, lang: &str) -> String {
+      lang::tf(lang, DishInfoForCaterer, &[&self.title, &self.info, &self.group_num, &active_to_str(lang, self.active), &settings::price_with_unit(self.price)])
    }
 }
 
@@ -676,8 +1153,8 @@ pub async fn dish_list(by: DishesBy) -> Option<DishList> {
       DishesBy::Find(_) =>
          "SELECT DISTINCT d.rest_num, d.dish_num, d.title, d.info, d.active, d.group_num, d.price, d.image_id FROM dishes as d
          INNER JOIN restaurants r ON r.rest_num = d.rest_num
-         INNER JOIN groups g ON g.group_num = d.group_num
-         WHERE r.active = TRUE AND r.enabled = TRUE AND g.active = TRUE AND UPPER(d.title) like UPPER($1::VARCHAR(100)) ORDER BY d.rest_num, d.group_num",
+         INNER JOIN groups g ON g.rest_num = d.rest_num AND g.group_num = d.group_num
+         WHERE r.active = TRUE AND r.enabled = TRUE AND g.active = TRUE AND d.active = TRUE AND UPPER(d.title) like UPPER($1::VARCHAR(100)) ORDER BY d.rest_num, d.group_num",
    };
 
    // Подготовим нужный запрос с кешем благодаря пулу
@@ -762,6 +1239,11 @@ pub async fn dish(by: DishBy) -> Option<Dish> {
 
 // Возвращает картинку блюда, если задана, иначе пытается получить картинку ресторана и т.д.
 pub async fn load_dish_image(dish: &Dish) -> InputFile {
+   InputFile::file_id(dish_image_id(dish).await)
+}
+
+// Возвращает идентификатор картинки блюда, ресторана или картинки по-умолчанию, может быть пустым
+pub async fn dish_image_id(dish: &Dish) -> String {
    
    // Возвращает картинку для указанного ресторана, если есть
    async fn load_rest_image(rest_num: i32) -> Option<String> {
@@ -798,12 +1280,10 @@ pub async fn load_dish_image(dish: &Dish) -> InputFile {
    }
 
    // Получаем идентификатор от первого доступного источника
-   let id = dish.image_id.to_owned()
-   .unwrap_or(load_rest_image(dish.rest_num).await
-   .unwrap_or(settings::default_photo_id()));
-
-   // Возврашаем объект
-   InputFile::file_id(id)
+   match dish.image_id.to_owned() {
+      Some(id) => id,
+      None => load_rest_image(dish.rest_num).await.unwrap_or_else(settings::default_photo_id),
+   }
 }
 
 // Добавляет новое блюдо
@@ -1036,46 +1516,116 @@ pub async fn user_toggle_interface(user: Option<&User>) {
    }
 }
 
+// Возвращает выбранный пользователем язык интерфейса
+pub async fn user_language(user_id: i32) -> Option<String> {
+   // Получаем клиента БД
+   let client = db_client().await?;
+
+   let query = client.query_opt("SELECT language FROM users WHERE user_id=$1::INTEGER", &[&user_id]).await;
+   match query {
+      Ok(row) => row.and_then(|row| row.get(0)),
+      Err(e) => {
+         settings::log(&format!("Error user_language: {}", e)).await;
+         None
+      }
+   }
+}
+
+// Сохраняет язык интерфейса, ложь если записи о пользователе ещё нет
+pub async fn user_set_language(user_id: i32, lang: &str) -> bool {
+   execute_one_no_error("UPDATE users SET language = $2::VARCHAR(5) WHERE user_id=$1::INTEGER", &[&user_id, &lang]).await
+}
+
+// Пользователь для списка в API администратора
+pub struct UserRecord {
+   pub user_id: i32,
+   pub name: String,
+   pub contact: String,
+   pub address: String,
+   pub last_seen: NaiveDateTime,
+   pub language: Option<String>,
+}
+
+impl UserRecord {
+   pub fn from_db(row: &Row) -> Self {
+      Self {
+         user_id: row.get(0),
+         name: row.get(1),
+         contact: row.get(2),
+         address: row.get(3),
+         last_seen: row.get(4),
+         language: row.get(5),
+      }
+   }
+}
+
+// Возвращает пользователей, начиная с недавно заходивших
+pub async fn user_list(limit: i64, offset: i64) -> Option<Vec<UserRecord>> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   // Выполняем запрос
+   let rows = client.query("SELECT u.user_id, u.user_name, u.contact, COALESCE(a.address, u.address), u.last_seen, u.language FROM users AS u
+      LEFT JOIN addresses AS a ON a.user_id = u.user_id AND a.is_default
+      ORDER BY u.last_seen DESC LIMIT $1::BIGINT OFFSET $2::BIGINT", &[&limit, &offset])
+   .await;
+
+   // Возвращаем результат
+   match rows {
+      Ok(data) => Some(data.into_iter().map(|row| UserRecord::from_db(&row)).collect()),
+      Err(e) => {
+         settings::log(&format!("db::user_list: {}", e)).await;
+         None
+      }
+   }
+}
+
 // Информация о пользователе для корзины
 pub struct UserBasketInfo {
    pub name: String, 
    pub contact: String, 
-   pub address: String,    // лежит либо текст с адресом либо LocationNNN, где NNN это id сообщения с локацией
+   pub address: Option<Address>,          // адрес по умолчанию из адресной книги
    pub pickup: bool,
+   pub order_time: Option<NaiveDateTime>, // желаемое время доставки или самовывоза, None - как можно скорее
+   pub promo_code: Option<String>,        // введённый промокод
 }
 
 impl UserBasketInfo {
    pub fn from_db(row: &Row) -> Self {
+      // Адреса может не быть вовсе
+      let address_id: Option<i32> = row.get(5);
+      let address = address_id.map(|id| Address {
+         id,
+         label: row.get(6),
+         address: row.get(7),
+         location: match (row.get(8), row.get(9)) {
+            (Some(latitude), Some(longitude)) => Some((latitude, longitude)),
+            _ => None,
+         },
+         is_default: true,
+      });
+
       Self {
          name: row.get(0),
          contact: row.get(1),
-         address: row.get(2),
-         pickup: row.get(3),
+         address,
+         pickup: row.get(2),
+         order_time: row.get(3),
+         promo_code: row.get(4),
       }
    }
 
-   // Возвращает истину, если адрес задан геопозицией
-   pub fn is_geolocation(&self) -> bool {
-      return self.address.get(..8).unwrap_or_default() == "Location";
-   }
-
-   // Возвращает либо сам адрес либо надпись, что задана точка
-   pub fn address_label(&self) -> String {
-      // Если адрес начинается с ключевого слова, значит там id сообщения с локацией
-      if self.is_geolocation() {String::from("на карте")} else {self.address.clone()}
+   // Возвращает желаемое время заказа для отображения
+   pub fn order_time_label(&self, lang: &str) -> String {
+      order_time_to_str(lang, self.order_time)
    }
 
-   // Возвращает id сообщения с локацией, если имеется и не самовывоз
-   pub fn address_message_id(&self) -> Option<i32> {
-      if self.pickup {return None;}
-
-      if self.is_geolocation() {
-         // Пытаемся получить продолжение строки
-         if let Some(s) = self.address.get(8..) {
-            // Пытаемся преобразовать в число.
-            if let Ok(res) = s.parse::<i32>() {Some(res)} else {None}
-         } else {None}
-      } else {None}
+   // Возвращает адрес по умолчанию для отображения
+   pub fn address_label(&self, lang: &str) -> String {
+      match &self.address {
+         Some(address) => address.text(lang),
+         None => lang::t(lang, No),
+      }
    }
 }
 
@@ -1084,7 +1634,9 @@ pub async fn user_basket_info(user_id: i32) -> Option<UserBasketInfo> {
    // Получаем клиента БД
    let client = db_client().await?;
 
-   let query = client.query("SELECT user_name, contact, address, pickup from users WHERE user_id=$1::INTEGER", &[&user_id])
+   let query = client.query("SELECT u.user_name, u.contact, u.pickup, u.order_time, u.promo_code, a.address_id, a.label, a.address, a.latitude, a.longitude FROM users AS u
+      LEFT JOIN addresses AS a ON a.user_id = u.user_id AND a.is_default
+      WHERE u.user_id=$1::INTEGER", &[&user_id])
    .await;
 
    match query {
@@ -1107,11 +1659,11 @@ pub async fn basket_edit_name(user_id: i32, s: String) -> bool {
    .await
 }
 
-// Возврат имени пользователя
-pub async fn user_name_by_id(user_id: i32) -> String {
+// Возврат имени пользователя, при ошибке на языке получателя
+pub async fn user_name_by_id(lang: &str, user_id: i32) -> String {
    // Получаем клиента БД
    let client = db_client().await;
-   if client.is_none() {return String::from("Неизвестное имя");}
+   if client.is_none() {return lang::t(lang, UnknownName);}
 
    // Выполняем запрос
    let query = client.unwrap()
@@ -1120,8 +1672,8 @@ pub async fn user_name_by_id(user_id: i32) -> String {
    match query {
        Ok(data) => data.get(0),
        Err(e) => {
-         settings::log(&format!("Error db::basket_edit_name: {}", e)).await;
-         String::from("Неизвестное имя")
+         settings::log(&format!("Error db::user_name_by_id: {}", e)).await;
+         lang::t(lang, UnknownName)
        }
    }
 }
@@ -1132,24 +1684,144 @@ pub async fn basket_edit_contact(user_id: i32, s: String) -> bool {
    .await
 }
 
-// Изменение адреса пользователя
-pub async fn basket_edit_address(user_id: i32, s: String) -> bool {
-   execute_one("UPDATE users SET address = $1::VARCHAR(100) WHERE user_id=$2::INTEGER", &[&s, &user_id])
-   .await
-}
-
 // Изменение способа доставки
 pub async fn basket_toggle_pickup(user_id: i32) -> bool {
    execute_one("UPDATE users SET pickup = NOT pickup WHERE user_id=$1::INTEGER", &[&user_id])
    .await
 }
 
+// Изменение желаемого времени заказа, None означает "как можно скорее"
+pub async fn basket_edit_order_time(user_id: i32, order_time: Option<NaiveDateTime>) -> bool {
+   execute_one("UPDATE users SET order_time = $1::TIMESTAMP WHERE user_id=$2::INTEGER", &[&order_time, &user_id])
+   .await
+}
+
+// Изменение промокода в корзине, None для удаления
+pub async fn basket_edit_promo_code(user_id: i32, code: Option<String>) -> bool {
+   execute_one("UPDATE users SET promo_code = $1::VARCHAR(32) WHERE user_id=$2::INTEGER", &[&code, &user_id]).await
+}
+
+// ============================================================================
+// [Addresses table]
+// ============================================================================
+// Адрес доставки из адресной книги едока
+pub struct Address {
+   pub id: i32,
+   pub label: String,                 // метка вроде "Дом" или "Офис", может быть пустой
+   pub address: String,               // текст адреса, для точки на карте её координаты
+   pub location: Option<(f64, f64)>,  // широта и долгота, если адрес задан точкой на карте
+   pub is_default: bool,
+}
+
+impl Address {
+   pub fn from_db(row: &Row) -> Self {
+      Self {
+         id: row.get(0),
+         label: row.get(1),
+         address: row.get(2),
+         location: match (row.get(3), row.get(4)) {
+            (Some(latitude), Some(longitude)) => Some((latitude, longitude)),
+            _ => None,
+         },
+         is_default: row.get(5),
+      }
+   }
+
+   // Адрес для отображения вместе с меткой
+   pub fn text(&self, lang: &str) -> String {
+      let address = if self.location.is_some() {lang::t(lang, AddressOnMap)} else {self.address.clone()};
+      if self.label.is_empty() {address} else {format!("{}: {}", self.label, address)}
+   }
+
+   // Краткое название, например для кнопки
+   pub fn title(&self, lang: &str) -> String {
+      if self.label.is_empty() {self.text(lang)} else {self.label.clone()}
+   }
+}
+
+// Адресная книга едока, адрес по умолчанию первым
+pub async fn address_list(user_id: i32) -> Option<Vec<Address>> {
+   let client = db_client().await?;
+   let rows = client.query("SELECT address_id, label, address, latitude, longitude, is_default FROM addresses
+      WHERE user_id = $1::INTEGER ORDER BY is_default DESC, address_id", &[&user_id])
+   .await;
+
+   match rows {
+      Ok(data) => Some(data.into_iter().map(|row| Address::from_db(&row)).collect()),
+      Err(e) => {
+         settings::log(&format!("db::address_list: {}", e)).await;
+         None
+      }
+   }
+}
+
+// Адрес едока по коду, 0 означает адрес по умолчанию
+pub async fn address(user_id: i32, address_id: i32) -> Option<Address> {
+   let client = db_client().await?;
+   let rows = client.query("SELECT address_id, label, address, latitude, longitude, is_default FROM addresses
+      WHERE user_id = $1::INTEGER AND (address_id = $2::INTEGER OR ($2::INTEGER = 0 AND is_default))", &[&user_id, &address_id])
+   .await;
+
+   match rows {
+      Ok(data) => data.first().map(Address::from_db),
+      Err(e) => {
+         settings::log(&format!("db::address: {}", e)).await;
+         None
+      }
+   }
+}
+
+// Добавляет адрес и делает его адресом по умолчанию, возвращает его код
+pub async fn address_add(user_id: i32, address: &str, location: Option<(f64, f64)>) -> Option<i32> {
+   let client = db_client().await?;
+   let (latitude, longitude) = match location {
+      Some((latitude, longitude)) => (Some(latitude), Some(longitude)),
+      None => (None, None),
+   };
+   let address: String = address.chars().take(100).collect();
+   let query = client.query_one("WITH reset AS (UPDATE addresses SET is_default = FALSE WHERE user_id = $1::INTEGER)
+      INSERT INTO addresses (user_id, label, address, latitude, longitude, is_default)
+      VALUES ($1::INTEGER, '', $2::VARCHAR(100), $3::FLOAT8, $4::FLOAT8, TRUE)
+      RETURNING address_id", &[&user_id, &address, &latitude, &longitude])
+   .await;
+
+   match query {
+      Ok(row) => Some(row.get(0)),
+      Err(e) => {
+         settings::log(&format!("db::address_add({}): {}", user_id, e)).await;
+         None
+      }
+   }
+}
+
+// Задаёт метку адреса
+pub async fn address_edit_label(user_id: i32, address_id: i32, label: &str) -> bool {
+   let label: String = label.chars().take(32).collect();
+   execute_one("UPDATE addresses SET label = $1::VARCHAR(32) WHERE user_id = $2::INTEGER AND address_id = $3::INTEGER", &[&label, &user_id, &address_id]).await
+}
+
+// Делает адрес адресом по умолчанию
+pub async fn address_set_default(user_id: i32, address_id: i32) -> bool {
+   // Чужой или несуществующий адрес ничего не меняет, иначе у пользователя не осталось бы адреса по умолчанию
+   execute("UPDATE addresses SET is_default = (address_id = $2::INTEGER) WHERE user_id = $1::INTEGER
+      AND EXISTS (SELECT 1 FROM addresses WHERE user_id = $1::INTEGER AND address_id = $2::INTEGER)", &[&user_id, &address_id]).await
+}
+
+// Удаляет адрес, при удалении адреса по умолчанию им станет последний добавленный
+pub async fn address_remove(user_id: i32, address_id: i32) -> bool {
+   if !execute_one_no_error("DELETE FROM addresses WHERE user_id = $1::INTEGER AND address_id = $2::INTEGER", &[&user_id, &address_id]).await {
+      return false;
+   }
+   execute("UPDATE addresses SET is_default = TRUE WHERE address_id = (SELECT MAX(address_id) FROM addresses WHERE user_id = $1::INTEGER)
+      AND NOT EXISTS (SELECT 1 FROM addresses WHERE user_id = $1::INTEGER AND is_default)", &[&user_id]).await
+}
+
 // ============================================================================
 // [Orders table]
 // ============================================================================
 
 // Перемещает заказ из таблицы orders в tickets
-pub async fn order_to_ticket(eater_id: i32, caterer_id: i32, eater_order_msg_id: i32, caterer_order_msg_id: i32) -> bool {
+pub async fn order_to_ticket(eater_id: i32, caterer_id: i32, eater_order_msg_id: i32, caterer_order_msg_id: i32, promo: Option<&PromoResult>) -> bool {
    // Получаем клиента БД
    let client = db_client().await;
    if client.is_none() {return false;}
@@ -1165,26 +1837,90 @@ pub async fn order_to_ticket(eater_id: i32, caterer_id: i32, eater_order_msg_id:
    }
    let trans = trans.unwrap();
 
-   // Удаляем все блюда ресторана из orders
-   let res = trans.execute("DELETE FROM orders o USING restaurants r WHERE o.rest_num = r.rest_num AND o.user_id = $1::INTEGER AND r.user_id = $2::INTEGER", &[&eater_id, &caterer_id])
-   .await;
-   match res {
-      Ok(_) => {
-         // Создаём запись в tickets
-         let res = trans.execute("INSERT INTO tickets (eater_id, caterer_id, eater_msg_id, caterer_msg_id, eater_status_msg_id, caterer_status_msg_id, stage) VALUES ($1::INTEGER, $2::INTEGER, $3::INTEGER, $4::INTEGER, NULL, NULL, 1)", &[&eater_id, &caterer_id, &eater_order_msg_id, &caterer_order_msg_id])
-         .await;
+   // Создаём запись в tickets
+   // Скидка учитывается, только если промокод действительно применён
+   let (promo_code, discount) = match promo {
+      Some(PromoResult {code, discount, error: None}) => (Some(code.clone()), *discount),
+      _ => (None, 0),
+   };
+
+   // Промокод блокируется до конца транзакции, чтобы одновременные заказы проверяли его лимиты по очереди
+   if let Some(code) = &promo_code {
+      if let Err(e) = trans.query("SELECT code FROM promo_codes WHERE code=$1::VARCHAR(32) FOR UPDATE", &[code]).await {
+         settings::log(&format!("db::order_to_ticket promo lock: {}", e)).await;
+         return false;
+      }
+   }
+
+   let created = settings::current_date_time();
+   let res = trans.query_one("INSERT INTO tickets (eater_id, caterer_id, eater_msg_id, caterer_msg_id, eater_status_msg_id, caterer_status_msg_id, stage, order_time, created, payment_required, promo_code, discount) 
+      VALUES ($1::INTEGER, $2::INTEGER, $3::INTEGER, $4::INTEGER, NULL, NULL, 1, (SELECT order_time FROM users WHERE user_id = $1::INTEGER), $5::TIMESTAMP,
+         (SELECT payment_token IS NOT NULL FROM restaurants WHERE user_id = $2::INTEGER), $6::VARCHAR(32), $7::INTEGER)
+      RETURNING ticket_id", &[&eater_id, &caterer_id, &eater_order_msg_id, &caterer_order_msg_id, &created, &promo_code, &discount])
+   .await;
+   match res {
+      Ok(row) => {
+         let ticket_id: i32 = row.get(0);
+
+         // Учтём использование промокода, если лимиты ещё позволяют, иначе заказ не оформляется
+         if let Some(code) = promo_code {
+            let res = trans.execute("INSERT INTO promo_uses (ticket_id, code, user_id)
+               SELECT $1::INTEGER, p.code, $3::INTEGER FROM promo_codes p WHERE p.code = $2::VARCHAR(32)
+               AND (p.max_uses = 0 OR (SELECT COUNT(*) FROM promo_uses u WHERE u.code = p.code) < p.max_uses)
+               AND (p.max_uses_per_user = 0 OR (SELECT COUNT(*) FROM promo_uses u WHERE u.code = p.code AND u.user_id = $3::INTEGER) < p.max_uses_per_user)",
+               &[&ticket_id, &code, &eater_id])
+            .await;
+            match res {
+               Ok(1) => (),
+               Ok(_) => {
+                  settings::log(&format!("db::order_to_ticket({}, {}): promo code {} is no longer available", eater_id, caterer_id, code)).await;
+                  return false;
+               }
+               Err(e) => {
+                  settings::log(&format!("db::order_to_ticket promo: {}", e)).await;
+                  return false;
+               }
+            }
+         }
+
+         // Сохраняем состав заказа для истории
+         let res = trans.execute("INSERT INTO ticket_items (ticket_id, rest_num, group_num, dish_num, title, price, amount)
+            SELECT $1::INTEGER, o.rest_num, o.group_num, o.dish_num, d.title, d.price, o.amount FROM orders o
+            INNER JOIN restaurants r ON o.rest_num = r.rest_num
+            INNER JOIN dishes d ON o.rest_num = d.rest_num AND o.group_num = d.group_num AND o.dish_num = d.dish_num
+            WHERE o.user_id = $2::INTEGER AND r.user_id = $3::INTEGER", &[&ticket_id, &eater_id, &caterer_id])
+         .await;
          match res {
             Ok(_) => {
-               // Завершаем транзацию и возвращаем успех
-               match trans.commit().await {
-                  Ok(_) => return true,
-                  Err(e) => settings::log(&format!("db::order_to_ticket commit: {}", e)).await,
+               // Удаляем все блюда ресторана из orders
+               let res = trans.execute("DELETE FROM orders o USING restaurants r WHERE o.rest_num = r.rest_num AND o.user_id = $1::INTEGER AND r.user_id = $2::INTEGER", &[&eater_id, &caterer_id])
+               .await;
+               if let Err(e) = res {
+                  settings::log(&format!("db::order_to_ticket delete from orders: {}", e)).await;
+                  return false;
+               }
+
+               // Желаемое время относится ко всей корзине, поэтому сбрасываем его, когда заказаны все рестораны
+               let res = trans.execute("UPDATE users SET order_time = NULL WHERE user_id = $1::INTEGER AND NOT EXISTS (SELECT 1 FROM orders WHERE user_id = $1::INTEGER)", &[&eater_id])
+               .await;
+               match res {
+                  Ok(_) => {
+                     // Завершаем транзацию и возвращаем успех
+                     match trans.commit().await {
+                        Ok(_) => {
+                           metrics::ticket_stage(0, 1);
+                           return true;
+                        }
+                        Err(e) => settings::log(&format!("db::order_to_ticket commit: {}", e)).await,
+                     }
+                  }
+                  Err(e) => settings::log(&format!("db::order_to_ticket reset order time: {}", e)).await,
                }
             }
-            Err(e) => settings::log(&format!("db::order_to_ticket insert: {}", e)).await,
+            Err(e) => settings::log(&format!("db::order_to_ticket insert items: {}", e)).await,
          }
       }
-      Err(e) => settings::log(&format!("db::order_to_ticket delete from orders: {}", e)).await,
+      Err(e) => settings::log(&format!("db::order_to_ticket insert: {}", e)).await,
    }
    false
 }
@@ -1301,7 +2037,10 @@ pub async fn add_dish_to_basket(rest_num: i32, group_num: i32, dish_num: i32, us
 
          // Возвращаем результат
          match rows {
-            Ok(1) => return Ok(old_amount + 1),
+            Ok(1) => {
+               metrics::basket_addition();
+               return Ok(old_amount + 1);
+            }
             Err(e) => settings::log(&format!("db::add_dish_to_basket (rest_num={}, group_num={}, dish_num={}, user_id={}): {}", rest_num, group_num, dish_num, user_id, e)).await,
             _ => settings::log(&format!("db::add_dish_to_basket more than 1 (rest_num={}, group_num={}, dish_num={}, user_id={})", rest_num, group_num, dish_num, user_id)).await,
          }
@@ -1356,6 +2095,17 @@ pub struct Basket {
    pub restaurant: String,
    pub dishes: Vec<String>,
    pub total: i32,
+   pub promo: Option<PromoResult>,  // промокод едока применительно к этому ресторану
+}
+
+impl Basket {
+   // Скидка по промокоду, если он применим
+   pub fn discount(&self) -> i32 {
+      match &self.promo {
+         Some(PromoResult {discount, error: None, ..}) => *discount,
+         _ => 0,
+      }
+   }
 }
 
 // Содержимое корзин всех ресторанов
@@ -1365,7 +2115,7 @@ pub struct Baskets {
 }
 
 // Возвращает содержимое корзины всех ресторанов и итоговую сумму заказа
-pub async fn basket_contents(user_id: i32) -> Option<Baskets> {
+pub async fn basket_contents(lang: &str, user_id: i32) -> Option<Baskets> {
    // Получим клиента БД из пула
    let client = db_client().await?;
 
@@ -1397,11 +2147,11 @@ pub async fn basket_contents(user_id: i32) -> Option<Baskets> {
                   let rest_id: i32 = record.get(3);
          
                   // Создаём корзину ресторана
-                  let basket_opt = basket_content(user_id, rest_num, rest_id, &rest_title, &rest_info, false).await;
+                  let basket_opt = basket_content(lang, user_id, rest_num, rest_id, &rest_title, &rest_info, false).await;
          
                   if let Some(basket) = basket_opt {
                      // Обновляем общий итог
-                     grand_total += basket.total;
+                     grand_total += basket.total - basket.discount();
 
                      // Помещаем ресторан в список
                      baskets.push(basket);
@@ -1420,7 +2170,7 @@ pub async fn basket_contents(user_id: i32) -> Option<Baskets> {
 }
 
 // Возвращает содержимое корзины и итоговую сумму заказа
-pub async fn basket_content(user_id: i32, rest_num: i32, rest_id: i32, rest_title: &String, rest_info: &String, no_commands: bool) -> Option<Basket> {
+pub async fn basket_content(lang: &str, user_id: i32, rest_num: i32, rest_id: i32, rest_title: &String, rest_info: &String, no_commands: bool) -> Option<Basket> {
    // Получим клиента БД из пула
    let client = db_client().await?;
 
@@ -1443,6 +2193,9 @@ pub async fn basket_content(user_id: i32, rest_num: i32, rest_id: i32, rest_titl
                let mut total: i32 = 0;
                let mut dishes = Vec::<String>::new();
 
+               // Суммы по группам для проверки промокода
+               let mut group_sums = Vec::<(i32, i32)>::new();
+
                // Двигаемся по каждой записи и сохраняем информацию о блюде
                for record in data {
                   // Данные из запроса
@@ -1454,24 +2207,28 @@ pub async fn basket_content(user_id: i32, rest_num: i32, rest_id: i32, rest_titl
 
                   // Добавляем стоимость в итог
                   total += price * amount;
+                  group_sums.push((group_num, price * amount));
 
                   // Строка с информацией о блюде - с командами или без
-                  let s = if no_commands {
-                     format!("{}: {} x {} шт. = {}", title, price, amount, settings::price_with_unit(price * amount))
-                  } else {
-                     format!("{}: {} x {} шт. = {} /del{}", title, price, amount, settings::price_with_unit(price * amount), make_key_3_int(rest_num, group_num, dish_num))
+                  let s = lang::tf(lang, BasketDishLine, &[&title, &price, &amount, &settings::price_with_unit(price * amount)]);
+                  let s = if no_commands {s} else {
+                     format!("{} /del{}", s, make_key_3_int(rest_num, group_num, dish_num))
                   };
 
                   // Помещаем блюдо в список
                   dishes.push(s);
                }
             
+               // Применим промокод, если он введён
+               let promo = promo_apply(lang, user_id, rest_num, &group_sums).await;
+
                // Возвращаем результат
                return Some(Basket{
                   rest_id,
                   restaurant: format!("{}. {}. {}\n", rest_num, rest_title, rest_info),
                   dishes,
                   total,
+                  promo,
                })
             }
             Err(e) => settings::log(&format!("db::basket_content: {}", e)).await,
@@ -1482,11 +2239,55 @@ pub async fn basket_content(user_id: i32, rest_num: i32, rest_id: i32, rest_titl
    None
 }
 
+// Добавляет в корзину сразу несколько порций блюда
+pub async fn basket_add_amount(rest_num: i32, group_num: i32, dish_num: i32, user_id: i32, amount: i32) -> bool {
+   metrics::basket_addition();
+   execute_one("INSERT INTO orders (rest_num, group_num, dish_num, user_id, amount) VALUES ($1::INTEGER, $2::INTEGER, $3::INTEGER, $4::INTEGER, $5::INTEGER)
+      ON CONFLICT (user_id, rest_num, group_num, dish_num) DO UPDATE SET amount = orders.amount + EXCLUDED.amount",
+      &[&rest_num, &group_num, &dish_num, &user_id, &amount]).await
+}
+
 // Очищает корзину указанного пользователя
 pub async fn clear_basket(user_id: i32) -> bool {
    execute("DELETE FROM orders WHERE user_id = $1::INTEGER", &[&user_id]).await
 }
 
+// Возвращает группы, к которым относятся блюда в корзине пользователя для указанного ресторана
+pub async fn basket_groups(user_id: i32, rest_num: i32) -> Option<GroupList> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   // Выполняем запрос
+   let rows = client.query("SELECT g.rest_num, g.group_num, g.title, g.info, g.active, g.cat_id, g.opening_time, g.closing_time FROM groups as g
+      INNER JOIN (SELECT DISTINCT group_num FROM orders WHERE user_id = $1::INTEGER AND rest_num = $2::INTEGER) o ON g.group_num = o.group_num
+      WHERE g.rest_num = $2::INTEGER", &[&user_id, &rest_num])
+   .await;
+
+   // Возвращаем результат
+   match rows {
+      Ok(data) => if data.is_empty() {None} else {Some(data.into_iter().map(|row| Group::from_db(&row)).collect())},
+      Err(e) => {
+         settings::log(&format!("db::basket_groups: {}", e)).await;
+         None
+      }
+   }
+}
+
+// Первое заведение в корзине пользователя, по его поясу понимается желаемое время заказа
+pub async fn basket_restaurant(user_id: i32) -> Option<Restaurant> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   let rest_num: Option<i32> = match client.query_one("SELECT MIN(rest_num) FROM orders WHERE user_id = $1::INTEGER", &[&user_id]).await {
+      Ok(row) => row.get(0),
+      Err(e) => {
+         settings::log(&format!("db::basket_restaurant: {}", e)).await;
+         None
+      }
+   };
+   restaurant(RestBy::Num(rest_num?)).await
+}
+
 // ============================================================================
 // [Tickets table]
 // ============================================================================
@@ -1496,10 +2297,15 @@ pub struct Ticket {
    pub eater_id: i32,                     // Уникальный ключ БД
    pub caterer_id: i32,                   // Уникальный ключ БД
    pub eater_order_msg_id: i32,           // Сообщение с самим заказом в чате с едоком
-   pub caterer_order_msg_id: i32,         // Сообщение с самим заказом в чате с ресторатором
+   pub caterer_order_msg_id: i32,         // Сообщение с самим заказом в чате с ресторатором, 0 - ещё не доставлено
    pub eater_status_msg_id: Option<i32>,  // Сообщение со статусом заказа в чате с едоком
    pub caterer_status_msg_id: Option<i32>,// Сообщение со статусом заказа в чате с ресторатором
    pub stage: i32,
+   pub order_time: Option<NaiveDateTime>, // Время, к которому нужно выполнить заказ, None - как можно скорее
+   pub payment_required: bool,            // Заказ нужно оплатить через бота до начала выполнения
+   pub paid: bool,                        // Заказ оплачен
+   pub promo_code: Option<String>,        // Применённый промокод
+   pub discount: i32,                     // Скидка по промокоду
 }
 
 impl Ticket {
@@ -1513,8 +2319,28 @@ impl Ticket {
          eater_status_msg_id: row.get(5),
          caterer_status_msg_id: row.get(6),
          stage: row.get(7),
+         order_time: row.get(8),
+         payment_required: row.get(9),
+         paid: row.get(10),
+         promo_code: row.get(11),
+         discount: row.get(12),
       }
    }
+
+   // Сообщение с заказом у ресторатора для цитирования, если оно уже доставлено
+   pub fn caterer_reply_to(&self) -> Option<i32> {
+      if self.caterer_order_msg_id != 0 {Some(self.caterer_order_msg_id)} else {None}
+   }
+
+   // Ожидает ли заказ оплаты
+   pub fn is_awaiting_payment(&self) -> bool {
+      self.payment_required && !self.paid
+   }
+
+   // Возвращает желаемое время заказа для отображения
+   pub fn order_time_label(&self, lang: &str) -> String {
+      order_time_to_str(lang, self.order_time)
+   }
 }
 
 // Тип запроса информации о тикете
@@ -1527,6 +2353,7 @@ pub enum TicketBy {
 pub enum TicketListBy {
    EaterId(i32),     // по номеру едока
    CatererId(i32),   // по номеру ресторатора
+   Recent(bool, i64),   // последние, только незавершённые или все, не больше указанного количества
 }
 
 // Для списка тикетов
@@ -1540,9 +2367,12 @@ pub async fn ticket_list_by(by: TicketListBy) -> Option<TicketList> {
    // Выберем нужный текст запроса
    let statement_text =  match by {
       TicketListBy::EaterId(_id) =>
-         "SELECT ticket_id, eater_id, caterer_id, eater_msg_id, caterer_msg_id, eater_status_msg_id, caterer_status_msg_id, stage FROM tickets WHERE eater_id=$1::INTEGER AND stage < 5",
+         "SELECT ticket_id, eater_id, caterer_id, eater_msg_id, caterer_msg_id, eater_status_msg_id, caterer_status_msg_id, stage, order_time, payment_required, paid, promo_code, discount FROM tickets WHERE eater_id=$1::INTEGER AND stage < 5",
       TicketListBy::CatererId(_id) =>
-         "SELECT ticket_id, eater_id, caterer_id, eater_msg_id, caterer_msg_id, eater_status_msg_id, caterer_status_msg_id, stage FROM tickets WHERE caterer_id=$1::INTEGER AND stage < 5",
+         "SELECT ticket_id, eater_id, caterer_id, eater_msg_id, caterer_msg_id, eater_status_msg_id, caterer_status_msg_id, stage, order_time, payment_required, paid, promo_code, discount FROM tickets WHERE caterer_id=$1::INTEGER AND stage < 5",
+      TicketListBy::Recent(_open_only, _limit) =>
+         "SELECT ticket_id, eater_id, caterer_id, eater_msg_id, caterer_msg_id, eater_status_msg_id, caterer_status_msg_id, stage, order_time, payment_required, paid, promo_code, discount FROM tickets
+         WHERE $1::BOOLEAN = FALSE OR stage < 5 ORDER BY ticket_id DESC LIMIT $2::BIGINT",
    };
 
    // Подготовим нужный запрос с кешем благодаря пулу
@@ -1554,6 +2384,7 @@ pub async fn ticket_list_by(by: TicketListBy) -> Option<TicketList> {
          let rows = match by {
             TicketListBy::EaterId(id) => client.query(&stmt, &[&id]).await,
             TicketListBy::CatererId(id) => client.query(&stmt, &[&id]).await,
+            TicketListBy::Recent(open_only, limit) => client.query(&stmt, &[&open_only, &limit]).await,
          };
 
          // Возвращаем результат
@@ -1582,9 +2413,9 @@ pub async fn ticket(by: TicketBy) -> Option<Ticket> {
    // Выберем нужный текст запроса
    let statement_text =  match by {
       TicketBy::TicketId(_id) =>
-         "SELECT ticket_id, eater_id, caterer_id, eater_msg_id, caterer_msg_id, eater_status_msg_id, caterer_status_msg_id, stage FROM tickets WHERE ticket_id=$1::INTEGER",
+         "SELECT ticket_id, eater_id, caterer_id, eater_msg_id, caterer_msg_id, eater_status_msg_id, caterer_status_msg_id, stage, order_time, payment_required, paid, promo_code, discount FROM tickets WHERE ticket_id=$1::INTEGER",
       TicketBy::EaterAndCatererId(_eater_id, _caterer_id) =>
-         "SELECT ticket_id, eater_id, caterer_id, eater_msg_id, caterer_msg_id, eater_status_msg_id, caterer_status_msg_id, stage FROM tickets WHERE eater_id=$1::INTEGER AND caterer_id=$2::INTEGER AND stage < 5",
+         "SELECT ticket_id, eater_id, caterer_id, eater_msg_id, caterer_msg_id, eater_status_msg_id, caterer_status_msg_id, stage, order_time, payment_required, paid, promo_code, discount FROM tickets WHERE eater_id=$1::INTEGER AND caterer_id=$2::INTEGER AND stage < 5",
    };
 
    // Подготовим нужный запрос с кешем благодаря пулу
@@ -1617,21 +2448,100 @@ pub async fn ticket(by: TicketBy) -> Option<Ticket> {
 }
 
 // Сохраняет ссылки на сообщения со статусом для последующего редактирования при изменении тикета
-pub async fn ticket_save_status_msg(ticket_id: i32, eater_status_msg_id: i32, caterer_status_msg_id: i32) -> bool {
-   execute_one("UPDATE tickets SET eater_status_msg_id = $1::INTEGER, caterer_status_msg_id = $2::INTEGER WHERE ticket_id=$3::INTEGER", &[&eater_status_msg_id, &caterer_status_msg_id, &ticket_id])
+pub async fn ticket_save_status_msg(ticket_id: i32, eater_status_msg_id: Option<i32>, caterer_status_msg_id: Option<i32>) -> bool {
+   // Стороны сохраняют свои сообщения независимо, None оставляет прежнее значение
+   execute_one("UPDATE tickets SET eater_status_msg_id = COALESCE($1::INTEGER, eater_status_msg_id), caterer_status_msg_id = COALESCE($2::INTEGER, caterer_status_msg_id)
+      WHERE ticket_id=$3::INTEGER", &[&eater_status_msg_id, &caterer_status_msg_id, &ticket_id])
+   .await
+}
+
+// Сохраняет сообщение с заказом, отправленное едоку после создания тикета
+pub async fn ticket_save_eater_order_msg(ticket_id: i32, eater_order_msg_id: i32) -> bool {
+   execute_one("UPDATE tickets SET eater_msg_id = $1::INTEGER WHERE ticket_id=$2::INTEGER", &[&eater_order_msg_id, &ticket_id])
+   .await
+}
+
+// Сохраняет сообщение с заказом, доставленное ресторатору
+pub async fn ticket_save_caterer_order_msg(ticket_id: i32, caterer_order_msg_id: i32) -> bool {
+   execute_one("UPDATE tickets SET caterer_msg_id = $1::INTEGER WHERE ticket_id=$2::INTEGER", &[&caterer_order_msg_id, &ticket_id])
    .await
 }
 
 // Изменяет стадию заказа
 pub async fn basket_edit_stage(ticket_id: i32, stage: i32) -> bool {
-   execute_one("UPDATE tickets SET stage = $1::INTEGER WHERE ticket_id=$2::INTEGER AND stage < 5", &[&stage, &ticket_id])
+   // Отмена незавершённого заказа возвращает использование промокода
+   update_stage("WITH released AS (DELETE FROM promo_uses WHERE $1::INTEGER = 6 AND ticket_id = $2::INTEGER
+         AND EXISTS (SELECT 1 FROM tickets WHERE ticket_id = $2::INTEGER AND stage < 5))
+      UPDATE tickets t SET stage = $1::INTEGER FROM tickets old
+      WHERE t.ticket_id=$2::INTEGER AND old.ticket_id = t.ticket_id AND t.stage < 5
+      RETURNING old.stage, t.stage", &[&stage, &ticket_id])
    .await
 }
 
 // Увеличивает стадию заказа
 pub async fn basket_next_stage(user_id: i32, ticket_id: i32) -> bool {
    // Выполняем запрос, статус ещё должен быть незавешённым
-   execute_one("UPDATE tickets SET stage = stage + 1 WHERE ticket_id=$1::INTEGER AND stage < 5 AND (stage != 4 OR caterer_id != $2::INTEGER)", &[&ticket_id, &user_id])
+   // Неоплаченный заказ не может начать выполняться
+   update_stage("UPDATE tickets t SET stage = t.stage + 1 FROM tickets old
+      WHERE t.ticket_id=$1::INTEGER AND old.ticket_id = t.ticket_id AND t.stage < 5 AND (t.stage != 4 OR t.caterer_id != $2::INTEGER)
+      AND (t.stage > 1 OR t.paid OR NOT t.payment_required)
+      RETURNING old.stage, t.stage", &[&ticket_id, &user_id])
+   .await
+}
+
+// Выполняет запрос на изменение стадии одного заказа, возвращающий прежнюю и новую стадии, и учитывает переход в метриках
+async fn update_stage(sql_text: &str, params: &[&(dyn ToSql + Sync)]) -> bool {
+   // Получим клиента БД из пула
+   let client = db_client().await;
+   if client.is_none() {return false;}
+
+   // Выполняем запрос
+   let query = client.unwrap().query(sql_text, params).await;
+
+   // При успешной операции должна быть обновлена 1 запись
+   match query {
+      Ok(rows) if rows.len() == 1 => {
+         metrics::ticket_stage(rows[0].get(0), rows[0].get(1));
+         true
+      }
+      Ok(rows) => {
+         settings::log(&format!("db::update_stage({}): updated {} records instead one", sql_text, rows.len())).await;
+         false
+      }
+      Err(e) => {
+         settings::log(&format!("db::update_stage({}): {}", sql_text, e)).await;
+         false
+      }
+   }
+}
+
+// Возвращает незавершённые заказы ко времени, до которого осталось не больше minutes минут
+pub async fn ticket_list_to_remind(minutes: i32) -> Option<TicketList> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   // Время заказа задано по часовому поясу заведения
+   let statement_text = format!("SELECT ticket_id, eater_id, caterer_id, eater_msg_id, caterer_msg_id, eater_status_msg_id, caterer_status_msg_id, stage, order_time, payment_required, paid, promo_code, discount FROM tickets 
+      WHERE stage < 5 AND reminded = FALSE AND order_time IS NOT NULL AND order_time <= {} + make_interval(mins => $2::INTEGER)",
+      local_now("$1", "(SELECT z.time_zone FROM restaurants z WHERE z.user_id = tickets.caterer_id)"));
+
+   // Выполняем запрос
+   let rows = client.query(statement_text.as_str(), &[&default_zone(), &minutes])
+   .await;
+
+   // Возвращаем результат
+   match rows {
+      Ok(data) => if data.is_empty() {None} else {Some(data.into_iter().map(|row| Ticket::from_db(&row)).collect())},
+      Err(e) => {
+         settings::log(&format!("db::ticket_list_to_remind: {}", e)).await;
+         None
+      }
+   }
+}
+
+// Отмечает, что напоминание о заказе отправлено
+pub async fn ticket_set_reminded(ticket_id: i32) -> bool {
+   execute_one("UPDATE tickets SET reminded = TRUE WHERE ticket_id=$1::INTEGER", &[&ticket_id])
    .await
 }
 
@@ -1650,170 +2560,1459 @@ pub async fn basket_stage(ticket_id: i32) -> i32 {
    }
 }
 
+// Отмечает заказ оплаченным, если он ещё ожидает оплаты
+pub async fn ticket_set_paid(ticket_id: i32, charge_id: &str) -> bool {
+   // Стадию не проверяем - платёж, пришедший после отмены, тоже нужно сохранить для возврата
+   execute_one("UPDATE tickets SET paid = TRUE, charge_id = $1::VARCHAR(256) WHERE ticket_id=$2::INTEGER AND payment_required AND NOT paid", &[&charge_id, &ticket_id])
+   .await
+}
 
-// ============================================================================
-// [Misc]
-// ============================================================================
-// Для отображения статуса
-pub fn active_to_str(active : bool) -> &'static str {
-   if active {
-       "показывается"
-   } else {
-       "скрыт"
+// Возвращает сумму заказа по сохранённому составу
+pub async fn ticket_total(ticket_id: i32) -> Option<i32> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   let query = client.query_one("SELECT (SUM(i.price * i.amount) - MIN(t.discount))::INTEGER FROM ticket_items i
+      INNER JOIN tickets t ON t.ticket_id = i.ticket_id
+      WHERE i.ticket_id=$1::INTEGER", &[&ticket_id])
+   .await;
+
+   match query {
+      Ok(row) => row.get(0),
+      Err(e) => {
+         settings::log(&format!("db::ticket_total({}): {}", ticket_id, e)).await;
+         None
+      }
    }
 }
 
-pub fn enabled_to_str(enabled : bool) -> &'static str {
-   if enabled {
-       "доступен"
-   } else {
-       "в бане"
-   }
+// Завершённый или отменённый заказ для истории
+pub struct TicketHistory {
+   pub ticket_id: i32,
+   pub created: Option<NaiveDateTime>,
+   pub stage: i32,
+   pub rest_title: String,
+   pub total: i32,
 }
 
-pub fn enabled_to_cmd(enabled : bool) -> &'static str {
-   if enabled {
-       "/hold"
-   } else {
-       "/regi"
+impl TicketHistory {
+   pub fn from_db(row: &Row) -> Self {
+      Self {
+         ticket_id: row.get(0),
+         created: row.get(1),
+         stage: row.get(2),
+         rest_title: row.get(3),
+         total: row.get(4),
+      }
    }
-}
 
-// Успешно-неуспешно
-pub fn is_success(flag : bool) -> &'static str {
-   if flag {
-      "успешно"
-  } else {
-      "ошибка"
-  }
+   // Дата заказа для отображения
+   pub fn created_label(&self) -> String {
+      match self.created {
+         Some(t) => t.format("%d.%m.%Y %H:%M").to_string(),
+         None => String::from("-"),
+      }
+   }
 }
 
-// Используется при редактировании категории группы
-pub fn id_to_category(cat_id : i32) -> &'static str {
-   match cat_id {
-      1 => "Соки воды",
-      2 => "Еда",
-      3 => "Напитки",
-      4 => "Развлечения",
-      _ => "Неизвестная категория",
-   }
-} 
+// Сколько последних заказов показывать в истории
+const HISTORY_LIMIT: i64 = 10;
 
-pub fn category_to_id(category: &str) -> i32 {
-   match category {
-      "Соки воды" => 1,
-      "Еда" => 2,
-      "Напитки" => 3,
-      "Развлечения" => 4,
-      _ => 0,
+// Возвращает последние завершённые заказы едока, для которых сохранён состав
+pub async fn ticket_history(eater_id: i32) -> Option<Vec<TicketHistory>> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   // Выполняем запрос
+   let rows = client.query("SELECT t.ticket_id, t.created, t.stage, r.title, (SUM(i.price * i.amount) - t.discount)::INTEGER FROM tickets t
+      INNER JOIN restaurants r ON r.user_id = t.caterer_id
+      INNER JOIN ticket_items i ON i.ticket_id = t.ticket_id
+      WHERE t.eater_id = $1::INTEGER AND t.stage >= 5
+      GROUP BY t.ticket_id, t.created, t.stage, r.title
+      ORDER BY t.ticket_id DESC LIMIT $2::BIGINT", &[&eater_id, &HISTORY_LIMIT])
+   .await;
+
+   // Возвращаем результат
+   match rows {
+      Ok(data) => if data.is_empty() {None} else {Some(data.into_iter().map(|row| TicketHistory::from_db(&row)).collect())},
+      Err(e) => {
+         settings::log(&format!("db::ticket_history: {}", e)).await;
+         None
+      }
    }
 }
 
-// Режим интерфейса
-pub fn interface_mode(is_compact: bool) -> String {
-   if is_compact {
-      String::from("со ссылками")
-   } else {
-      String::from("с кнопками")
+// Позиция сохранённого заказа вместе с текущим состоянием блюда
+pub struct TicketItem {
+   pub rest_num: i32,
+   pub group_num: i32,
+   pub dish_num: i32,
+   pub title: String,
+   pub price: i32,                  // цена на момент заказа
+   pub amount: i32,
+   pub current_price: Option<i32>,  // цена сейчас, если блюдо ещё существует
+   pub available: bool,             // блюдо, группа и ресторан сейчас доступны
+}
+
+impl TicketItem {
+   pub fn from_db(row: &Row) -> Self {
+      let available: Option<bool> = row.get(7);
+      Self {
+         rest_num: row.get(0),
+         group_num: row.get(1),
+         dish_num: row.get(2),
+         title: row.get(3),
+         price: row.get(4),
+         amount: row.get(5),
+         current_price: row.get(6),
+         available: available.unwrap_or(false),
+      }
    }
 }
 
-// Формирование ключа блюда на основе аргументов
-pub fn make_key_3_int(first: i32, second: i32, third: i32) -> String {
-   format!("{}_{}_{}", first, second, third)
+// Возвращает состав заказа едока, блюдо считается тем же, если не изменилось его название
+pub async fn ticket_items(ticket_id: i32, eater_id: i32) -> Option<Vec<TicketItem>> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   // Выполняем запрос
+   let rows = client.query("SELECT i.rest_num, i.group_num, i.dish_num, i.title, i.price, i.amount, d.price,
+      d.active AND g.active AND r.active AND r.enabled AND d.title = i.title FROM ticket_items i
+      INNER JOIN tickets t ON t.ticket_id = i.ticket_id
+      LEFT JOIN dishes d ON d.rest_num = i.rest_num AND d.group_num = i.group_num AND d.dish_num = i.dish_num
+      LEFT JOIN groups g ON g.rest_num = i.rest_num AND g.group_num = i.group_num
+      LEFT JOIN restaurants r ON r.rest_num = i.rest_num
+      WHERE i.ticket_id = $1::INTEGER AND t.eater_id = $2::INTEGER
+      ORDER BY i.group_num, i.dish_num", &[&ticket_id, &eater_id])
+   .await;
+
+   // Возвращаем результат
+   match rows {
+      Ok(data) => if data.is_empty() {None} else {Some(data.into_iter().map(|row| TicketItem::from_db(&row)).collect())},
+      Err(e) => {
+         settings::log(&format!("db::ticket_items: {}", e)).await;
+         None
+      }
+   }
 }
 
-// Разбор строки на три числа, например ключа блюда на аргументы
-pub fn parse_key_3_int(text: &str) -> Result<(i32, i32, i32), Box<dyn std::error::Error>> {
-   let first: i32;
-   let second: i32;
-   let third: i32;
 
-   try_scan!(text.bytes() => "{}_{}_{}", first, second, third);
+// ============================================================================
+// [Promo codes]
+// ============================================================================
 
-   Ok((first, second, third))
+// Промокод
+pub struct PromoCode {
+   pub code: String,
+   pub rest_num: i32,                  // 0 для всей площадки
+   pub percent: bool,                  // скидка в процентах, иначе фиксированная сумма
+   pub value: i32,
+   pub min_total: i32,                 // минимальная сумма заказа в ресторане
+   pub expires: Option<NaiveDateTime>,
+   pub max_uses: i32,                  // 0 без ограничений
+   pub max_uses_per_user: i32,         // 0 без ограничений
+   pub group_nums: Option<Vec<i32>>,   // группы, на блюда которых действует скидка, None - на все
 }
 
-// Формирование информации о пользователе для лога
-//
-fn user_info_optional_part(user: &User) -> String {
-   // Строка для возврата результата
-   let mut s = String::default();
+impl PromoCode {
+   pub fn from_db(row: &Row) -> Self {
+      Self {
+         code: row.get(0),
+         rest_num: row.get(1),
+         percent: row.get(2),
+         value: row.get(3),
+         min_total: row.get(4),
+         expires: row.get(5),
+         max_uses: row.get(6),
+         max_uses_per_user: row.get(7),
+         group_nums: row.get(8),
+      }
+   }
 
-   if let Some(last_name) = &user.last_name {
-      s.push_str(&format!(" {}", last_name));
+   pub fn is_expired(&self) -> bool {
+      matches!(self.expires, Some(t) if t < settings::current_date_time())
    }
-   if let Some(username) = &user.username {
-      s.push_str(&format!(" @{}", username));
+
+   // Действует ли скидка на блюда группы
+   fn is_for_group(&self, group_num: i32) -> bool {
+      match &self.group_nums {
+         Some(nums) => nums.contains(&group_num),
+         None => true,
+      }
    }
-   if let Some(language_code) = &user.language_code {
-      s.push_str(&format!(" lang={}", language_code));
+
+   // Описание условий для администратора
+   pub fn description(&self, lang: &str) -> String {
+      let value = if self.percent {format!("{}%", self.value)} else {settings::price_with_unit(self.value)};
+      let rest = if self.rest_num == 0 {lang::t(lang, PromoAllRests)} else {lang::tf(lang, PromoRest, &[&self.rest_num])};
+      let groups = match &self.group_nums {
+         Some(nums) => lang::tf(lang, PromoGroups, &[&nums.iter().map(|n| n.to_string()).collect::<Vec<String>>().join(",")]),
+         None => String::default(),
+      };
+      let expires = match self.expires {
+         Some(t) => lang::tf(lang, PromoUntil, &[&t.format("%d.%m.%Y")]),
+         None => String::default(),
+      };
+      lang::tf(lang, PromoDescription, &[&self.code, &value, &rest, &groups, &settings::price_with_unit(self.min_total), &expires,
+         &uses_limit_to_str(lang, self.max_uses), &uses_limit_to_str(lang, self.max_uses_per_user)])
    }
-   s
-}
 
-pub fn user_info(user: Option<&User>, detail: bool) -> String {
-   if let Some(u) = user {
-      let mut s = format!("{}:{}", u.id, u.first_name);
+   // Скидка для сумм по группам блюд одного ресторана
+   pub fn discount(&self, lang: &str, group_sums: &[(i32, i32)]) -> Result<i32, String> {
+      let total: i32 = group_sums.iter().map(|(_, sum)| sum).sum();
+      if total < self.min_total {
+         return Err(lang::tf(lang, PromoMinTotal, &[&settings::price_with_unit(self.min_total)]));
+      }
 
-      // Эту информацию выводим только для полного описания
-      if detail {
-         s.push_str(&user_info_optional_part(u));
+      // Сумма по блюдам, на которые распространяется скидка
+      let eligible: i32 = group_sums.iter()
+      .filter(|(group_num, _)| self.is_for_group(*group_num))
+      .map(|(_, sum)| sum)
+      .sum();
+      if eligible == 0 {
+         return Err(lang::t(lang, PromoNotForDishes));
       }
-      s
-   } else {
-      String::from("None user info")
+
+      let discount = if self.percent {eligible * self.value / 100} else {self.value};
+      Ok(discount.min(eligible))
    }
 }
 
-
-// Возвращает название стадии
-pub fn stage_to_str(stage: i32) -> String {
-   let res = match stage {
-      1 => "Ожидание подтверждения",
-      2 => "В процессе приготовления",
-      3 => "Готово, идёт доставка",
-      4 => "Подтвердить получение и закрыть заказ",
-      5 => "Завершено",
-      6 => "Отменено",
-      _ => "Ошибка",
-   };
-   String::from(res)
+fn uses_limit_to_str(lang: &str, limit: i32) -> String {
+   if limit > 0 {limit.to_string()} else {lang::t(lang, PromoUnlimited)}
 }
 
-// Удаляет минуты из времени, если они нулевые
-pub fn str_time(time: NaiveTime) -> String {
-   if time.minute() == 0 {
-      time.format("%H").to_string()
-   } else {
-      time.format("%H:%M").to_string()
-   }
+// Результат применения промокода к корзине одного ресторана
+pub struct PromoResult {
+   pub code: String,
+   pub discount: i32,
+   pub error: Option<String>, // причина, по которой промокод не применён
 }
 
-// Обёртка, выполняет запрос, обновляющий 1 запись и возвращает истину, если успешно
-async fn execute_one(sql_text: &str, params: &[&(dyn ToSql + Sync)]) -> bool {
+// Возвращает промокод
+pub async fn promo_code(code: &str) -> Option<PromoCode> {
    // Получим клиента БД из пула
-   let client = db_client().await;
-   if client.is_none() {return false;}
+   let client = db_client().await?;
 
-   // Выполняем запрос
-   let query = client.unwrap().execute(sql_text, params).await;
+   let rows = client.query("SELECT code, rest_num, percent, value, min_total, expires, max_uses, max_uses_per_user, group_nums FROM promo_codes WHERE code=$1::VARCHAR(32)", &[&code])
+   .await;
 
-   // При успешной операции должна быть обновлена 1 запись
-   match query {
-      Ok(1) => true,
-      Ok(n) => {
-         settings::log(&format!("db::execute({}): updated {} records instead one", sql_text, n)).await;
-         false
-      }
+   match rows {
+      Ok(data) => data.first().map(PromoCode::from_db),
       Err(e) => {
-         settings::log(&format!("db::execute({}): {}", sql_text, e)).await;
-         false
+         settings::log(&format!("db::promo_code: {}", e)).await;
+         None
       }
    }
 }
 
-// Подобна execute_one(), но не выводит сообщений об ошибках
+// Возвращает все промокоды
+pub async fn promo_code_list() -> Option<Vec<PromoCode>> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   let rows = client.query("SELECT code, rest_num, percent, value, min_total, expires, max_uses, max_uses_per_user, group_nums FROM promo_codes ORDER BY rest_num, code", &[])
+   .await;
+
+   match rows {
+      Ok(data) => if data.is_empty() {None} else {Some(data.into_iter().map(|row| PromoCode::from_db(&row)).collect())},
+      Err(e) => {
+         settings::log(&format!("db::promo_code_list: {}", e)).await;
+         None
+      }
+   }
+}
+
+// Создаёт или заменяет промокод
+pub async fn promo_code_save(promo: &PromoCode) -> bool {
+   execute_one("INSERT INTO promo_codes (code, rest_num, percent, value, min_total, expires, max_uses, max_uses_per_user, group_nums)
+      VALUES ($1::VARCHAR(32), $2::INTEGER, $3::BOOLEAN, $4::INTEGER, $5::INTEGER, $6::TIMESTAMP, $7::INTEGER, $8::INTEGER, $9::INTEGER[])
+      ON CONFLICT (code) DO UPDATE SET rest_num = EXCLUDED.rest_num, percent = EXCLUDED.percent, value = EXCLUDED.value, min_total = EXCLUDED.min_total,
+      expires = EXCLUDED.expires, max_uses = EXCLUDED.max_uses, max_uses_per_user = EXCLUDED.max_uses_per_user, group_nums = EXCLUDED.group_nums",
+      &[&promo.code, &promo.rest_num, &promo.percent, &promo.value, &promo.min_total, &promo.expires, &promo.max_uses, &promo.max_uses_per_user, &promo.group_nums]).await
+}
+
+// Удаляет промокод
+pub async fn promo_code_remove(code: &str) -> bool {
+   execute_one("DELETE FROM promo_codes WHERE code=$1::VARCHAR(32)", &[&code]).await
+}
+
+// Количество использований промокода всего и указанным едоком
+async fn promo_uses(code: &str, user_id: i32) -> Option<(i64, i64)> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   let query = client.query_one("SELECT COUNT(*), COUNT(*) FILTER (WHERE user_id = $2::INTEGER) FROM promo_uses WHERE code=$1::VARCHAR(32)", &[&code, &user_id])
+   .await;
+
+   match query {
+      Ok(row) => Some((row.get(0), row.get(1))),
+      Err(e) => {
+         settings::log(&format!("db::promo_uses: {}", e)).await;
+         None
+      }
+   }
+}
+
+// Применяет введённый едоком промокод к корзине ресторана, None если промокода нет
+pub async fn promo_apply(lang: &str, user_id: i32, rest_num: i32, group_sums: &[(i32, i32)]) -> Option<PromoResult> {
+   let code = user_basket_info(user_id).await?.promo_code?;
+
+   // Проверки, не зависящие от содержимого корзины
+   let error = match promo_code(&code).await {
+      None => Some(lang::t(lang, PromoNotFound)),
+      Some(promo) => {
+         if promo.rest_num != 0 && promo.rest_num != rest_num {
+            Some(lang::t(lang, PromoOtherRest))
+         } else if promo.is_expired() {
+            Some(lang::t(lang, PromoExpired))
+         } else {
+            match promo_uses(&code, user_id).await {
+               None => Some(lang::t(lang, PromoCheckError)),
+               Some((total, by_user)) => {
+                  if promo.max_uses > 0 && total >= i64::from(promo.max_uses) {
+                     Some(lang::t(lang, PromoExhausted))
+                  } else if promo.max_uses_per_user > 0 && by_user >= i64::from(promo.max_uses_per_user) {
+                     Some(lang::t(lang, PromoUsedUp))
+                  } else {
+                     // Проверки по содержимому корзины
+                     match promo.discount(lang, group_sums) {
+                        Ok(discount) => return Some(PromoResult {code, discount, error: None}),
+                        Err(e) => Some(e),
+                     }
+                  }
+               }
+            }
+         }
+      }
+   };
+
+   Some(PromoResult {code, discount: 0, error})
+}
+
+// ============================================================================
+// [Reviews table]
+// ============================================================================
+
+// Отзыв о выполненном заказе
+pub struct Review {
+   pub review_id: i32,
+   pub ticket_id: i32,
+   pub rest_num: i32,
+   pub eater_id: i32,
+   pub rating: i32,
+   pub comment: Option<String>,
+   pub reply: Option<String>,
+   pub hidden: bool,
+   pub created: NaiveDateTime,
+}
+
+impl Review {
+   pub fn from_db(row: &Row) -> Self {
+      Self {
+         review_id: row.get(0),
+         ticket_id: row.get(1),
+         rest_num: row.get(2),
+         eater_id: row.get(3),
+         rating: row.get(4),
+         comment: row.get(5),
+         reply: row.get(6),
+         hidden: row.get(7),
+         created: row.get(8),
+      }
+   }
+
+   // Оценка звёздочками
+   pub fn stars(&self) -> String {
+      rating_to_stars(self.rating)
+   }
+}
+
+// Возвращает отзыв
+pub async fn review(review_id: i32) -> Option<Review> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   // Выполняем запрос
+   let rows = client.query("SELECT review_id, ticket_id, rest_num, eater_id, rating, comment, reply, hidden, created FROM reviews WHERE review_id=$1::INTEGER", &[&review_id])
+   .await;
+
+   // Возвращаем результат
+   match rows {
+      Ok(data) => data.first().map(Review::from_db),
+      Err(e) => {
+         settings::log(&format!("db::review: {}", e)).await;
+         None
+      }
+   }
+}
+
+// Сколько последних отзывов показывать
+const REVIEWS_LIMIT: i64 = 10;
+
+// Возвращает последние отзывы о ресторане, скрытые только по запросу
+pub async fn review_list(rest_num: i32, with_hidden: bool) -> Option<Vec<Review>> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   // Выполняем запрос
+   let rows = client.query("SELECT review_id, ticket_id, rest_num, eater_id, rating, comment, reply, hidden, created FROM reviews 
+      WHERE rest_num=$1::INTEGER AND (hidden = FALSE OR $2::BOOLEAN)
+      ORDER BY review_id DESC LIMIT $3::BIGINT", &[&rest_num, &with_hidden, &REVIEWS_LIMIT])
+   .await;
+
+   // Возвращаем результат
+   match rows {
+      Ok(data) => if data.is_empty() {None} else {Some(data.into_iter().map(|row| Review::from_db(&row)).collect())},
+      Err(e) => {
+         settings::log(&format!("db::review_list: {}", e)).await;
+         None
+      }
+   }
+}
+
+// Сохраняет оценку завершённого заказа едока, повторная оценка заменяет предыдущую. Возвращает код отзыва
+pub async fn review_rate(ticket_id: i32, eater_id: i32, rating: i32) -> Option<i32> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   // Ресторан определяем по заказу, который должен быть завершён
+   let query = client.query_one("INSERT INTO reviews (ticket_id, rest_num, eater_id, rating, created)
+      SELECT t.ticket_id, r.rest_num, t.eater_id, $3::INTEGER, $4::TIMESTAMP FROM tickets t
+      INNER JOIN restaurants r ON r.user_id = t.caterer_id
+      WHERE t.ticket_id = $1::INTEGER AND t.eater_id = $2::INTEGER AND t.stage = 5
+      ON CONFLICT (ticket_id) DO UPDATE SET rating = EXCLUDED.rating
+      RETURNING review_id", &[&ticket_id, &eater_id, &rating, &settings::current_date_time()])
+   .await;
+
+   match query {
+      Ok(row) => Some(row.get(0)),
+      Err(e) => {
+         settings::log(&format!("db::review_rate({}, {}): {}", ticket_id, eater_id, e)).await;
+         None
+      }
+   }
+}
+
+// Сохраняет комментарий едока к отзыву
+pub async fn review_edit_comment(review_id: i32, eater_id: i32, s: String) -> bool {
+   execute_one("UPDATE reviews SET comment = $1::VARCHAR(512) WHERE review_id=$2::INTEGER AND eater_id=$3::INTEGER", &[&s, &review_id, &eater_id]).await
+}
+
+// Сохраняет ответ ресторатора на отзыв о своём ресторане
+pub async fn review_edit_reply(review_id: i32, caterer_id: i32, s: String) -> bool {
+   execute_one("UPDATE reviews v SET reply = $1::VARCHAR(512) FROM restaurants r WHERE r.rest_num = v.rest_num AND v.review_id=$2::INTEGER AND r.user_id=$3::INTEGER", &[&s, &review_id, &caterer_id]).await
+}
+
+// Скрывает или возвращает отзыв
+pub async fn review_toggle_hidden(review_id: i32) -> bool {
+   execute_one("UPDATE reviews SET hidden = NOT hidden WHERE review_id=$1::INTEGER", &[&review_id]).await
+}
+
+// Средняя оценка и количество отзывов
+pub struct Rating {
+   pub average: f64,
+   pub count: i64,
+}
+
+impl Rating {
+   // Строка для списков вида "⭐4.5 (12)"
+   pub fn label(&self) -> String {
+      format!("⭐{:.1} ({})", self.average, self.count)
+   }
+}
+
+// Возвращает рейтинги ресторанов по видимым отзывам
+pub async fn rest_ratings() -> HashMap<i32, Rating> {
+   let mut res = HashMap::new();
+
+   // Получим клиента БД из пула
+   if let Some(client) = db_client().await {
+      let rows = client.query("SELECT rest_num, AVG(rating)::FLOAT8, COUNT(*) FROM reviews WHERE hidden = FALSE GROUP BY rest_num", &[])
+      .await;
+
+      match rows {
+         Ok(data) => {
+            for row in data {
+               res.insert(row.get(0), Rating{average: row.get(1), count: row.get(2)});
+            }
+         }
+         Err(e) => settings::log(&format!("db::rest_ratings: {}", e)).await,
+      }
+   }
+   res
+}
+
+// Название ресторана с рейтингом, если он есть
+pub fn title_with_rating(title: &str, rating: Option<&Rating>) -> String {
+   match rating {
+      Some(rating) => format!("{} {}", title, rating.label()),
+      None => String::from(title),
+   }
+}
+
+// Оценка звёздочками
+pub fn rating_to_stars(rating: i32) -> String {
+   "⭐".repeat(rating.max(0) as usize)
+}
+
+// ============================================================================
+// [Favorites table]
+// ============================================================================
+
+// Избранное заведение (группа и блюдо нулевые) или блюдо
+pub struct Favorite {
+   pub rest_num: i32,
+   pub group_num: i32,
+   pub dish_num: i32,
+   pub title: String,
+   pub price: i32,
+   pub available: bool, // можно заказать в указанное время
+}
+
+
+// Избранное пользователя с доступностью сейчас, сначала заведения
+pub async fn favorite_list(user_id: i32) -> Option<Vec<Favorite>> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   let statement_text = format!("SELECT f.rest_num, f.group_num, f.dish_num, r.title, 0,
+         r.active AND r.enabled AND EXISTS (SELECT 1 FROM groups g WHERE g.rest_num = r.rest_num AND {opened})
+      FROM favorites f INNER JOIN restaurants r ON r.rest_num = f.rest_num
+      WHERE f.user_id = $1::INTEGER AND f.group_num = 0
+   UNION ALL
+   SELECT f.rest_num, f.group_num, f.dish_num, d.title, d.price,
+         r.active AND r.enabled AND d.active AND {opened}
+      FROM favorites f
+      INNER JOIN restaurants r ON r.rest_num = f.rest_num
+      INNER JOIN groups g ON g.rest_num = f.rest_num AND g.group_num = f.group_num
+      INNER JOIN dishes d ON d.rest_num = f.rest_num AND d.group_num = f.group_num AND d.dish_num = f.dish_num
+      WHERE f.user_id = $1::INTEGER AND f.group_num > 0
+   ORDER BY 2, 1, 3", opened = group_opened_now("$2"));
+
+   let rows = client.query(statement_text.as_str(), &[&user_id, &default_zone()]).await;
+
+   match rows {
+      Ok(data) => if data.is_empty() {None} else {
+         Some(data.into_iter().map(|row| Favorite {
+            rest_num: row.get(0),
+            group_num: row.get(1),
+            dish_num: row.get(2),
+            title: row.get(3),
+            price: row.get(4),
+            available: row.get(5),
+         }).collect())
+      },
+      Err(e) => {
+         // Сообщаем об ошибке и возвращаем пустой результат
+         settings::log(&format!("db::favorite_list({}): {}", user_id, e)).await;
+         None
+      }
+   }
+}
+
+// Находится ли заведение или блюдо в избранном пользователя
+pub async fn is_favorite(user_id: i32, rest_num: i32, group_num: i32, dish_num: i32) -> bool {
+   match db_client().await {
+      Some(client) => {
+         let rows = client.query("SELECT 1 FROM favorites WHERE user_id=$1::INTEGER AND rest_num=$2::INTEGER AND group_num=$3::INTEGER AND dish_num=$4::INTEGER",
+            &[&user_id, &rest_num, &group_num, &dish_num])
+         .await;
+
+         match rows {
+            Ok(data) => !data.is_empty(),
+            Err(e) => {
+               settings::log(&format!("db::is_favorite({}, {}): {}", user_id, make_key_3_int(rest_num, group_num, dish_num), e)).await;
+               false
+            }
+         }
+      }
+      None => false,
+   }
+}
+
+// Добавляет в избранное или убирает из него, возвращает новое состояние
+pub async fn favorite_toggle(user_id: i32, rest_num: i32, group_num: i32, dish_num: i32) -> Option<bool> {
+   let params: [&(dyn ToSql + Sync); 4] = [&user_id, &rest_num, &group_num, &dish_num];
+   if is_favorite(user_id, rest_num, group_num, dish_num).await {
+      if execute_one("DELETE FROM favorites WHERE user_id=$1::INTEGER AND rest_num=$2::INTEGER AND group_num=$3::INTEGER AND dish_num=$4::INTEGER", &params).await {Some(false)}
+      else {None}
+   } else if execute_one("INSERT INTO favorites (user_id, rest_num, group_num, dish_num) VALUES ($1::INTEGER, $2::INTEGER, $3::INTEGER, $4::INTEGER)", &params).await {Some(true)}
+   else {None}
+}
+
+// ============================================================================
+// [Subscriptions table]
+// ============================================================================
+
+// Подписан ли пользователь на рассылки заведения
+pub async fn is_subscribed(user_id: i32, rest_num: i32) -> bool {
+   match db_client().await {
+      Some(client) => {
+         let rows = client.query("SELECT 1 FROM subscriptions WHERE user_id=$1::INTEGER AND rest_num=$2::INTEGER", &[&user_id, &rest_num])
+         .await;
+
+         match rows {
+            Ok(data) => !data.is_empty(),
+            Err(e) => {
+               settings::log(&format!("db::is_subscribed({}, {}): {}", user_id, rest_num, e)).await;
+               false
+            }
+         }
+      }
+      None => false,
+   }
+}
+
+// Подписывает или отписывает, возвращает новое состояние
+pub async fn subscription_toggle(user_id: i32, rest_num: i32) -> Option<bool> {
+   if is_subscribed(user_id, rest_num).await {
+      if unsubscribe(user_id, rest_num).await {Some(false)} else {None}
+   } else if execute_one("INSERT INTO subscriptions (user_id, rest_num) VALUES ($1::INTEGER, $2::INTEGER)", &[&user_id, &rest_num]).await {Some(true)}
+   else {None}
+}
+
+// Отписывает от рассылок заведения, повторная отписка не ошибка
+pub async fn unsubscribe(user_id: i32, rest_num: i32) -> bool {
+   execute("DELETE FROM subscriptions WHERE user_id=$1::INTEGER AND rest_num=$2::INTEGER", &[&user_id, &rest_num]).await
+}
+
+// Количество подписчиков заведения
+pub async fn subscriber_count(rest_num: i32) -> i64 {
+   match db_client().await {
+      Some(client) => {
+         match client.query_one("SELECT COUNT(*) FROM subscriptions WHERE rest_num=$1::INTEGER", &[&rest_num]).await {
+            Ok(row) => row.get(0),
+            Err(e) => {
+               settings::log(&format!("db::subscriber_count({}): {}", rest_num, e)).await;
+               0
+            }
+         }
+      }
+      None => 0,
+   }
+}
+
+// Очередная порция подписчиков после указанного, по возрастанию кода
+pub async fn subscriber_list(rest_num: i32, after: i32, limit: i64) -> Option<Vec<i32>> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   let rows = client.query("SELECT user_id FROM subscriptions WHERE rest_num=$1::INTEGER AND user_id > $2::INTEGER ORDER BY user_id LIMIT $3::BIGINT",
+      &[&rest_num, &after, &limit])
+   .await;
+
+   match rows {
+      Ok(data) => Some(data.into_iter().map(|row| row.get(0)).collect()),
+      Err(e) => {
+         settings::log(&format!("db::subscriber_list({}, {}): {}", rest_num, after, e)).await;
+         None
+      }
+   }
+}
+
+// Рассылка заведения подписчикам
+pub struct Broadcast {
+   pub id: i32,
+   pub rest_num: i32,
+   pub user_id: i32, // автор, получит отчёт о доставке
+   pub text: String,
+   pub image_id: Option<String>,
+   pub created: NaiveDateTime,
+   pub finished: Option<NaiveDateTime>,
+   pub cursor: i32, // код последнего обработанного подписчика
+   pub delivered: i32,
+   pub failed: i32,
+}
+
+impl Broadcast {
+   fn from_db(row: &Row) -> Self {
+      Self {
+         id: row.get(0),
+         rest_num: row.get(1),
+         user_id: row.get(2),
+         text: row.get(3),
+         image_id: row.get(4),
+         created: row.get(5),
+         finished: row.get(6),
+         cursor: row.get(7),
+         delivered: row.get(8),
+         failed: row.get(9),
+      }
+   }
+}
+
+const BROADCAST_FIELDS: &str = "broadcast_id, rest_num, user_id, text, image_id, created, finished, cursor, delivered, failed";
+
+// Сохраняет новую рассылку и возвращает её код
+pub async fn broadcast_add(rest_num: i32, user_id: i32, text: &str, image_id: Option<&str>) -> Option<i32> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   let created = settings::current_date_time();
+   let query = client.query_one("INSERT INTO broadcasts (rest_num, user_id, text, image_id, created) VALUES ($1::INTEGER, $2::INTEGER, $3::TEXT, $4::VARCHAR, $5::TIMESTAMP) RETURNING broadcast_id",
+      &[&rest_num, &user_id, &text, &image_id, &created])
+   .await;
+
+   match query {
+      Ok(row) => Some(row.get(0)),
+      Err(e) => {
+         settings::log(&format!("db::broadcast_add({}): {}", rest_num, e)).await;
+         None
+      }
+   }
+}
+
+// Рассылка по коду
+pub async fn broadcast(broadcast_id: i32) -> Option<Broadcast> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   let statement_text = format!("SELECT {} FROM broadcasts WHERE broadcast_id=$1::INTEGER", BROADCAST_FIELDS);
+   match client.query_opt(statement_text.as_str(), &[&broadcast_id]).await {
+      Ok(row) => row.map(|row| Broadcast::from_db(&row)),
+      Err(e) => {
+         settings::log(&format!("db::broadcast({}): {}", broadcast_id, e)).await;
+         None
+      }
+   }
+}
+
+// Последняя рассылка заведения
+pub async fn broadcast_last(rest_num: i32) -> Option<Broadcast> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   let statement_text = format!("SELECT {} FROM broadcasts WHERE rest_num=$1::INTEGER ORDER BY broadcast_id DESC LIMIT 1", BROADCAST_FIELDS);
+   match client.query_opt(statement_text.as_str(), &[&rest_num]).await {
+      Ok(row) => row.map(|row| Broadcast::from_db(&row)),
+      Err(e) => {
+         settings::log(&format!("db::broadcast_last({}): {}", rest_num, e)).await;
+         None
+      }
+   }
+}
+
+// Коды рассылок, прерванных перезапуском бота
+pub async fn broadcast_unfinished() -> Option<Vec<i32>> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   match client.query("SELECT broadcast_id FROM broadcasts WHERE finished IS NULL ORDER BY broadcast_id", &[]).await {
+      Ok(data) => Some(data.into_iter().map(|row| row.get(0)).collect()),
+      Err(e) => {
+         settings::log(&format!("db::broadcast_unfinished: {}", e)).await;
+         None
+      }
+   }
+}
+
+// Запоминает, до какого подписчика дошла рассылка
+pub async fn broadcast_progress(broadcast_id: i32, cursor: i32, delivered: bool) -> bool {
+   let statement_text = if delivered {
+      "UPDATE broadcasts SET cursor = $2::INTEGER, delivered = delivered + 1 WHERE broadcast_id=$1::INTEGER"
+   } else {
+      "UPDATE broadcasts SET cursor = $2::INTEGER, failed = failed + 1 WHERE broadcast_id=$1::INTEGER"
+   };
+   execute_one(statement_text, &[&broadcast_id, &cursor]).await
+}
+
+// Отмечает рассылку завершённой
+pub async fn broadcast_finish(broadcast_id: i32) -> bool {
+   let finished = settings::current_date_time();
+   execute_one("UPDATE broadcasts SET finished = $2::TIMESTAMP WHERE broadcast_id=$1::INTEGER", &[&broadcast_id, &finished]).await
+}
+
+// Часов между рассылками, заданное администратором для заведения
+pub async fn rest_broadcast_hours(rest_num: i32) -> Option<i32> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   match client.query_opt("SELECT broadcast_hours FROM restaurants WHERE rest_num=$1::INTEGER", &[&rest_num]).await {
+      Ok(row) => row.and_then(|row| row.get(0)),
+      Err(e) => {
+         settings::log(&format!("db::rest_broadcast_hours({}): {}", rest_num, e)).await;
+         None
+      }
+   }
+}
+
+// Задаёт часы между рассылками заведения, пустое значение возвращает настройку по-умолчанию
+pub async fn rest_edit_broadcast_hours(rest_num: i32, hours: Option<i32>) -> bool {
+   execute_one("UPDATE restaurants SET broadcast_hours = $1::INTEGER WHERE rest_num=$2::INTEGER", &[&hours, &rest_num]).await
+}
+
+// ============================================================================
+// [Notifications table]
+// ============================================================================
+
+// Запрошенное уведомление, цель которого стала доступна
+pub struct Notification {
+   pub user_id: i32,
+   pub rest_num: i32,
+   pub group_num: i32,
+   pub dish_num: i32,
+   pub title: String, // заведение, а для группы и блюда ещё и их название через запятую
+}
+
+// Заведение (группа и блюдо нулевые), группа (блюдо нулевое) или блюдо из n можно заказать сейчас
+const NOTIFY_TARGET_READY: &str = "EXISTS (SELECT 1 FROM restaurants r
+      INNER JOIN groups g ON g.rest_num = r.rest_num
+      LEFT JOIN dishes d ON d.rest_num = g.rest_num AND d.group_num = g.group_num AND d.dish_num = n.dish_num
+      WHERE r.rest_num = n.rest_num AND r.active = TRUE AND r.enabled = TRUE
+      AND (n.group_num = 0 OR g.group_num = n.group_num) AND (n.dish_num = 0 OR d.active = TRUE) AND {opened})";
+
+// Можно ли заказать заведение, группу или блюдо сейчас
+pub async fn notify_target_ready(rest_num: i32, group_num: i32, dish_num: i32) -> bool {
+   match db_client().await {
+      Some(client) => {
+         let statement_text = format!("SELECT 1 FROM (SELECT $1::INTEGER AS rest_num, $3::INTEGER AS group_num, $4::INTEGER AS dish_num) n WHERE {}",
+            NOTIFY_TARGET_READY.replace("{opened}", &group_opened_now("$2")));
+         let rows = client.query(statement_text.as_str(), &[&rest_num, &default_zone(), &group_num, &dish_num]).await;
+
+         match rows {
+            Ok(data) => !data.is_empty(),
+            Err(e) => {
+               settings::log(&format!("db::notify_target_ready({}): {}", make_key_3_int(rest_num, group_num, dish_num), e)).await;
+               false
+            }
+         }
+      }
+      None => false,
+   }
+}
+
+// Сохраняет запрос на уведомление, повторный запрос не ошибка
+pub async fn notify_add(user_id: i32, rest_num: i32, group_num: i32, dish_num: i32) -> bool {
+   let created = settings::current_date_time();
+   execute("INSERT INTO notifications (user_id, rest_num, group_num, dish_num, created) VALUES ($1::INTEGER, $2::INTEGER, $3::INTEGER, $4::INTEGER, $5::TIMESTAMP)
+      ON CONFLICT DO NOTHING", &[&user_id, &rest_num, &group_num, &dish_num, &created]).await
+}
+
+// Забирает уведомления, цель которых можно заказать сейчас. Удаление и выборка одним
+// запросом, чтобы одновременный вызов из планировщика и при переключении не отправил уведомление дважды
+pub async fn notify_take_ready() -> Option<Vec<Notification>> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   let statement_text = format!("WITH ready AS (
+         DELETE FROM notifications n WHERE {ready}
+         RETURNING n.user_id, n.rest_num, n.group_num, n.dish_num)
+      SELECT ready.user_id, ready.rest_num, ready.group_num, ready.dish_num,
+         CONCAT_WS(', ', r.title, CASE WHEN ready.dish_num = 0 THEN g.title ELSE d.title END)
+      FROM ready
+      INNER JOIN restaurants r ON r.rest_num = ready.rest_num
+      LEFT JOIN groups g ON g.rest_num = ready.rest_num AND g.group_num = ready.group_num
+      LEFT JOIN dishes d ON d.rest_num = ready.rest_num AND d.group_num = ready.group_num AND d.dish_num = ready.dish_num",
+      ready = NOTIFY_TARGET_READY.replace("{opened}", &group_opened_now("$1")));
+
+   match client.query(statement_text.as_str(), &[&default_zone()]).await {
+      Ok(data) => if data.is_empty() {None} else {
+         Some(data.into_iter().map(|row| Notification {
+            user_id: row.get(0),
+            rest_num: row.get(1),
+            group_num: row.get(2),
+            dish_num: row.get(3),
+            title: row.get(4),
+         }).collect())
+      },
+      Err(e) => {
+         settings::log(&format!("db::notify_take_ready: {}", e)).await;
+         None
+      }
+   }
+}
+
+// Удаляет давние запросы, например, к удалённым блюдам
+pub async fn notify_expire(before: NaiveDateTime) -> bool {
+   execute("DELETE FROM notifications WHERE created < $1::TIMESTAMP", &[&before]).await
+}
+
+// ============================================================================
+// [Group baskets table]
+// ============================================================================
+
+// Общий заказ, который собирается в групповом чате
+pub struct GroupBasket {
+   pub chat_id: i64,
+   pub rest_num: i32,
+   pub initiator: i32,             // кто начал заказ и отправит его в заведение
+   pub initiator_name: String,
+   pub message_id: Option<i32>,    // сообщение с кнопками, которое обновляется при заказе
+}
+
+// Строка общего заказа
+pub struct GroupOrderItem {
+   pub user_id: i32,
+   pub user_name: String,
+   pub title: String,
+   pub price: i32,
+   pub amount: i32,
+   pub group_num: i32,
+   pub dish_num: i32,
+}
+
+// Общий заказ чата
+pub async fn group_basket(chat_id: i64) -> Option<GroupBasket> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   match client.query_opt("SELECT chat_id, rest_num, initiator, initiator_name, message_id FROM group_baskets WHERE chat_id=$1::BIGINT", &[&chat_id]).await {
+      Ok(row) => row.map(|row| GroupBasket {
+         chat_id: row.get(0),
+         rest_num: row.get(1),
+         initiator: row.get(2),
+         initiator_name: row.get(3),
+         message_id: row.get(4),
+      }),
+      Err(e) => {
+         settings::log(&format!("db::group_basket({}): {}", chat_id, e)).await;
+         None
+      }
+   }
+}
+
+// Начинает общий заказ, в чате может собираться только один
+pub async fn group_basket_open(chat_id: i64, rest_num: i32, initiator: i32, initiator_name: &str) -> bool {
+   let created = settings::current_date_time();
+   execute_one("INSERT INTO group_baskets (chat_id, rest_num, initiator, initiator_name, created) VALUES ($1::BIGINT, $2::INTEGER, $3::INTEGER, $4::VARCHAR(100), $5::TIMESTAMP)",
+      &[&chat_id, &rest_num, &initiator, &initiator_name, &created]).await
+}
+
+// Запоминает сообщение с кнопками общего заказа
+pub async fn group_basket_set_message(chat_id: i64, message_id: i32) -> bool {
+   execute_one("UPDATE group_baskets SET message_id = $1::INTEGER WHERE chat_id=$2::BIGINT", &[&message_id, &chat_id]).await
+}
+
+// Отменяет общий заказ вместе с выбранными блюдами
+pub async fn group_basket_close(chat_id: i64) -> bool {
+   execute_one("WITH items AS (DELETE FROM group_orders WHERE chat_id=$1::BIGINT)
+      DELETE FROM group_baskets WHERE chat_id=$1::BIGINT", &[&chat_id]).await
+}
+
+// Добавляет участнику одну порцию блюда
+pub async fn group_order_add(chat_id: i64, user_id: i32, user_name: &str, group_num: i32, dish_num: i32) -> bool {
+   execute_one("INSERT INTO group_orders (chat_id, user_id, user_name, group_num, dish_num, amount) VALUES ($1::BIGINT, $2::INTEGER, $3::VARCHAR(100), $4::INTEGER, $5::INTEGER, 1)
+      ON CONFLICT (chat_id, user_id, group_num, dish_num) DO UPDATE SET amount = group_orders.amount + 1, user_name = EXCLUDED.user_name",
+      &[&chat_id, &user_id, &user_name, &group_num, &dish_num]).await
+}
+
+// Убирает все блюда участника
+pub async fn group_order_clear(chat_id: i64, user_id: i32) -> bool {
+   execute("DELETE FROM group_orders WHERE chat_id=$1::BIGINT AND user_id=$2::INTEGER", &[&chat_id, &user_id]).await
+}
+
+// Состав общего заказа по участникам
+pub async fn group_order_items(chat_id: i64) -> Option<Vec<GroupOrderItem>> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   let rows = client.query("SELECT o.user_id, o.user_name, d.title, d.price, o.amount, o.group_num, o.dish_num FROM group_orders o
+      INNER JOIN group_baskets b ON b.chat_id = o.chat_id
+      INNER JOIN dishes d ON d.rest_num = b.rest_num AND d.group_num = o.group_num AND d.dish_num = o.dish_num
+      WHERE o.chat_id=$1::BIGINT ORDER BY o.user_name, o.user_id, o.group_num, o.dish_num", &[&chat_id])
+   .await;
+
+   match rows {
+      Ok(data) => Some(data.into_iter().map(|row| GroupOrderItem {
+         user_id: row.get(0),
+         user_name: row.get(1),
+         title: row.get(2),
+         price: row.get(3),
+         amount: row.get(4),
+         group_num: row.get(5),
+         dish_num: row.get(6),
+      }).collect()),
+      Err(e) => {
+         settings::log(&format!("db::group_order_items({}): {}", chat_id, e)).await;
+         None
+      }
+   }
+}
+
+// Активные блюда заведения из групп, работающих сейчас
+pub async fn group_basket_menu(rest_num: i32) -> Option<DishList> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   let statement_text = format!("SELECT d.rest_num, d.dish_num, d.title, d.info, d.active, d.group_num, d.price, d.image_id FROM dishes d
+      INNER JOIN restaurants r ON r.rest_num = d.rest_num
+      INNER JOIN groups g ON g.rest_num = d.rest_num AND g.group_num = d.group_num
+      WHERE d.rest_num=$1::INTEGER AND r.active = TRUE AND r.enabled = TRUE AND d.active = TRUE AND d.price > 0 AND {}
+      ORDER BY d.group_num, d.dish_num", group_opened_now("$2"));
+
+   match client.query(statement_text.as_str(), &[&rest_num, &default_zone()]).await {
+      Ok(data) => if data.is_empty() {None} else {Some(data.into_iter().map(|row| Dish::from_db(&row)).collect())},
+      Err(e) => {
+         settings::log(&format!("db::group_basket_menu({}): {}", rest_num, e)).await;
+         None
+      }
+   }
+}
+
+// Превращает общий заказ в один тикет от инициатора и возвращает его код вместе с составом.
+// Первым делом общий заказ забирается из таблицы, поэтому при одновременных нажатиях
+// тикет создаст только одно из них, а для остальных вернётся None
+pub async fn group_basket_to_ticket(chat_id: i64, rest_num: i32, eater_id: i32, caterer_id: i32) -> Option<(i32, Vec<GroupOrderItem>)> {
+   // Получим клиента БД из пула
+   let mut client = db_client().await?;
+
+   // Начинаем транзакцию
+   let trans = match client.transaction().await {
+      Ok(trans) => trans,
+      Err(e) => {
+         settings::log(&format!("db::group_basket_to_ticket: {}", e)).await;
+         return None;
+      }
+   };
+
+   // Забираем общий заказ, если его ещё никто не отправил
+   match trans.query_opt("DELETE FROM group_baskets WHERE chat_id=$1::BIGINT AND rest_num=$2::INTEGER RETURNING chat_id", &[&chat_id, &rest_num]).await {
+      Ok(Some(_)) => (),
+      Ok(None) => return None,
+      Err(e) => {
+         settings::log(&format!("db::group_basket_to_ticket delete basket: {}", e)).await;
+         return None;
+      }
+   }
+
+   // Вместе с ним строки участников, именно они и попадут в заказ
+   let rows = trans.query("WITH o AS (DELETE FROM group_orders WHERE chat_id=$1::BIGINT RETURNING user_id, user_name, group_num, dish_num, amount)
+      SELECT o.user_id, o.user_name, d.title, d.price, o.amount, o.group_num, o.dish_num FROM o
+      INNER JOIN dishes d ON d.rest_num = $2::INTEGER AND d.group_num = o.group_num AND d.dish_num = o.dish_num
+      ORDER BY o.user_name, o.user_id, o.group_num, o.dish_num", &[&chat_id, &rest_num])
+   .await;
+   let items: Vec<GroupOrderItem> = match rows {
+      Ok(data) => data.into_iter().map(|row| GroupOrderItem {
+         user_id: row.get(0),
+         user_name: row.get(1),
+         title: row.get(2),
+         price: row.get(3),
+         amount: row.get(4),
+         group_num: row.get(5),
+         dish_num: row.get(6),
+      }).collect(),
+      Err(e) => {
+         settings::log(&format!("db::group_basket_to_ticket delete items: {}", e)).await;
+         return None;
+      }
+   };
+   if items.is_empty() {
+      return None;
+   }
+
+   // Заказ как можно скорее и без промокода, сообщения с заказом сохраняются после отправки
+   let created = settings::current_date_time();
+   let ticket_id: i32 = match trans.query_one("INSERT INTO tickets (eater_id, caterer_id, eater_msg_id, caterer_msg_id, eater_status_msg_id, caterer_status_msg_id, stage, order_time, created, payment_required, promo_code, discount)
+      VALUES ($1::INTEGER, $2::INTEGER, 0, 0, NULL, NULL, 1, NULL, $3::TIMESTAMP,
+         (SELECT payment_token IS NOT NULL FROM restaurants WHERE user_id = $2::INTEGER), NULL, 0)
+      RETURNING ticket_id", &[&eater_id, &caterer_id, &created])
+   .await {
+      Ok(row) => row.get(0),
+      Err(e) => {
+         settings::log(&format!("db::group_basket_to_ticket insert ticket: {}", e)).await;
+         return None;
+      }
+   };
+
+   // Одинаковые блюда разных участников складываем в одну строку
+   let mut dishes: BTreeMap<(i32, i32), (&str, i32, i32)> = BTreeMap::new();
+   for item in &items {
+      dishes.entry((item.group_num, item.dish_num)).or_insert((&item.title, item.price, 0)).2 += item.amount;
+   }
+   for ((group_num, dish_num), (title, price, amount)) in dishes {
+      let res = trans.execute("INSERT INTO ticket_items (ticket_id, rest_num, group_num, dish_num, title, price, amount)
+         VALUES ($1::INTEGER, $2::INTEGER, $3::INTEGER, $4::INTEGER, $5::VARCHAR(100), $6::INTEGER, $7::INTEGER)",
+         &[&ticket_id, &rest_num, &group_num, &dish_num, &title, &price, &amount])
+      .await;
+      if let Err(e) = res {
+         settings::log(&format!("db::group_basket_to_ticket insert items: {}", e)).await;
+         return None;
+      }
+   }
+
+   match trans.commit().await {
+      Ok(_) => {
+         metrics::ticket_stage(0, 1);
+         Some((ticket_id, items))
+      }
+      Err(e) => {
+         settings::log(&format!("db::group_basket_to_ticket commit: {}", e)).await;
+         None
+      }
+   }
+}
+
+// ============================================================================
+// [Outbox table]
+// ============================================================================
+
+// Важное сообщение, ожидающее доставки. Для нового заказа ресторатору
+// к тексту добавляются пересылаемое сообщение с заказом и геопозиция
+#[derive(Default)]
+pub struct OutboxMessage {
+   pub msg_id: i32,
+   pub chat_id: i64,
+   pub text: String,
+   pub reply_to: Option<i32>,
+   pub attempts: i32,
+   pub ticket_id: Option<i32>,
+   pub forward: Option<(i64, i32)>,
+   pub location: Option<(f64, f64)>,
+}
+
+// Сохраняет сообщение до подтверждения доставки
+pub async fn outbox_add(msg: &OutboxMessage) -> Option<i32> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   let (forward_from, forward_msg_id) = msg.forward.map_or((None, None), |(chat, id)| (Some(chat), Some(id)));
+   let (latitude, longitude) = msg.location.map_or((None, None), |(lat, lon)| (Some(lat), Some(lon)));
+   let query = client.query_one("INSERT INTO outbox (chat_id, text, reply_to, created, ticket_id, forward_from, forward_msg_id, latitude, longitude)
+      VALUES ($1::BIGINT, $2::TEXT, $3::INTEGER, now(), $4::INTEGER, $5::BIGINT, $6::INTEGER, $7::FLOAT8, $8::FLOAT8) RETURNING msg_id",
+      &[&msg.chat_id, &msg.text, &msg.reply_to, &msg.ticket_id, &forward_from, &forward_msg_id, &latitude, &longitude])
+   .await;
+
+   match query {
+      Ok(row) => Some(row.get(0)),
+      Err(e) => {
+         settings::log(&format!("db::outbox_add: {}", e)).await;
+         None
+      }
+   }
+}
+
+// Сообщения, которые не удалось доставить сразу. Недавние пропускаем, их ещё может отправлять основная очередь
+pub async fn outbox_list() -> Option<Vec<OutboxMessage>> {
+   // Получим клиента БД из пула
+   let client = db_client().await?;
+
+   // Время сравнивается на стороне БД, поэтому пояс бота здесь не участвует
+   let rows = client.query("SELECT msg_id, chat_id, text, reply_to, attempts, ticket_id, forward_from, forward_msg_id, latitude, longitude FROM outbox
+      WHERE created < now() - interval '2 minutes' ORDER BY msg_id", &[])
+   .await;
+
+   match rows {
+      Ok(data) => if data.is_empty() {None} else {
+         Some(data.into_iter().map(|row| {
+            let forward_from: Option<i64> = row.get(6);
+            let forward_msg_id: Option<i32> = row.get(7);
+            let latitude: Option<f64> = row.get(8);
+            let longitude: Option<f64> = row.get(9);
+            OutboxMessage {
+               msg_id: row.get(0),
+               chat_id: row.get(1),
+               text: row.get(2),
+               reply_to: row.get(3),
+               attempts: row.get(4),
+               ticket_id: row.get(5),
+               forward: forward_from.zip(forward_msg_id),
+               location: latitude.zip(longitude),
+            }
+         }).collect())
+      },
+      Err(e) => {
+         settings::log(&format!("db::outbox_list: {}", e)).await;
+         None
+      }
+   }
+}
+
+// Отмечает, что текст сообщения уже доставлен и при повторе отправлять его не нужно
+pub async fn outbox_text_sent(msg_id: i32) -> bool {
+   execute_one("UPDATE outbox SET text = '' WHERE msg_id=$1::INTEGER", &[&msg_id]).await
+}
+
+// Удаляет доставленное сообщение
+pub async fn outbox_remove(msg_id: i32) -> bool {
+   execute_one("DELETE FROM outbox WHERE msg_id=$1::INTEGER", &[&msg_id]).await
+}
+
+// Учитывает неудачную попытку доставки
+pub async fn outbox_attempt(msg_id: i32) -> bool {
+   execute_one("UPDATE outbox SET attempts = attempts + 1 WHERE msg_id=$1::INTEGER", &[&msg_id]).await
+}
+
+// ============================================================================
+// [Misc]
+// ============================================================================
+// Для отображения статуса
+pub fn active_to_str(lang: &str, active : bool) -> String {
+   if active {
+       lang::t(lang, StatusShown)
+   } else {
+       lang::t(lang, StatusHidden)
+   }
+}
+
+pub fn enabled_to_str(lang: &str, enabled : bool) -> String {
+   if enabled {
+       lang::t(lang, StatusEnabled)
+   } else {
+       lang::t(lang, StatusBanned)
+   }
+}
+
+pub fn enabled_to_cmd(enabled : bool) -> &'static str {
+   if enabled {
+       "/hold"
+   } else {
+       "/regi"
+   }
+}
+
+// Успешно-неуспешно
+pub fn is_success(lang: &str, flag : bool) -> String {
+   if flag {
+      lang::t(lang, Success)
+  } else {
+      lang::t(lang, Failure)
+  }
+}
+
+// Используется при редактировании категории группы, названия совпадают с кнопками главного меню
+pub fn id_to_category(lang: &str, cat_id : i32) -> String {
+   match cat_id {
+      1 => lang::t(lang, BtnJuice),
+      2 => lang::t(lang, BtnFood),
+      3 => lang::t(lang, BtnDrinks),
+      4 => lang::t(lang, BtnFun),
+      _ => lang::t(lang, UnknownCategory),
+   }
+} 
+
+// Название категории может быть на любом из языков
+pub fn category_to_id(category: &str) -> i32 {
+   match lang::button(category) {
+      Some(BtnJuice) => 1,
+      Some(BtnFood) => 2,
+      Some(BtnDrinks) => 3,
+      Some(BtnFun) => 4,
+      _ => 0,
+   }
+}
+
+// Режим интерфейса
+pub fn interface_mode(lang: &str, is_compact: bool) -> String {
+   if is_compact {
+      lang::t(lang, InterfaceCompact)
+   } else {
+      lang::t(lang, InterfaceInline)
+   }
+}
+
+// Формирование ключа блюда на основе аргументов
+pub fn make_key_3_int(first: i32, second: i32, third: i32) -> String {
+   format!("{}_{}_{}", first, second, third)
+}
+
+// Разбор строки на три числа, например ключа блюда на аргументы
+pub fn parse_key_3_int(text: &str) -> Result<(i32, i32, i32), Box<dyn std::error::Error>> {
+   let first: i32;
+   let second: i32;
+   let third: i32;
+
+   try_scan!(text.bytes() => "{}_{}_{}", first, second, third);
+
+   Ok((first, second, third))
+}
+
+// Формирование информации о пользователе для лога
+//
+fn user_info_optional_part(user: &User) -> String {
+   // Строка для возврата результата
+   let mut s = String::default();
+
+   if let Some(last_name) = &user.last_name {
+      s.push_str(&format!(" {}", last_name));
+   }
+   if let Some(username) = &user.username {
+      s.push_str(&format!(" @{}", username));
+   }
+   if let Some(language_code) = &user.language_code {
+      s.push_str(&format!(" lang={}", language_code));
+   }
+   s
+}
+
+pub fn user_info(user: Option<&User>, detail: bool) -> String {
+   if let Some(u) = user {
+      let mut s = format!("{}:{}", u.id, u.first_name);
+
+      // Эту информацию выводим только для полного описания
+      if detail {
+         s.push_str(&user_info_optional_part(u));
+      }
+      s
+   } else {
+      String::from("None user info")
+   }
+}
+
+
+// Возвращает название стадии
+pub fn stage_to_str(lang: &str, stage: i32) -> String {
+   let res = match stage {
+      1 => Stage1,
+      2 => Stage2,
+      3 => Stage3,
+      4 => Stage4,
+      5 => Stage5,
+      6 => Stage6,
+      _ => StageError,
+   };
+   lang::t(lang, res)
+}
+
+// Удаляет минуты из времени, если они нулевые
+pub fn str_time(time: NaiveTime) -> String {
+   if time.minute() == 0 {
+      time.format("%H").to_string()
+   } else {
+      time.format("%H:%M").to_string()
+   }
+}
+
+// Возвращает истину, если время попадает в интервал работы, в т.ч. переходящий через полночь
+pub fn is_time_in(time: NaiveTime, opening_time: NaiveTime, closing_time: NaiveTime) -> bool {
+   if opening_time <= closing_time {
+      opening_time <= time && time <= closing_time
+   } else {
+      time >= opening_time || time <= closing_time
+   }
+}
+
+// Оставшееся время, после которого вместо "открыто до" показывается обратный отсчёт
+const CLOSING_SOON_MINUTES: i64 = 30;
+
+// Минут от одного времени суток до другого, в т.ч. через полночь
+fn minutes_between(from: NaiveTime, to: NaiveTime) -> i64 {
+   let res = (to - from).num_minutes();
+   if res < 0 {res + 24 * 60} else {res}
+}
+
+// Состояние работы группы или заведения в текущий момент
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum WorkStatus {
+   AllDay,                 // круглосуточно
+   OpenUntil(NaiveTime),   // открыто до указанного времени
+   ClosingIn(i64),         // закроется через указанное число минут
+   OpensAt(NaiveTime),     // закрыто, откроется в указанное время
+}
+
+impl WorkStatus {
+   // Состояние интервала работы в указанное время
+   pub fn of(time: NaiveTime, opening_time: NaiveTime, closing_time: NaiveTime) -> Self {
+      // Интервал вроде 00:00-23:59 означает работу без перерыва
+      if minutes_between(closing_time, opening_time) == 1 {
+         WorkStatus::AllDay
+      } else if is_time_in(time, opening_time, closing_time) {
+         let left = minutes_between(time, closing_time);
+         if left < CLOSING_SOON_MINUTES {WorkStatus::ClosingIn(left.max(1))} else {WorkStatus::OpenUntil(closing_time)}
+      } else {
+         WorkStatus::OpensAt(opening_time)
+      }
+   }
+
+   // Состояние заведения по его активным группам: открыто, пока работает хоть одна, иначе ближайшее открытие
+   pub fn of_groups(groups: &[Group], time: NaiveTime) -> Option<Self> {
+      let statuses: Vec<WorkStatus> = groups.iter()
+      .filter(|group| group.active)
+      .map(|group| group.status(time))
+      .collect();
+
+      // Сколько минут осталось до закрытия или открытия
+      let minutes = |status: &WorkStatus| match *status {
+         WorkStatus::AllDay => i64::MAX,
+         WorkStatus::OpenUntil(closing_time) => minutes_between(time, closing_time),
+         WorkStatus::ClosingIn(left) => left,
+         WorkStatus::OpensAt(opening_time) => minutes_between(time, opening_time),
+      };
+
+      let (open, closed): (Vec<_>, Vec<_>) = statuses.into_iter().partition(WorkStatus::is_open);
+      if open.is_empty() {
+         closed.into_iter().min_by_key(minutes)
+      } else {
+         open.into_iter().max_by_key(minutes)
+      }
+   }
+
+   pub fn is_open(&self) -> bool {
+      !matches!(self, WorkStatus::OpensAt(_))
+   }
+
+   pub fn to_str(self, lang: &str) -> String {
+      match self {
+         WorkStatus::AllDay => lang::t(lang, WorkAllDay),
+         WorkStatus::OpenUntil(closing_time) => lang::tf(lang, WorkOpenUntil, &[&closing_time.format("%H:%M")]),
+         WorkStatus::ClosingIn(left) => lang::tf(lang, WorkClosingIn, &[&left]),
+         WorkStatus::OpensAt(opening_time) => lang::tf(lang, WorkOpensAt, &[&opening_time.format("%H:%M")]),
+      }
+   }
+}
+
+// Состояние заведений с активными группами по времени каждого заведения
+pub async fn rest_statuses() -> HashMap<i32, WorkStatus> {
+   let mut groups: HashMap<i32, (Option<String>, GroupList)> = HashMap::new();
+
+   // Получим клиента БД из пула
+   if let Some(client) = db_client().await {
+      let rows = client.query("SELECT g.rest_num, g.group_num, g.title, g.info, g.active, g.cat_id, g.opening_time, g.closing_time, r.time_zone FROM groups AS g
+         INNER JOIN restaurants r ON r.rest_num = g.rest_num
+         WHERE g.active = TRUE AND r.active = TRUE", &[])
+      .await;
+
+      match rows {
+         Ok(data) => {
+            for row in data {
+               let group = Group::from_db(&row);
+               groups.entry(group.rest_num).or_insert_with(|| (row.get(8), Vec::new())).1.push(group);
+            }
+         }
+         Err(e) => settings::log(&format!("db::rest_statuses: {}", e)).await,
+      }
+   }
+
+   groups.into_iter()
+   .filter_map(|(rest_num, (time_zone, groups))| {
+      let time = settings::zone_date_time(time_zone.as_deref()).time();
+      WorkStatus::of_groups(&groups, time).map(|status| (rest_num, status))
+   })
+   .collect()
+}
+
+// Желаемое время заказа для отображения
+pub fn order_time_to_str(lang: &str, order_time: Option<NaiveDateTime>) -> String {
+   match order_time {
+      Some(t) => t.format("%H:%M %d.%m").to_string(),
+      None => lang::t(lang, OrderAsap),
+   }
+}
+
+// Обёртка, выполняет запрос, обновляющий 1 запись и возвращает истину, если успешно
+async fn execute_one(sql_text: &str, params: &[&(dyn ToSql + Sync)]) -> bool {
+   // Получим клиента БД из пула
+   let client = db_client().await;
+   if client.is_none() {return false;}
+
+   // Выполняем запрос
+   let query = client.unwrap().execute(sql_text, params).await;
+
+   // При успешной операции должна быть обновлена 1 запись
+   match query {
+      Ok(1) => true,
+      Ok(n) => {
+         settings::log(&format!("db::execute({}): updated {} records instead one", sql_text, n)).await;
+         false
+      }
+      Err(e) => {
+         settings::log(&format!("db::execute({}): {}", sql_text, e)).await;
+         false
+      }
+   }
+}
+
+// Подобна execute_one(), но не выводит сообщений об ошибках
 async fn execute_one_no_error(sql_text: &str, params: &[&(dyn ToSql + Sync)]) -> bool {
    // Получим клиента БД из пула
    let client = db_client().await;
@@ -1851,7 +4050,10 @@ async fn execute(sql_text: &str, params: &[&(dyn ToSql + Sync)]) -> bool {
 
 // Обёртка, возвращает пул клиентов
 async fn db_client() -> Option<Client> {
-   match DB.get().unwrap().get().await {
+   let start = Instant::now();
+   let res = DB.get().unwrap().get().await;
+   metrics::db_pool_wait(start, res.is_ok());
+   match res {
       Ok(client) => Some(client),
       Err(e) => {
          settings::log(&format!("No db client: {}", e)).await;
@@ -1916,4 +4118,40 @@ pub async fn save_cat_image(cat_id: i32, image_id: String) {
       // Если не получилось, вставляем новую
       execute_one("INSERT INTO category(cat_id, image_id) VALUES ($1::INTEGER, $2::VARCHAR(512))", &[&cat_id, &image_id]).await;
    }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   fn time(s: &str) -> NaiveTime {
+      NaiveTime::parse_from_str(s, "%H:%M").unwrap()
+   }
+
+   fn group(opening_time: &str, closing_time: &str) -> Group {
+      Group {rest_num: 1, num: 1, title: String::default(), info: String::default(), active: true, cat_id: 1, opening_time: time(opening_time), closing_time: time(closing_time)}
+   }
+
+   #[test]
+   fn work_status() {
+      assert_eq!(WorkStatus::of(time("12:00"), time("10:00"), time("22:00")), WorkStatus::OpenUntil(time("22:00")));
+      assert_eq!(WorkStatus::of(time("21:45"), time("10:00"), time("22:00")), WorkStatus::ClosingIn(15));
+      assert_eq!(WorkStatus::of(time("23:00"), time("10:00"), time("22:00")), WorkStatus::OpensAt(time("10:00")));
+      assert_eq!(WorkStatus::of(time("23:50"), time("00:00"), time("23:59")), WorkStatus::AllDay);
+
+      // Через полночь
+      assert_eq!(WorkStatus::of(time("01:50"), time("18:00"), time("02:00")), WorkStatus::ClosingIn(10));
+      assert_eq!(WorkStatus::of(time("03:00"), time("18:00"), time("02:00")), WorkStatus::OpensAt(time("18:00")));
+   }
+
+   #[test]
+   fn rest_status_by_groups() {
+      let groups = vec![group("08:00", "11:00"), group("12:00", "22:00"), group("10:00", "21:00")];
+      assert_eq!(WorkStatus::of_groups(&groups, time("12:30")), Some(WorkStatus::OpenUntil(time("22:00"))));
+      assert_eq!(WorkStatus::of_groups(&groups, time("23:00")), Some(WorkStatus::OpensAt(time("08:00"))));
+      assert_eq!(WorkStatus::of_groups(&groups, time("07:00")), Some(WorkStatus::OpensAt(time("08:00"))));
+      assert_eq!(WorkStatus::of_groups(&groups, time("11:30")), Some(WorkStatus::OpenUntil(time("21:00"))));
+      assert_eq!(WorkStatus::of_groups(&groups[..1], time("10:50")), Some(WorkStatus::ClosingIn(10)));
+      assert_eq!(WorkStatus::of_groups(&[], time("10:50")), None);
+   }
+}
@@ -11,24 +11,29 @@ use teloxide::{
     prelude::*,
     types::{InputFile, ReplyMarkup, ParseMode, },
 };
+use std::sync::Arc;
 
 use crate::commands as cmd;
 use crate::database as db;
 use crate::eater;
 use crate::caterer;
 use crate::cat_group;
+use crate::notify;
 use crate::settings;
+use crate::language::{self as lang, Res::*};
 
 // Показывает информацию о блюде 
 //
 pub async fn next_with_info(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
    // Извлечём параметры
    let (rest_num, group_num, dish_num) = cx.dialogue;
 
    // Получаем информацию из БД
    let (info, dish_image_id) = match db::dish(db::DishBy::All(rest_num, group_num, dish_num)).await {
-      Some(dish) => (dish.info_for_caterer(), dish.image_id),
-      None => (String::from("Информация недоступна"), None)
+      Some(dish) => (dish.info_for_caterer(lang), dish.image_id),
+      None => (lang::t(lang, DishNoInfo), None)
    };
 
    // Отображаем информацию о блюде и оставляем кнопки главного меню. Если для блюда задана картинка, то текст будет комментарием
@@ -39,13 +44,13 @@ pub async fn next_with_info(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
       // Отправляем картинку и текст как комментарий
       cx.answer_photo(image)
       .caption(info)
-      .reply_markup(ReplyMarkup::ReplyKeyboardMarkup(cmd::Caterer::main_menu_markup()))
+      .reply_markup(ReplyMarkup::ReplyKeyboardMarkup(cmd::Caterer::main_menu_markup(lang)))
       .disable_notification(true)
       .send()
       .await?;
    } else {
       cx.answer(info)
-      .reply_markup(cmd::Caterer::main_menu_markup())
+      .reply_markup(cmd::Caterer::main_menu_markup(lang))
       .disable_notification(true)
       .send()
       .await?;
@@ -56,8 +61,9 @@ pub async fn next_with_info(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
 }
 
 async fn next_with_cancel(cx: cmd::Cx<(i32, i32, i32)>, text: &str) -> cmd::Res {
+    let lang = lang::user_lang(cx.update.from()).await;
     cx.answer(text)
-    .reply_markup(cmd::Caterer::main_menu_markup())
+    .reply_markup(cmd::Caterer::main_menu_markup(lang))
     .disable_notification(true)
     .send()
     .await?;
@@ -71,6 +77,8 @@ async fn next_with_cancel(cx: cmd::Cx<(i32, i32, i32)>, text: &str) -> cmd::Res
 
 // Режим редактирования у ресторана rest_id группы group_id
 pub async fn handle_commands(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
    // Извлечём параметры
    let (rest_num, group_num, dish_num) = cx.dialogue;
    
@@ -78,7 +86,7 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
    match cx.update.text() {
       None => {
          let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
-         next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, (rest_num, group_num, dish_num)), "Текстовое сообщение, пожалуйста!").await
+         next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, (rest_num, group_num, dish_num)), &lang::t(lang, TextPlease)).await
       }
       Some(command) => {
          match cmd::CatDish::from(rest_num, group_num, dish_num, command) {
@@ -100,7 +108,7 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
             cmd::CatDish::EditTitle(rest_num, group_num, dish_num) => {
 
                // Отправляем приглашение ввести строку со слешем в меню для отмены
-               cx.answer(format!("Введите название (/ для отмены)"))
+               cx.answer(lang::t(lang, EnterTitle))
                .reply_markup(cmd::Caterer::slash_markup())
                .disable_notification(true)
                .send()
@@ -114,7 +122,7 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
             cmd::CatDish::EditInfo(rest_num, group_num, dish_num) => {
 
                // Отправляем приглашение ввести строку со слешем в меню для отмены
-               cx.answer(format!("Введите пояснения для блюда (пояснения короче 3 символов клиентам показываться не будут)"))
+               cx.answer(lang::t(lang, DishEnterInfo))
                .reply_markup(cmd::Caterer::slash_markup())
                .disable_notification(true)
                .send()
@@ -129,6 +137,9 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
                // Запрос доп.данных не требуется, сразу переключаем активность
                db::rest_dish_toggle(rest_num, group_num, dish_num).await;
 
+               // Возможно, кто-то ждёт появления блюда
+               tokio::spawn(notify::fire(Arc::clone(&cx.bot)));
+
                // Покажем изменённую информацию
                let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
                next_with_info(DialogueDispatcherHandlerCx::new(bot, update, (rest_num, group_num, dish_num))).await
@@ -138,8 +149,8 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
             cmd::CatDish::EditGroup(rest_num, group_num, dish_num) => {
 
                // Отправляем приглашение ввести строку с категориями в меню для выбора
-               cx.answer(format!("Введите номер группы для переноса блюда из текущей группы в другую"))
-               .reply_markup(cmd::Caterer::main_menu_markup())
+               cx.answer(lang::t(lang, DishEnterGroup))
+               .reply_markup(cmd::Caterer::main_menu_markup(lang))
                .disable_notification(true)
                .send()
                .await?;
@@ -152,8 +163,8 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
             cmd::CatDish::EditPrice(rest_num, group_num, dish_num) => {
 
                // Отправляем приглашение ввести строку с категориями в меню для выбора
-               cx.answer(format!("Введите сумму в тыс. донгов"))
-               .reply_markup(cmd::Caterer::main_menu_markup())
+               cx.answer(lang::t(lang, DishEnterPrice))
+               .reply_markup(cmd::Caterer::main_menu_markup(lang))
                .disable_notification(true)
                .send()
                .await?;
@@ -166,8 +177,8 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
             cmd::CatDish::EditImage(rest_num, group_num, dish_num) => {
 
                // Отправляем приглашение ввести строку с категориями в меню для выбора
-               cx.answer(format!("Загрузите картинку"))
-               .reply_markup(cmd::Caterer::main_menu_markup())
+               cx.answer(lang::t(lang, CatUploadImage))
+               .reply_markup(cmd::Caterer::main_menu_markup(lang))
                .disable_notification(true)
                .send()
                .await?;
@@ -195,12 +206,12 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
             cmd::CatDish::Promote(rest_num, group_num, dish_num) => {
                // Получаем информацию из БД
                let (info, dish_image_id) = match db::dish(db::DishBy::Active(rest_num, group_num, dish_num)).await {
-                  Some(dish) => (dish.info_for_eater(), dish.image_id),
-                  None => (String::from("Информация недоступна для клиента, возможно позиция скрыта"), None)
+                  Some(dish) => (dish.info_for_eater(lang), dish.image_id),
+                  None => (lang::t(lang, DishNoInfoForEater), None)
                };
 
                // Добавляем гиперссылку
-               let info = format!("{}\n{}{}", info, settings::link(), db::make_key_3_int(rest_num, group_num, dish_num));
+               let info = settings::with_link(info, &db::make_key_3_int(rest_num, group_num, dish_num));
 
                // Отображаем информацию о блюде и оставляем кнопки главного меню. Если для блюда задана картинка, то текст будет комментарием
                if let Some(image_id) = dish_image_id {
@@ -211,14 +222,14 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
                   cx.answer_photo(image)
                   .caption(info)
                   .parse_mode(ParseMode::HTML)
-                  .reply_markup(ReplyMarkup::ReplyKeyboardMarkup(cmd::Caterer::main_menu_markup()))
+                  .reply_markup(ReplyMarkup::ReplyKeyboardMarkup(cmd::Caterer::main_menu_markup(lang)))
                   .disable_notification(true)
                   .send()
                   .await?;
                } else {
                   cx.answer(info)
                   .parse_mode(ParseMode::HTML)
-                  .reply_markup(cmd::Caterer::main_menu_markup())
+                  .reply_markup(cmd::Caterer::main_menu_markup(lang))
                   .disable_notification(true)
                   .send()
                   .await?;
@@ -230,14 +241,14 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
             // Ошибочная команда
             cmd::CatDish::UnknownCommand => {
                // Сохраним текущее состояние для возврата
-               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::CatEditDish(rest_num, group_num, dish_num), m : cmd::Caterer::main_menu_markup()});
+               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::CatEditDish(rest_num, group_num, dish_num), m : cmd::Caterer::main_menu_markup(lang)});
 
                // Возможно это общая команда
                if let Some(res) = eater::handle_common_commands(DialogueDispatcherHandlerCx::new(cx.bot.clone(), cx.update.clone(), ()), command, origin).await {return res;}
                else {
                   let s = String::from(command);
                   let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
-                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, (rest_num, group_num, dish_num)), &format!("Вы в меню блюда: неизвестная команда '{}'", s)).await
+                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, (rest_num, group_num, dish_num)), &lang::tf(lang, DishUnknownCommand, &[&s])).await
                }
             }
          }
@@ -248,6 +259,7 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
 // Изменение названия rest_id, dish_id
 //
 pub async fn edit_title_mode(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
+    let lang = lang::user_lang(cx.update.from()).await;
     
     if let Some(text) = cx.update.text() {
         // Удалим из строки слеши
@@ -266,12 +278,13 @@ pub async fn edit_title_mode(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
         }
     } 
     // Сообщим об отмене
-    next_with_cancel(cx, "Отмена ввода названия").await
+    next_with_cancel(cx, &lang::t(lang, CancelTitle)).await
 }
 
 // Изменение описания rest_id, dish_id
 //
 pub async fn edit_info_mode(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
+    let lang = lang::user_lang(cx.update.from()).await;
     if let Some(text) = cx.update.text() {
         // Удалим из строки слеши
         let s = cmd::remove_slash(text).await;
@@ -289,12 +302,13 @@ pub async fn edit_info_mode(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
       }
     } 
     // Сообщим об отмене
-    next_with_cancel(cx, "Отмена ввода описания").await
+    next_with_cancel(cx, &lang::t(lang, CancelInfo)).await
 }
 
 // Изменение группы блюда rest_id, dish_id
 //
 pub async fn edit_dish_group_mode(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
+    let lang = lang::user_lang(cx.update.from()).await;
     if let Some(text) = cx.update.text() {
         // Попытаемся преобразовать ответ пользователя в код группы
         let new_group_id = text.parse::<i32>().unwrap_or_default();
@@ -311,14 +325,14 @@ pub async fn edit_dish_group_mode(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
                cat_group::next_with_info(DialogueDispatcherHandlerCx::new(bot, update, (rest_num, group_num))).await
             } else {
                 // Сообщим об ошибке
-                next_with_cancel(cx, "Ошибка, возможно нет группы с таким кодом, отмена изменения группы").await
+                next_with_cancel(cx, &lang::t(lang, DishNoGroup)).await
             }
         } else {
             // Сообщим об ошибке
-            next_with_cancel(cx, "Должно быть число 1 или больше, отмена изменения группы").await
+            next_with_cancel(cx, &lang::t(lang, DishGroupNumber)).await
         }
     } else {
-        next_with_cancel(cx, "Ошибка, отмена изменения группы").await
+        next_with_cancel(cx, &lang::t(lang, DishGroupCancel)).await
     }
 }
 
@@ -22,12 +22,22 @@ use crate::eater;
 use crate::eat_group;
 use crate::eat_group_now;
 use crate::basket;
-use crate::language as lang;
+use crate::favorites;
+use crate::notify;
+use crate::language::{self as lang, Res::*};
 use crate::settings;
+use crate::pages;
 
 
 // Основная информация режима
 pub async fn next_with_info(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
+   next_with_page(cx, 1).await
+}
+
+// Информация о группе с указанной страницей списка блюд
+async fn next_with_page(cx: cmd::Cx<(i32, i32, i32)>, page: i32) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
    // Извлечём параметры
    let (cat_id, rest_num, group_num) = cx.dialogue;
    
@@ -35,22 +45,28 @@ pub async fn next_with_info(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
    match db::group(rest_num, group_num).await {
       None => {
          // Такая ситуация не должна возникнуть
-         let s = String::from("Ошибка, информации о группе нет");
+         let s = lang::t(lang, EatNoGroupInfo);
          let new_cx = DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ());
-         cmd::send_text(&new_cx, &s, cmd::EaterRest::markup()).await;
+         cmd::send_text(&new_cx, &s, cmd::EaterRest::markup(lang)).await;
       }
       Some(group) => {
          // Сформируем информацию о группе
          let group_info = format!("{}. {}", group.title, group.info);
 
+         // Если группа сейчас закрыта, предложим сообщить об открытии
+         let group_info = if group.is_open_at(db::rest_now(rest_num).await.time()) {group_info}
+         else {format!("{}\n{}", group_info, notify::offer(lang, NotifyGroupOffer, rest_num, group_num, 0))};
+
          // Получаем из БД список блюд
          let dishes_desc = match db::dish_list(db::DishesBy::Active(rest_num, group_num)).await {
             None => {
-               String::from(lang::t("ru", lang::Res::EatDishEmpty))
+               lang::t(lang, EatDishEmpty)
             }
             Some(dishes) => {
                // Сформируем строку вида "название /ссылка\n"
-               dishes.into_iter().map(|dish| (format!("   {} /dish{}\n", dish.title_with_price(), dish.num))).collect()
+               let page = pages::Page::text(dishes, page);
+               let s: String = page.items.iter().map(|dish| format!("   {} /dish{}\n", dish.title_with_price(), dish.num)).collect();
+               format!("{}{}", s, page.links(lang, "/page"))
             }
          };
                
@@ -60,7 +76,7 @@ pub async fn next_with_info(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
          // Отображаем список блюд
          cx.answer(s)
          .parse_mode(ParseMode::HTML)
-         .reply_markup(cmd::EaterGroup::markup())
+         .reply_markup(cmd::EaterGroup::markup(lang))
          .disable_notification(true)
          .send()
          .await?;
@@ -73,8 +89,9 @@ pub async fn next_with_info(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
 
 // Показывает сообщение об ошибке/отмене без повторного вывода информации
 async fn next_with_cancel(cx: cmd::Cx<(i32, i32, i32)>, text: &str) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
    cx.answer(text)
-   .reply_markup(cmd::EaterDish::markup())
+   .reply_markup(cmd::EaterDish::markup(lang))
    .disable_notification(true)
    .send()
    .await?;
@@ -88,13 +105,15 @@ async fn next_with_cancel(cx: cmd::Cx<(i32, i32, i32)>, text: &str) -> cmd::Res
 
 // Обработчик команд
 pub async fn handle_commands(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
    // Извлечём параметры
    let (cat_id, rest_id, group_id) = cx.dialogue;
 
    // Разбираем команду.
    match cx.update.text() {
       None => {
-         next_with_cancel(cx, "Текстовое сообщение, пожалуйста!").await
+         next_with_cancel(cx, &lang::t(lang, TextPlease)).await
       }
       Some(command) => {
          match cmd::EaterDish::from(command) {
@@ -130,16 +149,19 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32, i32)>) -> cmd::Res {
             // Выбор блюда
             cmd::EaterDish::Dish(dish_num) => show_dish(DishMode::Compact(&cx, dish_num)).await,
 
+            // Другая страница списка
+            cmd::EaterDish::Page(page) => next_with_page(cx, page).await,
+
             cmd::EaterDish::UnknownCommand => {
                // Сохраним текущее состояние для возврата
-               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::EatRestGroupDishSelectionMode(cat_id, rest_id, group_id), m : cmd::EaterDish::markup()});
+               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::EatRestGroupDishSelectionMode(cat_id, rest_id, group_id), m : cmd::EaterDish::markup(lang)});
 
                // Возможно это общая команда
                if let Some(res) = eater::handle_common_commands(DialogueDispatcherHandlerCx::new(cx.bot.clone(), cx.update.clone(), ()), command, origin).await {return res;}
                else {
                   let s = String::from(command);
                   let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
-                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, (cat_id, rest_id, group_id)), &format!("Вы в меню выбора блюда: неизвестная команда '{}'", s)).await
+                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, (cat_id, rest_id, group_id)), &lang::tf(lang, EatDishUnknownCommand, &[&s])).await
                }
             }
          }
@@ -153,36 +175,41 @@ struct InlineData {
    markup: InlineKeyboardMarkup,
    photo_id: String,
 }
-async fn inline_data(cat_id: i32, rest_num: i32, group_num: i32) -> InlineData {
+async fn inline_data(lang: &str, cat_id: i32, rest_num: i32, group_num: i32, page: i32) -> InlineData {
    // Получаем информацию из БД сначала о группе
    let (text, markup) = match db::group(rest_num, group_num).await {
       None => {
          // Такая ситуация не должна возникнуть
          // Кнопка назад
-         let buttons = vec![InlineKeyboardButton::callback(String::from("⏪Назад"), format!("rrg{}", db::make_key_3_int(rest_num, cat_id, 0)))];
+         let buttons = vec![InlineKeyboardButton::callback(lang::t(lang, BtnBack), format!("rrg{}", db::make_key_3_int(rest_num, cat_id, 0)))];
          // Формируем меню
          let markup = InlineKeyboardMarkup::default()
          .append_row(buttons);
 
          // Сформированные данные
-         (String::from("Ошибка, информации о группе нет"), markup)
+         (lang::t(lang, EatNoGroupInfo), markup)
       }
       Some(group) => {
          // Сформируем информацию о группе
          let group_info = format!("{}. {}", group.title, group.info);
 
+         // Если группа сейчас закрыта, предложим сообщить об открытии
+         let group_info = if group.is_open_at(db::rest_now(rest_num).await.time()) {group_info}
+         else {format!("{}\n{}", group_info, notify::offer(lang, NotifyGroupOffer, rest_num, group_num, 0))};
+
          // Получаем из БД список блюд
          let markup = match db::dish_list(db::DishesBy::Active(rest_num, group_num)).await {
             None => {
                // Такая ситуация может возникнуть, если ресторатор скрыл группы только что
-               let buttons = vec![InlineKeyboardButton::callback(String::from("⏪Назад"), format!("rca{}", db::make_key_3_int(cat_id, 0, 0)))];
+               let buttons = vec![InlineKeyboardButton::callback(lang::t(lang, BtnBack), format!("rca{}", db::make_key_3_int(cat_id, 0, 0)))];
                let markup = InlineKeyboardMarkup::default()
                .append_row(buttons);
                markup
             }
             Some(dishes) => {
                // Создадим кнопки
-               let buttons: Vec<InlineKeyboardButton> = dishes.into_iter()
+               let page = pages::Page::buttons(dishes, page);
+               let buttons: Vec<InlineKeyboardButton> = page.items.iter()
                .map(|dish| (InlineKeyboardButton::callback(dish.title_with_price(), format!("dis{}", db::make_key_3_int(rest_num, group_num, dish.num)))))
                .collect();
 
@@ -201,9 +228,12 @@ async fn inline_data(cat_id: i32, rest_num: i32, group_num: i32) -> InlineData {
                // Короткие по две в ряд
                let markup = short.into_iter().array_chunks::<[_; 2]>()
                .fold(markup, |acc, [left, right]| acc.append_row(vec![left, right]));
+
+               // Переход на соседние страницы
+               let markup = page.append_nav(markup, |num| format!("dpg{}", db::make_key_3_int(rest_num, group_num, num)));
             
                // Кнопка назад
-               let button_back = InlineKeyboardButton::callback(String::from("⏪Назад"), format!("rrg{}", db::make_key_3_int(rest_num, cat_id, 0)));
+               let button_back = InlineKeyboardButton::callback(lang::t(lang, BtnBack), format!("rrg{}", db::make_key_3_int(rest_num, cat_id, 0)));
 
                // Добавляем последнюю непарную кнопку и кнопку назад
                let markup = if let Some(last_button) = last {
@@ -231,7 +261,9 @@ async fn inline_data(cat_id: i32, rest_num: i32, group_num: i32) -> InlineData {
 }
 
 // Выводит инлайн кнопки, редактируя предыдущее сообщение
-pub async fn show_inline_interface(cx: &DispatcherHandlerCx<CallbackQuery>, cat_id: i32, rest_num: i32, group_num: i32) -> bool {
+pub async fn show_inline_interface(cx: &DispatcherHandlerCx<CallbackQuery>, cat_id: i32, rest_num: i32, group_num: i32, page: i32) -> bool {
+   let lang = lang::user_lang(Some(&cx.update.from)).await;
+
 
    // Если категория не задана, запросим её из базы
    let cat_id = if cat_id != 0 {cat_id}
@@ -240,7 +272,7 @@ pub async fn show_inline_interface(cx: &DispatcherHandlerCx<CallbackQuery>, cat_
    else {return false;};
 
    // Получаем информацию
-   let data = inline_data(cat_id, rest_num, group_num).await;
+   let data = inline_data(lang, cat_id, rest_num, group_num, page).await;
 
    // Достаём chat_id
    let message = cx.update.message.as_ref().unwrap();
@@ -271,6 +303,8 @@ pub async fn show_inline_interface(cx: &DispatcherHandlerCx<CallbackQuery>, cat_
 
 // Выводит инлайн кнопки с новым сообщением
 pub async fn force_inline_interface(cx: cmd::Cx<(i32, i32, i32)>) -> bool {
+   let lang = lang::user_lang(cx.update.from()).await;
+
    // Извлечём параметры
    let (cat_id, rest_num, group_num) = cx.dialogue;
    
@@ -281,7 +315,7 @@ pub async fn force_inline_interface(cx: cmd::Cx<(i32, i32, i32)>) -> bool {
    else {return false;};
 
    // Получаем информацию
-   let data = inline_data(cat_id, rest_num, group_num).await;
+   let data = inline_data(lang, cat_id, rest_num, group_num, 1).await;
 
    // Отправляем сообщение как фото
    let res = cx.answer_photo(InputFile::file_id(data.photo_id))
@@ -322,12 +356,18 @@ pub async fn show_dish<'a>(mode: DishMode<'_>) -> cmd::Res {
       // Отобразить в режиме со ссылками
       DishMode::Compact(cx, dish_num) => (cx.bot.to_owned(), cx.chat_id(), cx.update.from().unwrap().id, cx.dialogue.0, cx.dialogue.1, cx.dialogue.2, dish_num),
    };
+   let lang = lang::lang_of(user_id).await;
 
    // Получаем информацию из БД
    let dish = db::dish(db::DishBy::Active(rest_num, group_num, dish_num)).await;
    if dish.is_none() {
-      bot.send_message(chat_id, "Информация недоступна из БД")
-      .reply_markup(cmd::EaterDish::markup())
+      // Если блюдо только скрыто, предложим сообщить о его появлении
+      let s = match db::dish(db::DishBy::All(rest_num, group_num, dish_num)).await {
+         Some(_) => format!("{}\n{}", lang::t(lang, DishNoInfo), notify::offer(lang, NotifyDishOffer, rest_num, group_num, dish_num)),
+         None => lang::t(lang, DishNoInfo),
+      };
+      bot.send_message(chat_id, s)
+      .reply_markup(cmd::EaterDish::markup(lang))
       .disable_notification(true)
       .send()
       .await?;
@@ -358,30 +398,32 @@ pub async fn show_dish<'a>(mode: DishMode<'_>) -> cmd::Res {
       vec![]
    };
 
+   // Звёздочка избранного
+   buttons.push(favorites::dish_button(user_id, rest_num, group_num, dish_num).await);
+
    // Для удобства
    let compact_mode = matches!(mode, DishMode::Compact(_cx, _dish_num));
-   let dish_text = dish.info_for_eater();
+   let dish_text = dish.info_for_eater(lang);
 
    // Если мы не в режиме со ссылками, надо добавить кнопку возврата
    if !compact_mode {
-      let button_back = InlineKeyboardButton::callback(String::from("В меню"), format!("rrd{}", db::make_key_3_int(rest_num, group_num, 0)));
+      let button_back = InlineKeyboardButton::callback(lang::t(lang, EatToMenu), format!("rrd{}", db::make_key_3_int(rest_num, group_num, 0)));
       buttons.push(button_back);
    }
 
+   // Кнопок всегда не меньше одной - звёздочки
+   let markup = InlineKeyboardMarkup::default()
+   .append_row(buttons);
+
    // Формируем и отправляем сообщение
    let res = if compact_mode && dish.image_id.is_none() {
       // Если мы в компактном режиме и картинка для блюда не задана, приготовим текстовое сообщение, иначе с картинкой
-      let msg = bot.send_message(chat_id, dish_text)
+      bot.send_message(chat_id, dish_text)
       .parse_mode(ParseMode::HTML)
-      .disable_notification(true);
-      let msg = if buttons.is_empty() {msg} else {
-         let markup = InlineKeyboardMarkup::default()
-         .append_row(buttons);
-
-         msg.reply_markup(ReplyMarkup::InlineKeyboardMarkup(markup))
-      };
-      
-      msg.send().await
+      .disable_notification(true)
+      .reply_markup(ReplyMarkup::InlineKeyboardMarkup(markup))
+      .send()
+      .await
    } else if let DishMode::CallbackInline(cx, _rest, _group, _dish) = mode {
       // Редактируем существующее сообщение
       let chat_message = ChatOrInlineMessage::Chat {
@@ -395,29 +437,19 @@ pub async fn show_dish<'a>(mode: DishMode<'_>) -> cmd::Res {
          parse_mode: Some(ParseMode::HTML),
       };
    
-      let msg = bot.edit_message_media(chat_message, media);
-      let msg = if buttons.is_empty() {msg} else {
-         let markup = InlineKeyboardMarkup::default()
-         .append_row(buttons);
-
-         msg.reply_markup(markup)
-      };
-      
-      msg.send().await
+      bot.edit_message_media(chat_message, media)
+      .reply_markup(markup)
+      .send()
+      .await
    } else {
       // Выводим новое сообщение
-      let msg = bot.send_photo(chat_id, db::load_dish_image(&dish).await)
+      bot.send_photo(chat_id, db::load_dish_image(&dish).await)
       .caption(dish_text)
       .parse_mode(ParseMode::HTML)
-      .disable_notification(true);
-      let msg = if buttons.is_empty() {msg} else {
-         let markup = InlineKeyboardMarkup::default()
-         .append_row(buttons);
-
-         msg.reply_markup(ReplyMarkup::InlineKeyboardMarkup(markup))
-      };
-      
-      msg.send().await
+      .disable_notification(true)
+      .reply_markup(ReplyMarkup::InlineKeyboardMarkup(markup))
+      .send()
+      .await
    };
 
    if let Err(e) = res {
@@ -22,11 +22,22 @@ use crate::eater;
 use crate::eat_rest;
 use crate::eat_dish;
 use crate::basket;
-use crate::language as lang;
+use crate::favorites;
+use crate::broadcast;
+use crate::language::{self as lang, Res::*};
 use crate::settings;
+use crate::review;
+use crate::pages;
 
 // Основная информация режима
 pub async fn next_with_info(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
+   next_with_page(cx, 1).await
+}
+
+// Информация о ресторане с указанной страницей списка групп
+async fn next_with_page(cx: cmd::Cx<(i32, i32)>, page: i32) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
    // Извлечём параметры
    let (cat_id, rest_num) = cx.dialogue;
    
@@ -34,28 +45,32 @@ pub async fn next_with_info(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
    match db::restaurant(db::RestBy::Num(rest_num)).await {
       None => {
          // Такая ситуация не должна возникнуть
-         let s = String::from("Ошибка, информации о ресторане нет");
+         let s = lang::t(lang, EatNoRestInfo);
          let new_cx = DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ());
-         cmd::send_text(&new_cx, &s, cmd::EaterRest::markup()).await;
+         cmd::send_text(&new_cx, &s, cmd::EaterRest::markup(lang)).await;
       }
       Some(rest) => {
          // Сформируем информацию о ресторане
-         let rest_info = format!("<b>{}</b>\n{}\nОсновное время работы: {}-{}", rest.title, rest.info, db::str_time(rest.opening_time), db::str_time(rest.closing_time));
+         let rest_info = lang::tf(lang, EatRestInfo, &[&rest.title, &rest.info, &db::str_time(rest.opening_time), &db::str_time(rest.closing_time), &review::rating_line(lang, rest.num).await]);
 
          // Получаем из БД список групп
          let groups_desc = match db::group_list(db::GroupListBy::Category(rest_num, cat_id)).await {
             None => {
                // Такая ситуация может возникнуть, если ресторатор скрыл группы только что
-               String::from(lang::t("ru", lang::Res::EatGroupsEmpty))
+               lang::t(lang, EatGroupsEmpty)
             }
             Some(groups) => {
                // Сформируем строку вида "название /ссылка\n"
-               groups.into_iter().map(|group| (format!("   {} /grou{}\n", group.title_with_time(rest.opening_time, rest.closing_time), group.num))).collect()
+               let time = rest.now().time();
+               let page = pages::Page::text(groups, page);
+               let s: String = page.items.iter().map(|group| format!("   {} /grou{}\n", group.title_with_status(lang, time), group.num)).collect();
+               format!("{}{}", s, page.links(lang, "/page"))
             }
          };
                
-         // Формируем итоговую информацию
-         let s = format!("{}\n{}", rest_info, groups_desc);
+         // Формируем итоговую информацию с командами для избранного и подписки
+         let user_id = cx.update.from().unwrap().id;
+         let s = format!("{}\n{}\n{}\n{}", rest_info, groups_desc, favorites::rest_line(lang, user_id, rest_num).await, broadcast::rest_line(lang, user_id, rest_num).await);
 
          // Отображаем информацию о группах ресторана. Если для ресторана задана картинка, то текст будет комментарием
          if let Some(image_id) = rest.image_id {
@@ -66,14 +81,14 @@ pub async fn next_with_info(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
             cx.answer_photo(image)
             .caption(s)
             .parse_mode(ParseMode::HTML)
-            .reply_markup(ReplyMarkup::ReplyKeyboardMarkup(cmd::EaterGroup::markup()))
+            .reply_markup(ReplyMarkup::ReplyKeyboardMarkup(cmd::EaterGroup::markup(lang)))
             .disable_notification(true)
             .send()
             .await?;
          } else {
                cx.answer(s)
                .parse_mode(ParseMode::HTML)
-               .reply_markup(cmd::EaterGroup::markup())
+               .reply_markup(cmd::EaterGroup::markup(lang))
                .disable_notification(true)
                .send()
                .await?;
@@ -87,8 +102,9 @@ pub async fn next_with_info(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
 
 // Показывает сообщение об ошибке/отмене без повторного вывода информации
 async fn next_with_cancel(cx: cmd::Cx<(i32, i32)>, text: &str) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
    cx.answer(text)
-   .reply_markup(cmd::EaterGroup::markup())
+   .reply_markup(cmd::EaterGroup::markup(lang))
    .disable_notification(true)
    .send()
    .await?;
@@ -102,13 +118,15 @@ async fn next_with_cancel(cx: cmd::Cx<(i32, i32)>, text: &str) -> cmd::Res {
 
 // Обработчик команд
 pub async fn handle_commands(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
    // Извлечём параметры
    let (cat_id, rest_id) = cx.dialogue;
 
    // Разбираем команду.
    match cx.update.text() {
       None => {
-         next_with_cancel(cx, "Текстовое сообщение, пожалуйста!").await
+         next_with_cancel(cx, &lang::t(lang, TextPlease)).await
       }
       Some(command) => {
          match cmd::EaterGroup::from(command) {
@@ -134,22 +152,39 @@ pub async fn handle_commands(cx: cmd::Cx<(i32, i32)>) -> cmd::Res {
                eat_rest::next_with_info(DialogueDispatcherHandlerCx::new(bot, update, cat_id)).await
             }
 
+            // Добавить в избранное или убрать
+            cmd::EaterGroup::Favorite => {
+               let user_id = cx.update.from().unwrap().id;
+               let s = favorites::toggle_rest(lang, user_id, rest_id).await;
+               next_with_cancel(cx, &s).await
+            }
+
+            // Подписаться на новости или отписаться
+            cmd::EaterGroup::Subscribe => {
+               let user_id = cx.update.from().unwrap().id;
+               let s = broadcast::toggle_rest(lang, user_id, rest_id).await;
+               next_with_cancel(cx, &s).await
+            }
+
             // Выбор группы
             cmd::EaterGroup::Group(group_id) => {
                let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
                eat_dish::next_with_info(DialogueDispatcherHandlerCx::new(bot, update, (cat_id, rest_id, group_id))).await
             }
 
+            // Другая страница списка
+            cmd::EaterGroup::Page(page) => next_with_page(cx, page).await,
+
             cmd::EaterGroup::UnknownCommand => {
                // Сохраним текущее состояние для возврата
-               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::EatRestGroupSelectionMode(cat_id, rest_id), m : cmd::EaterGroup::markup()});
+               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::EatRestGroupSelectionMode(cat_id, rest_id), m : cmd::EaterGroup::markup(lang)});
 
                // Возможно это общая команда
                if let Some(res) = eater::handle_common_commands(DialogueDispatcherHandlerCx::new(cx.bot.clone(), cx.update.clone(), ()), command, origin).await {return res;}
                else {
                   let s = String::from(command);
                   let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
-                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, (cat_id, rest_id)), &format!("Вы в меню выбора группы: неизвестная команда '{}'", s)).await
+                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, (cat_id, rest_id)), &lang::tf(lang, EatGroupUnknownCommand, &[&s])).await
                }
             }
          }
@@ -163,37 +198,39 @@ struct InlineData {
    markup: InlineKeyboardMarkup,
    photo_id: String,
 }
-async fn inline_data(cat_id: i32, rest_num: i32) -> InlineData {
+async fn inline_data(lang: &str, user_id: i32, cat_id: i32, rest_num: i32, page: i32) -> InlineData {
    // Получаем информацию из БД - нужен текст, картинка и кнопки
    let (text, markup, photo_id) = match db::restaurant(db::RestBy::Num(rest_num)).await {
       None => {
          // Такая ситуация не должна возникнуть
 
          // Кнопка назад
-         let buttons = vec![InlineKeyboardButton::callback(String::from("⏪Назад"), format!("rca{}", db::make_key_3_int(cat_id, 0, 0)))];
+         let buttons = vec![InlineKeyboardButton::callback(lang::t(lang, BtnBack), format!("rca{}", db::make_key_3_int(cat_id, 0, 0)))];
          let markup = InlineKeyboardMarkup::default()
          .append_row(buttons);
 
          // Сформированные данные
-         (String::from("Ошибка, информации о ресторане нет"), markup, settings::default_photo_id())
+         (lang::t(lang, EatNoRestInfo), markup, settings::default_photo_id())
       }
       Some(rest) => {
          // Сформируем информацию о ресторане
-         let rest_info = format!("<b>{}</b>\n{}\nОсновное время работы: {}-{}", rest.title, rest.info, db::str_time(rest.opening_time), db::str_time(rest.closing_time));
+         let rest_info = lang::tf(lang, EatRestInfo, &[&rest.title, &rest.info, &db::str_time(rest.opening_time), &db::str_time(rest.closing_time), &review::rating_line(lang, rest.num).await]);
 
          // Получаем из БД список групп
          let (markup, photo_id) = match db::group_list(db::GroupListBy::Category(rest_num, cat_id)).await {
             None => {
                // Такая ситуация может возникнуть, если ресторатор скрыл группы только что
-               let buttons = vec![InlineKeyboardButton::callback(String::from("⏪Назад"), format!("rca{}", db::make_key_3_int(cat_id, 0, 0)))];
+               let buttons = vec![InlineKeyboardButton::callback(lang::t(lang, BtnBack), format!("rca{}", db::make_key_3_int(cat_id, 0, 0)))];
                let markup = InlineKeyboardMarkup::default()
                .append_row(buttons);
                (markup, settings::default_photo_id())
             }
             Some(groups) => {
                // Создадим кнопки
-               let buttons: Vec<InlineKeyboardButton> = groups.into_iter()
-               .map(|group| (InlineKeyboardButton::callback(group.title_with_time(rest.opening_time, rest.closing_time), format!("drg{}", db::make_key_3_int(rest.num, group.num, cat_id)))))
+               let time = rest.now().time();
+               let page = pages::Page::buttons(groups, page);
+               let buttons: Vec<InlineKeyboardButton> = page.items.iter()
+               .map(|group| (InlineKeyboardButton::callback(group.title_with_status(lang, time), format!("drg{}", db::make_key_3_int(rest.num, group.num, cat_id)))))
                .collect();
 
                // Поделим на длинные и короткие
@@ -211,15 +248,25 @@ async fn inline_data(cat_id: i32, rest_num: i32) -> InlineData {
                // Короткие по две в ряд
                let markup = short.into_iter().array_chunks::<[_; 2]>()
                .fold(markup, |acc, [left, right]| acc.append_row(vec![left, right]));
+
+               // Переход на соседние страницы
+               let markup = page.append_nav(markup, |num| format!("grc{}", db::make_key_3_int(rest.num, cat_id, num)));
             
                // Кнопка назад
-               let button_back = InlineKeyboardButton::callback(String::from("⏪Назад"), format!("rca{}", db::make_key_3_int(cat_id, 0, 0)));
+               let button_back = InlineKeyboardButton::callback(lang::t(lang, BtnBack), format!("rca{}", db::make_key_3_int(cat_id, 0, 0)));
 
-               // Добавляем последнюю непарную кнопку и кнопку назад
+               // Звёздочка избранного
+               let favorite = db::is_favorite(user_id, rest.num, 0, 0).await;
+               let button_fav = favorites::button(favorite, &db::make_key_3_int(rest.num, 0, 0));
+
+               // Колокольчик подписки на новости
+               let button_sub = broadcast::rest_button(user_id, rest.num).await;
+
+               // Добавляем последнюю непарную кнопку, звёздочку, колокольчик и кнопку назад
                let markup = if let Some(last_button) = last {
-                  markup.append_row(vec![last_button, button_back])
+                  markup.append_row(vec![last_button, button_fav, button_sub, button_back])
                } else {
-                  markup.append_row(vec![button_back])
+                  markup.append_row(vec![button_fav, button_sub, button_back])
                };
 
                // Если у ресторана есть собственная картинка, вставим её, иначе плашку
@@ -242,10 +289,12 @@ async fn inline_data(cat_id: i32, rest_num: i32) -> InlineData {
 }
 
 // Выводит инлайн кнопки
-pub async fn show_inline_interface(cx: &DispatcherHandlerCx<CallbackQuery>, cat_id: i32, rest_num: i32) -> bool {
+pub async fn show_inline_interface(cx: &DispatcherHandlerCx<CallbackQuery>, cat_id: i32, rest_num: i32, page: i32) -> bool {
+   let lang = lang::user_lang(Some(&cx.update.from)).await;
+
 
    // Получаем данные
-   let data = inline_data(cat_id, rest_num).await;
+   let data = inline_data(lang, cx.update.from.id, cat_id, rest_num, page).await;
 
    // Достаём chat_id
    let message = cx.update.message.as_ref().unwrap();
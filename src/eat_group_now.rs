@@ -15,6 +15,7 @@ use teloxide::{
    },
 };
 use arraylib::iter::IteratorExt;
+use chrono::NaiveTime;
 
 use crate::commands as cmd;
 use crate::database as db;
@@ -22,11 +23,30 @@ use crate::eater;
 use crate::eat_rest_now;
 use crate::eat_dish;
 use crate::basket;
-use crate::language as lang;
+use crate::favorites;
+use crate::broadcast;
+use crate::notify;
+use crate::language::{self as lang, Res::*};
 use crate::settings;
+use crate::review;
+use crate::pages;
+
+// Открытые группы в начале списка, второе значение истинно, если такие есть
+fn sort_by_status(mut groups: db::GroupList, time: NaiveTime) -> (db::GroupList, bool) {
+   groups.sort_by_key(|group| !group.status(time).is_open());
+   let opened = groups.first().is_some_and(|group| group.status(time).is_open());
+   (groups, opened)
+}
 
 // Основную информацию режима
 pub async fn next_with_info(cx: cmd::Cx<i32>) -> cmd::Res {
+   next_with_page(cx, 1).await
+}
+
+// Информация о ресторане с указанной страницей списка групп
+async fn next_with_page(cx: cmd::Cx<i32>, page: i32) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
    // Извлечём параметры
    let rest_num = cx.dialogue;
   
@@ -34,31 +54,38 @@ pub async fn next_with_info(cx: cmd::Cx<i32>) -> cmd::Res {
    match db::restaurant(db::RestBy::Num(rest_num)).await {
       None => {
          // Такая ситуация не должна возникнуть
-         let s = String::from("Ошибка, информации о ресторане нет");
+         let s = lang::t(lang, EatNoRestInfo);
          let new_cx = DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ());
-         cmd::send_text(&new_cx, &s, cmd::EaterRest::markup()).await;
+         cmd::send_text(&new_cx, &s, cmd::EaterRest::markup(lang)).await;
       }
       Some(rest) => {
          // Сформируем информацию о ресторане
-         let rest_info = format!("<b>{}</b>\n{}\nОсновное время работы: {}-{}", rest.title, rest.info, db::str_time(rest.opening_time), db::str_time(rest.closing_time));
+         let rest_info = lang::tf(lang, EatRestInfo, &[&rest.title, &rest.info, &db::str_time(rest.opening_time), &db::str_time(rest.closing_time), &review::rating_line(lang, rest.num).await]);
 
-         // Текущее время
-         let time = settings::current_date_time().time();
+         // Текущее время заведения
+         let time = rest.now().time();
 
          // Получаем из БД список групп
-         let groups_desc = match db::group_list(db::GroupListBy::Time(rest_num, time)).await {
+         let groups_desc = match db::group_list(db::GroupListBy::Active(rest_num)).await {
             None => {
                // Такая ситуация может возникнуть, если ресторатор скрыл группы только что
-               String::from(lang::t("ru", lang::Res::EatGroupsEmpty))
+               format!("{}\n{}", lang::t(lang, EatGroupsEmpty), notify::offer(lang, NotifyRestOffer, rest_num, 0, 0))
             }
             Some(groups) => {
-               // Сформируем строку вида: Вторые блюда (00-23:59) /grou2
-               groups.into_iter().map(|group| (format!("   {} /EdGr{}\n", group.title_with_time(rest.opening_time, rest.closing_time), group.num))).collect()
+               // Сформируем строку вида: Вторые блюда · открыто до 22:00 /grou2
+               let (groups, opened) = sort_by_status(groups, time);
+               let page = pages::Page::text(groups, page);
+               let s: String = page.items.iter().map(|group| format!("   {} /grou{}\n", group.title_with_status(lang, time), group.num)).collect();
+               let s = format!("{}{}", s, page.links(lang, "/page"));
+
+               // Если всё закрыто, меню можно посмотреть заранее и попросить сообщить об открытии
+               if opened {s} else {format!("{}\n{}", s, notify::offer(lang, NotifyRestOffer, rest_num, 0, 0))}
             }
          };
                
-         // Формируем итоговую информацию
-         let s = format!("{}\n{}", rest_info, groups_desc);
+         // Формируем итоговую информацию с командами для избранного и подписки
+         let user_id = cx.update.from().unwrap().id;
+         let s = format!("{}\n{}\n{}\n{}", rest_info, groups_desc, favorites::rest_line(lang, user_id, rest_num).await, broadcast::rest_line(lang, user_id, rest_num).await);
 
          // Отображаем информацию о группах ресторана. Если для ресторана задана картинка, то текст будет комментарием
          if let Some(image_id) = rest.image_id {
@@ -69,14 +96,14 @@ pub async fn next_with_info(cx: cmd::Cx<i32>) -> cmd::Res {
             cx.answer_photo(image)
             .parse_mode(ParseMode::HTML)
             .caption(s)
-            .reply_markup(ReplyMarkup::ReplyKeyboardMarkup(cmd::EaterGroup::markup()))
+            .reply_markup(ReplyMarkup::ReplyKeyboardMarkup(cmd::EaterGroup::markup(lang)))
             .disable_notification(true)
             .send()
             .await?;
          } else {
                cx.answer(s)
                .parse_mode(ParseMode::HTML)
-               .reply_markup(cmd::EaterGroup::markup())
+               .reply_markup(cmd::EaterGroup::markup(lang))
                .disable_notification(true)
                .send()
                .await?;
@@ -90,8 +117,9 @@ pub async fn next_with_info(cx: cmd::Cx<i32>) -> cmd::Res {
 
 // Показывает сообщение об ошибке/отмене без повторного вывода информации
 async fn next_with_cancel(cx: cmd::Cx<i32>, text: &str) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
    cx.answer(text)
-   .reply_markup(cmd::EaterGroup::markup())
+   .reply_markup(cmd::EaterGroup::markup(lang))
    .disable_notification(true)
    .send()
    .await?;
@@ -107,13 +135,15 @@ async fn next_with_cancel(cx: cmd::Cx<i32>, text: &str) -> cmd::Res {
 
 // Обработчик команд
 pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
    // Извлечём параметры
    let rest_num = cx.dialogue;
 
    // Разбираем команду.
    match cx.update.text() {
       None => {
-         next_with_cancel(cx, "Текстовое сообщение, пожалуйста!").await
+         next_with_cancel(cx, &lang::t(lang, TextPlease)).await
       }
       Some(command) => {
          match cmd::EaterGroup::from(command) {
@@ -139,22 +169,39 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
                eat_rest_now::next_with_info(DialogueDispatcherHandlerCx::new(bot, update, ())).await
             }
 
+            // Добавить в избранное или убрать
+            cmd::EaterGroup::Favorite => {
+               let user_id = cx.update.from().unwrap().id;
+               let s = favorites::toggle_rest(lang, user_id, rest_num).await;
+               next_with_cancel(cx, &s).await
+            }
+
+            // Подписаться на новости или отписаться
+            cmd::EaterGroup::Subscribe => {
+               let user_id = cx.update.from().unwrap().id;
+               let s = broadcast::toggle_rest(lang, user_id, rest_num).await;
+               next_with_cancel(cx, &s).await
+            }
+
             // Выбор группы
             cmd::EaterGroup::Group(group_id) => {
                let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
                eat_dish::next_with_info(DialogueDispatcherHandlerCx::new(bot, update, (0, rest_num, group_id))).await
             }
 
+            // Другая страница списка
+            cmd::EaterGroup::Page(page) => next_with_page(cx, page).await,
+
             cmd::EaterGroup::UnknownCommand => {
                // Сохраним текущее состояние для возврата
-               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::EatRestGroupNowSelectionMode(rest_num), m : cmd::EaterGroup::markup()});
+               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::EatRestGroupNowSelectionMode(rest_num), m : cmd::EaterGroup::markup(lang)});
 
                // Возможно это общая команда
                if let Some(res) = eater::handle_common_commands(DialogueDispatcherHandlerCx::new(cx.bot.clone(), cx.update.clone(), ()), command, origin).await {return res;}
                else {
                   let s = String::from(command);
                   let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
-                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, rest_num), &format!("Вы в меню выбора группы: неизвестная команда '{}'", s)).await
+                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, rest_num), &lang::tf(lang, EatGroupUnknownCommand, &[&s])).await
                }
             }
          }
@@ -168,41 +215,46 @@ struct InlineData {
    markup: InlineKeyboardMarkup,
    photo_id: String,
 }
-async fn inline_data(rest_num: i32) -> InlineData {
+async fn inline_data(lang: &str, user_id: i32, rest_num: i32, page: i32) -> InlineData {
    // Получаем информацию из БД - нужен текст, картинка и кнопки
    let (text, markup, photo_opt) = match db::restaurant(db::RestBy::Num(rest_num)).await {
       None => {
          // Такая ситуация не должна возникнуть
 
          // Кнопка назад
-         let buttons = vec![InlineKeyboardButton::callback(String::from("⏪Назад"), format!("rno{}", db::make_key_3_int(0, 0, 0)))]; 
+         let buttons = vec![InlineKeyboardButton::callback(lang::t(lang, BtnBack), format!("rno{}", db::make_key_3_int(0, 0, 0)))]; 
          let markup = InlineKeyboardMarkup::default()
          .append_row(buttons);
 
          // Сформированные данные
-         (String::from("Ошибка, информации о ресторане нет"), markup, None)
+         (lang::t(lang, EatNoRestInfo), markup, None)
       }
       Some(rest) => {
          // Сформируем информацию о ресторане
-         let rest_info = format!("<b>{}</b>\n{}\nОсновное время работы: {}-{}", rest.title, rest.info, db::str_time(rest.opening_time), db::str_time(rest.closing_time));
+         let rest_info = lang::tf(lang, EatRestInfo, &[&rest.title, &rest.info, &db::str_time(rest.opening_time), &db::str_time(rest.closing_time), &review::rating_line(lang, rest.num).await]);
 
-         // Текущее время
-         let time = settings::current_date_time().time();
+         // Текущее время заведения
+         let time = rest.now().time();
 
          // Получаем из БД список групп и формируем из них инлайн кнопки
-         let markup = match db::group_list(db::GroupListBy::Time(rest_num, time)).await {
+         let markup = match db::group_list(db::GroupListBy::Active(rest_num)).await {
             None => {
                // Такая ситуация может возникнуть, если ресторатор скрыл группы только что
-               let buttons = vec![InlineKeyboardButton::callback(String::from("⏪Назад"), format!("rno{}", db::make_key_3_int(0, 0, 0)))]; 
+               let buttons = vec![
+                  notify::button(&db::make_key_3_int(rest_num, 0, 0)),
+                  InlineKeyboardButton::callback(lang::t(lang, BtnBack), format!("rno{}", db::make_key_3_int(0, 0, 0))),
+               ];
 
-               // Возвращаем меню, состоящее из одной кнопки назад
+               // Возвращаем меню из кнопок уведомления об открытии и назад
                InlineKeyboardMarkup::default()
                .append_row(buttons)
             }
             Some(groups) => {
                // Создадим кнопки
-               let buttons: Vec<InlineKeyboardButton> = groups.into_iter()
-               .map(|group| (InlineKeyboardButton::callback(group.title_with_time(rest.opening_time, rest.closing_time), format!("drg{}", db::make_key_3_int(rest.num, group.num, 0)))))
+               let (groups, opened) = sort_by_status(groups, time);
+               let page = pages::Page::buttons(groups, page);
+               let buttons: Vec<InlineKeyboardButton> = page.items.iter()
+               .map(|group| (InlineKeyboardButton::callback(group.title_with_status(lang, time), format!("drg{}", db::make_key_3_int(rest.num, group.num, 0)))))
                .collect();
 
                // Поделим на длинные и короткие
@@ -220,19 +272,30 @@ async fn inline_data(rest_num: i32) -> InlineData {
                // Короткие по две в ряд
                let markup = short.into_iter().array_chunks::<[_; 2]>()
                .fold(markup, |acc, [left, right]| acc.append_row(vec![left, right]));
+
+               // Переход на соседние страницы
+               let markup = page.append_nav(markup, |num| format!("rng{}", db::make_key_3_int(rest.num, num, 0)));
             
                // Кнопка назад
-               let button_back = InlineKeyboardButton::callback(String::from("⏪Назад"), format!("rno{}", db::make_key_3_int(0, 0, 0)));
+               let button_back = InlineKeyboardButton::callback(lang::t(lang, BtnBack), format!("rno{}", db::make_key_3_int(0, 0, 0)));
 
-               // Добавляем последнюю непарную кнопку и кнопку назад
+               // Звёздочка избранного
+               let favorite = db::is_favorite(user_id, rest.num, 0, 0).await;
+               let button_fav = favorites::button(favorite, &db::make_key_3_int(rest.num, 0, 0));
+
+               // Колокольчик подписки на новости
+               let button_sub = broadcast::rest_button(user_id, rest.num).await;
+
+               // Добавляем последнюю непарную кнопку, звёздочку, колокольчик и кнопку назад
                let markup = if let Some(last_button) = last {
-                  markup.append_row(vec![last_button, button_back])
+                  markup.append_row(vec![last_button, button_fav, button_sub, button_back])
                } else {
-                  markup.append_row(vec![button_back])
+                  markup.append_row(vec![button_fav, button_sub, button_back])
                };
 
-               // Сформированные данные
-               markup
+               // Если всё закрыто, предложим сообщить об открытии
+               if opened {markup}
+               else {markup.append_row(vec![notify::button(&db::make_key_3_int(rest_num, 0, 0))])}
             }
          };
 
@@ -251,10 +314,12 @@ async fn inline_data(rest_num: i32) -> InlineData {
 }
 
 // Выводит инлайн кнопки из колбека
-pub async fn show_inline_interface(cx: &DispatcherHandlerCx<CallbackQuery>, rest_num: i32) -> bool {
+pub async fn show_inline_interface(cx: &DispatcherHandlerCx<CallbackQuery>, rest_num: i32, page: i32) -> bool {
+   let lang = lang::user_lang(Some(&cx.update.from)).await;
+
 
    // Получаем информацию
-   let data = inline_data(rest_num).await;
+   let data = inline_data(lang, cx.update.from.id, rest_num, page).await;
 
    // Достаём chat_id
    let message = cx.update.message.as_ref().unwrap();
@@ -285,11 +350,13 @@ pub async fn show_inline_interface(cx: &DispatcherHandlerCx<CallbackQuery>, rest
 
 // Выводит инлайн кнопки для нового сообщения
 pub async fn force_inline_interface(cx: cmd::Cx<i32>) -> bool {
+   let lang = lang::user_lang(cx.update.from()).await;
+
    // Извлечём параметры
    let rest_num = cx.dialogue;
 
    // Получаем информацию
-   let data = inline_data(rest_num).await;
+   let data = inline_data(lang, cx.update.from().unwrap().id, rest_num, 1).await;
 
    // Отправляем сообщение как фото
    let res = cx.answer_photo(InputFile::file_id(data.photo_id))
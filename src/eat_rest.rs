@@ -14,18 +14,27 @@ use teloxide::{
    },
 };
 use arraylib::iter::IteratorExt;
+use std::collections::HashMap;
 
 use crate::commands as cmd;
 use crate::database as db;
 use crate::eater;
 use crate::eat_group;
 use crate::basket;
-use crate::language as lang;
+use crate::language::{self as lang, Res::*};
 use crate::settings;
+use crate::pages;
 
 
 // Показывает список ресторанов с группами заданной категории
 pub async fn next_with_info(cx: cmd::Cx<i32>) -> cmd::Res {
+   next_with_page(cx, 1).await
+}
+
+// Показывает указанную страницу списка ресторанов
+async fn next_with_page(cx: cmd::Cx<i32>, page: i32) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
    // Извлечём параметры
    let cat_id = cx.dialogue;
    
@@ -37,20 +46,22 @@ pub async fn next_with_info(cx: cmd::Cx<i32>) -> cmd::Res {
 
          // Выводим информацию либо ссылками, либо инлайн кнопками
          if compact_mode {
-            // Сформируем строку вида "название /ссылка\n"
-            let s: String = rest_list.into_iter().map(|restaurant| (format!("   {} /rest{}\n", restaurant.title, restaurant.num))).collect();
+            // Сформируем строку вида "название рейтинг /ссылка\n"
+            let ratings = db::rest_ratings().await;
+            let page = pages::Page::text(rest_list, page);
+            let s: String = page.items.iter().map(|restaurant| format!("   {} /rest{}\n", db::title_with_rating(&restaurant.title, ratings.get(&restaurant.num)), restaurant.num)).collect();
             
             // Отображаем информацию и кнопки меню
-            let s = format!("Заведения с подходящим меню:\n{}", s);
+            let s = format!("{}\n{}{}", lang::t(lang, EatRestTitle), s, page.links(lang, "/page"));
             let new_cx = DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ());
-            cmd::send_text(&new_cx, &s, cmd::EaterRest::markup()).await;
+            cmd::send_text(&new_cx, &s, cmd::EaterRest::markup(lang)).await;
       
          } else {
             // Создадим кнопки
-            let markup = make_markup(rest_list, cat_id);
+            let markup = make_markup(rest_list, cat_id, &db::rest_ratings().await, page);
 
             // Отправляем сообщение с плашкой в качестве картинки
-            let s = String::from("Заведения с подходящим меню:");
+            let s = lang::t(lang, EatRestTitle);
             let new_cx = DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ());
             cmd::send_photo(&new_cx, &s, ReplyMarkup::InlineKeyboardMarkup(markup), db::cat_image(cat_id)).await;
 
@@ -60,10 +71,10 @@ pub async fn next_with_info(cx: cmd::Cx<i32>) -> cmd::Res {
       }
       None => {
          // Если там пусто, то сообщим об этом
-         let s = String::from(lang::t("ru", lang::Res::EatRestEmpty));
-         let s = format!("Заведения с подходящим меню:\n{}", s);
+         let s = lang::t(lang, EatRestEmpty);
+         let s = format!("{}\n{}", lang::t(lang, EatRestTitle), s);
          let new_cx = DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ());
-         cmd::send_text(&new_cx, &s, cmd::EaterRest::markup()).await;
+         cmd::send_text(&new_cx, &s, cmd::EaterRest::markup(lang)).await;
       }
    }
 
@@ -73,8 +84,9 @@ pub async fn next_with_info(cx: cmd::Cx<i32>) -> cmd::Res {
 
 // Показывает сообщение об ошибке/отмене без повторного вывода информации
 async fn next_with_cancel(cx: cmd::Cx<i32>, text: &str) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
    cx.answer(text)
-   .reply_markup(cmd::EaterRest::markup())
+   .reply_markup(cmd::EaterRest::markup(lang))
    .disable_notification(true)
    .send()
    .await?;
@@ -89,6 +101,8 @@ async fn next_with_cancel(cx: cmd::Cx<i32>, text: &str) -> cmd::Res {
 
 // Обработчик команд
 pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
    // Код категории
    let cat_id = cx.dialogue;
 
@@ -98,7 +112,7 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
    // Разбираем команду.
    match cx.update.text() {
       None => {
-         next_with_cancel(cx, "Текстовое сообщение, пожалуйста!").await
+         next_with_cancel(cx, &lang::t(lang, TextPlease)).await
       }
       Some(command) => {
          match cmd::EaterRest::from(command) {
@@ -121,16 +135,19 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
                eat_group::next_with_info(DialogueDispatcherHandlerCx::new(bot, update, (cat_id, rest_id))).await
             }
 
+            // Другая страница списка
+            cmd::EaterRest::Page(page) => next_with_page(cx, page).await,
+
             cmd::EaterRest::UnknownCommand => {
                // Сохраним текущее состояние для возврата
-               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::EatRestSelectionMode(cat_id), m : cmd::EaterRest::markup()});
+               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::EatRestSelectionMode(cat_id), m : cmd::EaterRest::markup(lang)});
 
                // Возможно это общая команда
                if let Some(res) = eater::handle_common_commands(DialogueDispatcherHandlerCx::new(cx.bot.clone(), cx.update.clone(), ()), command, origin).await {return res;}
                else {
                   let s = String::from(command);
                   let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
-                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, cat_id), &format!("Вы в меню выбора ресторана: неизвестная команда '{}'", s)).await
+                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, cat_id), &lang::tf(lang, EatRestUnknownCommand, &[&s])).await
                }
             }
          }
@@ -138,12 +155,13 @@ pub async fn handle_commands(cx: cmd::Cx<i32>) -> cmd::Res {
    }
 }
 
-// Формирует инлайн кнопки по данным из БД
+// Формирует инлайн кнопки по данным из БД для указанной страницы
 //
-fn make_markup(rest_list: db::RestList, cat_id: i32) -> InlineKeyboardMarkup {
+fn make_markup(rest_list: db::RestList, cat_id: i32, ratings: &HashMap<i32, db::Rating>, page: i32) -> InlineKeyboardMarkup {
    // Создадим кнопки под рестораны
-   let buttons: Vec<InlineKeyboardButton> = rest_list.into_iter()
-   .map(|restaurant| (InlineKeyboardButton::callback(restaurant.title, format!("grc{}", db::make_key_3_int(restaurant.num, cat_id, 0)))))  // third argument unused
+   let page = pages::Page::buttons(rest_list, page);
+   let buttons: Vec<InlineKeyboardButton> = page.items.iter()
+   .map(|restaurant| (InlineKeyboardButton::callback(db::title_with_rating(&restaurant.title, ratings.get(&restaurant.num)), format!("grc{}", db::make_key_3_int(restaurant.num, cat_id, 0)))))  // third argument unused
    .collect();
 
    let (long, mut short) : (Vec<_>, Vec<_>) = buttons
@@ -161,21 +179,24 @@ fn make_markup(rest_list: db::RestList, cat_id: i32) -> InlineKeyboardMarkup {
    let markup = short.into_iter().array_chunks::<[_; 2]>()
    .fold(markup, |acc, [left, right]| acc.append_row(vec![left, right]));
    
-   // Возвращаем результат
-   if let Some(last_button) = last {
+   // Последняя кнопка и переход на соседние страницы
+   let markup = if let Some(last_button) = last {
       markup.append_row(vec![last_button])
    } else {
       markup
-   }
+   };
+   page.append_nav(markup, |num| format!("rca{}", db::make_key_3_int(cat_id, num, 0)))
 }
 
 // Выводит инлайн кнопки, редактируя предыдущее сообщение
-pub async fn show_inline_interface(cx: &DispatcherHandlerCx<CallbackQuery>, cat_id: i32) -> bool {
+pub async fn show_inline_interface(cx: &DispatcherHandlerCx<CallbackQuery>, cat_id: i32, page: i32) -> bool {
+   let lang = lang::user_lang(Some(&cx.update.from)).await;
+
    // Получаем информацию из БД
    match db::rest_list(db::RestListBy::Category(cat_id)).await {
       Some(rest_list) => {
          // Создадим кнопки
-         let markup = make_markup(rest_list, cat_id);
+         let markup = make_markup(rest_list, cat_id, &db::rest_ratings().await, page);
 
          // Достаём chat_id
          let message = cx.update.message.as_ref().unwrap();
@@ -187,7 +208,7 @@ pub async fn show_inline_interface(cx: &DispatcherHandlerCx<CallbackQuery>, cat_
          // Приготовим структуру для редактирования
          let media = InputMedia::Photo{
             media: InputFile::file_id(db::cat_image(cat_id)),
-            caption: Some( String::from("Заведения с подходящим меню:")),
+            caption: Some( lang::t(lang, EatRestTitle)),
             parse_mode: None,
          };
 
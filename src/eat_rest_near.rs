@@ -0,0 +1,107 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Режим едока, заведения рядом с присланной геопозицией. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+use teloxide::{
+   prelude::*,
+   types::{InlineKeyboardButton, InlineKeyboardMarkup, ReplyMarkup},
+};
+use std::collections::HashSet;
+
+use crate::commands as cmd;
+use crate::database as db;
+use crate::language::{self as lang, Res::*};
+use crate::settings;
+
+// Сколько ближайших заведений показывать
+const MAX_RESTAURANTS: usize = 20;
+
+// Средний радиус Земли в метрах
+const EARTH_RADIUS: f64 = 6_371_000.0;
+
+// Расстояние в метрах между точками (широта, долгота) по формуле гаверсинусов
+pub fn distance(from: (f64, f64), to: (f64, f64)) -> f64 {
+   let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+   let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+   let a = ((lat2 - lat1) / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+   2.0 * EARTH_RADIUS * a.sqrt().asin()
+}
+
+// До километра в метрах с точностью до десятка, дальше в километрах с десятыми
+fn distance_str(lang: &str, meters: f64) -> String {
+   if meters < 1000.0 {lang::tf(lang, DistanceMeters, &[&((meters / 10.0).round() * 10.0)])}
+   else {lang::tf(lang, DistanceKm, &[&format!("{:.1}", meters / 1000.0)])}
+}
+
+// Показывает ближайшие заведения с отметкой, открыты ли они сейчас
+pub async fn next_with_info(cx: cmd::Cx<(f64, f64)>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
+   // Откуда ищем
+   let here = cx.dialogue;
+   let cx = DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ());
+
+   // Открытые сейчас определяем так же, как для списка "Сейчас"
+   let opened: HashSet<i32> = db::rest_list(db::RestListBy::OpenNow).await
+   .unwrap_or_default()
+   .into_iter()
+   .map(|rest| rest.num)
+   .collect();
+
+   // Заведения с геопозицией по возрастанию расстояния
+   let mut list: Vec<(f64, db::Restaurant)> = db::rest_list(db::RestListBy::Located).await
+   .unwrap_or_default()
+   .into_iter()
+   .filter_map(|rest| rest.location.map(|location| (distance(here, location), rest)))
+   .collect();
+   list.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+   // Дальние не показываем, но сообщаем об этом в заголовке
+   let title = if list.len() > MAX_RESTAURANTS {
+      format!("{}\n{}", lang::t(lang, EatRestNearTitle), lang::tf(lang, EatRestNearMore, &[&MAX_RESTAURANTS, &list.len()]))
+   } else {
+      lang::t(lang, EatRestNearTitle)
+   };
+   list.truncate(MAX_RESTAURANTS);
+
+   if list.is_empty() {
+      let s = format!("{}\n{}", lang::t(lang, EatRestNearTitle), lang::t(lang, EatRestNearEmpty));
+      cmd::send_text(&cx, &s, cmd::User::main_menu_markup(lang)).await;
+      return next(cmd::Dialogue::UserMode);
+   }
+
+   // Строка вида "✅ название рейтинг · 350 м"
+   let ratings = db::rest_ratings().await;
+   let label = |meters: f64, rest: &db::Restaurant| format!("{} {} · {}",
+      if opened.contains(&rest.num) {"✅"} else {"⛔"},
+      db::title_with_rating(&rest.title, ratings.get(&rest.num)),
+      distance_str(lang, meters)
+   );
+
+   // Запросим настройку пользователя с режимом интерфейса
+   let compact_mode = db::user_compact_interface(cx.update.from()).await;
+
+   // Выводим информацию либо ссылками, либо инлайн кнопками
+   if compact_mode {
+      let s: String = list.iter().map(|(meters, rest)| format!("   {} /rest{}\n", label(*meters, rest), rest.num)).collect();
+      let s = format!("{}\n{}", title, s);
+      cmd::send_text(&cx, &s, cmd::EaterRest::markup(lang)).await;
+
+      // Выбор заведения такой же, как в списке открытых сейчас
+      next(cmd::Dialogue::EatRestNowSelectionMode)
+   } else {
+      let markup = list.iter()
+      .map(|(meters, rest)| InlineKeyboardButton::callback(label(*meters, rest), format!("rng{}", db::make_key_3_int(rest.num, 0, 0))))
+      .fold(InlineKeyboardMarkup::default(), |acc, button| acc.append_row(vec![button]));
+
+      cmd::send_photo(&cx, &title, ReplyMarkup::InlineKeyboardMarkup(markup), settings::default_photo_id()).await;
+
+      // В инлайн-режиме всегда остаёмся в главном меню
+      next(cmd::Dialogue::UserMode)
+   }
+}
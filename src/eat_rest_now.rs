@@ -14,43 +14,53 @@ use teloxide::{
    },
 };
 use arraylib::iter::IteratorExt;
+use std::collections::HashMap;
 
 use crate::commands as cmd;
 use crate::database as db;
 use crate::eater;
 use crate::eat_group_now;
 use crate::basket;
-use crate::language as lang;
+use crate::language::{self as lang, Res::*};
 use crate::settings;
+use crate::pages;
 
 // Показывает список ресторанов с группами заданной категории
 pub async fn next_with_info(cx: cmd::Cx<()>) -> cmd::Res {
+   next_with_page(cx, 1).await
+}
+
+// Показывает указанную страницу списка ресторанов
+async fn next_with_page(cx: cmd::Cx<()>, page: i32) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
 
    // Текущее время
    let now = settings::current_date_time();
-   let time = settings::current_date_time().time();
    
-   match db::rest_list(db::RestListBy::Time(time)).await {
-      Some(rest_list) => {
+   match rest_list_with_status().await {
+      Some((rest_list, statuses)) => {
          // Запросим настройку пользователя с режимом интерфейса и обновим время последнего входа в БД
          let compact_mode = db::user_compact_interface(cx.update.from()).await;
 
          // Выводим информацию либо ссылками, либо инлайн кнопками
          if compact_mode {
-            // Сформируем строку вида "название /ссылка\n"
-            let s: String = rest_list.into_iter().map(|restaurant| (format!("   {} /rest{}\n", restaurant.title, restaurant.num))).collect();
+            // Сформируем строку вида "название рейтинг /ссылка\n"
+            let ratings = db::rest_ratings().await;
+            let page = pages::Page::text(rest_list, page);
+            let s: String = page.items.iter().map(|restaurant| format!("   {} /rest{}\n", rest_title(lang, restaurant, &ratings, &statuses), restaurant.num)).collect();
             
             // Отображаем информацию и кнопки меню
-            let s = format!("Рестораны, открытые сейчас ({}):\n{}", now.format("%H:%M"), s);
+            let s = format!("{}\n{}{}", lang::tf(lang, EatRestNowTitle, &[&now.format("%H:%M")]), s, page.links(lang, "/page"));
             let new_cx = DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ());
-            cmd::send_text(&new_cx, &s, cmd::EaterRest::markup()).await;
+            cmd::send_text(&new_cx, &s, cmd::EaterRest::markup(lang)).await;
       
          } else {
             // Создадим кнопки
-            let markup = make_markup(rest_list);
+            let markup = make_markup(lang, rest_list, &db::rest_ratings().await, &statuses, page);
 
             // Отправляем сообщение с плашкой в качестве картинки
-            let s = String::from(format!("Рестораны, открытые сейчас ({}):", now.format("%H:%M")));
+            let s = lang::tf(lang, EatRestNowTitle, &[&now.format("%H:%M")]);
             let new_cx = DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ());
             cmd::send_photo(&new_cx, &s, ReplyMarkup::InlineKeyboardMarkup(markup), settings::default_photo_id()).await;
 
@@ -60,10 +70,10 @@ pub async fn next_with_info(cx: cmd::Cx<()>) -> cmd::Res {
       }
       None => {
          // Если там пусто, то сообщим об этом
-         let s = String::from(lang::t("ru", lang::Res::EatRestNowEmpty));
-         let s = format!("Рестораны, открытые сейчас ({}):\n{}", now.format("%H:%M"), s);
+         let s = lang::t(lang, EatRestNowEmpty);
+         let s = format!("{}\n{}", lang::tf(lang, EatRestNowTitle, &[&now.format("%H:%M")]), s);
          let new_cx = DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ());
-         cmd::send_text(&new_cx, &s, cmd::EaterRest::markup()).await;
+         cmd::send_text(&new_cx, &s, cmd::EaterRest::markup(lang)).await;
       }
    }
 
@@ -73,9 +83,10 @@ pub async fn next_with_info(cx: cmd::Cx<()>) -> cmd::Res {
 
 // Показывает сообщение об ошибке/отмене без повторного вывода информации
 async fn next_with_cancel(cx: cmd::Cx<()>, text: &str) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
 
    cx.answer(text)
-   .reply_markup(cmd::EaterRest::markup())
+   .reply_markup(cmd::EaterRest::markup(lang))
    .disable_notification(true)
    .send()
    .await?;
@@ -88,6 +99,8 @@ async fn next_with_cancel(cx: cmd::Cx<()>, text: &str) -> cmd::Res {
 
 // Обработчик команд
 pub async fn handle_commands(cx: cmd::Cx<()>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
 
    // Код едока
    let user_id = cx.update.from().unwrap().id;
@@ -95,7 +108,7 @@ pub async fn handle_commands(cx: cmd::Cx<()>) -> cmd::Res {
    // Разбираем команду.
    match cx.update.text() {
       None => {
-         next_with_cancel(cx, "Текстовое сообщение, пожалуйста!").await
+         next_with_cancel(cx, &lang::t(lang, TextPlease)).await
       }
       Some(command) => {
          match cmd::EaterRest::from(command) {
@@ -118,16 +131,19 @@ pub async fn handle_commands(cx: cmd::Cx<()>) -> cmd::Res {
                eat_group_now::next_with_info(DialogueDispatcherHandlerCx::new(bot, update, rest_id)).await
             }
 
+            // Другая страница списка
+            cmd::EaterRest::Page(page) => next_with_page(cx, page).await,
+
             cmd::EaterRest::UnknownCommand => {
                // Сохраним текущее состояние для возврата
-               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::EatRestNowSelectionMode, m : cmd::EaterRest::markup()});
+               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::EatRestNowSelectionMode, m : cmd::EaterRest::markup(lang)});
 
                // Возможно это общая команда
                if let Some(res) = eater::handle_common_commands(DialogueDispatcherHandlerCx::new(cx.bot.clone(), cx.update.clone(), ()), command, origin).await {return res;}
                else {
                   let s = String::from(command);
                   let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
-                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, ()), &format!("Вы в меню выбора ресторана: неизвестная команда '{}'", s)).await
+                  next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, ()), &lang::tf(lang, EatRestUnknownCommand, &[&s])).await
                }
             }
          }
@@ -135,12 +151,30 @@ pub async fn handle_commands(cx: cmd::Cx<()>) -> cmd::Res {
    }
 }
 
-// Формирует инлайн кнопки по данным из БД
+// Рестораны с меню вместе с состоянием работы, открытые в начале списка, закрытые можно посмотреть заранее
+async fn rest_list_with_status() -> Option<(db::RestList, HashMap<i32, db::WorkStatus>)> {
+   let mut rest_list = db::rest_list(db::RestListBy::WithMenu).await?;
+   let statuses = db::rest_statuses().await;
+   rest_list.sort_by_key(|restaurant| !statuses.get(&restaurant.num).is_some_and(db::WorkStatus::is_open));
+   Some((rest_list, statuses))
+}
+
+// Название ресторана с рейтингом и состоянием работы
+fn rest_title(lang: &str, restaurant: &db::Restaurant, ratings: &HashMap<i32, db::Rating>, statuses: &HashMap<i32, db::WorkStatus>) -> String {
+   let title = db::title_with_rating(&restaurant.title, ratings.get(&restaurant.num));
+   match statuses.get(&restaurant.num) {
+      Some(status) => format!("{} · {}", title, status.to_str(lang)),
+      None => title,
+   }
+}
+
+// Формирует инлайн кнопки по данным из БД для указанной страницы
 //
-fn make_markup(rest_list: db::RestList) -> InlineKeyboardMarkup {
+fn make_markup(lang: &str, rest_list: db::RestList, ratings: &HashMap<i32, db::Rating>, statuses: &HashMap<i32, db::WorkStatus>, page: i32) -> InlineKeyboardMarkup {
    // Создадим кнопки под рестораны
-   let buttons: Vec<InlineKeyboardButton> = rest_list.into_iter()
-   .map(|restaurant| (InlineKeyboardButton::callback(restaurant.title, format!("rng{}", db::make_key_3_int(restaurant.num, 0, 0)))))
+   let page = pages::Page::buttons(rest_list, page);
+   let buttons: Vec<InlineKeyboardButton> = page.items.iter()
+   .map(|restaurant| (InlineKeyboardButton::callback(rest_title(lang, restaurant, ratings, statuses), format!("rng{}", db::make_key_3_int(restaurant.num, 0, 0)))))
    .collect();
 
    let (long, mut short) : (Vec<_>, Vec<_>) = buttons
@@ -158,24 +192,27 @@ fn make_markup(rest_list: db::RestList) -> InlineKeyboardMarkup {
    let markup = short.into_iter().array_chunks::<[_; 2]>()
    .fold(markup, |acc, [left, right]| acc.append_row(vec![left, right]));
    
-   // Возвращаем результат
-   if let Some(last_button) = last {
+   // Последняя кнопка и переход на соседние страницы
+   let markup = if let Some(last_button) = last {
       markup.append_row(vec![last_button])
    } else {
       markup
-   }
+   };
+   page.append_nav(markup, |num| format!("rno{}", db::make_key_3_int(num, 0, 0)))
 }
 
 // Выводит инлайн кнопки, редактируя предыдущее сообщение
-pub async fn show_inline_interface(cx: &DispatcherHandlerCx<CallbackQuery>) -> bool {
+pub async fn show_inline_interface(cx: &DispatcherHandlerCx<CallbackQuery>, page: i32) -> bool {
+   let lang = lang::user_lang(Some(&cx.update.from)).await;
+
    // Текущее время
    let now = settings::current_date_time().time();
    
    // Получаем информацию из БД
-   match db::rest_list(db::RestListBy::Time(now)).await {
-      Some(rest_list) => {
+   match rest_list_with_status().await {
+      Some((rest_list, statuses)) => {
          // Создадим кнопки
-         let markup = make_markup(rest_list);
+         let markup = make_markup(lang, rest_list, &db::rest_ratings().await, &statuses, page);
 
          // Достаём chat_id
          let message = cx.update.message.as_ref().unwrap();
@@ -187,7 +224,7 @@ pub async fn show_inline_interface(cx: &DispatcherHandlerCx<CallbackQuery>) -> b
          // Приготовим структуру для редактирования
          let media = InputMedia::Photo{
             media: InputFile::file_id(settings::default_photo_id()),
-            caption: Some( format!("Рестораны, открытые сейчас ({}):", now.format("%H:%M"))),
+            caption: Some( lang::tf(lang, EatRestNowTitle, &[&now.format("%H:%M")])),
             parse_mode: None,
          };
 
@@ -15,13 +15,22 @@ use crate::commands as cmd;
 use crate::database as db;
 use crate::eat_rest;
 use crate::eat_rest_now;
+use crate::eat_rest_near;
 use crate::basket;
 use crate::settings;
 use crate::gear;
 use crate::eat_dish;
 use crate::eat_group_now;
+use crate::review;
+use crate::favorites;
+use crate::broadcast;
+use crate::notify;
+use crate::registry;
+use crate::language::{self as lang, Res::*};
 
 pub async fn start(cx: cmd::Cx<()>, after_restart: bool) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
    
    // Различаем перезапуск и возврат из меню ресторатора
    let s = if after_restart {
@@ -31,12 +40,12 @@ pub async fn start(cx: cmd::Cx<()>, after_restart: bool) -> cmd::Res {
 
       // Для администратора отдельное приветствие
       if settings::is_admin(cx.update.from()) {
-         String::from("Начат новый сеанс. Список команд администратора в описании: https://github.com/ArtHome12/vzmuinebot")
+         lang::t(lang, EaterNewSessionAdmin)
       } else {
-         String::from("Начат новый сеанс. Пожалуйста, выберите в основном меню снизу какие заведения показать, либо отправьте часть названия блюда для поиска.")
+         lang::t(lang, EaterNewSession)
       }
    } else {
-      String::from("Пожалуйста, выберите в основном меню снизу какие заведения показать, либо отправьте часть названия блюда для поиска.")
+      lang::t(lang, EaterWelcome)
    };
    
    // Если сессия началась с какой-то команды, то попробуем сразу её обработать
@@ -50,35 +59,43 @@ pub async fn start(cx: cmd::Cx<()>, after_restart: bool) -> cmd::Res {
    }
 
    // Если команды не было или она не распознана, отображаем приветственное сообщение и меню с кнопками.
-   cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update.clone(), ()), &s, cmd::User::main_menu_markup()).await;
+   cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update.clone(), ()), &s, cmd::User::main_menu_markup(lang)).await;
    
    // Переходим в режим получения выбранного пункта в главном меню
    next(cmd::Dialogue::UserMode)
 }
 
 pub async fn handle_commands(cx: cmd::Cx<()>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
 
    // Разбираем команду
    match cx.update.text() {
       None => {
+         // По геопозиции покажем ближайшие заведения
+         if let Some(location) = cx.update.location() {
+            let here = (location.latitude, location.longitude);
+            return eat_rest_near::next_with_info(DialogueDispatcherHandlerCx::new(cx.bot, cx.update, here)).await;
+         }
+
          let s = match cx.update.photo() {
             Some(photo_size) => {
                let image_id = photo_size[0].file_id.to_owned();
-               let s = format!("Вы прислали картинку с id\n{}", &image_id);
+               let s = lang::tf(lang, EaterPictureId, &[&image_id]);
 
                // Если картинка получена от админа, предложим установить её как картинку категории
                if settings::is_admin(cx.update.from()) {
-                  let s = format!("{}\n\nВы можете установить её как главную для бота через переменную окружения либо выберите внизу категорию, чтобы сделать её по-умолчанию для данной категории (/ для отмены)", s);
-                  cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update.clone(), ()), &s, cmd::CatGroup::category_markup()).await;
+                  let s = lang::tf(lang, EaterPictureAdmin, &[&s]);
+                  cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update.clone(), ()), &s, cmd::CatGroup::category_markup(lang)).await;
                   return next(cmd::Dialogue::UserModeEditCatImage(image_id))
                } else {
                   // Иначе просто сообщим её идентификатор
                   s
                }
             }
-            None => String::from("Текстовое сообщение, пожалуйста!"),
+            None => lang::t(lang, TextPlease),
          };
-         cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ()), &s, cmd::User::main_menu_markup()).await
+         cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ()), &s, cmd::User::main_menu_markup(lang)).await
       }
       Some(command) => {
          match cmd::User::from(command) {
@@ -92,13 +109,13 @@ pub async fn handle_commands(cx: cmd::Cx<()>) -> cmd::Res {
             }
             cmd::User::UnknownCommand => {
                // Сохраним текущее состояние для возврата
-               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::UserMode, m : cmd::User::main_menu_markup()});
+               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::UserMode, m : cmd::User::main_menu_markup(lang)});
 
                // Возможно это общая команда
                if let Some(res) = handle_common_commands(DialogueDispatcherHandlerCx::new(cx.bot.clone(), cx.update.clone(), ()), command, origin).await {return res;}
                else {
-                  let s = &format!("Вы в главном меню: неизвестная команда {}", command);
-                  cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ()), s, cmd::User::main_menu_markup()).await
+                  let s = &lang::tf(lang, EaterUnknownCommand, &[&command]);
+                  cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ()), s, cmd::User::main_menu_markup(lang)).await
                }
             }
             cmd::User::Gear => {
@@ -112,10 +129,18 @@ pub async fn handle_commands(cx: cmd::Cx<()>) -> cmd::Res {
                // Переходим в корзину
                return basket::next_with_info(DialogueDispatcherHandlerCx::new(cx.bot, cx.update, user_id)).await;
             }
+            cmd::User::NearMe => {
+               // Кнопка прислала текст вместо геопозиции
+               cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ()), &lang::t(lang, EatRestNearHint), cmd::User::main_menu_markup(lang)).await;
+            }
+            cmd::User::Favorites(page) => {
+               // Избранное с доступностью прямо сейчас
+               return favorites::next_with_info(cx, page).await;
+            }
             cmd::User::ChatId => {
                // Отправим информацию о чате
                let id = cx.chat_id();
-               cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ()), &format!("Chat id={}", id), cmd::User::main_menu_markup()).await;
+               cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ()), &format!("Chat id={}", id), cmd::User::main_menu_markup(lang)).await;
             }
          }
       }
@@ -127,22 +152,25 @@ pub async fn handle_commands(cx: cmd::Cx<()>) -> cmd::Res {
 
 // Обработка глобальных команд
 pub async fn handle_common_commands(cx: cmd::Cx<()>, command: &str, origin : Box<cmd::DialogueState>) -> Option<cmd::Res> {
+   let lang = lang::user_lang(cx.update.from()).await;
 
    async fn goto(cx: cmd::Cx<()>, rest_num: i32, group_num: i32, dish_num: i32)-> cmd::Res {
+      let lang = lang::user_lang(cx.update.from()).await;
+
 
       // Запросим настройку пользователя с режимом интерфейса и обновим время последнего входа в БД
       let compact_mode = db::user_compact_interface(cx.update.from()).await;
 
       // Название ресторана
-      let rest_name = if let Some(rest) = db::restaurant(db::RestBy::Num(rest_num)).await {rest.title} else {String::from("ошибка получения названия")};
+      let rest_name = if let Some(rest) = db::restaurant(db::RestBy::Num(rest_num)).await {rest.title} else {lang::t(lang, ErrorTitle)};
 
       // Приветственное сообщение и меню с кнопками (иначе нижнего меню не будет в инлайн-режиме)
-      let s = format!("Добро пожаловать в {}!", rest_name);
+      let s = lang::tf(lang, EaterWelcomeTo, &[&rest_name]);
 
          // Режим "со ссылками"
       if compact_mode {
          // Приветственное сообщение с правильным меню
-         cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot.clone(), cx.update.clone(), ()), &s, cmd::EaterGroup::markup()).await;
+         cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot.clone(), cx.update.clone(), ()), &s, cmd::EaterGroup::markup(lang)).await;
 
          // Если второй аргумент нулевой, надо отобразить группы ресторана
          if group_num == 0 {
@@ -165,7 +193,7 @@ pub async fn handle_common_commands(cx: cmd::Cx<()>, command: &str, origin : Box
          }
       } else {
          // Приветственное сообщение с правильным меню
-         cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot.clone(), cx.update.clone(), ()), &s, cmd::User::main_menu_markup()).await;
+         cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot.clone(), cx.update.clone(), ()), &s, cmd::User::main_menu_markup(lang)).await;
 
          // Режим с инлайн-кнопками
          if group_num == 0 {
@@ -194,8 +222,8 @@ pub async fn handle_common_commands(cx: cmd::Cx<()>, command: &str, origin : Box
    match cmd::Common::from(command) {
       cmd::Common::Start => {
          // Отображаем приветственное сообщение и меню с кнопками
-         let s = "Пожалуйста, выберите в основном меню снизу какие заведения показать.";
-         cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update.clone(), ()), s, cmd::User::main_menu_markup()).await;
+         let s = lang::t(lang, EaterStart);
+         cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update.clone(), ()), &s, cmd::User::main_menu_markup(lang)).await;
 
          Some(next(cmd::Dialogue::UserMode))
       },
@@ -203,7 +231,7 @@ pub async fn handle_common_commands(cx: cmd::Cx<()>, command: &str, origin : Box
       cmd::Common::Goto(first, second, third) => Some(goto(cx, first, second, third).await),
       cmd::Common::SendMessage(caterer_id) => {
          // Отправляем приглашение ввести строку со слешем в меню для отмены
-         let res = cx.answer(format!("Введите сообщение (/ для отмены)"))
+         let res = cx.answer(lang::t(lang, EaterEnterMessage))
          .reply_markup(cmd::Caterer::slash_markup())
          .disable_notification(true)
          .send()
@@ -217,7 +245,112 @@ pub async fn handle_common_commands(cx: cmd::Cx<()>, command: &str, origin : Box
             Some(next(cmd::Dialogue::MessageToCaterer(user_id, caterer_id, origin)))
          } else {None}
       },
+      cmd::Common::Reviews(rest_num) => {
+         // Администраторы видят и скрытые отзывы
+         let s = review::reviews_text(lang, rest_num, settings::is_admin(cx.update.from())).await;
+         let res = cx.answer(s)
+         .reply_markup(origin.m)
+         .disable_notification(true)
+         .send()
+         .await;
+
+         if res.is_ok() {Some(next(origin.d))} else {None}
+      },
+      cmd::Common::ReviewComment(review_id) => {
+         // Отправляем приглашение ввести строку со слешем в меню для отмены
+         let res = cx.answer(lang::t(lang, EaterEnterReview))
+         .reply_markup(cmd::Caterer::slash_markup())
+         .disable_notification(true)
+         .send()
+         .await;
+
+         if res.is_ok() {
+            // Код пользователя
+            let user_id = cx.update.from().unwrap().id;
+
+            // Переходим в режим ввода
+            Some(next(cmd::Dialogue::ReviewComment(user_id, review_id, origin)))
+         } else {None}
+      },
+      cmd::Common::ReviewReply(review_id) => {
+         // Отправляем приглашение ввести строку со слешем в меню для отмены
+         let res = cx.answer(lang::t(lang, EaterEnterReviewReply))
+         .reply_markup(cmd::Caterer::slash_markup())
+         .disable_notification(true)
+         .send()
+         .await;
+
+         if res.is_ok() {
+            // Код пользователя
+            let user_id = cx.update.from().unwrap().id;
+
+            // Переходим в режим ввода
+            Some(next(cmd::Dialogue::ReviewReply(user_id, review_id, origin)))
+         } else {None}
+      },
+      cmd::Common::ReviewHide(review_id) => {
+         // Только для администраторов
+         if settings::is_admin(cx.update.from()) {
+            let s = lang::tf(lang, EaterReviewHidden, &[&review_id, &db::is_success(lang, db::review_toggle_hidden(review_id).await)]);
+            let res = cx.answer(s)
+            .reply_markup(origin.m)
+            .disable_notification(true)
+            .send()
+            .await;
+
+            if res.is_ok() {Some(next(origin.d))} else {None}
+         } else {None}
+      },
+      cmd::Common::Unsubscribe(rest_num) => {
+         // Ссылка из рассылки заведения
+         let user_id = cx.update.from().unwrap().id;
+         let s = broadcast::unsubscribe(lang, user_id, rest_num).await;
+         let res = cx.answer(s)
+         .reply_markup(origin.m)
+         .disable_notification(true)
+         .send()
+         .await;
+
+         if res.is_ok() {Some(next(origin.d))} else {None}
+      },
+      cmd::Common::Notify(rest_num, group_num, dish_num) => {
+         // Разовое уведомление, когда можно будет заказать
+         let user_id = cx.update.from().unwrap().id;
+         let s = notify::request(lang, user_id, rest_num, group_num, dish_num).await;
+         let res = cx.answer(s)
+         .reply_markup(origin.m)
+         .disable_notification(true)
+         .send()
+         .await;
+
+         if res.is_ok() {Some(next(origin.d))} else {None}
+      },
+      cmd::Common::Help => {
+         // Команды текущего меню, доступные пользователю
+         let menu = registry::Menu::of(&origin.d);
+         let role = registry::Role::of(cx.update.from()).await;
+         let s = lang::tf(lang, HelpTitle, &[&registry::help(lang, menu, role)]);
+         let res = cx.answer(s)
+         .reply_markup(origin.m)
+         .disable_notification(true)
+         .send()
+         .await;
+
+         if res.is_ok() {Some(next(origin.d))} else {None}
+      },
       cmd::Common::UnknownCommand => {
+         // Известная команда с неправильными аргументами
+         if let Some(command) = registry::wrong_args(registry::Menu::of(&origin.d), command) {
+            let s = lang::tf(lang, CommandWrongArgs, &[&command.name, &registry::usage(command)]);
+            let res = cx.answer(s)
+            .reply_markup(origin.m)
+            .disable_notification(true)
+            .send()
+            .await;
+
+            return if res.is_ok() {Some(next(origin.d))} else {None};
+         }
+
          // Попробуем поискать блюда по заданной строке
          match db::dish_list(db::DishesBy::Find(format!("%{}%", command))).await {
             None => {
@@ -246,6 +379,7 @@ pub async fn handle_common_commands(cx: cmd::Cx<()>, command: &str, origin : Box
 
 // Изменение категории группы rest_id, group_id
 pub async fn edit_cat_image(cx: cmd::Cx<String>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
    if let Some(text) = cx.update.text() {
        // Попытаемся преобразовать ответ пользователя в код категории
        let cat_id = db::category_to_id(text);
@@ -259,16 +393,16 @@ pub async fn edit_cat_image(cx: cmd::Cx<String>) -> cmd::Res {
            db::save_cat_image(cat_id, image_id).await;
 
          // Покажем группу сообщение для восстановления кнопок внизу и обновлённую группу
-         cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot.to_owned(), cx.update.to_owned(), ()), "Изменения приняты", cmd::User::main_menu_markup()).await;
+         cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot.to_owned(), cx.update.to_owned(), ()), &lang::t(lang, ChangesAccepted), cmd::User::main_menu_markup(lang)).await;
          return eat_rest::next_with_info(DialogueDispatcherHandlerCx::new(cx.bot, cx.update, cat_id)).await;
 
        } else {
            // Сообщим об отмене
-           cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ()), "Неизвестная категория, отмена изменения", cmd::User::main_menu_markup()).await
+           cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ()), &lang::t(lang, UnknownCategoryCancel), cmd::User::main_menu_markup(lang)).await
        }
    } else {
      // Сообщим об отмене
-     cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ()), "Отмена изменения", cmd::User::main_menu_markup()).await
+     cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ()), &lang::t(lang, ChangesCancelled), cmd::User::main_menu_markup(lang)).await
    }
 
    // Остаёмся в пользовательском режиме.
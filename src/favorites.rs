@@ -0,0 +1,157 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Избранные заведения и блюда едока. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+use teloxide::{
+   prelude::*,
+   types::{CallbackQuery, ChatId, ChatOrInlineMessage, InlineKeyboardButton, InlineKeyboardMarkup, ReplyMarkup},
+};
+
+use crate::callback;
+use crate::commands as cmd;
+use crate::database as db;
+use crate::language::{self as lang, Res::*};
+use crate::notify;
+use crate::settings;
+use crate::pages;
+
+// Надпись кнопки-переключателя
+fn star(favorite: bool) -> &'static str {
+   if favorite {"⭐"} else {"☆"}
+}
+
+// Отметка доступности в списке
+fn mark(available: bool) -> &'static str {
+   if available {"✅"} else {"⛔"}
+}
+
+// Инлайн-кнопка для заведения (группа и блюдо нулевые) или блюда
+pub fn button(favorite: bool, key: &str) -> InlineKeyboardButton {
+   InlineKeyboardButton::callback(star(favorite).to_string(), format!("fav{}", key))
+}
+
+// Кнопка для блюда с текущим состоянием
+pub async fn dish_button(user_id: i32, rest_num: i32, group_num: i32, dish_num: i32) -> InlineKeyboardButton {
+   let favorite = db::is_favorite(user_id, rest_num, group_num, dish_num).await;
+   button(favorite, &db::make_key_3_int(rest_num, group_num, dish_num))
+}
+
+// Строка с командой /fav для режима со ссылками
+pub async fn rest_line(lang: &str, user_id: i32, rest_num: i32) -> String {
+   let favorite = db::is_favorite(user_id, rest_num, 0, 0).await;
+   lang::t(lang, if favorite {RestFavoriteOn} else {RestFavoriteOff})
+}
+
+// Ответ пользователю о результате переключения
+pub fn result_text(lang: &str, favorite: Option<bool>) -> String {
+   match favorite {
+      Some(true) => lang::t(lang, CallbackFavoriteOn),
+      Some(false) => lang::t(lang, CallbackFavoriteOff),
+      None => db::is_success(lang, false),
+   }
+}
+
+// Переключает заведение по команде /fav
+pub async fn toggle_rest(lang: &str, user_id: i32, rest_num: i32) -> String {
+   result_text(lang, db::favorite_toggle(user_id, rest_num, 0, 0).await)
+}
+
+// Переключает по инлайн-кнопке и меняет её надпись, остальные кнопки сообщения не трогаем
+pub async fn toggle(cx: &DispatcherHandlerCx<CallbackQuery>, rest_num: i32, group_num: i32, dish_num: i32) -> Option<bool> {
+   let favorite = db::favorite_toggle(cx.update.from.id, rest_num, group_num, dish_num).await?;
+
+   let data = format!("fav{}", db::make_key_3_int(rest_num, group_num, dish_num));
+   callback::update_button_text(cx, &data, star(favorite)).await;
+
+   Some(favorite)
+}
+
+// Название в списке: заведение ведёт к работающим сейчас группам, блюдо к самому блюду
+fn title(f: &db::Favorite) -> String {
+   if f.group_num == 0 {f.title.clone()}
+   else {format!("{} {}", f.title, settings::price_with_unit(f.price))}
+}
+
+// Инлайн-кнопки для указанной страницы избранного
+fn make_markup(list: Vec<db::Favorite>, page: i32) -> InlineKeyboardMarkup {
+   let page = pages::Page::buttons(list, page);
+   let markup = page.items.iter()
+   .map(|f| {
+      let key = db::make_key_3_int(f.rest_num, f.group_num, f.dish_num);
+      let data = if f.group_num == 0 {format!("rng{}", key)} else {format!("dis{}", key)};
+      let button = InlineKeyboardButton::callback(format!("{} {}", mark(f.available), title(f)), data);
+
+      // Рядом с недоступным кнопка уведомления
+      if f.available {vec![button]} else {vec![button, notify::button(&key)]}
+   })
+   .fold(InlineKeyboardMarkup::default(), |acc, row| acc.append_row(row));
+   page.append_nav(markup, |num| format!("fvp{}", db::make_key_3_int(num, 0, 0)))
+}
+
+// Показывает избранное с доступностью прямо сейчас
+pub async fn next_with_info(cx: cmd::Cx<()>, page: i32) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+   let user_id = cx.update.from().unwrap().id;
+
+   // Запросим настройку пользователя с режимом интерфейса
+   let compact_mode = db::user_compact_interface(cx.update.from()).await;
+
+   match db::favorite_list(user_id).await {
+      None => cmd::send_text(&cx, &lang::t(lang, FavoritesEmpty), cmd::User::main_menu_markup(lang)).await,
+      Some(list) => {
+         if compact_mode {
+            // Строки вида "✅ название /goto1_0_0", для недоступных ещё и запрос уведомления
+            let page = pages::Page::text(list, page);
+            let s: String = page.items.iter()
+            .map(|f| {
+               let key = db::make_key_3_int(f.rest_num, f.group_num, f.dish_num);
+               let ntf = if f.available {String::default()} else {format!(" ⏰/ntf{}", key)};
+               format!("   {} {} /goto{}{}\n", mark(f.available), title(f), key, ntf)
+            })
+            .collect();
+            let s = format!("{}\n{}{}", lang::t(lang, FavoritesTitle), s, page.links(lang, "/favs"));
+            cmd::send_text(&cx, &s, cmd::User::main_menu_markup(lang)).await;
+         } else {
+            let markup = make_markup(list, page);
+            cmd::send_photo(&cx, &lang::t(lang, FavoritesTitle), ReplyMarkup::InlineKeyboardMarkup(markup), settings::default_photo_id()).await;
+         }
+      }
+   }
+
+   // Остаёмся в главном меню
+   next(cmd::Dialogue::UserMode)
+}
+
+// Листает избранное в режиме с инлайн-кнопками, меняя кнопки прежнего сообщения
+pub async fn page(cx: &DispatcherHandlerCx<CallbackQuery>, page: i32) -> bool {
+   let message = match cx.update.message.as_ref() {
+      Some(message) => message,
+      None => return false,
+   };
+
+   // Избранное могло опустеть, тогда кнопки остаются прежними
+   let list = match db::favorite_list(cx.update.from.id).await {
+      Some(list) => list,
+      None => return false,
+   };
+
+   let chat_message = ChatOrInlineMessage::Chat {
+      chat_id: ChatId::Id(message.chat_id()),
+      message_id: message.id,
+   };
+   match cx.bot.edit_message_reply_markup(chat_message)
+   .reply_markup(make_markup(list, page))
+   .send()
+   .await {
+      Err(e) => {
+         settings::log(&format!("Error favorites::page({}): {}", page, e)).await;
+         false
+      }
+      _ => true,
+   }
+}
@@ -10,24 +10,28 @@ Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
 use teloxide::{
    prelude::*,
 };
+use chrono::{NaiveDate, NaiveTime};
 
 use crate::commands as cmd;
 use crate::database as db;
 use crate::eater;
 use crate::settings;
-use crate::language as lang;
+use crate::config;
+use crate::language::{self as lang, Res::*};
 use crate::caterer;
+use crate::pages;
 
 
 // Показывает приветствие
 pub async fn next_with_info(cx: cmd::Cx<()>) -> cmd::Res {
    // Запросим настройку пользователя с режимом интерфейса и обновим время последнего входа в БД
    let compact_mode = db::user_compact_interface(cx.update.from()).await;
+   let lang = lang::user_lang(cx.update.from()).await;
 
    // Отображаем приветствие
-   let s = format!("Режим интерфейса: {} /toggle", db::interface_mode(compact_mode));
+   let s = settings_info(lang, compact_mode);
    cx.answer(s)
-   .reply_markup(cmd::Gear::bottom_markup())
+   .reply_markup(cmd::Gear::bottom_markup(lang))
    .disable_notification(true)
    .send()
    .await?;
@@ -39,11 +43,12 @@ pub async fn next_with_info(cx: cmd::Cx<()>) -> cmd::Res {
 pub async fn next_with_cancel(cx: cmd::Cx<()>, text: &str) -> cmd::Res {
    // Запросим настройку пользователя с режимом интерфейса и обновим время последнего входа в БД
    let compact_mode = db::user_compact_interface(cx.update.from()).await;
+   let lang = lang::user_lang(cx.update.from()).await;
 
    // Отображаем сообщение
-   let s = format!("{}\n\nРежим интерфейса: {} /toggle", text, db::interface_mode(compact_mode));
+   let s = format!("{}\n\n{}", text, settings_info(lang, compact_mode));
    cx.answer(s)
-   .reply_markup(cmd::Gear::bottom_markup())
+   .reply_markup(cmd::Gear::bottom_markup(lang))
    .disable_notification(true)
    .send()
    .await?;
@@ -52,12 +57,20 @@ pub async fn next_with_cancel(cx: cmd::Cx<()>, text: &str) -> cmd::Res {
    next(cmd::Dialogue::GearMode)
 }
 
+// Текущие настройки пользователя
+fn settings_info(lang: &str, compact_mode: bool) -> String {
+   lang::tf(lang, GearInfo, &[&db::interface_mode(lang, compact_mode), &lang::name(lang)])
+}
+
 pub async fn handle_commands(cx: cmd::Cx<()>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
+
    // Разбираем команду.
    match cx.update.text() {
       None => {
          let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
-         next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, ()), "Текстовое сообщение, пожалуйста!").await
+         next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, ()), &lang::t(lang, TextPlease)).await
       }
       Some(command) => {
          match cmd::Gear::from(command) {
@@ -69,12 +82,12 @@ pub async fn handle_commands(cx: cmd::Cx<()>) -> cmd::Res {
 
             cmd::Gear::UnknownCommand => {
                // Сохраним текущее состояние для возврата
-               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::UserMode, m : cmd::Gear::bottom_markup()});
+               let origin = Box::new(cmd::DialogueState{ d : cmd::Dialogue::GearMode, m : cmd::Gear::bottom_markup(lang)});
 
                // Возможно это общая команда
                if let Some(res) = eater::handle_common_commands(DialogueDispatcherHandlerCx::new(cx.bot.clone(), cx.update.clone(), ()), command, origin).await {return res;}
                else {
-                  let s = &format!("Вы в меню ⚙ настроек: неизвестная команда '{}'", command);
+                  let s = &lang::tf(lang, GearUnknownCommand, &[&command]);
                   next_with_cancel(DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ()), s).await
                }
             }
@@ -82,8 +95,8 @@ pub async fn handle_commands(cx: cmd::Cx<()>) -> cmd::Res {
                // Переключим настройку интерфейса
                db::user_toggle_interface(cx.update.from()).await;
                let compact_mode = db::user_compact_interface(cx.update.from()).await;
-               let s = db::interface_mode(compact_mode);
-               let s = &format!("Режим интерфейса изменён на '{}' (пояснение - режим с кнопками может быть удобнее, а со ссылками экономнее к трафику)", s);
+               let s = db::interface_mode(lang, compact_mode);
+               let s = &lang::tf(lang, GearInterfaceChanged, &[&s]);
                next_with_cancel(DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ()), s).await
             }
             cmd::Gear::CatererMode => {
@@ -92,23 +105,7 @@ pub async fn handle_commands(cx: cmd::Cx<()>) -> cmd::Res {
 
                // Если это администратор, то выводим для него команды sudo
                if settings::is_admin(user) {
-                  // Получим из БД список ресторанов и отправим его
-                  match db::rest_list(db::RestListBy::All).await {
-                     Some(rest_list) => {
-                        // Сформируем строку вида: 1371303352 'Ресторан "два супа"' /sudo1
-                        let s: String = rest_list.into_iter().map(|r| (format!("{} '{}' /sudo{}\n", r.user_id, r.title, r.num))).collect();
-
-                        // Отправим информацию
-                        cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ()), &format!("Выберите ресторан для входа\n{}", s), cmd::Gear::bottom_markup()).await;
-                        next(cmd::Dialogue::GearMode)
-                     }
-                     None => {
-                        // Если там пусто, то сообщим об этом
-                        let s = String::from(lang::t("ru", lang::Res::EatRestEmpty));
-                        next_with_cancel(DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ()), &s).await
-                     }
-                  }
-
+                  sudo_list(cx, 1).await
                } else {
                   // По коду пользователя получим код ресторана
                   match db::rest_num(user).await {
@@ -128,23 +125,32 @@ pub async fn handle_commands(cx: cmd::Cx<()>) -> cmd::Res {
                         // Попытаемся получить id пользователя и сообщить ему.
                         let user_id = match user {
                            Some(u) => u.id.to_string(),
-                           _ => String::from("ошибка id"),
+                           _ => lang::t(lang, GearErrorId),
                         };
 
-                        let s = &format!("Для доступа в режим рестораторов обратитесь к {} и сообщите свой Id={}", settings::admin_contact_info(), user_id);
+                        let s = &lang::tf(lang, GearCatererDenied, &[&settings::admin_contact_info(), &user_id]);
                         let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
                         next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, ()), &s).await
                      }
                   }
                }
             }
+            cmd::Gear::SudoList(page) => {
+               // Проверим права
+               if settings::is_admin(cx.update.from()) {
+                  sudo_list(cx, page).await
+               } else {
+                  let s = lang::t(lang, GearNoRights);
+                  next_with_cancel(cx, &s).await
+               }
+            }
             cmd::Gear::RegisterCaterer(user_id) => {
                // Проверим права
                let s = if settings::is_admin(cx.update.from()) {
-                  let res = db::is_success(db::register_caterer(user_id).await);
-                  format!("Регистрация или разблокировка ресторатора {}: {}", user_id, res)
+                  let res = db::is_success(lang, db::register_caterer(lang::lang_of(user_id).await, user_id).await);
+                  lang::tf(lang, GearRegisterCaterer, &[&user_id, &res])
                } else {
-                  String::from("Недостаточно прав")
+                  lang::t(lang, GearNoRights)
                };
 
                let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
@@ -152,10 +158,10 @@ pub async fn handle_commands(cx: cmd::Cx<()>) -> cmd::Res {
          }
             cmd::Gear::HoldCaterer(user_id) => {
                let s = if settings::is_admin(cx.update.from()) {
-                  let res = db::is_success(db::hold_caterer(user_id).await);
-                  format!("Блокировка ресторатора {}: {}", user_id, res)
+                  let res = db::is_success(lang, db::hold_caterer(user_id).await);
+                  lang::tf(lang, GearHoldCaterer, &[&user_id, &res])
                } else {
-                  String::from("Недостаточно прав")
+                  lang::t(lang, GearNoRights)
                };
 
                let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
@@ -166,38 +172,243 @@ pub async fn handle_commands(cx: cmd::Cx<()>) -> cmd::Res {
                if settings::is_admin(cx.update.from()) {
                   return caterer::next_with_info(DialogueDispatcherHandlerCx::new(cx.bot, cx.update, rest_num), true).await;
                } else {
-                  let s = "Недостаточно прав";
+                  let s = lang::t(lang, GearNoRights);
                   let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
                   next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, ()), &s).await
                }
             }
-            cmd::Gear::List => {
+            cmd::Gear::PaymentToken(rest_num) => {
+               // Проверим права
+               let s = if settings::is_admin(cx.update.from()) {
+                  // Токен идёт после номера ресторана через пробел, без токена оплата отключается
+                  let token = cx.update.text().unwrap_or_default().split_whitespace().nth(1).map(String::from);
+                  let action = if token.is_some() {GearPaymentOn} else {GearPaymentOff};
+                  let res = db::is_success(lang, db::rest_edit_payment_token(rest_num, token).await);
+                  lang::tf(lang, action, &[&rest_num, &res])
+               } else {
+                  lang::t(lang, GearNoRights)
+               };
+
+               let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
+               next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, ()), &s).await
+            }
+            cmd::Gear::BroadcastHours(rest_num, hours) => {
+               // Проверим права
+               let s = if settings::is_admin(cx.update.from()) {
+                  let res = db::is_success(lang, db::rest_edit_broadcast_hours(rest_num, hours).await);
+                  match hours {
+                     Some(0) => lang::tf(lang, GearBroadcastUnlimited, &[&rest_num, &res]),
+                     Some(hours) => lang::tf(lang, GearBroadcastHours, &[&rest_num, &hours, &res]),
+                     None => lang::tf(lang, GearBroadcastDefault, &[&rest_num, &res]),
+                  }
+               } else {
+                  lang::t(lang, GearNoRights)
+               };
+
+               let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
+               next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, ()), &s).await
+            }
+            cmd::Gear::TimeZone(rest_num) => {
+               // Проверим права
+               let s = if !settings::is_admin(cx.update.from()) {
+                  lang::t(lang, GearNoRights)
+               } else {
+                  // Пояс идёт после номера ресторана через пробел, без него возвращается пояс по-умолчанию
+                  let time_zone = cx.update.text().unwrap_or_default().split_whitespace().nth(1).map(String::from);
+                  match time_zone.as_deref().map(config::parse_time_zone) {
+                     None => {
+                        let res = db::is_success(lang, db::rest_edit_time_zone(rest_num, None).await);
+                        lang::tf(lang, GearTimeZoneDefault, &[&rest_num, &res])
+                     }
+                     Some(Some(tz)) => {
+                        let res = db::is_success(lang, db::rest_edit_time_zone(rest_num, Some(tz.name())).await);
+                        lang::tf(lang, GearTimeZone, &[&rest_num, &tz.name(), &res])
+                     }
+                     Some(None) => lang::tf(lang, GearTimeZoneUnknown, &[&time_zone.unwrap_or_default()]),
+                  }
+               };
+
+               let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
+               next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, ()), &s).await
+            }
+            cmd::Gear::PromoList => {
+               let s = if settings::is_admin(cx.update.from()) {
+                  match db::promo_code_list().await {
+                     Some(list) => list.into_iter().map(|promo| format!("{} /unpromo {}\n", promo.description(lang), promo.code)).collect(),
+                     None => lang::t(lang, GearNoPromos),
+                  }
+               } else {
+                  lang::t(lang, GearNoRights)
+               };
+
+               let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
+               next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, ()), &s).await
+            }
+            cmd::Gear::PromoSave => {
+               let s = if settings::is_admin(cx.update.from()) {
+                  match parse_promo(lang, cx.update.text().unwrap_or_default()) {
+                     Ok(promo) => {
+                        let res = db::is_success(lang, db::promo_code_save(&promo).await);
+                        lang::tf(lang, GearPromoSaved, &[&promo.description(lang), &res])
+                     }
+                     Err(e) => lang::tf(lang, GearPromoFormat, &[&e]),
+                  }
+               } else {
+                  lang::t(lang, GearNoRights)
+               };
+
+               let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
+               next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, ()), &s).await
+            }
+            cmd::Gear::PromoRemove => {
+               let s = if settings::is_admin(cx.update.from()) {
+                  let code = cx.update.text().unwrap_or_default().split_whitespace().nth(1).unwrap_or_default().to_uppercase();
+                  let res = db::is_success(lang, db::promo_code_remove(&code).await);
+                  lang::tf(lang, GearPromoRemoved, &[&code, &res])
+               } else {
+                  lang::t(lang, GearNoRights)
+               };
+
+               let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
+               next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, ()), &s).await
+            }
+            cmd::Gear::List(page) => {
                // Проверим права
                if settings::is_admin(cx.update.from()) {
                   // Получим из БД список ресторанов и отправим его
                   match db::rest_list(db::RestListBy::All).await {
                      Some(rest_list) => {
                         // Сформируем строку вида: 1 'Ресторан "два супа"', доступен /hold1371303352
-                        let s: String = rest_list.into_iter().map(|r| (format!("{} '{}', {} {}{}\n", 
-                        r.num, r.title, db::enabled_to_str(r.enabled), db::enabled_to_cmd(r.enabled), r.user_id
+                        let page = pages::Page::text(rest_list, page);
+                        let s: String = page.items.iter().map(|r| (format!("{} '{}', {} {}{}{}\n", 
+                        r.num, r.title, db::enabled_to_str(lang, r.enabled), db::enabled_to_cmd(r.enabled), r.user_id,
+                        if r.payment_token.is_some() {lang::t(lang, GearPaymentViaBot)} else {String::default()}
                         ))).collect();
-                        cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ()), &s, cmd::User::main_menu_markup()).await;
+                        let s = format!("{}{}", s, page.links(lang, "/list"));
+                        cmd::send_text(&DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ()), &s, cmd::User::main_menu_markup(lang)).await;
                         next(cmd::Dialogue::GearMode)
                      }
                      None => {
                         // Если там пусто, то сообщим об этом
-                        let s = String::from(lang::t("ru", lang::Res::EatRestEmpty));
+                        let s = lang::t(lang, EatRestEmpty);
                         next_with_cancel(DialogueDispatcherHandlerCx::new(cx.bot, cx.update, ()), &s).await
                      }
                   }
                } else {
-                  let s = "Недостаточно прав";
+                  let s = lang::t(lang, GearNoRights);
                   let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
                   next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, ()), &s).await
                }
             }
+            cmd::Gear::Languages => {
+               // Список языков с командами для выбора
+               let s: String = lang::LANGUAGES.iter().map(|l| format!("/lang_{} {}\n", l, lang::name(l))).collect();
+               let s = lang::tf(lang, GearLanguages, &[&s]);
+               let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
+               next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, ()), &s).await
+            }
+            cmd::Gear::SetLanguage(new_lang) => {
+               let user_id = cx.update.from().map(|u| u.id).unwrap_or_default();
+               let s = if lang::set_user_lang(user_id, new_lang).await {
+                  // Отвечаем уже на новом языке
+                  lang::tf(new_lang, GearLanguageChanged, &[&lang::name(new_lang)])
+               } else {
+                  lang::t(lang, GearLanguageFailed)
+               };
+
+               // Обновляем меню снизу на выбранном языке
+               let DialogueDispatcherHandlerCx { bot, update, dialogue:_ } = cx;
+               next_with_cancel(DialogueDispatcherHandlerCx::new(bot, update, ()), &s).await
+            }
          }
       }
    }
 }
 
+// Список заведений для администратора со ссылками для входа в их меню
+async fn sudo_list(cx: cmd::Cx<()>, page: i32) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
+   // Получим из БД список ресторанов и отправим его
+   match db::rest_list(db::RestListBy::All).await {
+      Some(rest_list) => {
+         // Сформируем строку вида: 1371303352 'Ресторан "два супа"' /sudo1
+         let page = pages::Page::text(rest_list, page);
+         let s: String = page.items.iter().map(|r| (format!("{} '{}' /sudo{}\n", r.user_id, r.title, r.num))).collect();
+         let s = format!("{}{}", s, page.links(lang, "/rests"));
+
+         // Отправим информацию
+         cmd::send_text(&cx, &lang::tf(lang, GearSelectRest, &[&s]), cmd::Gear::bottom_markup(lang)).await;
+         next(cmd::Dialogue::GearMode)
+      }
+      None => {
+         // Если там пусто, то сообщим об этом
+         let s = lang::t(lang, EatRestEmpty);
+         next_with_cancel(cx, &s).await
+      }
+   }
+}
+
+// Разбирает строку вида "/promo КОД 10% rest=1 min=500 until=31.12.2020 uses=100 per_user=1 groups=1,2"
+fn parse_promo(lang: &str, text: &str) -> Result<db::PromoCode, String> {
+   let mut parts = text.split_whitespace().skip(1);
+
+   let code = parts.next().ok_or_else(|| lang::t(lang, PromoNoCode))?.to_uppercase();
+   if code.chars().count() > 32 {
+      return Err(lang::t(lang, PromoCodeTooLong));
+   }
+
+   // Скидка в процентах или фиксированной суммой
+   let value = parts.next().ok_or_else(|| lang::t(lang, PromoNoValue))?;
+   let (percent, value) = match value.strip_suffix('%') {
+      Some(v) => (true, v),
+      None => (false, value),
+   };
+   let value = value.parse::<i32>().map_err(|_| lang::tf(lang, PromoWrongValue, &[&value]))?;
+   if value <= 0 || percent && value > 100 {
+      return Err(lang::tf(lang, PromoInvalidValue, &[&value]));
+   }
+
+   let mut promo = db::PromoCode {
+      code,
+      rest_num: 0,
+      percent,
+      value,
+      min_total: 0,
+      expires: None,
+      max_uses: 0,
+      max_uses_per_user: 0,
+      group_nums: None,
+   };
+
+   // Необязательные параметры
+   for part in parts {
+      let mut kv = part.splitn(2, '=');
+      let key = kv.next().unwrap_or_default();
+      let value = kv.next().ok_or_else(|| lang::tf(lang, PromoWrongParam, &[&part]))?;
+      let err = || lang::tf(lang, PromoWrongParamValue, &[&part]);
+      match key {
+         "rest" => promo.rest_num = value.parse().map_err(|_| err())?,
+         "min" => promo.min_total = value.parse().map_err(|_| err())?,
+         "uses" => promo.max_uses = value.parse().map_err(|_| err())?,
+         "per_user" => promo.max_uses_per_user = value.parse().map_err(|_| err())?,
+         "until" => {
+            // Действует до конца указанного дня
+            let date = NaiveDate::parse_from_str(value, "%d.%m.%Y").map_err(|_| err())?;
+            promo.expires = Some(date.and_time(NaiveTime::from_hms_opt(23, 59, 59).unwrap_or(NaiveTime::MIN)));
+         }
+         "groups" => {
+            let nums = value.split(',').map(|n| n.parse::<i32>()).collect::<Result<Vec<i32>, _>>().map_err(|_| err())?;
+            promo.group_nums = Some(nums);
+         }
+         _ => return Err(lang::tf(lang, PromoUnknownParam, &[&key])),
+      }
+   }
+
+   // Группы имеют смысл только внутри одного ресторана
+   if promo.group_nums.is_some() && promo.rest_num == 0 {
+      return Err(lang::t(lang, PromoGroupsNeedRest));
+   }
+
+   Ok(promo)
+}
@@ -0,0 +1,380 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Общий заказ в групповом чате. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+use teloxide::{
+   prelude::*,
+   types::{CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, User},
+};
+
+use crate::basket;
+use crate::callback;
+use crate::commands as cmd;
+use crate::database as db;
+use crate::language::{self as lang, Res::*};
+use crate::outbox;
+use crate::pages;
+use crate::payment;
+use crate::settings;
+
+// Имя участника, как его видят остальные
+fn member_name(user: &User) -> String {
+   match &user.last_name {
+      Some(last_name) => format!("{} {}", user.first_name, last_name),
+      None => user.first_name.clone(),
+   }
+}
+
+// Кто что выбрал с суммой по каждому и общим итогом
+fn members_text(lang: &str, items: &[db::GroupOrderItem]) -> String {
+   if items.is_empty() {
+      return lang::t(lang, GroupBasketEmpty);
+   }
+
+   // Строки отсортированы по участникам, перед первой строкой участника его имя и сумма
+   let mut s = String::default();
+   for (i, item) in items.iter().enumerate() {
+      if i == 0 || items[i - 1].user_id != item.user_id {
+         let sum: i32 = items.iter().filter(|other| other.user_id == item.user_id).map(|other| other.price * other.amount).sum();
+         s.push_str(&format!("\n{}\n", lang::tf(lang, GroupBasketPerson, &[&item.user_name, &settings::price_with_unit(sum)])));
+      }
+      let line = lang::tf(lang, BasketDishLine, &[&item.title, &item.price, &item.amount, &settings::price_with_unit(item.price * item.amount)]);
+      s.push_str(&format!("   {}\n", line));
+   }
+
+   let total: i32 = items.iter().map(|item| item.price * item.amount).sum();
+   format!("{}{}", s, lang::tf(lang, BasketTotal, &[&settings::price_with_unit(total)]))
+}
+
+// Текст сообщения с общим заказом
+async fn basket_text(lang: &str, basket: &db::GroupBasket, rest_title: &str) -> String {
+   let items = db::group_order_items(basket.chat_id).await.unwrap_or_default();
+   format!("{}\n{}", lang::tf(lang, GroupBasketHeader, &[&rest_title, &basket.initiator_name]), members_text(lang, &items))
+}
+
+// Кнопки блюд страницы меню и управления заказом
+async fn markup(lang: &str, rest_num: i32, page: i32) -> InlineKeyboardMarkup {
+   let dishes = db::group_basket_menu(rest_num).await.unwrap_or_default();
+   let page = pages::Page::buttons(dishes, page);
+
+   // Названия блюд бывают длинными, поэтому по одному в ряд
+   let markup = page.items.iter()
+   .map(|dish| InlineKeyboardButton::callback(dish.title_with_price(), format!("gad{}", db::make_key_3_int(rest_num, dish.group_num, dish.num))))
+   .fold(InlineKeyboardMarkup::default(), |acc, button| acc.append_row(vec![button]));
+
+   // Переход на соседние страницы
+   let markup = page.append_nav(markup, |num| format!("gpg{}", db::make_key_3_int(rest_num, num, 0)));
+
+   let key = db::make_key_3_int(rest_num, 0, 0);
+   markup.append_row(vec![
+      InlineKeyboardButton::callback(lang::t(lang, GroupBasketClear), format!("gcl{}", key)),
+      InlineKeyboardButton::callback(lang::t(lang, GroupBasketSubmit), format!("gsn{}", key)),
+      InlineKeyboardButton::callback(lang::t(lang, GroupBasketCancel), format!("gcn{}", key)),
+   ])
+}
+
+// Отправляет в чат сообщение с общим заказом и запоминает его для обновлений
+async fn post(cx: &cmd::Cx<()>, basket: &db::GroupBasket, rest_title: &str) {
+   // Сообщение видят все участники, поэтому на языке инициатора
+   let lang = lang::lang_of(basket.initiator).await;
+   let chat = ChatId::Id(cx.chat_id());
+   let request = cx.answer(basket_text(lang, basket, rest_title).await)
+   .reply_markup(markup(lang, basket.rest_num, 1).await)
+   .disable_notification(true);
+
+   match outbox::send(&chat, &request).await {
+      Ok(message) => {db::group_basket_set_message(basket.chat_id, message.id).await;}
+      Err(e) => settings::log(&format!("Error group_basket::post({}): {}", basket.chat_id, e)).await,
+   }
+}
+
+// Команда /lunch в групповом чате, без номера список работающих заведений
+pub async fn start(cx: &cmd::Cx<()>, rest_num: Option<i32>) {
+   let lang = lang::user_lang(cx.update.from()).await;
+   let chat_id = cx.chat_id();
+   let user = match cx.update.from() {
+      Some(user) => user,
+      None => return,
+   };
+
+   // Заведение для уже собираемого заказа, на случай если сообщение с ним ушло далеко вверх
+   if let Some(basket) = db::group_basket(chat_id).await {
+      let rest_title = match db::restaurant(db::RestBy::Num(basket.rest_num)).await {
+         Some(rest) => rest.title,
+         None => String::default(),
+      };
+
+      if rest_num.is_none() || rest_num == Some(basket.rest_num) {
+         post(cx, &basket, &rest_title).await;
+      } else {
+         cmd::send_text_without_markup(cx, &lang::tf(lang, GroupBasketBusy, &[&rest_title])).await;
+      }
+      return;
+   }
+
+   let rest_num = match rest_num {
+      Some(rest_num) => rest_num,
+      None => {
+         // Работающие сейчас заведения со ссылками для начала заказа
+         let s = match db::rest_list(db::RestListBy::OpenNow).await {
+            Some(rests) => {
+               let list: String = rests.iter().map(|rest| format!("   {} /lunch{}\n", rest.title, rest.num)).collect();
+               lang::tf(lang, GroupBasketRests, &[&list])
+            }
+            None => lang::t(lang, GroupBasketNoRests),
+         };
+         cmd::send_text_without_markup(cx, &s).await;
+         return;
+      }
+   };
+
+   let rest = match db::restaurant(db::RestBy::Num(rest_num)).await {
+      Some(rest) => rest,
+      None => {
+         cmd::send_text_without_markup(cx, &lang::t(lang, EatNoRestInfo)).await;
+         return;
+      }
+   };
+
+   // Без доступных блюд начинать заказ бессмысленно
+   if db::group_basket_menu(rest_num).await.is_none() {
+      cmd::send_text_without_markup(cx, &lang::tf(lang, GroupBasketNoMenu, &[&rest.title])).await;
+      return;
+   }
+
+   let name = member_name(user);
+   if db::group_basket_open(chat_id, rest_num, user.id, &name).await {
+      settings::log(&format!("{} начал общий заказ в {} из {}", db::user_info(Some(user), false), chat_id, rest_num)).await;
+      if let Some(basket) = db::group_basket(chat_id).await {
+         post(cx, &basket, &rest.title).await;
+      }
+   }
+}
+
+// Подсказывает, как начать общий заказ, когда бота добавили в группу
+pub async fn welcome(cx: &cmd::Cx<()>, members: &[User]) {
+   // Своё имя бот узнаёт, только если среди новых участников вообще есть боты
+   if !members.iter().any(|member| member.is_bot) {
+      return;
+   }
+
+   match cx.bot.get_me().send().await {
+      Ok(me) if members.iter().any(|member| member.id == me.user.id) => {
+         let lang = lang::user_lang(cx.update.from()).await;
+         cmd::send_text_without_markup(cx, &lang::t(lang, GroupBasketWelcome)).await;
+      }
+      Ok(_) => (),
+      Err(e) => settings::log(&format!("Error group_basket::welcome({}): {}", cx.chat_id(), e)).await,
+   }
+}
+
+// Общий заказ, к которому относится нажатая кнопка, для устаревшего сообщения ошибка
+async fn current(cx: &DispatcherHandlerCx<CallbackQuery>, rest_num: i32) -> Result<(db::GroupBasket, Message), String> {
+   let lang = lang::user_lang(Some(&cx.update.from)).await;
+   let closed = || lang::t(lang, GroupBasketClosed);
+
+   let message = cx.update.message.clone().ok_or_else(closed)?;
+   match db::group_basket(message.chat_id()).await {
+      Some(basket) if basket.rest_num == rest_num && basket.message_id == Some(message.id) => Ok((basket, message)),
+      _ => Err(closed()),
+   }
+}
+
+// Обновляет текст сообщения с общим заказом, кнопки остаются прежними
+async fn refresh(cx: &DispatcherHandlerCx<CallbackQuery>, basket: &db::GroupBasket, message: &Message) {
+   // Сообщение видят все участники, поэтому на языке инициатора
+   let lang = lang::lang_of(basket.initiator).await;
+   let rest_title = match db::restaurant(db::RestBy::Num(basket.rest_num)).await {
+      Some(rest) => rest.title,
+      None => String::default(),
+   };
+
+   let s = basket_text(lang, basket, &rest_title).await;
+   callback::edit_message(cx, ChatId::Id(message.chat_id()), message.id, &s, message.reply_markup().cloned()).await;
+}
+
+// Добавляет нажавшему одну порцию блюда
+pub async fn add(cx: &DispatcherHandlerCx<CallbackQuery>, rest_num: i32, group_num: i32, dish_num: i32) -> String {
+   let lang = lang::user_lang(Some(&cx.update.from)).await;
+   let (basket, message) = match current(cx, rest_num).await {
+      Ok(res) => res,
+      Err(s) => return s,
+   };
+
+   // Блюдо могли скрыть, а его группа могла закрыться после отправки меню
+   let dish = db::group_basket_menu(rest_num).await
+   .and_then(|menu| menu.into_iter().find(|dish| dish.group_num == group_num && dish.num == dish_num));
+   let dish = match dish {
+      Some(dish) => dish,
+      None => return lang::t(lang, GroupBasketUnavailable),
+   };
+
+   let user = &cx.update.from;
+   if !db::group_order_add(basket.chat_id, user.id, &member_name(user), group_num, dish_num).await {
+      return db::is_success(lang, false);
+   }
+
+   refresh(cx, &basket, &message).await;
+   lang::tf(lang, GroupBasketAdded, &[&dish.title])
+}
+
+// Убирает все блюда нажавшего
+pub async fn clear(cx: &DispatcherHandlerCx<CallbackQuery>, rest_num: i32) -> String {
+   let lang = lang::user_lang(Some(&cx.update.from)).await;
+   let (basket, message) = match current(cx, rest_num).await {
+      Ok(res) => res,
+      Err(s) => return s,
+   };
+
+   if !db::group_order_clear(basket.chat_id, cx.update.from.id).await {
+      return db::is_success(lang, false);
+   }
+
+   refresh(cx, &basket, &message).await;
+   lang::t(lang, GroupBasketCleared)
+}
+
+// Показывает другую страницу меню
+pub async fn page(cx: &DispatcherHandlerCx<CallbackQuery>, rest_num: i32, page: i32) -> String {
+   let lang = lang::user_lang(Some(&cx.update.from)).await;
+   let (basket, message) = match current(cx, rest_num).await {
+      Ok(res) => res,
+      Err(s) => return s,
+   };
+
+   let initiator_lang = lang::lang_of(basket.initiator).await;
+   let markup = markup(initiator_lang, rest_num, page).await;
+   callback::edit_message(cx, ChatId::Id(message.chat_id()), message.id, message.text().unwrap_or_default(), Some(markup)).await;
+   db::is_success(lang, true)
+}
+
+// Отменяет общий заказ, это может только инициатор
+pub async fn cancel(cx: &DispatcherHandlerCx<CallbackQuery>, rest_num: i32) -> String {
+   let lang = lang::user_lang(Some(&cx.update.from)).await;
+   let (basket, message) = match current(cx, rest_num).await {
+      Ok(res) => res,
+      Err(s) => return s,
+   };
+
+   if cx.update.from.id != basket.initiator {
+      return lang::tf(lang, GroupBasketOnlyInitiator, &[&basket.initiator_name]);
+   }
+
+   if !db::group_basket_close(basket.chat_id).await {
+      return db::is_success(lang, false);
+   }
+
+   let s = lang::t(lang, GroupBasketCancelled);
+   callback::edit_message(cx, ChatId::Id(message.chat_id()), message.id, &s, None).await;
+   s
+}
+
+// Отправляет общий заказ в заведение одним тикетом от имени инициатора
+pub async fn submit(cx: &DispatcherHandlerCx<CallbackQuery>, rest_num: i32) -> String {
+   let lang = lang::user_lang(Some(&cx.update.from)).await;
+   let (basket, message) = match current(cx, rest_num).await {
+      Ok(res) => res,
+      Err(s) => return s,
+   };
+
+   if cx.update.from.id != basket.initiator {
+      return lang::tf(lang, GroupBasketOnlyInitiator, &[&basket.initiator_name]);
+   }
+
+   let items = match db::group_order_items(basket.chat_id).await {
+      Some(items) if !items.is_empty() => items,
+      Some(_) => return lang::t(lang, GroupBasketNothing),
+      None => return db::is_success(lang, false),
+   };
+
+   // Заведение должно быть подключено к боту и принимать заказы
+   let rest = match db::restaurant(db::RestBy::Num(rest_num)).await {
+      Some(rest) => rest,
+      None => return lang::t(lang, EatNoRestInfo),
+   };
+   if rest.user_id < 9999 {
+      return lang::t(lang, SendNotConnected);
+   }
+   if !rest.active || !rest.enabled {
+      return lang::tf(lang, RestNotAccepting, &[&rest.title]);
+   }
+
+   // Пока собирался заказ, часть групп могла закрыться, как и для обычной корзины проверим время
+   let menu = db::group_basket_menu(rest_num).await.unwrap_or_default();
+   let mut closed: Vec<String> = items.iter()
+   .filter(|item| !menu.iter().any(|dish| dish.group_num == item.group_num && dish.num == item.dish_num))
+   .map(|item| item.title.clone())
+   .collect();
+   if !closed.is_empty() {
+      closed.sort();
+      closed.dedup();
+      return lang::tf(lang, GroupBasketUnavailableDishes, &[&closed.join(", ")]);
+   }
+
+   // Контакты и способ получения берутся из корзины инициатора
+   let info = match db::user_basket_info(basket.initiator).await {
+      Some(info) => info,
+      None => return lang::t(lang, GroupBasketNeedPrivate),
+   };
+   if !info.pickup && info.address.is_none() {
+      return lang::t(lang, SendEnterAddress);
+   }
+
+   // Общий заказ становится обычным тикетом. Состав берётся из забранных строк,
+   // при повторном нажатии забирать уже нечего и никто ничего не получит
+   let (ticket_id, items) = match db::group_basket_to_ticket(basket.chat_id, rest_num, basket.initiator, rest.user_id).await {
+      Some(res) => res,
+      None => return match current(cx, rest_num).await {
+         Err(s) => s,
+         Ok(_) => db::is_success(lang, false),
+      },
+   };
+
+   // Состав заказа в личку инициатору
+   let order_text = |lang: &str| format!("{}\n{}", lang::tf(lang, GroupBasketOrder, &[&basket.initiator_name]), members_text(lang, &items));
+   let eater_chat = ChatId::Id(i64::from(basket.initiator));
+   let eater_lang = lang::lang_of(basket.initiator).await;
+   match outbox::send(&eater_chat, &cx.bot.send_message(eater_chat.clone(), order_text(eater_lang))).await {
+      Ok(message) => {db::ticket_save_eater_order_msg(ticket_id, message.id).await;}
+      Err(e) => settings::log(&format!("Error group_basket::submit({}) to eater: {}", ticket_id, e)).await,
+   }
+   let ticket = match db::ticket(db::TicketBy::TicketId(ticket_id)).await {
+      Some(ticket) => ticket,
+      None => return db::is_success(lang, false),
+   };
+
+   // Ресторатору контакты и состав заказа на его языке через очередь важных сообщений
+   let eater_info = |lang: &str| {
+      let method = if info.pickup {lang::t(lang, OrderPickup)} else {lang::tf(lang, OrderCourier, &[&info.address_label(lang)])};
+      lang::tf(lang, OrderFrom, &[&info.name, &info.contact, &method])
+   };
+   settings::log_and_notify(&eater_info(lang::DEFAULT)).await;
+   let caterer_lang = lang::lang_of(rest.user_id).await;
+   let s = format!("{}\n\n{}", eater_info(caterer_lang), order_text(caterer_lang));
+   let location = if info.pickup {None} else {info.address.as_ref().and_then(|address| address.location)};
+   if !outbox::send_order(&cx.bot, &ticket, s, None, location).await {
+      settings::log(&format!("Error group_basket::submit({}): caterer notification postponed", ticket.ticket_id)).await;
+   }
+
+   // Сообщение со статусом заказа у инициатора, как для обычной корзины
+   if let Err(e) = basket::send_message_for(cx.bot.clone(), eater_chat, basket::InfoFor::Eater, &ticket).await {
+      settings::log(&format!("Error group_basket::submit({}): status message: {}", ticket.ticket_id, e)).await;
+   }
+
+   // Если заведение принимает оплату через бота, счёт получит инициатор
+   if ticket.payment_required {
+      payment::send_invoice(&cx.bot, &ticket).await;
+   }
+
+   // В групповом чате остаётся итог без кнопок
+   let group_lang = lang::lang_of(basket.initiator).await;
+   let s = format!("{}\n{}", lang::tf(group_lang, GroupBasketSent, &[&rest.title, &basket.initiator_name]), members_text(group_lang, &items));
+   callback::edit_message(cx, ChatId::Id(message.chat_id()), message.id, &s, None).await;
+
+   lang::tf(lang, CallbackSend, &[&db::is_success(lang, true)])
+}
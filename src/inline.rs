@@ -8,51 +8,120 @@ Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
 =============================================================================== */
 
 use teloxide::{
-   prelude::*, 
-   types::{InlineQuery},
+   prelude::*,
+   types::{InlineQuery, InlineQueryResult, InlineQueryResultArticle, InlineQueryResultCachedPhoto, InputMessageContent,
+      InlineKeyboardButton, InlineKeyboardMarkup, ParseMode, },
 };
 
-// use crate::database as db;
+use crate::database as db;
+use crate::language::{self as lang, Res::*};
+use crate::settings;
+
+// Telegram принимает не более 50 результатов на один запрос
+const MAX_RESULTS: usize = 50;
+
+// Более короткий запрос находит слишком много
+const MIN_QUERY_LEN: usize = 2;
+
+// Время кеширования ответа на стороне Telegram, в секундах
+const CACHE_TIME: i32 = 60;
 
-#[derive(Copy, Clone)]
 enum InlineCommand {
-    UnknownCommand,
+   Search(String),   // поиск заведений и блюд по части названия
+   UnknownCommand,
 }
 
 impl InlineCommand {
-   pub fn from(_input: &str) -> InlineCommand {
-      InlineCommand::UnknownCommand
+   pub fn from(input: &str) -> InlineCommand {
+      let input = input.trim();
+      if input.chars().count() >= MIN_QUERY_LEN {InlineCommand::Search(input.to_string())}
+      else {InlineCommand::UnknownCommand}
    }
 }
 
 pub async fn handle_message(cx: DispatcherHandlerCx<InlineQuery>) {
-   let query = &cx.update.query;
+   let query = &cx.update;
+   let lang = lang::user_lang(Some(&query.from)).await;
 
+   // Распознаем полученную команду, на слишком короткий запрос отвечаем пустым списком
+   let results = match InlineCommand::from(&query.query) {
+      InlineCommand::Search(text) => search(lang, &text).await,
+      InlineCommand::UnknownCommand => Vec::new(),
+   };
 
-   // Распознаем полученную команду
-   match InlineCommand::from(&query) {
-      InlineCommand::UnknownCommand => log::error!("Unknown inline message: {} from {}", &query, cx.update.from.username.unwrap_or_default())
+   // Результаты зависят от языка пользователя, поэтому кешируем их только для него
+   if let Err(e) = cx.bot.answer_inline_query(query.id.clone(), results)
+   .cache_time(CACHE_TIME)
+   .is_personal(true)
+   .send()
+   .await {
+      settings::log(&format!("inline::handle_message {} from {}: {}", query.query, query.from.id, e)).await;
    }
+}
 
+// Заведения и блюда, в названии которых есть искомый текст
+async fn search(lang: &str, text: &str) -> Vec<InlineQueryResult> {
+   let pattern = format!("%{}%", text);
+   let mut res = Vec::new();
 
-/*   let query_id = &query.id;
-
-   // Сообщение для отправки обратно
-   let msg = match &query.data {
-      None => {
-         String::from("Error handle_message None")
+   // Сначала заведения
+   if let Some(rests) = db::rest_list(db::RestListBy::Find(pattern.clone())).await {
+      for rest in rests {
+         let caption = format!("<b>{}</b>\n{}", rest.title, rest.info);
+         let title = format!("{}: {}", lang::t(lang, InlineRestaurant), rest.title);
+         res.push(result(lang, format!("r{}", rest.num), rest.image_or_default(), title, rest.info.clone(), caption, db::make_key_3_int(rest.num, 0, 0)));
       }
-      Some(data) => {
-         // Код едока
-         let user_id = query.from.id;
-
-         // Идентифицируем и исполним команду
-         match InlineCommand::from(&data) {
-            InlineCommand::UnknownCommand => format!("Error handle_message {}", &data),
-            InlineCommand::Add(rest_num, group_num, dish_num) => format!("Добавить {} {}", db::make_dish_key(rest_num, group_num, dish_num), db::is_success(add_dish(&cx, rest_num, group_num, dish_num, user_id).await)),
-            InlineCommand::Remove(rest_num, group_num, dish_num) => format!("Удалить {} {}", db::make_dish_key(rest_num, group_num, dish_num), db::is_success(remove_dish(&cx, rest_num, group_num, dish_num, user_id).await)),
-         }
+   }
+
+   // Затем блюда
+   if let Some(dishes) = db::dish_list(db::DishesBy::Find(pattern)).await {
+      for dish in dishes.into_iter().take(MAX_RESULTS.saturating_sub(res.len())) {
+         let key = db::make_key_3_int(dish.rest_num, dish.group_num, dish.num);
+         let image = db::dish_image_id(&dish).await;
+         res.push(result(lang, format!("d{}", key), image, dish.title_with_price(), dish.info.clone(), dish.info_for_eater(lang), key));
       }
    }
- */
+
+   res.truncate(MAX_RESULTS);
+   res
+}
+
+// Результат с картинкой, если она есть, и кнопкой перехода в бота на указанное блюдо, группу или заведение
+fn result(lang: &str, id: String, image: String, title: String, description: String, caption: String, key: String) -> InlineQueryResult {
+   let markup = settings::link(&key).map(|url| {
+      let button = InlineKeyboardButton::url(lang::t(lang, InlineOpenInBot), url);
+      InlineKeyboardMarkup::default()
+      .append_row(vec![button])
+   });
+
+   // Без картинки по-умолчанию отправляем текст
+   if image.is_empty() {
+      InlineQueryResult::Article(InlineQueryResultArticle {
+         id,
+         title,
+         input_message_content: InputMessageContent::Text {
+            message_text: caption,
+            parse_mode: Some(ParseMode::HTML),
+            disable_web_page_preview: None,
+         },
+         reply_markup: markup,
+         url: None,
+         hide_url: None,
+         description: Some(description),
+         thumb_url: None,
+         thumb_width: None,
+         thumb_height: None,
+      })
+   } else {
+      InlineQueryResult::CachedPhoto(InlineQueryResultCachedPhoto {
+         id,
+         photo_file_id: image,
+         title: Some(title),
+         description: Some(description),
+         caption: Some(caption),
+         parse_mode: Some(ParseMode::HTML),
+         reply_markup: markup,
+         input_message_content: None,
+      })
+   }
 }
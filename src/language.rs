@@ -7,29 +7,639 @@ http://www.gnu.org/licenses/gpl-3.0.html
 Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
 =============================================================================== */
 
-// use once_cell::sync::{OnceCell};
-// use std::collections::HashMap;
+use once_cell::sync::Lazy;
+use std::{collections::HashMap, fmt::Display, sync::Mutex};
+use teloxide::types::User;
 
-// pub static LANG: OnceCell<HashMap<String, HashMap<String, String>>> = OnceCell::new();
+use crate::database as db;
 
+// Поддерживаемые языки, первый используется по-умолчанию
+pub const LANGUAGES: [&str; 3] = ["ru", "en", "vi"];
+pub const DEFAULT: &str = "ru";
+
+// Файлы с текстами, встроенные в программу
+const FILES: [(&str, &str); 3] = [
+   ("ru", include_str!("../locales/ru.toml")),
+   ("en", include_str!("../locales/en.toml")),
+   ("vi", include_str!("../locales/vi.toml")),
+];
+
+// Ключи текстов, в файлах ресурсов записываются так же, как названия вариантов
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, enum_utils::FromStr, enum_utils::IterVariants)]
 pub enum Res {
-   EatGroupsEmpty, // "   пусто :("
-   EatRestEmpty, // "   пусто :("
-   EatRestNowEmpty, // "   пусто :("
-   EatDishEmpty, // "   пусто :("
-   CatGroupsEmpty, // "   пусто :("
-   // DatabaseRestInfo, // "Заведение: {}\nОписание: {}\nПодходящие разделы меню для {}:\n{}"
+   BtnJuice,
+   BtnFood,
+   BtnDrinks,
+   BtnFun,
+   BtnNow,
+   BtnBasket,
+   BtnMain,
+   BtnExit,
+   BtnHome,
+   BtnBack,
+   BtnBackToRest,
+   BtnRefresh,
+   BtnClear,
+   BtnHistory,
+   BtnLocation,
+   BtnOrderViaBot,
+   BtnOrderTo,
+   BtnCancelOrder,
+   BtnConfirm,
+   BtnPay,
+   BtnNext,
+   BtnAddMenu,
+   BtnFavorites,
+   BtnNearMe,
+   ErrorTitle,
+   TextPlease,
+   ChangesAccepted,
+   ChangesCancelled,
+   UnknownCategoryCancel,
+   Success,
+   Failure,
+   EaterNewSessionAdmin,
+   EaterNewSession,
+   EaterWelcome,
+   EaterStart,
+   EaterPictureId,
+   EaterPictureAdmin,
+   EaterUnknownCommand,
+   EaterWelcomeTo,
+   EaterEnterMessage,
+   EaterEnterReview,
+   EaterEnterReviewReply,
+   EaterReviewHidden,
+   GearInfo,
+   InterfaceCompact,
+   InterfaceInline,
+   GearUnknownCommand,
+   GearInterfaceChanged,
+   GearSelectRest,
+   GearErrorId,
+   GearCatererDenied,
+   GearRegisterCaterer,
+   NewRestTitle,
+   NewRestInfo,
+   GearHoldCaterer,
+   GearNoRights,
+   GearPaymentOn,
+   GearPaymentOff,
+   GearBroadcastHours,
+   GearBroadcastUnlimited,
+   GearBroadcastDefault,
+   GearTimeZone,
+   GearTimeZoneDefault,
+   GearTimeZoneUnknown,
+   GearPaymentViaBot,
+   GearNoPromos,
+   GearPromoSaved,
+   GearPromoFormat,
+   GearPromoRemoved,
+   GearLanguages,
+   GearLanguageChanged,
+   GearLanguageFailed,
+   PromoNoCode,
+   PromoCodeTooLong,
+   PromoNoValue,
+   PromoWrongValue,
+   PromoInvalidValue,
+   PromoWrongParam,
+   PromoWrongParamValue,
+   PromoUnknownParam,
+   PromoGroupsNeedRest,
+   ErrorCall,
+   EnterTitle,
+   CancelTitle,
+   CancelInfo,
+   CatDefaultsHint,
+   CatWelcome,
+   CatInfo,
+   CatLocation,
+   CatNoLocation,
+   CatTimeZone,
+   CatSubscribers,
+   CatBroadcastLast,
+   CatBroadcastRunning,
+   CatBroadcastEnter,
+   CatBroadcastCancel,
+   CatBroadcastTooLong,
+   CatBroadcastTooOften,
+   CatBroadcastNoSubscribers,
+   CatBroadcastStarted,
+   CatBroadcastDone,
+   CatTransfer,
+   CatEnterRestInfo,
+   CatUploadImage,
+   CatPromoteRest,
+   CatUnknownCommand,
+   CatCancelAddGroup,
+   CancelTime,
+   CatGroupInfo,
+   CatGroupEnterInfo,
+   CatGroupSelectCategory,
+   CatGroupEnterTime,
+   CatGroupRemoveError,
+   CatGroupEnterDish,
+   CatGroupUnknownCommand,
+   CatGroupCancelCategory,
+   CatGroupTimeError,
+   DishNoInfo,
+   DishNoInfoForEater,
+   DishEnterInfo,
+   DishEnterGroup,
+   DishEnterPrice,
+   DishUnknownCommand,
+   DishNoGroup,
+   DishGroupNumber,
+   DishGroupCancel,
+   EatRestEmpty,
+   EatRestNowEmpty,
+   EatGroupsEmpty,
+   EatDishEmpty,
+   CatGroupsEmpty,
+   EatRestTitle,
+   EatRestNowTitle,
+   EatRestNearTitle,
+   EatRestNearEmpty,
+   EatRestNearHint,
+   EatRestNearMore,
+   DistanceMeters,
+   DistanceKm,
+   PageOf,
+   EatRestInfo,
+   EatNoRestInfo,
+   EatNoGroupInfo,
+   EatToMenu,
+   EatRestUnknownCommand,
+   EatGroupUnknownCommand,
+   EatDishUnknownCommand,
+   ReviewAsk,
+   ReviewYourRating,
+   ReviewNotify,
+   ReviewRestNotFound,
+   ReviewsAbout,
+   ReviewsNone,
+   ReviewRestReply,
+   ReviewHidden,
+   ReviewRatingLine,
+   ReviewReplyToEater,
+   ReviewThanks,
+   ReviewSaveError,
+   ReviewCancel,
+   ReviewReplySaved,
+   ReviewReplyError,
+   ReviewReplyCancel,
+   CallbackAdd,
+   CallbackRemove,
+   CallbackGroups,
+   CallbackReturnTo,
+   CallbackDishes,
+   CallbackDish,
+   CallbackOpenNow,
+   CallbackSend,
+   CallbackRate,
+   CallbackFavoriteOn,
+   CallbackFavoriteOff,
+   FavoritesTitle,
+   FavoritesEmpty,
+   RestFavoriteOff,
+   RestFavoriteOn,
+   RestSubscribeOff,
+   RestSubscribeOn,
+   CallbackSubscribeOn,
+   CallbackSubscribeOff,
+   BroadcastFooter,
+   BroadcastUnsubscribe,
+   NotifyAdded,
+   NotifyAlready,
+   NotifyReady,
+   NotifyRestOffer,
+   NotifyGroupOffer,
+   NotifyDishOffer,
+   GroupBasketHeader,
+   GroupBasketEmpty,
+   GroupBasketPerson,
+   GroupBasketClear,
+   GroupBasketSubmit,
+   GroupBasketCancel,
+   GroupBasketRests,
+   GroupBasketNoRests,
+   GroupBasketNoMenu,
+   GroupBasketBusy,
+   GroupBasketAdded,
+   GroupBasketCleared,
+   GroupBasketOnlyInitiator,
+   GroupBasketClosed,
+   GroupBasketNothing,
+   GroupBasketCancelled,
+   GroupBasketNeedPrivate,
+   GroupBasketSent,
+   GroupBasketOrder,
+   GroupBasketWelcome,
+   GroupBasketUnavailable,
+   GroupBasketUnavailableDishes,
+   TicketCancelledByCaterer,
+   TicketCancelledByEater,
+   TicketCancelledPaid,
+   TicketClosedByAdmin,
+   TicketStatusChanged,
+   GroupFrom,
+   GroupUntil,
+   WorkAllDay,
+   WorkOpenUntil,
+   WorkClosingIn,
+   WorkOpensAt,
+   DishPrice,
+   DishInfoForCaterer,
+   AddressOnMap,
+   BasketDishLine,
+   PromoAllRests,
+   PromoRest,
+   PromoGroups,
+   PromoUntil,
+   PromoDescription,
+   PromoUnlimited,
+   PromoMinTotal,
+   PromoNotForDishes,
+   PromoNotFound,
+   PromoOtherRest,
+   PromoExpired,
+   PromoCheckError,
+   PromoExhausted,
+   PromoUsedUp,
+   StatusShown,
+   StatusHidden,
+   StatusEnabled,
+   StatusBanned,
+   UnknownCategory,
+   Stage1,
+   Stage2,
+   Stage3,
+   Stage4,
+   Stage5,
+   Stage6,
+   StageError,
+   OrderAsap,
+   No,
+   BasketEmpty,
+   BasketEmptyLower,
+   BasketPickup,
+   BasketCourier,
+   BasketEaterInfo,
+   BasketNoUserInfo,
+   BasketGrandTotal,
+   BasketTotal,
+   BasketPromoApplied,
+   BasketPromoRejected,
+   BasketUnknownCommand,
+   BasketClearError,
+   BasketDeleteError,
+   BasketEnterName,
+   BasketEnterContact,
+   BasketEnterAddress,
+   BasketEnterTime,
+   BasketEnterPromo,
+   BasketCancelName,
+   BasketCancelContact,
+   BasketCancelAddress,
+   BasketCancelLocation,
+   BasketAddressBook,
+   BasketEnterAddressLabel,
+   BasketAddressNotFound,
+   BasketCancelPromo,
+   PromoCodeNotFound,
+   PromoCodeExpired,
+   HistoryEmpty,
+   HistoryTitle,
+   HistoryTicket,
+   ItemAmount,
+   RepeatNotFound,
+   RepeatPrice,
+   RepeatNothing,
+   RepeatAdded,
+   RepeatSkipped,
+   RepeatChanged,
+   OrderTimeError,
+   OrderTooFar,
+   OrderTimePassed,
+   RestNotAccepting,
+   OrderAt,
+   OrderNow,
+   OrderImpossible,
+   OrderTimeHeader,
+   OrderPickup,
+   OrderCourier,
+   OrderFrom,
+   UnknownName,
+   SendNotConnected,
+   SendNoUserInfo,
+   SendEnterAddress,
+   TicketForEater,
+   TicketForCaterer,
+   TicketAwaitingPayment,
+   TicketPaid,
+   TicketPromo,
+   Reminder,
+   InvoicePromo,
+   InvoiceTitle,
+   InvoiceDescription,
+   PayUnknownInvoice,
+   PayTicketNotFound,
+   PayNotYours,
+   PayAlreadyPaid,
+   PayWrongCurrency,
+   PayTotalChanged,
+   PaidNotify,
+   PaidAfterCancel,
+   PaidAfterCancelLog,
+   MessageFrom,
+   MessageSent,
+   MessageSendError,
+   MessageCancel,
+   HelpTitle,
+   CommandWrongArgs,
+   HelpStart,
+   HelpHelp,
+   HelpGoto,
+   HelpSendMessage,
+   HelpReviews,
+   HelpReviewComment,
+   HelpReviewReply,
+   HelpReviewHide,
+   HelpUnsubscribe,
+   HelpNotify,
+   HelpChatId,
+   HelpFavorites,
+   HelpLunch,
+   HelpRestTitle,
+   HelpRestInfo,
+   HelpRestToggle,
+   HelpRestImage,
+   HelpAddGroup,
+   HelpRestPromote,
+   HelpBroadcast,
+   HelpEditGroup,
+   HelpTransfer,
+   HelpGroupTitle,
+   HelpGroupInfo,
+   HelpGroupToggle,
+   HelpGroupCategory,
+   HelpGroupTime,
+   HelpGroupRemove,
+   HelpAddDish,
+   HelpGroupPromote,
+   HelpEditDish,
+   HelpDishTitle,
+   HelpDishInfo,
+   HelpDishToggle,
+   HelpDishGroup,
+   HelpDishPrice,
+   HelpDishImage,
+   HelpDishRemove,
+   HelpDishPromote,
+   HelpRestaurant,
+   HelpGroup,
+   HelpDish,
+   HelpPage,
+   HelpFavorite,
+   HelpSubscribe,
+   HelpBasketName,
+   HelpBasketContact,
+   HelpBasketAddress,
+   HelpBasketSetAddress,
+   HelpBasketRemoveAddress,
+   HelpBasketPickup,
+   HelpBasketTime,
+   HelpBasketPromo,
+   HelpBasketDelete,
+   HelpBasketRepeat,
+   HelpInterface,
+   HelpLanguages,
+   HelpSetLanguage,
+   HelpRestList,
+   HelpRegister,
+   HelpHold,
+   HelpSudo,
+   HelpSudoList,
+   HelpPayment,
+   HelpBroadcastHours,
+   HelpTimeZone,
+   HelpPromoList,
+   HelpPromoSave,
+   HelpPromoRemove,
+   InlineOpenInBot,
+   InlineRestaurant,
+}
+
+// Тексты по языкам
+static TEXTS: Lazy<HashMap<&'static str, HashMap<Res, String>>> = Lazy::new(|| {
+   FILES.iter()
+   .map(|(lang, text)| (*lang, parse(lang, text)))
+   .collect()
+});
+
+// Выбранный пользователями язык, чтобы не обращаться к БД на каждое сообщение
+static USERS: Lazy<Mutex<HashMap<i32, &'static str>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Разбор файла вида Key = "текст", ошибки выводятся в журнал, а ключ берётся из языка по-умолчанию
+fn parse(lang: &str, text: &str) -> HashMap<Res, String> {
+   let values: HashMap<String, String> = match toml::from_str(text) {
+      Ok(values) => values,
+      Err(e) => {
+         log::warn!("locales/{}.toml: {}", lang, e);
+         return HashMap::new();
+      }
+   };
+
+   values.into_iter()
+   .filter_map(|(key, value)| match key.parse::<Res>() {
+      Ok(key) => Some((key, value)),
+      Err(_) => {
+         log::warn!("locales/{}.toml: unknown key '{}'", lang, key);
+         None
+      }
+   })
+   .collect()
 }
 
-// Возвращает шаблон на нужном языке
+// Поддерживаемый язык для кода из Telegram, например "en-US"
+pub fn from_code(code: Option<&str>) -> &'static str {
+   match code.and_then(|c| c.get(..2)) {
+      None => DEFAULT,
+      Some("ru") | Some("uk") | Some("be") | Some("kk") => "ru",
+      Some("vi") => "vi",
+      Some(_) => "en",
+   }
+}
+
+// Поддерживаемый язык или None
+pub fn supported(code: &str) -> Option<&'static str> {
+   LANGUAGES.iter().find(|lang| **lang == code).copied()
+}
+
+// Название языка на нём самом
+pub fn name(lang: &str) -> &'static str {
+   match lang {
+      "en" => "English",
+      "vi" => "Tiếng Việt",
+      _ => "Русский",
+   }
+}
+
+// Возвращает текст на нужном языке
 //
-pub fn t(_lang: &str, resource: Res) -> String {
-   match resource {
-      Res::EatGroupsEmpty => String::from("   пусто :("),
-      Res::EatRestEmpty => String::from("   пусто :("),
-      Res::EatRestNowEmpty => String::from("   пусто :("),
-      Res::EatDishEmpty => String::from("   пусто :("),
-      Res::CatGroupsEmpty => String::from("   пусто :("),
-      // DatabaseRestInfo => String::from("Заведение: {}\nОписание: {}\nПодходящие разделы меню для {}:\n{}"),
-   }
-}
\ No newline at end of file
+pub fn t(lang: &str, resource: Res) -> String {
+   TEXTS.get(lang)
+   .and_then(|texts| texts.get(&resource))
+   .or_else(|| TEXTS.get(DEFAULT).and_then(|texts| texts.get(&resource)))
+   .cloned()
+   .unwrap_or_else(|| format!("{:?}", resource))
+}
+
+// Возвращает текст с подставленными аргументами вместо {} по порядку или {0}, {1} по номеру
+pub fn tf(lang: &str, resource: Res, args: &[&(dyn Display + Sync)]) -> String {
+   format_template(&t(lang, resource), args)
+}
+
+fn format_template(template: &str, args: &[&(dyn Display + Sync)]) -> String {
+   let mut res = String::with_capacity(template.len());
+   let mut next = 0;
+   let mut rest = template;
+   while let Some(start) = rest.find('{') {
+      res.push_str(&rest[..start]);
+      let after = &rest[start + 1..];
+      match after.find('}') {
+         Some(end) if after[..end].chars().all(|c| c.is_ascii_digit()) => {
+            let index = if end == 0 {next += 1; next - 1} else {after[..end].parse().unwrap_or_default()};
+            if let Some(arg) = args.get(index) {
+               res.push_str(&arg.to_string());
+            }
+            rest = &after[end + 1..];
+         }
+         _ => {
+            res.push('{');
+            rest = after;
+         }
+      }
+   }
+   res.push_str(rest);
+   res
+}
+
+// Надписи кнопок на всех языках, чтобы команды распознавались независимо от языка пользователя
+static BUTTONS: Lazy<HashMap<String, Res>> = Lazy::new(|| {
+   Res::iter()
+   .filter(|r| format!("{:?}", r).starts_with("Btn"))
+   .flat_map(|r| LANGUAGES.iter().map(move |lang| (t(lang, r), r)))
+   .collect()
+});
+
+// Кнопка по её надписи на любом из языков
+pub fn button(input: &str) -> Option<Res> {
+   BUTTONS.get(input).copied()
+}
+
+// Язык пользователя: выбранный им, либо определённый по настройкам Telegram при первом обращении
+pub async fn user_lang(user: Option<&User>) -> &'static str {
+   let user = match user {
+      Some(user) => user,
+      None => return DEFAULT,
+   };
+
+   if let Some(lang) = USERS.lock().unwrap().get(&user.id) {
+      return lang;
+   }
+
+   match db::user_language(user.id).await.as_deref().and_then(supported) {
+      Some(lang) => {
+         USERS.lock().unwrap().insert(user.id, lang);
+         lang
+      }
+      None => {
+         // Запомним язык, чтобы им же писать пользователю от имени других, например, о заказе.
+         // При самом первом сообщении записи о пользователе ещё нет, тогда сохраним в следующий раз
+         let lang = from_code(user.language_code.as_deref());
+         set_user_lang(user.id, lang).await;
+         lang
+      }
+   }
+}
+
+// Язык получателя сообщения, если известен только его идентификатор
+pub async fn lang_of(user_id: i32) -> &'static str {
+   if let Some(lang) = USERS.lock().unwrap().get(&user_id) {
+      return lang;
+   }
+
+   let lang = db::user_language(user_id).await.as_deref().and_then(supported).unwrap_or(DEFAULT);
+   USERS.lock().unwrap().insert(user_id, lang);
+   lang
+}
+
+// Сохраняет выбор пользователя
+pub async fn set_user_lang(user_id: i32, lang: &'static str) -> bool {
+   let res = db::user_set_language(user_id, lang).await;
+   if res {
+      USERS.lock().unwrap().insert(user_id, lang);
+   }
+   res
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   #[test]
+   fn all_texts_translated() {
+      for lang in LANGUAGES.iter() {
+         let texts = &TEXTS[lang];
+         let missing: Vec<String> = Res::iter().filter(|r| !texts.contains_key(r)).map(|r| format!("{:?}", r)).collect();
+         assert!(missing.is_empty(), "locales/{}.toml misses {}", lang, missing.join(", "));
+      }
+   }
+
+   #[test]
+   fn locale_files_parse() {
+      // Каждый файл - корректный TOML, и все ключи в нём известны
+      for (lang, text) in FILES.iter() {
+         let values: HashMap<String, String> = toml::from_str(text).unwrap_or_else(|e| panic!("locales/{}.toml: {}", lang, e));
+         let unknown: Vec<&String> = values.keys().filter(|key| key.parse::<Res>().is_err()).collect();
+         assert!(unknown.is_empty(), "locales/{}.toml has unknown keys {:?}", lang, unknown);
+      }
+   }
+
+   #[test]
+   fn placeholders_match() {
+      // В переводе столько же аргументов, сколько в исходном тексте
+      let count = |s: &str| s.matches('{').count();
+      for r in Res::iter() {
+         for lang in LANGUAGES.iter() {
+            assert_eq!(count(&t(lang, r)), count(&t(DEFAULT, r)), "{:?} in {}", r, lang);
+         }
+      }
+   }
+
+   #[test]
+   fn format_arguments() {
+      let s = String::from("x");
+      assert_eq!(format_template("{} и {}", &[&1, &s]), "1 и x");
+      assert_eq!(format_template("{1} {0} {}", &[&1, &2]), "2 1 1");
+      assert_eq!(format_template("{a}", &[&1]), "{a}");
+   }
+
+   #[test]
+   fn buttons_in_any_language() {
+      // Надписи разных кнопок не должны совпадать ни на одном из языков
+      let buttons: Vec<Res> = Res::iter().filter(|r| format!("{:?}", r).starts_with("Btn")).collect();
+      for r in buttons.iter() {
+         for lang in LANGUAGES.iter() {
+            assert_eq!(button(&t(lang, *r)), Some(*r), "{:?} in {}", r, lang);
+         }
+      }
+      assert_eq!(from_code(Some("en-US")), "en");
+      assert_eq!(from_code(Some("vi")), "vi");
+      assert_eq!(from_code(Some("uk")), "ru");
+      assert_eq!(from_code(None), DEFAULT);
+   }
+}
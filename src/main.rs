@@ -16,14 +16,11 @@ extern crate smart_default;
 use teloxide::{
    dispatching::update_listeners, 
    prelude::*, 
-   types::{CallbackQuery, InlineQuery, ChatId, },
+   types::{CallbackQuery, InlineQuery, PreCheckoutQuery, ChatId, },
 };
 
-use std::{convert::Infallible, env, net::SocketAddr, sync::Arc};
-use tokio::sync::mpsc;
+use std::{sync::Arc, time::Instant};
 use tokio_postgres::{NoTls};
-use warp::Filter;
-use reqwest::StatusCode;
 use deadpool_postgres::{Manager, ManagerConfig, Pool, RecyclingMethod};
 
 mod database;
@@ -37,14 +34,30 @@ mod eat_group;
 mod eat_dish;
 mod eat_rest_now;
 mod eat_group_now;
+mod eat_rest_near;
 mod callback;
+mod review;
+mod favorites;
+mod broadcast;
+mod notify;
+mod group_basket;
+mod payment;
 mod basket;
 mod inline;
 mod language;
 mod settings;
+mod config;
+mod shutdown;
+mod outbox;
 mod gear;
+mod registry;
+mod pages;
+mod metrics;
+mod webhook;
+mod admin_api;
 
 use commands as cmd;
+use language::{self as lang, Res::*};
 
 // ============================================================================
 // [Control a dialogue]
@@ -55,6 +68,12 @@ async fn handle_message(cx: cmd::Cx<cmd::Dialogue>) -> cmd::Res {
    // Для различения, в личку или в группу пишут
    let chat_id = update.chat_id();
 
+   // Сообщение об успешной оплате обрабатываем вне зависимости от режима
+   if let Some(successful_payment) = update.successful_payment() {
+      payment::successful_payment(&bot, successful_payment).await;
+      return next(dialogue);
+   }
+
    // Обрабатываем команду, если она пришла в личку
    if chat_id > 0 {
       match dialogue {
@@ -91,6 +110,10 @@ async fn handle_message(cx: cmd::Cx<cmd::Dialogue>) -> cmd::Res {
             caterer::add_rest_group(DialogueDispatcherHandlerCx::new(bot, update, rest_id))
                   .await
          }
+         cmd::Dialogue::CatBroadcast(rest_id) => {
+            broadcast::enter_mode(DialogueDispatcherHandlerCx::new(bot, update, rest_id))
+                  .await
+         }
          cmd::Dialogue::CatEditGroupTitle(rest_id, group_id) => {
             cat_group::edit_title_mode(DialogueDispatcherHandlerCx::new(bot, update, (rest_id, group_id)))
                   .await
@@ -172,22 +195,47 @@ async fn handle_message(cx: cmd::Cx<cmd::Dialogue>) -> cmd::Res {
             basket::edit_address_mode(DialogueDispatcherHandlerCx::new(bot, update, user_id))
                   .await
          }
+         cmd::Dialogue::BasketEditAddressLabel(user_id, address_id) => {
+            basket::edit_address_label_mode(DialogueDispatcherHandlerCx::new(bot, update, (user_id, address_id)))
+                  .await
+         }
+         cmd::Dialogue::BasketEditTime(user_id) => {
+            basket::edit_time_mode(DialogueDispatcherHandlerCx::new(bot, update, user_id))
+                  .await
+         }
+         cmd::Dialogue::BasketEditPromo(user_id) => {
+            basket::edit_promo_mode(DialogueDispatcherHandlerCx::new(bot, update, user_id))
+                  .await
+         }
          cmd::Dialogue::MessageToCaterer(user_id, caterer_id, origin) => {
             edit_message_to_caterer_mode(DialogueDispatcherHandlerCx::new(bot, update, (user_id, caterer_id, origin)))
                   .await
          }
+         cmd::Dialogue::ReviewComment(user_id, review_id, origin) => {
+            review::edit_comment_mode(DialogueDispatcherHandlerCx::new(bot, update, (user_id, review_id, origin)))
+                  .await
+         }
+         cmd::Dialogue::ReviewReply(user_id, review_id, origin) => {
+            review::edit_reply_mode(DialogueDispatcherHandlerCx::new(bot, update, (user_id, review_id, origin)))
+                  .await
+         }
          cmd::Dialogue::GearMode => {
             gear::handle_commands(DialogueDispatcherHandlerCx::new(bot, update, ()))
                   .await
          }
       } 
 } else {
-      // Для сообщений не в личке обрабатываем только команду вывода id группы
-      if let Some(input) = update.text() {
-         match input.get(..5).unwrap_or_default() {
-            "/chat" => cmd::send_text_without_markup(&DialogueDispatcherHandlerCx::new(bot, update, ()), &format!("Chat id={}", chat_id)).await,
-            _ => (),
-         }
+      // Для сообщений не в личке обрабатываем только команды вывода id группы и общего заказа,
+      // а когда бота добавили в группу, подсказываем, как начать общий заказ
+      let cx = DialogueDispatcherHandlerCx::new(bot, update, ());
+      if let Some(input) = cx.update.text() {
+         match cmd::GroupChat::from(input) {
+            cmd::GroupChat::ChatId => cmd::send_text_without_markup(&cx, &format!("Chat id={}", chat_id)).await,
+            cmd::GroupChat::Lunch(rest_num) => group_basket::start(&cx, rest_num).await,
+            cmd::GroupChat::UnknownCommand => (),
+         }
+      } else if let Some(members) = cx.update.new_chat_members() {
+         group_basket::welcome(&cx, members).await;
       }
       exit()
    }
@@ -196,6 +244,8 @@ async fn handle_message(cx: cmd::Cx<cmd::Dialogue>) -> cmd::Res {
 
 async fn handle_callback_query(rx: DispatcherHandlerRx<CallbackQuery>) {
    rx.for_each_concurrent(None, |cx| async move {
+      metrics::update("callback");
+      let _work = shutdown::work();
       callback::handle_message(cx).await
    })
   .await;
@@ -203,11 +253,22 @@ async fn handle_callback_query(rx: DispatcherHandlerRx<CallbackQuery>) {
 
 async fn handle_inline_query(rx: DispatcherHandlerRx<InlineQuery>) {
    rx.for_each_concurrent(None, |cx| async move {
+      metrics::update("inline");
+      let _work = shutdown::work();
       inline::handle_message(cx).await
    })
   .await;
 }
 
+async fn handle_pre_checkout_query(rx: DispatcherHandlerRx<PreCheckoutQuery>) {
+   rx.for_each_concurrent(None, |cx| async move {
+      metrics::update("pre_checkout");
+      let _work = shutdown::work();
+      payment::handle_pre_checkout_query(cx).await
+   })
+  .await;
+}
+
 
 // ============================================================================
 // [Run!]
@@ -217,114 +278,122 @@ async fn main() {
    run().await;
 }
 
-async fn handle_rejection(error: warp::Rejection) -> Result<impl warp::Reply, Infallible> {
-   log::error!("Cannot process the request due to: {:?}", error);
-   Ok(StatusCode::INTERNAL_SERVER_ERROR)
-}
-
-pub async fn webhook<'a>(bot: Arc<Bot>) -> impl update_listeners::UpdateListener<Infallible> {
-   // Heroku defines auto defines a port value
-   let teloxide_token = env::var("TELOXIDE_TOKEN").expect("TELOXIDE_TOKEN env variable missing");
-   let port: u16 = env::var("PORT")
-      .expect("PORT env variable missing")
-      .parse()
-      .expect("PORT value to be integer");
-   // Heroku host example .: "heroku-ping-pong-bot.herokuapp.com"
-   let host = env::var("HOST").expect("have HOST env variable");
-   let path = format!("bot{}", teloxide_token);
-   let url = format!("https://{}/{}", host, path);
-
-   bot.set_webhook(url)
+// Получение обновлений длинными опросами, для запуска без HTTPS, например, при разработке
+pub async fn polling(bot: Arc<Bot>) -> impl update_listeners::UpdateListener<RequestError> {
+   // Пока установлен вебхук, Telegram не отдаёт обновления через getUpdates
+   bot.delete_webhook()
       .send()
       .await
-      .expect("Cannot setup a webhook");
-   
-   let (tx, rx) = mpsc::unbounded_channel();
-
-   let server = warp::post()
-      .and(warp::path(path))
-      .and(warp::body::json())
-      .map(move |json: serde_json::Value| {
-         let try_parse = match serde_json::from_str(&json.to_string()) {
-               Ok(update) => Ok(update),
-               Err(error) => {
-                  log::error!(
-                     "Cannot parse an update.\nError: {:?}\nValue: {}\n\
-                     This is a bug in teloxide, please open an issue here: \
-                     https://github.com/teloxide/teloxide/issues.",
-                     error,
-                     json
-                  );
-                  Err(error)
-               }
-         };
-         if let Ok(update) = try_parse {
-               tx.send(Ok(update))
-                  .expect("Cannot send an incoming update from the webhook")
-         }
-
-         StatusCode::OK
-      })
-      .recover(handle_rejection);
-
-   let serve = warp::serve(server);
-
-   let address = format!("0.0.0.0:{}", port);
-   tokio::spawn(serve.run(address.parse::<SocketAddr>().unwrap()));
-   rx
+      .expect("Cannot delete a webhook");
+
+   update_listeners::polling_default(bot)
 }
 
 async fn run() {
    teloxide::enable_logging!();
    log::info!("Starting...");
 
-   let bot = Bot::from_env();
+   // Настройки из файла и переменных окружения, при ошибках запуск невозможен
+   let (config, warnings) = match config::Config::load() {
+      Ok(res) => res,
+      Err(errors) => {
+         let s = config::report("Configuration errors", &errors);
+         log::error!("{}", s);
+         std::process::exit(1);
+      }
+   };
+
+   // Для проверки на локальной замене Bot API запросы можно направить через прокси
+   let bot = match &config.telegram_api_proxy {
+      Some(proxy) => {
+         log::info!("Telegram API through proxy {}", proxy);
+         let mut builder = reqwest::Client::builder()
+         .proxy(reqwest::Proxy::all(proxy).expect("TELEGRAM_API_PROXY env variable wrong"));
+         if let Some(pem) = &config.telegram_api_proxy_ca {
+            builder = builder.add_root_certificate(reqwest::Certificate::from_pem(pem).expect("TELEGRAM_API_PROXY_CA wrong"));
+         }
+         let client = builder.build().expect("Cannot build client for TELEGRAM_API_PROXY");
+         Bot::with_client(&config.token, client)
+      }
+      None => Bot::new(&config.token),
+   };
 
-   // Настройки из переменных окружения
-   let vars = settings::Vars::from_env(&bot).await;
+   let vars = settings::Vars::from_config(&config, &bot).await;
    match settings::VARS.set(vars) {
       Ok(_) => settings::log_and_notify("Bot restarted").await,
       _ => log::info!("Something wrong with TELEGRAM_LOG_CHAT"),
    }
 
-   // Откроем БД
-   let database_url = env::var("DATABASE_URL").expect("DATABASE_URL env variable missing");
-   let pg_config = database_url.parse::<tokio_postgres::Config>().expect("DATABASE_URL env variable wrong");
+   // Предупреждения о необязательных настройках одним сообщением
+   if !warnings.is_empty() {
+      let s = config::report("Configuration warnings", &warnings);
+      log::warn!("{}", s);
+      settings::log(&s).await;
+   }
+
+   // Откроем БД, строка соединения уже проверена
+   let pg_config = config.database_url.parse::<tokio_postgres::Config>().expect("DATABASE_URL env variable wrong");
    let mgr_config = ManagerConfig {recycling_method: RecyclingMethod::Fast};
    let mgr = Manager::from_config(pg_config, NoTls, mgr_config);
    let pool = Pool::new(mgr, 16);
 
-   // Протестируем соединение
-   let test_pool = pool.clone();
-   tokio::spawn(async move {
-      if let Err(e) = test_pool.get().await {
-         settings::log(&format!("Database connection error: {}", e)).await;
-      }
-   });
-
    // Сохраним доступ к БД
-   match database::DB.set(pool) {
-      Ok(_) => log::info!("Database connected"),
-      _ => {
-         log::info!("Something wrong with database");
-         settings::log("Something wrong with database").await;
-      }
+   if database::DB.set(pool).is_err() {
+      log::info!("Something wrong with database");
+      settings::log("Something wrong with database").await;
+   }
+
+   // Дождёмся БД, прежде чем проверять таблицы
+   if database::wait_for_connection().await {
+      log::info!("Database connected");
+   } else {
+      let s = "Database connection failed, giving up";
+      log::error!("{}", s);
+      settings::log_and_notify(s).await;
+      std::process::exit(1);
    }
 
    // Проверим существование таблиц и если их нет, создадим
    if database::is_tables_exist().await {
       log::info!("Table restaurants exist, open existing data");
    } else {
-      log::info!("Table restaurants do not exist, create new tables: {}", database::is_success(database::create_tables().await));
+      log::info!("Table restaurants do not exist, create new tables: {}", database::is_success(lang::DEFAULT, database::create_tables().await));
    }
    
+   // Дополним таблицы новыми полями, если они были созданы предыдущей версией
+   if !database::update_tables().await {
+      log::info!("Tables update failed");
+   }
+
    // Инициализируем структуру с картинками для категорий
    database::cat_image_init().await;
+
+   // Напоминания рестораторам о заказах ко времени
+   tokio::spawn(basket::remind_loop(Arc::clone(&bot)));
+
+   // Досылка важных сообщений, которые не удалось доставить сразу
+   tokio::spawn(outbox::resend_loop(Arc::clone(&bot)));
+
+   // Уведомления об открытии заведений и групп по расписанию
+   tokio::spawn(notify::notify_loop(Arc::clone(&bot)));
+
+   // Рассылки подписчикам, прерванные перезапуском
+   tokio::spawn(broadcast::resume(Arc::clone(&bot)));
+
+   // Запрос на остановку от платформы
+   tokio::spawn(shutdown::listen_signals());
    
-   Dispatcher::new(Arc::clone(&bot))
-   .messages_handler(DialogueDispatcher::new(|cx| async move {
+   let dispatcher = Dispatcher::new(Arc::clone(&bot))
+   .messages_handler(DialogueDispatcher::new(|cx: cmd::Cx<cmd::Dialogue>| async move {
+      // Замер времени обработки в текущем состоянии диалога
+      metrics::update("message");
+      let _work = shutdown::work();
+      let state = cx.dialogue.name();
+      let start = Instant::now();
       let res = handle_message(cx).await;
+      metrics::handler_time(state, start);
       if let Err(e) = res {
+         metrics::telegram_error("message");
          settings::log(&format!("main:{}", e)).await;
          DialogueStage::Exit
       } else {
@@ -333,16 +402,43 @@ async fn run() {
    }))
    .callback_queries_handler(handle_callback_query)
    .inline_queries_handler(handle_inline_query)
-   .dispatch_with_listener(
-      webhook(bot).await,
-      LoggingErrorHandler::with_custom_text("An error from the update listener"),
-   )
-   .await;
+   .pre_checkout_queries_handler(handle_pre_checkout_query);
+
+   // Способ получения обновлений, по умолчанию вебхук
+   match config.update_mode {
+      config::UpdateMode::Polling => {
+         log::info!("Updates by long polling");
+         dispatcher.dispatch_with_listener(
+            polling(bot).await.take_until(shutdown::requested()),
+            LoggingErrorHandler::with_custom_text("An error from the update listener"),
+         )
+         .await;
+      }
+      config::UpdateMode::Webhook => {
+         dispatcher.dispatch_with_listener(
+            webhook::listener(bot, &config).await,
+            LoggingErrorHandler::with_custom_text("An error from the update listener"),
+         )
+         .await;
+      }
+   }
+
+   // Новые обновления больше не поступают, закрываем очереди и дожидаемся начатой обработки
+   drop(dispatcher);
+   if shutdown::drain().await {
+      log::info!("All updates processed");
+   } else {
+      log::warn!("Shutdown timeout, {} updates are still processing", shutdown::in_flight());
+   }
+   settings::log("Bot stopped").await;
+   database::close_pool().await;
+   log::info!("Stopped");
 }
 
 // Отправить сообщение
 pub async fn send_message(bot: &Arc<Bot>, chat_id: ChatId, s: &str) -> bool {
-   if let Err(e) = bot.send_message(chat_id, s).send().await {
+   if let Err(e) = outbox::send(&chat_id, &bot.send_message(chat_id.clone(), s)).await {
+      metrics::telegram_error("send_message");
       settings::log(&format!("Ошибка {}", e)).await;
       false
    } else {true}
@@ -352,6 +448,7 @@ pub async fn send_message(bot: &Arc<Bot>, chat_id: ChatId, s: &str) -> bool {
 pub async fn edit_message_to_caterer_mode(cx: cmd::Cx<(i32, i32, Box<cmd::DialogueState>)>) -> cmd::Res {
    // Извлечём параметры
    let (user_id, caterer_id, boxed_origin) = cx.dialogue;
+   let lang = lang::user_lang(cx.update.from()).await;
 
    if let Some(text) = cx.update.text() {
       // Удалим из строки слеши
@@ -365,13 +462,13 @@ pub async fn edit_message_to_caterer_mode(cx: cmd::Cx<(i32, i32, Box<cmd::Dialog
       
          // Текст для отправки
          let user_name = if let Some(u) = cx.update.from() {&u.first_name} else {""};
-         let s = format!("Сообщение от {}\n{}\n Для ответа нажмите ссылку /snd{}", user_name, s, user_id);
+         let s = lang::tf(lang::lang_of(caterer_id).await, MessageFrom, &[&user_name, &s, &user_id]);
 
          // Отправляем сообщение и сообщаем результат
-         if send_message(&cx.bot, to, &s).await {String::from("Сообщение отправлено")}
-         else {String::from("Ошибка отправки сообщения")}
+         if send_message(&cx.bot, to, &s).await {lang::t(lang, MessageSent)}
+         else {lang::t(lang, MessageSendError)}
       } else {
-         String::from("Отмена отправки сообщения")
+         lang::t(lang, MessageCancel)
       };
 
       // Уведомим о результате
@@ -0,0 +1,137 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Метрики для Prometheus. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+use once_cell::sync::Lazy;
+use std::{collections::BTreeMap, fmt::Write, sync::Mutex, time::Instant};
+
+// Описание метрики для вывода
+struct Meta {
+   name: &'static str,
+   help: &'static str,
+   histogram: bool,
+}
+
+const UPDATES: Meta = Meta {name: "vzmuinebot_updates_total", help: "Updates received from Telegram by kind", histogram: false};
+const HANDLER_SECONDS: Meta = Meta {name: "vzmuinebot_handler_seconds", help: "Message handler latency by dialogue state", histogram: true};
+const TELEGRAM_ERRORS: Meta = Meta {name: "vzmuinebot_telegram_api_errors_total", help: "Failed Telegram Bot API requests by context", histogram: false};
+const DB_POOL_WAIT_SECONDS: Meta = Meta {name: "vzmuinebot_db_pool_wait_seconds", help: "Time waiting for a database connection from the pool", histogram: true};
+const DB_POOL_ERRORS: Meta = Meta {name: "vzmuinebot_db_pool_errors_total", help: "Failures to get a database connection from the pool", histogram: false};
+const TICKET_STAGES: Meta = Meta {name: "vzmuinebot_ticket_stage_transitions_total", help: "Ticket stage transitions, stage 0 is a new ticket", histogram: false};
+const BASKET_ADDITIONS: Meta = Meta {name: "vzmuinebot_basket_additions_total", help: "Dishes added to baskets", histogram: false};
+
+const ALL: [&Meta; 7] = [&UPDATES, &HANDLER_SECONDS, &TELEGRAM_ERRORS, &DB_POOL_WAIT_SECONDS, &DB_POOL_ERRORS, &TICKET_STAGES, &BASKET_ADDITIONS];
+
+// Границы корзин гистограмм в секундах
+const BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Default)]
+struct Histogram {
+   buckets: [u64; 11],
+   sum: f64,
+   count: u64,
+}
+
+// Значения по имени метрики и строке с метками
+#[derive(Default)]
+struct Registry {
+   counters: BTreeMap<(&'static str, String), u64>,
+   histograms: BTreeMap<(&'static str, String), Histogram>,
+}
+
+static REGISTRY: Lazy<Mutex<Registry>> = Lazy::new(|| Mutex::new(Registry::default()));
+
+fn labels(pairs: &[(&str, &str)]) -> String {
+   pairs.iter()
+   .map(|(k, v)| format!("{}=\"{}\"", k, v.replace('\\', "\\\\").replace('"', "\\\"")))
+   .collect::<Vec<String>>()
+   .join(",")
+}
+
+fn inc(meta: &Meta, pairs: &[(&str, &str)]) {
+   if let Ok(mut registry) = REGISTRY.lock() {
+      *registry.counters.entry((meta.name, labels(pairs))).or_insert(0) += 1;
+   }
+}
+
+fn observe(meta: &Meta, pairs: &[(&str, &str)], seconds: f64) {
+   if let Ok(mut registry) = REGISTRY.lock() {
+      let histogram = registry.histograms.entry((meta.name, labels(pairs))).or_default();
+      for (i, bound) in BUCKETS.iter().enumerate() {
+         if seconds <= *bound {
+            histogram.buckets[i] += 1;
+         }
+      }
+      histogram.sum += seconds;
+      histogram.count += 1;
+   }
+}
+
+// Получено обновление: message, callback, inline, pre_checkout
+pub fn update(kind: &str) {
+   inc(&UPDATES, &[("kind", kind)]);
+}
+
+// Время обработки сообщения в указанном состоянии диалога
+pub fn handler_time(state: &str, start: Instant) {
+   observe(&HANDLER_SECONDS, &[("state", state)], start.elapsed().as_secs_f64());
+}
+
+// Ошибка запроса к Telegram
+pub fn telegram_error(context: &str) {
+   inc(&TELEGRAM_ERRORS, &[("context", context)]);
+}
+
+// Ожидание соединения с БД и его результат
+pub fn db_pool_wait(start: Instant, success: bool) {
+   observe(&DB_POOL_WAIT_SECONDS, &[], start.elapsed().as_secs_f64());
+   if !success {
+      inc(&DB_POOL_ERRORS, &[]);
+   }
+}
+
+// Переход заказа между стадиями
+pub fn ticket_stage(from: i32, to: i32) {
+   inc(&TICKET_STAGES, &[("from", &from.to_string()), ("to", &to.to_string())]);
+}
+
+// Добавление блюда в корзину
+pub fn basket_addition() {
+   inc(&BASKET_ADDITIONS, &[]);
+}
+
+// Текст в формате Prometheus
+pub fn render() -> String {
+   let registry = match REGISTRY.lock() {
+      Ok(registry) => registry,
+      Err(_) => return String::default(),
+   };
+
+   let mut s = String::new();
+   for meta in ALL.iter() {
+      let _ = writeln!(s, "# HELP {} {}", meta.name, meta.help);
+      if meta.histogram {
+         let _ = writeln!(s, "# TYPE {} histogram", meta.name);
+         for ((_, l), h) in registry.histograms.iter().filter(|((name, _), _)| *name == meta.name) {
+            let sep = if l.is_empty() {""} else {","};
+            for (bound, count) in BUCKETS.iter().zip(h.buckets.iter()) {
+               let _ = writeln!(s, "{}_bucket{{{}{}le=\"{}\"}} {}", meta.name, l, sep, bound, count);
+            }
+            let _ = writeln!(s, "{}_bucket{{{}{}le=\"+Inf\"}} {}", meta.name, l, sep, h.count);
+            let _ = writeln!(s, "{}_sum{{{}}} {}", meta.name, l, h.sum);
+            let _ = writeln!(s, "{}_count{{{}}} {}", meta.name, l, h.count);
+         }
+      } else {
+         let _ = writeln!(s, "# TYPE {} counter", meta.name);
+         for ((_, l), value) in registry.counters.iter().filter(|((name, _), _)| *name == meta.name) {
+            let _ = writeln!(s, "{}{{{}}} {}", meta.name, l, value);
+         }
+      }
+   }
+   s
+}
@@ -0,0 +1,68 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Разовые уведомления об открытии заведения или появлении блюда. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+use teloxide::{prelude::*, types::InlineKeyboardButton};
+use std::{sync::Arc, time::Duration};
+
+use crate::database as db;
+use crate::language::{self as lang, Res::{self, *}};
+use crate::outbox;
+use crate::settings;
+use crate::shutdown;
+
+// Через сколько дней забывать невыполненные запросы
+const EXPIRE_DAYS: i64 = 30;
+
+// Инлайн-кнопка для заведения (группа и блюдо нулевые), группы (блюдо нулевое) или блюда
+pub fn button(key: &str) -> InlineKeyboardButton {
+   InlineKeyboardButton::callback(String::from("⏰"), format!("ntf{}", key))
+}
+
+// Предложение с командой /ntf для недоступного сейчас заведения, группы или блюда
+pub fn offer(lang: &str, res: Res, rest_num: i32, group_num: i32, dish_num: i32) -> String {
+   lang::tf(lang, res, &[&db::make_key_3_int(rest_num, group_num, dish_num)])
+}
+
+// Запрос уведомления, возвращает ответ пользователю
+pub async fn request(lang: &str, user_id: i32, rest_num: i32, group_num: i32, dish_num: i32) -> String {
+   // Если уже можно заказать, ждать нечего
+   if db::notify_target_ready(rest_num, group_num, dish_num).await {
+      return lang::tf(lang, NotifyAlready, &[&db::make_key_3_int(rest_num, group_num, dish_num)]);
+   }
+
+   if db::notify_add(user_id, rest_num, group_num, dish_num).await {lang::t(lang, NotifyAdded)}
+   else {db::is_success(lang, false)}
+}
+
+// Отправляет уведомления, цель которых стала доступна
+pub async fn fire(bot: Arc<Bot>) {
+   if let Some(list) = db::notify_take_ready().await {
+      for n in list {
+         // Запрос уже удалён, поэтому доставка через сохраняемую очередь
+         let lang = lang::lang_of(n.user_id).await;
+         let s = lang::tf(lang, NotifyReady, &[&n.title, &db::make_key_3_int(n.rest_num, n.group_num, n.dish_num)]);
+         outbox::send_critical(&bot, i64::from(n.user_id), &s, None).await;
+      }
+   }
+}
+
+// Периодически проверяет запросы, цель которых открылась по расписанию
+pub async fn notify_loop(bot: Arc<Bot>) {
+   let mut interval = tokio::time::interval(Duration::from_secs(60));
+   loop {
+      interval.tick().await;
+      if shutdown::is_requested() {
+         return;
+      }
+      let _work = shutdown::work();
+
+      fire(Arc::clone(&bot)).await;
+      db::notify_expire(settings::current_date_time() - chrono::Duration::days(EXPIRE_DAYS)).await;
+   }
+}
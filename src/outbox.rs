@@ -0,0 +1,239 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Очередь исходящих сообщений с учётом ограничений Telegram. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+use teloxide::{
+   prelude::*,
+   requests::Request,
+   types::ChatId,
+   RequestError,
+};
+use reqwest::StatusCode;
+use once_cell::sync::Lazy;
+use std::{collections::{HashMap, VecDeque}, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+use crate::basket;
+use crate::database as db;
+use crate::metrics;
+use crate::settings;
+use crate::shutdown;
+
+// Не более 30 сообщений в секунду на всех
+const GLOBAL_PER_SECOND: usize = 30;
+
+// В личный чат не чаще раза в секунду, в группу не более 20 в минуту
+const PRIVATE_INTERVAL: Duration = Duration::from_millis(1000);
+const GROUP_INTERVAL: Duration = Duration::from_millis(3000);
+
+// Попытки отправки при перегрузке или сетевой ошибке
+const MAX_ATTEMPTS: u32 = 5;
+
+// Пауза при перегрузке, если Telegram не сообщил её длительность. teloxide 0.2 ищет retry_after
+// в поле response_parameters вместо parameters, поэтому ответ 429 приходит как ApiError без времени
+const FLOOD_WAIT: Duration = Duration::from_secs(3);
+
+// Сколько раз пытаться доставить сохранённое важное сообщение, примерно сутки при повторе раз в минуту
+const MAX_PERSISTED_ATTEMPTS: i32 = 1440;
+
+// Время последних отправок и моменты, раньше которых нельзя писать в чат
+struct Limiter {
+   global: VecDeque<Instant>,
+   chats: HashMap<i64, Instant>,
+}
+
+static LIMITER: Lazy<Mutex<Limiter>> = Lazy::new(|| Mutex::new(Limiter {
+   global: VecDeque::with_capacity(GLOBAL_PER_SECOND),
+   chats: HashMap::new(),
+}));
+
+// Идентификатор чата для учёта ограничений, каналы по имени учитываются вместе
+pub fn chat_key(chat_id: &ChatId) -> i64 {
+   match chat_id {
+      ChatId::Id(id) => *id,
+      ChatId::ChannelUsername(_) => 0,
+   }
+}
+
+// Резервирует место для отправки или возвращает, сколько ещё ждать
+fn try_reserve(chat_id: i64) -> Option<Duration> {
+   let mut limiter = LIMITER.lock().unwrap();
+   let now = Instant::now();
+
+   // Забудем отправки старше секунды
+   while let Some(t) = limiter.global.front() {
+      if now.duration_since(*t) < Duration::from_secs(1) {
+         break;
+      }
+      limiter.global.pop_front();
+   }
+
+   let global_wait = if limiter.global.len() >= GLOBAL_PER_SECOND {
+      limiter.global.front().map(|t| Duration::from_secs(1) - now.duration_since(*t))
+   } else {None};
+
+   let chat_wait = limiter.chats.get(&chat_id).and_then(|t| t.checked_duration_since(now));
+
+   match global_wait.into_iter().chain(chat_wait).max() {
+      Some(wait) if wait > Duration::from_millis(0) => Some(wait),
+      _ => {
+         let interval = if chat_id < 0 {GROUP_INTERVAL} else {PRIVATE_INTERVAL};
+         limiter.global.push_back(now);
+         limiter.chats.insert(chat_id, now + interval);
+
+         // Не даём таблице чатов расти бесконечно
+         if limiter.chats.len() > 10000 {
+            limiter.chats.retain(|_, t| *t > now);
+         }
+         None
+      }
+   }
+}
+
+// Ответ Telegram о превышении частоты запросов, повтор позже поможет
+fn is_flood(e: &RequestError) -> bool {
+   matches!(e, RequestError::ApiError {status_code, ..} if *status_code == StatusCode::TOO_MANY_REQUESTS)
+}
+
+// Запрещает писать в чат указанное время, по требованию Telegram
+fn hold_chat(chat_id: i64, wait: Duration) {
+   let mut limiter = LIMITER.lock().unwrap();
+   limiter.chats.insert(chat_id, Instant::now() + wait);
+}
+
+// Отправляет запрос в свою очередь, повторяя его после retry_after и сетевых ошибок
+pub async fn send<R>(chat_id: &ChatId, request: &R) -> ResponseResult<R::Output>
+where
+   R: Request + Sync,
+{
+   let key = chat_key(chat_id);
+   let mut attempt = 1;
+   loop {
+      // Ждём своей очереди
+      while let Some(wait) = try_reserve(key) {
+         tokio::time::delay_for(wait).await;
+      }
+
+      match request.send().await {
+         Err(RequestError::RetryAfter(secs)) if attempt < MAX_ATTEMPTS => {
+            metrics::telegram_error("retry_after");
+            log::warn!("Telegram asks to retry after {} s for chat {}", secs, key);
+            hold_chat(key, Duration::from_secs(secs.max(1) as u64));
+         }
+         Err(e) if is_flood(&e) && attempt < MAX_ATTEMPTS => {
+            metrics::telegram_error("retry_after");
+            log::warn!("Telegram asks to slow down for chat {}", key);
+            hold_chat(key, FLOOD_WAIT * attempt);
+         }
+         Err(RequestError::NetworkError(e)) if attempt < MAX_ATTEMPTS => {
+            metrics::telegram_error("network");
+            log::warn!("Network error for chat {}, attempt {}: {}", key, attempt, e);
+            tokio::time::delay_for(Duration::from_secs(1 << attempt)).await;
+         }
+         res => return res,
+      }
+      attempt += 1;
+   }
+}
+
+// Одна попытка доставить важное сообщение. Новый заказ ресторатору доставляется по шагам
+async fn deliver(bot: &Arc<Bot>, msg: &db::OutboxMessage) -> ResponseResult<()> {
+   if let Some(ticket_id) = msg.ticket_id {
+      return basket::deliver_order(bot, msg, ticket_id).await;
+   }
+
+   let chat = ChatId::Id(msg.chat_id);
+   let request = bot.send_message(chat.clone(), msg.text.as_str());
+   let request = match msg.reply_to {
+      Some(message_id) => request.reply_to_message_id(message_id),
+      None => request,
+   };
+   send(&chat, &request).await.map(|_| ())
+}
+
+// Сохраняет сообщение в БД и пытается его доставить, при неудаче оно будет отправлено позже
+async fn persist_and_deliver(bot: &Arc<Bot>, msg: db::OutboxMessage) -> bool {
+   let msg_id = db::outbox_add(&msg).await;
+   let msg = db::OutboxMessage {msg_id: msg_id.unwrap_or_default(), ..msg};
+
+   match deliver(bot, &msg).await {
+      Ok(_) => {
+         if let Some(msg_id) = msg_id {
+            db::outbox_remove(msg_id).await;
+         }
+         true
+      }
+      Err(e) => {
+         metrics::telegram_error("critical");
+         log::warn!("Critical message to {} is postponed: {}", msg.chat_id, e);
+
+         // Отказ Telegram, например, бот заблокирован, повтор не поможет
+         if let (RequestError::ApiError {..}, Some(msg_id), false) = (&e, msg_id, is_flood(&e)) {
+            db::outbox_remove(msg_id).await;
+         }
+         false
+      }
+   }
+}
+
+// Отправляет важное сообщение, например, напоминание ресторатору о заказе.
+// Сообщение сохраняется в БД до подтверждения доставки и при неудаче будет отправлено позже
+pub async fn send_critical(bot: &Arc<Bot>, chat_id: i64, text: &str, reply_to: Option<i32>) -> bool {
+   persist_and_deliver(bot, db::OutboxMessage {
+      chat_id,
+      text: text.to_string(),
+      reply_to,
+      ..Default::default()
+   }).await
+}
+
+// Отправляет ресторатору уже созданный заказ: текст с контактами едока, точку на карте,
+// пересылку сообщения с заказом из чата едока, если она нужна, и сообщение со статусом
+pub async fn send_order(bot: &Arc<Bot>, ticket: &db::Ticket, text: String, forward: Option<(i64, i32)>, location: Option<(f64, f64)>) -> bool {
+   persist_and_deliver(bot, db::OutboxMessage {
+      chat_id: i64::from(ticket.caterer_id),
+      text,
+      ticket_id: Some(ticket.ticket_id),
+      forward,
+      location,
+      ..Default::default()
+   }).await
+}
+
+// Периодически досылает сохранённые важные сообщения
+pub async fn resend_loop(bot: Arc<Bot>) {
+   let mut interval = tokio::time::interval(settings::resend_interval());
+   loop {
+      interval.tick().await;
+      if shutdown::is_requested() {
+         return;
+      }
+      let _work = shutdown::work();
+
+      if let Some(messages) = db::outbox_list().await {
+         for msg in messages {
+            match deliver(&bot, &msg).await {
+               Ok(_) => {
+                  db::outbox_remove(msg.msg_id).await;
+               }
+               Err(RequestError::ApiError {kind, status_code}) if status_code != StatusCode::TOO_MANY_REQUESTS => {
+                  log::warn!("Critical message {} to {} dropped: {:?}", msg.msg_id, msg.chat_id, kind);
+                  db::outbox_remove(msg.msg_id).await;
+               }
+               Err(e) => {
+                  if msg.attempts + 1 >= MAX_PERSISTED_ATTEMPTS {
+                     log::warn!("Critical message {} to {} dropped after {} attempts: {}", msg.msg_id, msg.chat_id, msg.attempts + 1, e);
+                     db::outbox_remove(msg.msg_id).await;
+                  } else {
+                     db::outbox_attempt(msg.msg_id).await;
+                  }
+               }
+            }
+         }
+      }
+   }
+}
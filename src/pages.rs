@@ -0,0 +1,86 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Разбиение длинных списков на страницы. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+
+use crate::language::{self as lang, Res::*};
+use crate::settings;
+
+// Одна страница списка
+pub struct Page<T> {
+   pub items: Vec<T>,
+   pub num: i32,   // номер страницы, начиная с 1
+   pub count: i32, // всего страниц, не меньше одной
+}
+
+impl<T> Page<T> {
+   // Страница с указанным номером, номер за пределами списка заменяется ближайшим
+   pub fn new(list: Vec<T>, size: usize, num: i32) -> Self {
+      let size = size.max(1);
+      let count = list.len().div_ceil(size).max(1) as i32;
+      let num = num.max(1).min(count);
+      let items = list.into_iter().skip((num - 1) as usize * size).take(size).collect();
+      Page {items, num, count}
+   }
+
+   // Страница для режима со ссылками
+   pub fn text(list: Vec<T>, num: i32) -> Self {
+      Self::new(list, settings::page_size(), num)
+   }
+
+   // Страница для режима с инлайн-кнопками
+   pub fn buttons(list: Vec<T>, num: i32) -> Self {
+      Self::new(list, settings::page_buttons(), num)
+   }
+
+   // Строка вида "Страница 2 из 5: ◀ /page1 /page3 ▶", для единственной страницы пустая
+   pub fn links(&self, lang: &str, command: &str) -> String {
+      if self.count == 1 {
+         return String::default();
+      }
+
+      let prev = if self.num > 1 {format!(" ◀ {}{}", command, self.num - 1)} else {String::default()};
+      let next = if self.num < self.count {format!(" {}{} ▶", command, self.num + 1)} else {String::default()};
+      format!("{}{}{}\n", lang::tf(lang, PageOf, &[&self.num, &self.count]), prev, next)
+   }
+
+   // Добавляет ряд кнопок на соседние страницы, data формирует колбек для номера страницы
+   pub fn append_nav(&self, markup: InlineKeyboardMarkup, data: impl Fn(i32) -> String) -> InlineKeyboardMarkup {
+      let mut buttons = Vec::new();
+      if self.num > 1 {
+         buttons.push(InlineKeyboardButton::callback(format!("◀ {}/{}", self.num - 1, self.count), data(self.num - 1)));
+      }
+      if self.num < self.count {
+         buttons.push(InlineKeyboardButton::callback(format!("{}/{} ▶", self.num + 1, self.count), data(self.num + 1)));
+      }
+
+      if buttons.is_empty() {markup} else {markup.append_row(buttons)}
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   #[test]
+   fn splits_into_pages() {
+      let page = Page::new((1..=7).collect(), 3, 2);
+      assert_eq!((page.items, page.num, page.count), (vec![4, 5, 6], 2, 3));
+
+      // Номер за пределами списка
+      let page = Page::new((1..=7).collect(), 3, 10);
+      assert_eq!((page.items, page.num), (vec![7], 3));
+      let page = Page::new((1..=7).collect(), 3, 0);
+      assert_eq!((page.items, page.num), (vec![1, 2, 3], 1));
+
+      // Пустой список это одна пустая страница
+      let page = Page::<i32>::new(Vec::new(), 3, 1);
+      assert_eq!((page.items.len(), page.num, page.count), (0, 1, 1));
+   }
+}
@@ -0,0 +1,180 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Оплата заказов через Telegram Payments. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+use teloxide::{
+   prelude::*,
+   types::{LabeledPrice, PreCheckoutQuery, SuccessfulPayment, },
+};
+use std::sync::Arc;
+
+use crate::database as db;
+use crate::basket;
+use crate::settings;
+use crate::metrics;
+use crate::outbox;
+use crate::language::{self as lang, Res::*};
+
+// Полезная нагрузка счёта, по которой затем находим заказ
+fn make_payload(ticket_id: i32) -> String {
+   format!("ticket{}", ticket_id)
+}
+
+fn parse_payload(payload: &str) -> Option<i32> {
+   payload.strip_prefix("ticket")?.parse().ok()
+}
+
+// Выставляет едоку счёт на оплату заказа
+pub async fn send_invoice(bot: &Arc<Bot>, ticket: &db::Ticket) -> bool {
+   let lang = lang::lang_of(ticket.eater_id).await;
+
+   // Токен платёжного провайдера ресторана
+   let (token, rest_title) = match db::restaurant(db::RestBy::Id(ticket.caterer_id)).await {
+      Some(db::Restaurant {payment_token: Some(token), title, ..}) => (token, title),
+      _ => {
+         settings::log(&format!("Error payment::send_invoice({}): no payment token", ticket.ticket_id)).await;
+         return false;
+      }
+   };
+
+   // Позиции счёта из сохранённого состава заказа
+   let mut prices: Vec<LabeledPrice> = db::ticket_items(ticket.ticket_id, ticket.eater_id).await
+   .unwrap_or_default()
+   .into_iter()
+   .map(|item| LabeledPrice {
+      label: format!("{} x {}", item.title, item.amount),
+      amount: settings::payment_amount(item.price * item.amount),
+   })
+   .collect();
+
+   // Скидка по промокоду отдельной строкой
+   if let Some(code) = &ticket.promo_code {
+      prices.push(LabeledPrice {
+         label: lang::tf(lang, InvoicePromo, &[code]),
+         amount: -settings::payment_amount(ticket.discount),
+      });
+   }
+
+   // Бесплатный заказ оплачивать не нужно
+   if prices.iter().map(|price| price.amount).sum::<i32>() <= 0 {
+      if db::ticket_set_paid(ticket.ticket_id, "free").await {
+         if let Some(ticket) = db::ticket(db::TicketBy::TicketId(ticket.ticket_id)).await {
+            basket::update_status_messages(bot, &ticket).await;
+         }
+         return true;
+      }
+      return false;
+   }
+
+   let title = lang::tf(lang, InvoiceTitle, &[&ticket.ticket_id]);
+   let description: String = lang::tf(lang, InvoiceDescription, &[&rest_title]).chars().take(255).collect();
+
+   let res = bot.send_invoice(ticket.eater_id, title, description, make_payload(ticket.ticket_id), token,
+      format!("pay{}", ticket.ticket_id), settings::payment_currency(), prices)
+   .reply_to_message_id(ticket.eater_order_msg_id)
+   .send()
+   .await;
+
+   match res {
+      Ok(_) => true,
+      Err(e) => {
+         metrics::telegram_error("send_invoice");
+         settings::log(&format!("Error payment::send_invoice({}): {}", ticket.ticket_id, e)).await;
+         false
+      }
+   }
+}
+
+// Повторно выставляет счёт, если едок его потерял или прежний не удалось отправить
+pub async fn resend_invoice(bot: &Arc<Bot>, user_id: i32, ticket_id: i32) -> bool {
+   match db::ticket(db::TicketBy::TicketId(ticket_id)).await {
+      Some(ticket) if ticket.eater_id == user_id && ticket.stage == 1 && ticket.is_awaiting_payment() => send_invoice(bot, &ticket).await,
+      _ => false,
+   }
+}
+
+// Проверяет, можно ли принять оплату, иначе возвращает причину отказа
+async fn check_pre_checkout(query: &PreCheckoutQuery) -> Result<(), lang::Res> {
+   let ticket_id = parse_payload(&query.invoice_payload).ok_or(PayUnknownInvoice)?;
+
+   let ticket = db::ticket(db::TicketBy::TicketId(ticket_id)).await
+   .ok_or(PayTicketNotFound)?;
+
+   if ticket.eater_id != query.from.id {
+      return Err(PayNotYours);
+   }
+
+   if ticket.stage != 1 || !ticket.is_awaiting_payment() {
+      return Err(PayAlreadyPaid);
+   }
+
+   if format!("{:?}", query.currency) != settings::payment_currency() {
+      return Err(PayWrongCurrency);
+   }
+
+   let total = db::ticket_total(ticket_id).await.unwrap_or_default();
+   if query.total_amount != settings::payment_amount(total) {
+      return Err(PayTotalChanged);
+   }
+
+   Ok(())
+}
+
+// Обработчик запросов на подтверждение оплаты
+pub async fn handle_pre_checkout_query(cx: DispatcherHandlerCx<PreCheckoutQuery>) {
+   let query = &cx.update;
+
+   let res = match check_pre_checkout(query).await {
+      Ok(_) => cx.bot.answer_pre_checkout_query(&query.id, true).send().await,
+      Err(reason) => {
+         settings::log(&format!("Отказ в оплате {} от {}: {}", query.invoice_payload, query.from.id, lang::t(lang::DEFAULT, reason))).await;
+         let lang = lang::user_lang(Some(&query.from)).await;
+         cx.bot.answer_pre_checkout_query(&query.id, false).error_message(lang::t(lang, reason)).send().await
+      }
+   };
+
+   if let Err(e) = res {
+      settings::log(&format!("Error handle_pre_checkout_query: {}", e)).await;
+   }
+}
+
+// Обработка сообщения об успешной оплате
+pub async fn successful_payment(bot: &Arc<Bot>, payment: &SuccessfulPayment) {
+   let ticket_id = match parse_payload(&payment.invoice_payload) {
+      Some(ticket_id) => ticket_id,
+      None => {
+         settings::log(&format!("Error successful_payment: unknown payload {}", payment.invoice_payload)).await;
+         return;
+      }
+   };
+
+   // Сохраним отметку об оплате
+   let res = db::ticket_set_paid(ticket_id, &payment.telegram_payment_charge_id).await;
+   settings::log(&format!("Оплата заказа №{} на {} {:?}, {}: {}", ticket_id, payment.total_amount, payment.currency, payment.telegram_payment_charge_id, db::is_success(lang::DEFAULT, res))).await;
+
+   if let Some(ticket) = db::ticket(db::TicketBy::TicketId(ticket_id)).await {
+      // Заказ успели отменить, пока шла оплата - деньги нужно вернуть вручную
+      if ticket.stage > 4 {
+         settings::log_and_notify(&lang::tf(lang::DEFAULT, PaidAfterCancelLog, &[&ticket_id, &payment.telegram_payment_charge_id])).await;
+         let s = lang::tf(lang::lang_of(ticket.eater_id).await, PaidAfterCancel, &[&ticket_id, &settings::admin_contact_info()]);
+         if !outbox::send_critical(bot, i64::from(ticket.eater_id), &s, Some(ticket.eater_order_msg_id)).await {
+            settings::log(&format!("Error successful_payment({}): refund notification postponed", ticket_id)).await;
+         }
+         return;
+      }
+
+      // Обновим статусы у обеих сторон
+      basket::update_status_messages(bot, &ticket).await;
+
+      // Уведомим ресторатора, что можно приступать
+      let s = lang::tf(lang::lang_of(ticket.caterer_id).await, PaidNotify, &[&ticket_id]);
+      if !outbox::send_critical(bot, i64::from(ticket.caterer_id), &s, ticket.caterer_reply_to()).await {
+         settings::log(&format!("Error successful_payment({}): notification postponed", ticket_id)).await;
+      }
+   }
+}
@@ -0,0 +1,310 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Реестр команд по меню и справка /help. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+use teloxide::types::User;
+
+use crate::commands as cmd;
+use crate::database as db;
+use crate::settings;
+use crate::language::{self as lang, Res::{self, *}};
+
+// Меню, в котором действует команда
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Menu {
+   Common,
+   User,
+   Caterer,
+   CatGroup,
+   CatDish,
+   EaterRest,
+   EaterGroup,
+   EaterDish,
+   Basket,
+   Gear,
+   Chat, // групповой чат
+}
+
+impl Menu {
+   // Меню для состояния диалога, в режимах ввода своих команд нет
+   pub fn of(dialogue: &cmd::Dialogue) -> Option<Menu> {
+      match dialogue {
+         cmd::Dialogue::Start | cmd::Dialogue::UserMode => Some(Menu::User),
+         cmd::Dialogue::CatererMode(..) => Some(Menu::Caterer),
+         cmd::Dialogue::CatEditGroup(..) => Some(Menu::CatGroup),
+         cmd::Dialogue::CatEditDish(..) => Some(Menu::CatDish),
+         cmd::Dialogue::EatRestSelectionMode(..) | cmd::Dialogue::EatRestNowSelectionMode => Some(Menu::EaterRest),
+         cmd::Dialogue::EatRestGroupSelectionMode(..) | cmd::Dialogue::EatRestGroupNowSelectionMode(..) => Some(Menu::EaterGroup),
+         cmd::Dialogue::EatRestGroupDishSelectionMode(..) => Some(Menu::EaterDish),
+         cmd::Dialogue::BasketMode(..) => Some(Menu::Basket),
+         cmd::Dialogue::GearMode => Some(Menu::Gear),
+         _ => None,
+      }
+   }
+}
+
+// Кому доступна команда, роли упорядочены по возрастанию прав
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
+pub enum Role {
+   Anyone,
+   Caterer,
+   Admin,
+}
+
+impl Role {
+   // Роль пользователя
+   pub async fn of(user: Option<&User>) -> Role {
+      if settings::is_admin(user) {Role::Admin}
+      else if db::rest_num(user).await.is_ok() {Role::Caterer}
+      else {Role::Anyone}
+   }
+}
+
+// Формат аргументов команды
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Args {
+   None,    // /Toggle
+   Tail,    // /chat@имя_бота, остаток строки игнорируется
+   Num,     // /rest12
+   Key3,    // /goto1_2_3
+   OptKey3, // /start или /start 1_2_3
+   OptNum,  // /list или /list2
+   NumText, // /paym1 ТОКЕН, текст необязателен
+   Text,    // /unpromo КОД
+   Lang,    // /lang_en
+}
+
+// Разобранные аргументы
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+   None,
+   Num(i32),
+   Key3(i32, i32, i32),
+   NumText(i32, Option<String>),
+   Text(String),
+   Lang(&'static str),
+}
+
+// Описание команды
+pub struct Command {
+   pub menu: Menu,
+   pub name: &'static str,
+   pub args: Args,
+   pub role: Role,
+   pub help: Res,
+}
+
+// Ошибка разбора
+#[derive(Copy, Clone)]
+pub enum Error {
+   Unknown,
+   WrongArgs(&'static Command),
+}
+
+const fn command(menu: Menu, name: &'static str, args: Args, role: Role, help: Res) -> Command {
+   Command {menu, name, args, role, help}
+}
+
+// Все команды со слешем. Кнопки меню сюда не входят, они распознаются через lang::button
+pub static COMMANDS: &[Command] = &[
+   // Общие команды, действуют в любом меню
+   command(Menu::Common, "/start", Args::OptKey3, Role::Anyone, HelpStart),
+   command(Menu::Common, "/help", Args::None, Role::Anyone, HelpHelp),
+   command(Menu::Common, "/goto", Args::Key3, Role::Anyone, HelpGoto),
+   command(Menu::Common, "/snd", Args::Num, Role::Anyone, HelpSendMessage),
+   command(Menu::Common, "/rvl", Args::Num, Role::Anyone, HelpReviews),
+   command(Menu::Common, "/rvc", Args::Num, Role::Anyone, HelpReviewComment),
+   command(Menu::Common, "/rvr", Args::Num, Role::Caterer, HelpReviewReply),
+   command(Menu::Common, "/rvh", Args::Num, Role::Admin, HelpReviewHide),
+   command(Menu::Common, "/unsub", Args::Num, Role::Anyone, HelpUnsubscribe),
+   command(Menu::Common, "/ntf", Args::Key3, Role::Anyone, HelpNotify),
+
+   // Главное меню
+   command(Menu::User, "/chat", Args::Tail, Role::Anyone, HelpChatId),
+   command(Menu::User, "/favs", Args::OptNum, Role::Anyone, HelpFavorites),
+
+   // Групповой чат
+   command(Menu::Chat, "/chat", Args::Tail, Role::Anyone, HelpChatId),
+   command(Menu::Chat, "/lunch", Args::OptNum, Role::Anyone, HelpLunch),
+
+   // Ресторатор, заведение
+   command(Menu::Caterer, "/EditTitle", Args::None, Role::Caterer, HelpRestTitle),
+   command(Menu::Caterer, "/EditInfo", Args::None, Role::Caterer, HelpRestInfo),
+   command(Menu::Caterer, "/Toggle", Args::None, Role::Caterer, HelpRestToggle),
+   command(Menu::Caterer, "/EditImg", Args::None, Role::Caterer, HelpRestImage),
+   command(Menu::Caterer, "/AddGroup", Args::None, Role::Caterer, HelpAddGroup),
+   command(Menu::Caterer, "/Promote", Args::None, Role::Caterer, HelpRestPromote),
+   command(Menu::Caterer, "/Broadcast", Args::None, Role::Caterer, HelpBroadcast),
+   command(Menu::Caterer, "/EdGr", Args::Num, Role::Caterer, HelpEditGroup),
+   command(Menu::Caterer, "/move", Args::Num, Role::Caterer, HelpTransfer),
+
+   // Ресторатор, группа
+   command(Menu::CatGroup, "/EditTitle", Args::None, Role::Caterer, HelpGroupTitle),
+   command(Menu::CatGroup, "/EditInfo", Args::None, Role::Caterer, HelpGroupInfo),
+   command(Menu::CatGroup, "/Toggle", Args::None, Role::Caterer, HelpGroupToggle),
+   command(Menu::CatGroup, "/EditCat", Args::None, Role::Caterer, HelpGroupCategory),
+   command(Menu::CatGroup, "/EditTime", Args::None, Role::Caterer, HelpGroupTime),
+   command(Menu::CatGroup, "/Remove", Args::None, Role::Caterer, HelpGroupRemove),
+   command(Menu::CatGroup, "/AddDish", Args::None, Role::Caterer, HelpAddDish),
+   command(Menu::CatGroup, "/Promote", Args::None, Role::Caterer, HelpGroupPromote),
+   command(Menu::CatGroup, "/EdDi", Args::Num, Role::Caterer, HelpEditDish),
+
+   // Ресторатор, блюдо
+   command(Menu::CatDish, "/EditTitle", Args::None, Role::Caterer, HelpDishTitle),
+   command(Menu::CatDish, "/EditInfo", Args::None, Role::Caterer, HelpDishInfo),
+   command(Menu::CatDish, "/Toggle", Args::None, Role::Caterer, HelpDishToggle),
+   command(Menu::CatDish, "/EditGroup", Args::None, Role::Caterer, HelpDishGroup),
+   command(Menu::CatDish, "/EditPrice", Args::None, Role::Caterer, HelpDishPrice),
+   command(Menu::CatDish, "/EditImg", Args::None, Role::Caterer, HelpDishImage),
+   command(Menu::CatDish, "/Remove", Args::None, Role::Caterer, HelpDishRemove),
+   command(Menu::CatDish, "/Promote", Args::None, Role::Caterer, HelpDishPromote),
+
+   // Едок, выбор заведения, группы и блюда
+   command(Menu::EaterRest, "/rest", Args::Num, Role::Anyone, HelpRestaurant),
+   command(Menu::EaterRest, "/page", Args::Num, Role::Anyone, HelpPage),
+   command(Menu::EaterGroup, "/grou", Args::Num, Role::Anyone, HelpGroup),
+   command(Menu::EaterGroup, "/fav", Args::None, Role::Anyone, HelpFavorite),
+   command(Menu::EaterGroup, "/sub", Args::None, Role::Anyone, HelpSubscribe),
+   command(Menu::EaterGroup, "/page", Args::Num, Role::Anyone, HelpPage),
+   command(Menu::EaterDish, "/dish", Args::Num, Role::Anyone, HelpDish),
+   command(Menu::EaterDish, "/page", Args::Num, Role::Anyone, HelpPage),
+
+   // Корзина
+   command(Menu::Basket, "/edit_name", Args::None, Role::Anyone, HelpBasketName),
+   command(Menu::Basket, "/edit_contact", Args::None, Role::Anyone, HelpBasketContact),
+   command(Menu::Basket, "/edit_address", Args::None, Role::Anyone, HelpBasketAddress),
+   command(Menu::Basket, "/addr", Args::Num, Role::Anyone, HelpBasketSetAddress),
+   command(Menu::Basket, "/deladdr", Args::Num, Role::Anyone, HelpBasketRemoveAddress),
+   command(Menu::Basket, "/toggle", Args::None, Role::Anyone, HelpBasketPickup),
+   command(Menu::Basket, "/edit_time", Args::None, Role::Anyone, HelpBasketTime),
+   command(Menu::Basket, "/edit_promo", Args::None, Role::Anyone, HelpBasketPromo),
+   command(Menu::Basket, "/del", Args::Key3, Role::Anyone, HelpBasketDelete),
+   command(Menu::Basket, "/rep", Args::Num, Role::Anyone, HelpBasketRepeat),
+
+   // Настройки
+   command(Menu::Gear, "/toggle", Args::None, Role::Anyone, HelpInterface),
+   command(Menu::Gear, "/lang", Args::None, Role::Anyone, HelpLanguages),
+   command(Menu::Gear, "/lang_", Args::Lang, Role::Anyone, HelpSetLanguage),
+   command(Menu::Gear, "/list", Args::OptNum, Role::Admin, HelpRestList),
+   command(Menu::Gear, "/rests", Args::OptNum, Role::Admin, HelpSudoList),
+   command(Menu::Gear, "/regi", Args::Num, Role::Admin, HelpRegister),
+   command(Menu::Gear, "/hold", Args::Num, Role::Admin, HelpHold),
+   command(Menu::Gear, "/sudo", Args::Num, Role::Admin, HelpSudo),
+   command(Menu::Gear, "/paym", Args::NumText, Role::Admin, HelpPayment),
+   command(Menu::Gear, "/bcast", Args::NumText, Role::Admin, HelpBroadcastHours),
+   command(Menu::Gear, "/tz", Args::NumText, Role::Admin, HelpTimeZone),
+   command(Menu::Gear, "/promos", Args::None, Role::Admin, HelpPromoList),
+   command(Menu::Gear, "/promo", Args::Text, Role::Admin, HelpPromoSave),
+   command(Menu::Gear, "/unpromo", Args::Text, Role::Admin, HelpPromoRemove),
+];
+
+// Разбор аргументов, стоящих после имени команды
+fn parse_args(args: Args, rest: &str) -> Option<Value> {
+   // Текст отделяется от команды пробелом
+   let separated = rest.starts_with(char::is_whitespace);
+
+   match args {
+      Args::None => if rest.is_empty() {Some(Value::None)} else {None},
+      Args::Tail => Some(Value::None),
+      Args::Num => rest.parse().ok().map(Value::Num),
+      Args::Key3 => db::parse_key_3_int(rest).ok().map(|(a, b, c)| Value::Key3(a, b, c)),
+      Args::OptKey3 => {
+         if rest.is_empty() {Some(Value::None)}
+         else if separated {db::parse_key_3_int(rest.trim()).ok().map(|(a, b, c)| Value::Key3(a, b, c))}
+         else {None}
+      }
+      Args::OptNum => if rest.is_empty() {Some(Value::None)} else {rest.parse().ok().map(Value::Num)},
+      Args::NumText => {
+         let mut parts = rest.splitn(2, char::is_whitespace);
+         let num = parts.next().unwrap_or_default().parse().ok()?;
+         let text = parts.next().map(str::trim).filter(|s| !s.is_empty()).map(String::from);
+         Some(Value::NumText(num, text))
+      }
+      Args::Text => if separated && !rest.trim().is_empty() {Some(Value::Text(String::from(rest.trim())))} else {None},
+      Args::Lang => lang::supported(rest).map(Value::Lang),
+   }
+}
+
+// Разбирает команду меню. Из подходящих имён выбирается самое длинное, чтобы /promos не принималась за /promo
+pub fn parse(menu: Menu, input: &str) -> Result<(&'static str, Value), Error> {
+   let command = COMMANDS.iter()
+   .filter(|c| c.menu == menu && input.starts_with(c.name))
+   .max_by_key(|c| c.name.len())
+   .ok_or(Error::Unknown)?;
+
+   match parse_args(command.args, &input[command.name.len()..]) {
+      Some(value) => Ok((command.name, value)),
+      None => Err(Error::WrongArgs(command)),
+   }
+}
+
+// Команда меню или общая, которой переданы неверные аргументы
+pub fn wrong_args(menu: Option<Menu>, input: &str) -> Option<&'static Command> {
+   menu.into_iter().chain(Some(Menu::Common))
+   .find_map(|m| match parse(m, input) {
+      Err(Error::WrongArgs(command)) => Some(command),
+      _ => None,
+   })
+}
+
+// Образец вызова, например /rest<N>
+pub fn usage(command: &Command) -> String {
+   let args = match command.args {
+      Args::None | Args::Tail => "",
+      Args::Num => "<N>",
+      Args::Key3 => "<N_N_N>",
+      Args::OptKey3 => " [N_N_N]",
+      Args::OptNum => "[N]",
+      Args::NumText => "<N> [...]",
+      Args::Text => " <...>",
+      Args::Lang => "<xx>",
+   };
+   format!("{}{}", command.name, args)
+}
+
+// Список команд, доступных в меню для указанной роли, вместе с общими
+pub fn help(lang: &str, menu: Option<Menu>, role: Role) -> String {
+   let menus: Vec<Menu> = menu.into_iter().chain(Some(Menu::Common)).collect();
+   menus.iter()
+   .flat_map(|m| COMMANDS.iter().filter(move |c| c.menu == *m))
+   .filter(|c| c.role <= role)
+   .map(|c| format!("{} — {}\n", usage(c), lang::t(lang, c.help)))
+   .collect()
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   #[test]
+   fn parses_arguments() {
+      assert!(matches!(parse(Menu::EaterRest, "/rest12"), Ok(("/rest", Value::Num(12)))));
+      assert!(matches!(parse(Menu::EaterRest, "/restabc"), Err(Error::WrongArgs(c)) if c.name == "/rest"));
+      assert!(matches!(parse(Menu::EaterRest, "/dish1"), Err(Error::Unknown)));
+      assert!(matches!(parse(Menu::Common, "/start"), Ok(("/start", Value::None))));
+      assert!(matches!(parse(Menu::Common, "/start 1_2_3"), Ok(("/start", Value::Key3(1, 2, 3)))));
+      assert!(matches!(parse(Menu::Common, "/startx"), Err(Error::WrongArgs(_))));
+      assert!(matches!(parse(Menu::Gear, "/list"), Ok(("/list", Value::None))));
+      assert!(matches!(parse(Menu::Gear, "/list2"), Ok(("/list", Value::Num(2)))));
+      assert!(matches!(parse(Menu::Chat, "/lunch"), Ok(("/lunch", Value::None))));
+      assert!(matches!(parse(Menu::Chat, "/lunch12"), Ok(("/lunch", Value::Num(12)))));
+      assert!(matches!(parse(Menu::Gear, "/paym2"), Ok(("/paym", Value::NumText(2, None)))));
+      assert_eq!(parse(Menu::Gear, "/paym2 abc").ok().map(|(_, v)| v), Some(Value::NumText(2, Some(String::from("abc")))));
+   }
+
+   #[test]
+   fn longest_name_wins() {
+      assert!(matches!(parse(Menu::Gear, "/promos"), Ok(("/promos", Value::None))));
+      assert!(matches!(parse(Menu::Gear, "/promo A 10%"), Ok(("/promo", Value::Text(_)))));
+      assert!(matches!(parse(Menu::Gear, "/lang"), Ok(("/lang", Value::None))));
+      assert!(matches!(parse(Menu::Gear, "/lang_en"), Ok(("/lang_", Value::Lang("en")))));
+      assert!(matches!(parse(Menu::Gear, "/lang_xx"), Err(Error::WrongArgs(c)) if c.name == "/lang_"));
+      assert!(matches!(parse(Menu::Basket, "/deladdr3"), Ok(("/deladdr", Value::Num(3)))));
+      assert!(matches!(parse(Menu::Basket, "/del1_2_3"), Ok(("/del", Value::Key3(1, 2, 3)))));
+   }
+}
@@ -0,0 +1,196 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Оценки и отзывы о выполненных заказах. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+use teloxide::{
+   prelude::*,
+   types::{CallbackQuery, ChatId, },
+};
+use std::sync::Arc;
+
+use crate::commands as cmd;
+use crate::database as db;
+use crate::callback;
+use crate::settings;
+use crate::outbox;
+use crate::language::{self as lang, Res::*};
+
+// Предлагает едоку оценить завершённый заказ
+pub async fn ask_rating(bot: &Arc<Bot>, ticket: &db::Ticket) {
+   let lang = lang::lang_of(ticket.eater_id).await;
+   let res = bot.send_message(ChatId::Id(i64::from(ticket.eater_id)), lang::t(lang, ReviewAsk))
+   .reply_to_message_id(ticket.eater_order_msg_id)
+   .reply_markup(cmd::Basket::inline_markup_rate(ticket.ticket_id))
+   .send()
+   .await;
+
+   if let Err(e) = res {
+      settings::log(&format!("Error review::ask_rating({}): {}", ticket.ticket_id, e)).await;
+   }
+}
+
+// Сохраняет оценку по нажатию на кнопку
+pub async fn rate(cx: &DispatcherHandlerCx<CallbackQuery>, user_id: i32, ticket_id: i32, rating: i32) -> bool {
+   let lang = lang::user_lang(Some(&cx.update.from)).await;
+
+   // Оценка должна быть от 1 до 5
+   if !(1..=5).contains(&rating) {
+      return false;
+   }
+
+   match db::review_rate(ticket_id, user_id, rating).await {
+      Some(review_id) => {
+         // Изменим сообщение, оставив кнопки для изменения оценки
+         if let Some(message) = cx.update.message.as_ref() {
+            let s = lang::tf(lang, ReviewYourRating, &[&db::rating_to_stars(rating), &review_id]);
+            callback::edit_message(cx, ChatId::Id(message.chat_id()), message.id, &s, Some(cmd::Basket::inline_markup_rate(ticket_id))).await;
+         }
+
+         // Сообщим ресторатору
+         notify_caterer(&cx.bot, review_id).await;
+         true
+      }
+      None => false,
+   }
+}
+
+// Отправляет отзыв ресторатору и в служебный чат
+async fn notify_caterer(bot: &Arc<Bot>, review_id: i32) {
+   let review = match db::review(review_id).await {
+      Some(review) => review,
+      None => return,
+   };
+
+   let comment = review.comment.clone().unwrap_or_default();
+   let text = |lang: &str, eater_name: &str| lang::tf(lang, ReviewNotify, &[&review.ticket_id, &eater_name, &review.stars(), &comment, &review.review_id]);
+
+   if let Some(rest) = db::restaurant(db::RestBy::Num(review.rest_num)).await {
+      let lang = lang::lang_of(rest.user_id).await;
+      let s = text(lang, &db::user_name_by_id(lang, review.eater_id).await);
+      if !outbox::send_critical(bot, i64::from(rest.user_id), &s, None).await {
+         settings::log(&format!("Error review::notify_caterer({}): postponed", review_id)).await;
+      }
+   }
+
+   // Администраторы видят все отзывы и могут скрыть неприемлемые
+   settings::log(&format!("{}\nСкрыть/показать /rvh{}", text(lang::DEFAULT, &db::user_name_by_id(lang::DEFAULT, review.eater_id).await), review.review_id)).await;
+}
+
+// Формирует список отзывов о ресторане
+pub async fn reviews_text(lang: &str, rest_num: i32, for_admin: bool) -> String {
+   let title = match db::restaurant(db::RestBy::Num(rest_num)).await {
+      Some(rest) => rest.title,
+      None => return lang::t(lang, ReviewRestNotFound),
+   };
+
+   let header = db::title_with_rating(&title, db::rest_ratings().await.get(&rest_num));
+   match db::review_list(rest_num, for_admin).await {
+      None => format!("{}\n{}", lang::tf(lang, ReviewsAbout, &[&header]), lang::t(lang, ReviewsNone)),
+      Some(reviews) => {
+         let list: String = reviews.into_iter().map(|review| {
+            let comment = review.comment.as_ref().map(|c| format!("\n{}", c)).unwrap_or_default();
+            let reply = review.reply.as_ref().map(|r| format!("\n   {}", lang::tf(lang, ReviewRestReply, &[r]))).unwrap_or_default();
+            let admin = if for_admin {
+               format!("\n   {}/rvh{}", if review.hidden {lang::t(lang, ReviewHidden)} else {String::default()}, review.review_id)
+            } else {String::default()};
+            format!("\n{} {}{}{}{}\n", review.stars(), review.created.format("%d.%m.%Y"), comment, reply, admin)
+         }).collect();
+         format!("{}\n{}", lang::tf(lang, ReviewsAbout, &[&header]), list)
+      }
+   }
+}
+
+// Строка с рейтингом и ссылкой на отзывы для описания ресторана
+pub async fn rating_line(lang: &str, rest_num: i32) -> String {
+   match db::rest_ratings().await.get(&rest_num) {
+      Some(rating) => lang::tf(lang, ReviewRatingLine, &[&rating.label(), &rest_num]),
+      None => String::default(),
+   }
+}
+
+// Ввод комментария едока к отзыву
+pub async fn edit_comment_mode(cx: cmd::Cx<(i32, i32, Box<cmd::DialogueState>)>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
+   // Извлечём параметры
+   let (user_id, review_id, boxed_origin) = cx.dialogue;
+
+   if let Some(text) = cx.update.text() {
+      // Удалим из строки слеши
+      let s = cmd::remove_slash(text).await;
+
+      // Если строка не пустая, продолжим
+      let text = if !s.is_empty() {
+         if db::review_edit_comment(review_id, user_id, s).await {
+            notify_caterer(&cx.bot, review_id).await;
+            lang::t(lang, ReviewThanks)
+         } else {
+            lang::t(lang, ReviewSaveError)
+         }
+      } else {
+         lang::t(lang, ReviewCancel)
+      };
+
+      // Уведомим о результате
+      let new_cx = DialogueDispatcherHandlerCx::new(cx.bot.clone(), cx.update.clone(), ());
+      new_cx.answer(text)
+      .reply_markup(boxed_origin.m)
+      .disable_notification(true)
+      .send()
+      .await?;
+   }
+
+   // Возвращаемся в предыдущий режим c обновлением кнопок
+   next(boxed_origin.d)
+}
+
+// Ввод ответа ресторатора на отзыв
+pub async fn edit_reply_mode(cx: cmd::Cx<(i32, i32, Box<cmd::DialogueState>)>) -> cmd::Res {
+   let lang = lang::user_lang(cx.update.from()).await;
+
+   // Извлечём параметры
+   let (user_id, review_id, boxed_origin) = cx.dialogue;
+
+   if let Some(text) = cx.update.text() {
+      // Удалим из строки слеши
+      let s = cmd::remove_slash(text).await;
+
+      // Если строка не пустая, продолжим
+      let text = if !s.is_empty() {
+         if db::review_edit_reply(review_id, user_id, s.clone()).await {
+            // Перешлём ответ автору отзыва
+            if let Some(review) = db::review(review_id).await {
+               let rest_title = db::restaurant(db::RestBy::Num(review.rest_num)).await.map(|rest| rest.title).unwrap_or_default();
+               let msg = lang::tf(lang::lang_of(review.eater_id).await, ReviewReplyToEater, &[&rest_title, &review.ticket_id, &s]);
+               let res = cx.bot.send_message(ChatId::Id(i64::from(review.eater_id)), msg)
+               .send()
+               .await;
+               if let Err(e) = res {
+                  settings::log(&format!("Error review::edit_reply_mode({}): {}", review_id, e)).await;
+               }
+            }
+            lang::t(lang, ReviewReplySaved)
+         } else {
+            lang::t(lang, ReviewReplyError)
+         }
+      } else {
+         lang::t(lang, ReviewReplyCancel)
+      };
+
+      // Уведомим о результате
+      let new_cx = DialogueDispatcherHandlerCx::new(cx.bot.clone(), cx.update.clone(), ());
+      new_cx.answer(text)
+      .reply_markup(boxed_origin.m)
+      .disable_notification(true)
+      .send()
+      .await?;
+   }
+
+   // Возвращаемся в предыдущий режим c обновлением кнопок
+   next(boxed_origin.d)
+}
@@ -7,14 +7,18 @@ http://www.gnu.org/licenses/gpl-3.0.html
 Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
 =============================================================================== */
 
-use chrono::{FixedOffset, NaiveDateTime, Utc,};
+use chrono::{NaiveDateTime, Utc,};
+use chrono_tz::Tz;
 use once_cell::sync::{OnceCell};
-use std::{env, sync::Arc};
+use std::sync::Arc;
 use teloxide::{
    prelude::*,
    types::{ChatId},
 };
 
+use crate::config::Config;
+use crate::outbox;
+
 // Настройки
 pub static VARS: OnceCell<Vars> = OnceCell::new();
 
@@ -50,7 +54,8 @@ pub async fn log_and_notify(text: &str) {
 // Пересылает в служебный чат сообщение, возвращая идентификатор этого сообщения в служебном чате
 pub async fn log_forward(from_chat: ChatId, message_id: i32) {
    if let Some(chat) = &VARS.get().unwrap().chat {
-      if let Err(e) = chat.bot.forward_message(chat.id, from_chat, message_id).send().await {
+      let chat_id = ChatId::Id(chat.id);
+      if let Err(e) = outbox::send(&chat_id, &chat.bot.forward_message(chat_id.clone(), from_chat, message_id)).await {
          log::info!("Error log_forward(): {}", e);
       }
    }
@@ -59,21 +64,16 @@ pub async fn log_forward(from_chat: ChatId, message_id: i32) {
 // Отправляет сообщение без использования self
 async fn send_to_chat(chat: &ServiceChat, text: &str, silence: bool) {
    // Формируем сообщение для откравки
-   let res = chat.bot.send_message(chat.id, text).disable_notification(silence);
+   let chat_id = ChatId::Id(chat.id);
+   let request = chat.bot.send_message(chat_id.clone(), text).disable_notification(silence);
 
-   // Отправляем, при ошибке запись в консольный лог
-   if let Err(err) = res.send().await {
+   // Отправляем через общую очередь, при ошибке запись в консольный лог
+   if let Err(err) = outbox::send(&chat_id, &request).await {
       log::info!("Error log({}): {}", text, err);
    }
 }
 
-// Для удобства обёртка - отправляет сообщение в чат, если он есть, и не делает ничего, если его нет
-async fn int_log(chat: Option<ServiceChat>, text: &str) {
-   if let Some(c) = chat {send_to_chat(&c, text, true).await;}
-}
-
-
-// Информация из переменных окружения
+// Настройки для работы бота
 pub struct Vars {
    // Сервисный чат
    chat: Option<ServiceChat>,
@@ -81,158 +81,68 @@ pub struct Vars {
    // Контактная информация администраторов бота
    admin_contact_info: String,
 
-   // Идентификаторы админов, макс 3 штуки
-   admin_id1: i32,
-   admin_id2: i32,
-   admin_id3: i32,
+   // Идентификаторы админов
+   admins: Vec<i32>,
 
    // Единица измерения цены
    price_unit: String,
 
-   // Часовой пояс
-   time_zone: FixedOffset,
+   // Часовой пояс по-умолчанию
+   time_zone: Tz,
 
    // Картинка по-умолчанию
    def_image_id: String,
 
-   // Ссылка для рекламы
-   link: String,
-}
-
-impl Vars {
-   pub async fn from_env(bot: &Arc<Bot>) -> Self {
-
-      // Начинаем со служебного чата, чтобы иметь возможность выводить в него ошибки
-      let chat = if let Ok(log_group_id_env) = env::var("LOG_GROUP_ID") {
-         if let Ok(log_group_id) = log_group_id_env.parse::<i64>() {
-            // Сохраняем id и копию экземпляра бота в глобальной переменной
-            Some(ServiceChat {
-               id: log_group_id,
-               bot: Arc::clone(bot),
-            })
-         } else {
-            log::info!("Environment variable LOG_GROUP_ID must be integer");
-            None
-         }
-      } else {
-         log::info!("There is no environment variable LOG_GROUP_ID, no service chat");
-         None
-      };
-      
-      Vars {
-         // Контактная информация администраторов бота
-         admin_contact_info: {
-            match env::var("CONTACT_INFO") {
-               Ok(s) => {
-                  log::info!("admin name is {}", s);
-                  s   
-               }
-               Err(e) => {
-                  int_log(chat.clone(), &format!("Something wrong with CONTACT_INFO: {}", e)).await;
-                  String::default()
-               }
-            }
-         },
-
-         // Идентификаторы админов, макс 3 штуки
-         admin_id1: {
-            match env::var("TELEGRAM_ADMIN_ID1") {
-               Ok(s) => match s.parse::<i32>() {
-                     Ok(n) => n,
-                     Err(e) => {
-                        int_log(chat.clone(), &format!("Something wrong with TELEGRAM_ADMIN_ID1: {}", e)).await;
-                        0
-                     }
-               }
-               Err(e) => {
-                  int_log(chat.clone(), &format!("Something wrong with TELEGRAM_ADMIN_ID1: {}", e)).await;
-                  0
-               }
-            }
-         },
+   // Ссылка для рекламы, None если не удалось узнать имя бота
+   link: Option<String>,
 
-         admin_id2: {
-            match env::var("TELEGRAM_ADMIN_ID2") {
-               Ok(s) => if s.is_empty() {0} else {
-                  match s.parse::<i32>() {
-                     Ok(n) => n,
-                     Err(e) => {
-                        int_log(chat.clone(), &format!("Something wrong with TELEGRAM_ADMIN_ID2: {}", e)).await;
-                        0
-                     }
-                  }
-               }
-               Err(_) => 0 // если переменная не задана, это нормально
-            }
-         },
+   // Валюта для оплаты через бота и количество её минимальных единиц в единице цены
+   payment_currency: String,
+   payment_multiplier: i32,
 
-         admin_id3: {
-            match env::var("TELEGRAM_ADMIN_ID3") {
-               Ok(s) => if s.is_empty() {0} else {
-                  match s.parse::<i32>() {
-                     Ok(n) => n,
-                     Err(e) => {
-                        int_log(chat.clone(), &format!("Something wrong with TELEGRAM_ADMIN_ID3: {}", e)).await;
-                        0
-                     }
-                  }
-               }
-               Err(_) => 0 // если переменная не задана, это нормально
-            }
-         },
+   // Размеры страниц длинных списков
+   page_size: usize,
+   page_buttons: usize,
 
-         // Единица измерения цены
-         price_unit: {
-            match env::var("PRICE_UNIT") {
-               Ok(s) => s,  
-               Err(e) => {
-                  int_log(chat.clone(), &format!("Something wrong with PRICE_UNIT: {}", e)).await;
-                  String::default()
-               }
-            }
-         },
+   // Часов между рассылками заведения по-умолчанию
+   broadcast_interval: i32,
 
-         // Часовой пояс
-         time_zone: {
-            match env::var("TIME_ZONE") {
-               Ok(s) => match s.parse::<i32>() {
-                     Ok(n) => FixedOffset::east(n * 3600),
-                     Err(e) => {
-                        int_log(chat.clone(), &format!("Something wrong with TIME_ZONE: {}", e)).await;
-                        FixedOffset::east(0)
-                     }
-               }
-               Err(e) => {
-                  int_log(chat.clone(), &format!("Something wrong with TIME_ZONE: {}", e)).await;
-                  FixedOffset::east(0)
-               }
-            }
-         },
+   // Секунд между попытками дослать важные сообщения
+   resend_interval: u64,
+}
 
-         // Картинка по-умолчанию
-         def_image_id: {
-            match env::var("DEFAULT_IMAGE_ID") {
-               Ok(s) => s,
-               Err(e) => {
-                  int_log(chat.clone(), &format!("Something wrong with DEFAULT_IMAGE_ID: {}", e)).await;
-                  String::default()
-               }
-            }
-         },
+impl Vars {
+   // Значения уже проверены при загрузке конфигурации
+   pub async fn from_config(config: &Config, bot: &Arc<Bot>) -> Self {
+      Vars {
+         // Служебный чат, сохраняем id и копию экземпляра бота
+         chat: config.log_group_id.map(|id| ServiceChat {
+            id,
+            bot: Arc::clone(bot),
+         }),
+
+         admin_contact_info: config.contact_info.clone(),
+         admins: config.admins.clone(),
+         price_unit: config.price_unit.clone(),
+         time_zone: config.time_zone,
+         def_image_id: config.default_image_id.clone(),
+         payment_currency: config.payment_currency.clone(),
+         payment_multiplier: config.payment_multiplier,
+         page_size: config.page_size,
+         page_buttons: config.page_buttons,
+         broadcast_interval: config.broadcast_interval,
+         resend_interval: config.resend_interval,
 
          link: {
             // Определим собственное имя бота
             match bot.get_me().send().await {
-               Ok(me) => match me.user.username {
-                  Some(name) => format!("http://t.me/{}?start=", name),
-                  None => String::from("Ошибка"),
+               Ok(me) => me.user.username.map(|name| format!("http://t.me/{}?start=", name)),
+               Err(e) => {
+                  log::warn!("Cannot get bot name for links: {}", e);
+                  None
                }
-               Err(_) => String::from("Ошибка"),
             }
          },
-
-         // Служебный чат
-         chat,
       }
    }
 }
@@ -251,13 +161,22 @@ pub fn current_date_time() -> NaiveDateTime {
    Utc::now().with_timezone(&our_timezone).naive_local()
 }
 
+// Часовой пояс заведения, если не задан или неизвестен, то пояс по-умолчанию
+pub fn time_zone(zone: Option<&str>) -> Tz {
+   zone.and_then(|name| name.parse().ok())
+   .unwrap_or(VARS.get().unwrap().time_zone)
+}
+
+// Текущее время в часовом поясе заведения
+pub fn zone_date_time(zone: Option<&str>) -> NaiveDateTime {
+   Utc::now().with_timezone(&time_zone(zone)).naive_local()
+}
+
 // Возвращает истину, если user_id принадлежит администратору
 pub fn is_admin(user_id: Option<&teloxide::types::User>) -> bool {
    match user_id { 
       Some(user) => {
-         let vars = VARS.get().unwrap();
-         let test = user.id;
-         test == vars.admin_id1 || test == vars.admin_id2 || test == vars.admin_id3
+         VARS.get().unwrap().admins.contains(&user.id)
       }
       None => false,
    }
@@ -273,7 +192,45 @@ pub fn default_photo_id() -> String {
    VARS.get().unwrap().def_image_id.clone()
 }
 
-// Ссылка для рекламы
-pub fn link() -> String {
-   VARS.get().unwrap().link.clone()
-}
\ No newline at end of file
+// Ссылка для рекламы заведения, группы или блюда по ключу
+pub fn link(key: &str) -> Option<String> {
+   VARS.get().unwrap().link.as_ref().map(|link| format!("{}{}", link, key))
+}
+
+// Добавляет к тексту ссылку для рекламы, если она есть
+pub fn with_link(info: String, key: &str) -> String {
+   match link(key) {
+      Some(link) => format!("{}\n{}", info, link),
+      None => info,
+   }
+}
+
+// Валюта для оплаты через бота
+pub fn payment_currency() -> String {
+   VARS.get().unwrap().payment_currency.clone()
+}
+
+// Переводит цену в минимальные единицы валюты для платёжной системы
+pub fn payment_amount(price: i32) -> i32 {
+   price * VARS.get().unwrap().payment_multiplier
+}
+
+// Сколько строк выводить на одной странице списка со ссылками
+pub fn page_size() -> usize {
+   VARS.get().unwrap().page_size
+}
+
+// Сколько инлайн-кнопок выводить на одной странице списка
+pub fn page_buttons() -> usize {
+   VARS.get().unwrap().page_buttons
+}
+
+// Сколько часов должно пройти между рассылками заведения, если администратор не задал иное
+pub fn broadcast_interval() -> i32 {
+   VARS.get().unwrap().broadcast_interval
+}
+
+// Как часто досылать сохранённые важные сообщения
+pub fn resend_interval() -> std::time::Duration {
+   std::time::Duration::from_secs(VARS.get().unwrap().resend_interval)
+}
@@ -0,0 +1,97 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Корректное завершение работы по сигналу. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+use once_cell::sync::Lazy;
+use std::{sync::atomic::{AtomicUsize, Ordering}, time::Duration};
+use tokio::sync::watch;
+
+// Сколько ждать завершения начатой обработки
+const DRAIN_TIMEOUT_SECS: u64 = 25;
+
+// Сколько обработка должна отсутствовать, чтобы считать очереди диспетчера пустыми
+const DRAIN_QUIET_MILLIS: u64 = 500;
+
+// Признак завершения работы, на который подписываются ожидающие его задачи
+static SIGNAL: Lazy<(watch::Sender<bool>, watch::Receiver<bool>)> = Lazy::new(|| watch::channel(false));
+
+// Количество обрабатываемых в данный момент обновлений
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+// Отметка о выполняемой работе, снимается при выходе из области видимости
+pub struct Work;
+
+impl Drop for Work {
+   fn drop(&mut self) {
+      IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+   }
+}
+
+// Начало обработки, которую нужно дождаться при завершении
+pub fn work() -> Work {
+   IN_FLIGHT.fetch_add(1, Ordering::SeqCst);
+   Work
+}
+
+pub fn is_requested() -> bool {
+   *SIGNAL.1.borrow()
+}
+
+// Завершается, когда поступил запрос на остановку
+pub async fn requested() {
+   let mut rx = SIGNAL.1.clone();
+   while !*rx.borrow() {
+      if rx.recv().await.is_none() {
+         return;
+      }
+   }
+}
+
+// Ожидает SIGTERM от платформы или Ctrl+C и объявляет о завершении
+pub async fn listen_signals() {
+   #[cfg(unix)]
+   {
+      use tokio::signal::unix::{signal, SignalKind};
+      let mut term = signal(SignalKind::terminate()).expect("Cannot listen SIGTERM");
+      tokio::select! {
+         _ = term.recv() => log::info!("SIGTERM received"),
+         _ = tokio::signal::ctrl_c() => log::info!("Ctrl+C received"),
+      }
+   }
+   #[cfg(not(unix))]
+   {
+      let _ = tokio::signal::ctrl_c().await;
+      log::info!("Ctrl+C received");
+   }
+
+   let _ = SIGNAL.0.broadcast(true);
+}
+
+// Ждёт окончания обработки уже полученных обновлений, возвращает истину, если успели
+pub async fn drain() -> bool {
+   let deadline = tokio::time::Instant::now() + Duration::from_secs(DRAIN_TIMEOUT_SECS);
+   let mut quiet = 0;
+   while tokio::time::Instant::now() < deadline {
+      // Очереди диспетчера могут ещё передать обновления в обработку, поэтому ждём несколько проверок подряд
+      if IN_FLIGHT.load(Ordering::SeqCst) == 0 {
+         quiet += 1;
+         if quiet * 100 >= DRAIN_QUIET_MILLIS {
+            return true;
+         }
+      } else {
+         quiet = 0;
+      }
+      tokio::time::delay_for(Duration::from_millis(100)).await;
+   }
+   false
+}
+
+// Количество незавершённых обработок, для отчёта при остановке
+pub fn in_flight() -> usize {
+   IN_FLIGHT.load(Ordering::SeqCst)
+}
@@ -0,0 +1,193 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Веб-сервер для получения обновлений от Telegram и проверки состояния. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+use teloxide::{
+   dispatching::update_listeners, 
+   prelude::*, 
+   types::Update,
+};
+use std::{convert::Infallible, net::SocketAddr, sync::Arc, 
+   sync::atomic::{AtomicUsize, Ordering},
+};
+use tokio::sync::mpsc;
+use futures::StreamExt;
+use warp::{Filter, http::StatusCode, reply};
+use rand::{distributions::Alphanumeric, Rng};
+
+use crate::config::Config;
+use crate::database as db;
+use crate::metrics;
+use crate::shutdown;
+use crate::admin_api;
+
+// Заголовок, в котором Telegram передаёт секрет, указанный при установке вебхука
+const SECRET_HEADER: &str = "x-telegram-bot-api-secret-token";
+
+// Наибольший размер обновления, настоящие обновления намного меньше
+const UPDATE_LIMIT: u64 = 1024 * 1024;
+
+// Виды обновлений, которые обрабатывает бот, остальные Telegram присылать не будет
+const ALLOWED_UPDATES: [&str; 4] = ["message", "callback_query", "inline_query", "pre_checkout_query"];
+
+// Счётчик запросов с некорректным JSON
+static MALFORMED_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// Случайная строка из допустимых для пути и секрета символов
+fn random_string() -> String {
+   rand::thread_rng().sample_iter(&Alphanumeric).take(32).collect()
+}
+
+
+// Установка вебхука с секретом напрямую через Bot API, так как в teloxide 0.2 нет параметра secret_token
+async fn set_webhook(bot: &Bot, url: &str, secret: &str) -> Result<(), String> {
+   let api_url = format!("https://api.telegram.org/bot{}/setWebhook", bot.token());
+   let params = serde_json::json!({
+      "url": url,
+      "secret_token": secret,
+      "allowed_updates": ALLOWED_UPDATES,
+   });
+
+   let res = bot.client().post(&api_url)
+   .json(&params)
+   .send()
+   .await
+   .map_err(|e| e.to_string())?
+   .json::<serde_json::Value>()
+   .await
+   .map_err(|e| e.to_string())?;
+
+   if res["ok"].as_bool().unwrap_or(false) {
+      Ok(())
+   } else {
+      Err(res["description"].as_str().unwrap_or_default().to_string())
+   }
+}
+
+// Ответ на отклонённые запросы с подходящим кодом вместо 500 на всё подряд
+async fn handle_rejection(error: warp::Rejection) -> Result<impl warp::Reply, Infallible> {
+   let code = if error.is_not_found() {
+      StatusCode::NOT_FOUND
+   } else if error.find::<warp::reject::MethodNotAllowed>().is_some() {
+      StatusCode::METHOD_NOT_ALLOWED
+   } else if error.find::<warp::reject::PayloadTooLarge>().is_some() {
+      StatusCode::PAYLOAD_TOO_LARGE
+   } else if error.find::<warp::reject::LengthRequired>().is_some() {
+      StatusCode::LENGTH_REQUIRED
+   } else if error.find::<warp::reject::InvalidQuery>().is_some() {
+      StatusCode::BAD_REQUEST
+   } else {
+      log::error!("Cannot process the request due to: {:?}", error);
+      StatusCode::INTERNAL_SERVER_ERROR
+   };
+   Ok(code)
+}
+
+// Процесс жив, пул соединений создан
+async fn healthz() -> Result<impl warp::Reply, Infallible> {
+   let res = match db::pool_status() {
+      Some((size, max_size, available)) => reply::with_status(format!("ok, pool {}/{}, available {}", size, max_size, available), StatusCode::OK),
+      None => reply::with_status(String::from("database pool is not initialized"), StatusCode::SERVICE_UNAVAILABLE),
+   };
+   Ok(res)
+}
+
+// Бот готов обрабатывать обновления, БД отвечает на запросы
+async fn readyz() -> Result<impl warp::Reply, Infallible> {
+   let res = if shutdown::is_requested() {
+      reply::with_status("shutting down", StatusCode::SERVICE_UNAVAILABLE)
+   } else if db::is_ready().await {
+      reply::with_status("ready", StatusCode::OK)
+   } else {
+      reply::with_status("database is not ready", StatusCode::SERVICE_UNAVAILABLE)
+   };
+   Ok(res)
+}
+
+pub async fn listener(bot: Arc<Bot>, config: &Config) -> impl update_listeners::UpdateListener<Infallible> {
+   // Heroku defines auto defines a port value, host example .: "heroku-ping-pong-bot.herokuapp.com"
+   let port = config.port;
+
+   // Секретный путь вместо токена бота и секрет для проверки, что запрос пришёл от Telegram.
+   // Если они не заданы, то случайные, вебхук всё равно устанавливается заново при каждом запуске
+   let path = config.webhook_path.clone().unwrap_or_else(random_string);
+   let secret = config.webhook_secret.clone().unwrap_or_else(random_string);
+   let url = format!("https://{}/{}", config.host, path);
+
+   set_webhook(&bot, &url, &secret)
+      .await
+      .expect("Cannot setup a webhook");
+   
+   let (tx, rx) = mpsc::unbounded_channel();
+
+   // Сначала путь, затем метод, иначе на неизвестный путь отвечаем 405 вместо 404
+   let updates = warp::path(path)
+      .and(warp::path::end())
+      .and(warp::post())
+      .and(warp::header::optional::<String>(SECRET_HEADER))
+      .and(warp::body::content_length_limit(UPDATE_LIMIT))
+      .and(warp::body::bytes())
+      .map(move |header: Option<String>, body: warp::hyper::body::Bytes| {
+         // Запросы без правильного секрета отклоняем
+         if header.as_deref() != Some(secret.as_str()) {
+            log::warn!("Webhook request with wrong secret token");
+            return StatusCode::FORBIDDEN;
+         }
+
+         match serde_json::from_slice::<Update>(&body) {
+            Ok(update) => {
+               tx.send(Ok(update))
+                  .expect("Cannot send an incoming update from the webhook");
+               StatusCode::OK
+            }
+            // Корректный JSON с неизвестным видом обновления пропускаем, иначе Telegram будет повторять его
+            Err(error) if serde_json::from_slice::<serde_json::Value>(&body).is_ok() => {
+               log::info!("Skipped unsupported update: {}", error);
+               StatusCode::OK
+            }
+            Err(error) => {
+               let count = MALFORMED_COUNT.fetch_add(1, Ordering::Relaxed) + 1;
+               log::warn!("Malformed update #{}: {}", count, error);
+               StatusCode::BAD_REQUEST
+            }
+         }
+      });
+
+   let health = warp::path("healthz")
+      .and(warp::path::end())
+      .and(warp::get())
+      .and_then(healthz);
+
+   let ready = warp::path("readyz")
+      .and(warp::path::end())
+      .and(warp::get())
+      .and_then(readyz);
+
+   let metrics = warp::path("metrics")
+      .and(warp::path::end())
+      .and(warp::get())
+      .map(|| reply::with_header(metrics::render(), "content-type", "text/plain; version=0.0.4"));
+
+   // API администратора, если задан его токен
+   let admin = admin_api::routes(bot.clone(), config.admin_api_token.clone());
+
+   let server = updates
+      .or(health)
+      .or(ready)
+      .or(metrics)
+      .or(admin)
+      .recover(handle_rejection);
+
+   let serve = warp::serve(server);
+
+   // При остановке сервер перестаёт принимать запросы, а поток обновлений завершается
+   let address = format!("0.0.0.0:{}", port);
+   let (_, server) = serve.bind_with_graceful_shutdown(address.parse::<SocketAddr>().unwrap(), shutdown::requested());
+   tokio::spawn(server);
+   rx.take_until(shutdown::requested())
+}
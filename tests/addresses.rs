@@ -0,0 +1,93 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Сценарий: адресная книга едока и выбор адреса при оформлении. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+mod support;
+
+use support::{Scenario, TestUser};
+
+const CATERER: TestUser = TestUser {id: 20015, name: "Caterer"};
+const EATER: TestUser = TestUser {id: 30014, name: "Eater"};
+
+#[tokio::test]
+async fn address_book() {
+   let s = match Scenario::start(&[]).await {
+      Some(s) => s,
+      None => return,
+   };
+
+   // Заведение с одним блюдом, работающее весь день
+   let rest_num = s.restaurant_with_dish(CATERER, "Столовая", "Обеды", "Борщ", 150).await;
+
+   // Едок знаком с ботом и кладёт блюдо в корзину
+   s.send_text(EATER, "/start").await;
+   s.expect_text(EATER.id, "выберите в основном меню").await;
+   s.send_text(EATER, "⚙").await;
+   s.expect_text(EATER.id, "Режим интерфейса").await;
+   s.send_text(EATER, &format!("/start {}_1_1", rest_num)).await;
+   let (message, data) = s.expect_button(EATER.id, "add").await;
+   s.press(EATER, &message, &data).await;
+   s.expect_db("SELECT amount FROM orders WHERE user_id=$1", &[&EATER.id], 1).await;
+
+   // Первый адрес текстом с подписью
+   s.send_text(EATER, "🛒Корзина").await;
+   s.expect_text(EATER.id, "Борщ").await;
+   s.send_text(EATER, "/edit_address").await;
+   s.expect_text(EATER.id, "Введите новый адрес").await;
+   s.send_text(EATER, "ул. Ленина 1").await;
+   s.expect_text(EATER.id, "Как подписать этот адрес").await;
+   s.send_text(EATER, "Дом").await;
+   s.expect_text(EATER.id, "Адрес: Дом: ул. Ленина 1 /edit_address").await;
+   let home: i32 = s.query("SELECT address_id FROM addresses WHERE user_id=$1", &[&EATER.id]).await[0].get(0);
+
+   // Второй адрес точкой на карте становится адресом по умолчанию
+   s.send_text(EATER, "/edit_address").await;
+   s.expect_text(EATER.id, &format!("Дом: ул. Ленина 1 ⭐ /deladdr{}", home)).await;
+   s.send_location(EATER, 12.2400, 109.1900).await;
+   s.expect_text(EATER.id, "Как подписать этот адрес").await;
+   s.send_text(EATER, "Офис").await;
+   s.expect_text(EATER.id, "Адрес: Офис: на карте /edit_address").await;
+   let office: i32 = s.query("SELECT address_id FROM addresses WHERE user_id=$1 AND is_default", &[&EATER.id]).await[0].get(0);
+   s.expect_db("SELECT latitude FROM addresses WHERE address_id=$1", &[&office], 12.24f64).await;
+
+   // Переключение адреса по умолчанию и удаление несуществующего
+   s.send_text(EATER, &format!("/addr{}", home)).await;
+   s.expect_db("SELECT is_default FROM addresses WHERE address_id=$1", &[&home], true).await;
+   s.expect_db("SELECT is_default FROM addresses WHERE address_id=$1", &[&office], false).await;
+   s.send_text(EATER, "/deladdr999999").await;
+   s.expect_text(EATER.id, "Адрес 999999 не найден").await;
+
+   // Несуществующий или нулевой адрес не сбрасывает адрес по умолчанию
+   s.send_text(EATER, "/addr999999").await;
+   s.expect_text(EATER.id, "Адрес 999999 не найден").await;
+   s.send_text(EATER, "/addr0").await;
+   s.expect_text(EATER.id, "неизвестная команда").await;
+   s.expect_db("SELECT COUNT(*) FROM addresses WHERE user_id=$1 AND is_default", &[&EATER.id], 1i64).await;
+   s.expect_db("SELECT is_default FROM addresses WHERE address_id=$1", &[&home], true).await;
+
+   // При оформлении для каждого адреса своя кнопка
+   s.send_text(EATER, "⟳ Обновить").await;
+   let (message, _) = s.expect_button(EATER.id, "bas").await;
+   let buttons = message.inline_buttons();
+   assert_eq!(buttons, vec![
+      (String::from("Оформить: Дом"), format!("bas{}_{}_0", CATERER.id, home)),
+      (String::from("Оформить: Офис"), format!("bas{}_{}_0", CATERER.id, office)),
+   ]);
+
+   // Заказ в офис, ресторатор получает точку по сохранённым координатам
+   s.press(EATER, &message, &format!("bas{}_{}_0", CATERER.id, office)).await;
+   s.expect_text(CATERER.id, "Курьером по адресу Офис: на карте").await;
+   s.expect(CATERER.id, "sendLocation", "").await;
+   s.expect(CATERER.id, "forwardMessage", "").await;
+   s.expect_db("SELECT stage FROM tickets WHERE eater_id=$1", &[&EATER.id], 1).await;
+
+   // Удаление адреса по умолчанию делает основным оставшийся
+   s.send_text(EATER, &format!("/deladdr{}", home)).await;
+   s.expect_db("SELECT COUNT(*) FROM addresses WHERE user_id=$1", &[&EATER.id], 1i64).await;
+   s.expect_db("SELECT is_default FROM addresses WHERE address_id=$1", &[&office], true).await;
+}
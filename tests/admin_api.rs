@@ -0,0 +1,121 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Сценарий: API администратора для внешних скриптов. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+mod support;
+
+use serde_json::json;
+use support::{Scenario, TestUser, ADMIN_API_TOKEN};
+
+const CATERER: TestUser = TestUser {id: 20004, name: "Caterer"};
+const EATER: TestUser = TestUser {id: 30004, name: "Eater"};
+
+#[tokio::test]
+async fn admin_api() {
+   let s = match Scenario::start(&[]).await {
+      Some(s) => s,
+      None => return,
+   };
+
+   // Без правильного токена доступа нет
+   let (status, _) = s.api_with_token("GET", "restaurants", None, None).await;
+   assert_eq!(status, 401);
+   let (status, _) = s.api_with_token("GET", "restaurants", None, Some("wrong_token_wrong_token")).await;
+   assert_eq!(status, 401);
+
+   // Токен проверяется раньше чтения тела, слишком большое тело отклоняется без чтения
+   assert_eq!(s.head_only("PATCH", "api/restaurants/1", &[], 70000).await, 401);
+   let auth = format!("Bearer {}", ADMIN_API_TOKEN);
+   assert_eq!(s.head_only("PATCH", "api/restaurants/1", &[("authorization", &auth)], 70000).await, 413);
+
+   // Регистрация ресторатора с меню и изменение ресторана
+   let rest_num = s.restaurant_with_dish(CATERER, "Столовая", "Супы", "Борщ", 100).await;
+   let (status, rest) = s.api("POST", &format!("caterers/{}", CATERER.id), None).await;
+   assert_eq!(status, 200, "{}", rest);
+   assert_eq!(rest["enabled"], json!(true));
+   assert_eq!(rest["num"], json!(rest_num));
+
+   let (status, list) = s.api("GET", "restaurants", None).await;
+   assert_eq!(status, 200);
+   assert_eq!(list.as_array().map(Vec::len), Some(1));
+
+   let (status, rest) = s.api("PATCH", &format!("restaurants/{}", rest_num), Some(json!({"title": "Пельмени", "active": true}))).await;
+   assert_eq!(status, 200, "{}", rest);
+   assert_eq!(rest["title"], json!("Пельмени"));
+   s.expect_db("SELECT active FROM restaurants WHERE rest_num=$1", &[&(rest_num as i32)], true).await;
+
+   // Ошибка в одном поле не меняет остальные
+   let (status, _) = s.api("PATCH", &format!("restaurants/{}", rest_num), Some(json!({"title": "Вареники", "time_zone": "Mars/Olympus"}))).await;
+   assert_eq!(status, 400);
+   s.expect_db("SELECT title FROM restaurants WHERE rest_num=$1", &[&(rest_num as i32)], String::from("Пельмени")).await;
+   let (status, rest) = s.api("PATCH", &format!("restaurants/{}", rest_num), Some(json!({"title": "Вареники", "active": false, "time_zone": "Asia/Kolkata"}))).await;
+   assert_eq!(status, 200, "{}", rest);
+   assert_eq!(rest["title"], json!("Вареники"));
+   assert_eq!(rest["active"], json!(false));
+   s.expect_db("SELECT time_zone FROM restaurants WHERE rest_num=$1", &[&(rest_num as i32)], String::from("Asia/Kolkata")).await;
+   let (status, _) = s.api("PATCH", &format!("restaurants/{}", rest_num), Some(json!({"title": "Пельмени", "active": true, "time_zone": null}))).await;
+   assert_eq!(status, 200);
+
+   let (status, _) = s.api("PATCH", &format!("restaurants/{}", rest_num), Some(json!({"active": "yes"}))).await;
+   assert_eq!(status, 400);
+   let (status, _) = s.api("GET", "restaurants/999", None).await;
+   assert_eq!(status, 404);
+   let (status, _) = s.api("GET", "restaurants/abc", None).await;
+   assert_eq!(status, 400);
+
+   // Группа и блюдо, созданные ресторатором
+
+   let (status, group) = s.api("PATCH", &format!("restaurants/{}/groups/1", rest_num), Some(json!({"cat_id": 3, "opening_time": "10:00", "closing_time": "22:00"}))).await;
+   assert_eq!(status, 200, "{}", group);
+   assert_eq!(group["cat_id"], json!(3));
+   assert_eq!(group["opening_time"], json!("10:00"));
+
+   let (status, dish) = s.api("PATCH", &format!("restaurants/{}/groups/1/dishes/1", rest_num), Some(json!({"price": 200}))).await;
+   assert_eq!(status, 200, "{}", dish);
+   assert_eq!(dish["price"], json!(200));
+
+   let (_, rest) = s.api("GET", &format!("restaurants/{}", rest_num), None).await;
+   assert_eq!(rest["groups"][0]["dishes"][0]["title"], json!("Борщ"));
+
+   // Пользователи с последним временем входа
+   let (status, users) = s.api("GET", "users?limit=10", None).await;
+   assert_eq!(status, 200);
+   assert_eq!(users[0]["user_id"], json!(CATERER.id));
+   assert!(users[0]["last_seen"].is_string());
+
+   // Принудительное закрытие заказа
+   let ticket_id: i32 = s.query("INSERT INTO tickets (eater_id, caterer_id, eater_msg_id, caterer_msg_id, eater_status_msg_id, caterer_status_msg_id, stage)
+      VALUES ($1, $2, 1, 1, 2, 2, 1) RETURNING ticket_id",
+      &[&EATER.id, &CATERER.id]).await[0].get(0);
+
+   let (status, tickets) = s.api("GET", "tickets", None).await;
+   assert_eq!(status, 200);
+   assert_eq!(tickets[0]["ticket_id"], json!(ticket_id));
+
+   let (status, ticket) = s.api("POST", &format!("tickets/{}/close", ticket_id), None).await;
+   assert_eq!(status, 200, "{}", ticket);
+   assert_eq!(ticket["stage"], json!(6));
+   s.expect_text(EATER.id, &format!("Заказ #{} закрыт администратором", ticket_id)).await;
+   s.expect_text(CATERER.id, &format!("Заказ #{} закрыт администратором", ticket_id)).await;
+   s.expect(EATER.id, "editMessageText", "Отменено").await;
+   s.expect(CATERER.id, "editMessageText", "Отменено").await;
+
+   let (status, _) = s.api("POST", &format!("tickets/{}/close", ticket_id), None).await;
+   assert_eq!(status, 409);
+   let (_, tickets) = s.api("GET", "tickets", None).await;
+   assert_eq!(tickets, json!([]));
+   let (_, tickets) = s.api("GET", "tickets?state=all", None).await;
+   assert_eq!(tickets.as_array().map(Vec::len), Some(1));
+
+   // Приостановка ресторатора
+   let (status, rest) = s.api("DELETE", &format!("caterers/{}", CATERER.id), None).await;
+   assert_eq!(status, 200, "{}", rest);
+   assert_eq!(rest["enabled"], json!(false));
+   let (status, _) = s.api("DELETE", "caterers/1", None).await;
+   assert_eq!(status, 404);
+}
@@ -0,0 +1,102 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Сценарий: подписка на заведение и рассылка подписчикам. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+mod support;
+
+use support::{Scenario, TestUser};
+
+const CATERER: TestUser = TestUser {id: 20012, name: "Caterer"};
+const EATER: TestUser = TestUser {id: 30010, name: "Eater"};
+const ADMIN: TestUser = TestUser {id: 10010, name: "Admin"};
+
+#[tokio::test]
+async fn broadcast() {
+   let s = match Scenario::start(&[ADMIN.id]).await {
+      Some(s) => s,
+      None => return,
+   };
+
+   // Заведение с группой, работающей весь день
+   let rest_num = s.restaurant_with_dish(CATERER, "Блинная", "Блины", "Блин", 50).await;
+   s.send_text(CATERER, "Главная").await;
+   s.expect_text(CATERER.id, "Подписчиков: 0, сделать рассылку /Broadcast").await;
+
+   // Рассылать пока некому
+   s.send_text(CATERER, "/Broadcast").await;
+   s.expect_text(CATERER.id, "нет подписчиков").await;
+
+   // Подписка колокольчиком в режиме с кнопками
+   s.send_text(EATER, "/start").await;
+   s.expect_text(EATER.id, "выберите в основном меню").await;
+   s.send_text(EATER, &format!("/goto{}_0_0", rest_num)).await;
+   let (message, data) = s.expect_button(EATER.id, "sub").await;
+   assert_eq!(data, format!("sub{}_0_0", rest_num));
+   s.press(EATER, &message, &data).await;
+   s.expect_callback_answer("Вы подписались").await;
+   let edit = s.expect(EATER.id, "editMessageReplyMarkup", "").await;
+   assert!(edit.inline_buttons().contains(&(String::from("🔔"), data.clone())));
+
+   // И командой в режиме со ссылками
+   s.send_text(ADMIN, "/start").await;
+   s.expect_text(ADMIN.id, "выберите в основном меню").await;
+   s.send_text(ADMIN, "⚙").await;
+   s.expect_text(ADMIN.id, "Режим интерфейса").await;
+   s.send_text(ADMIN, "/toggle").await;
+   s.expect_text(ADMIN.id, "со ссылками").await;
+   s.send_text(ADMIN, &format!("/goto{}_0_0", rest_num)).await;
+   s.expect_text(ADMIN.id, "🔕 подписаться на новости заведения /sub").await;
+   s.send_text(ADMIN, "/sub").await;
+   s.expect_text(ADMIN.id, "Вы подписались").await;
+   s.expect_db("SELECT COUNT(*) FROM subscriptions WHERE rest_num=$1", &[&(rest_num as i32)], 2i64).await;
+
+   // Текстовая рассылка доходит до обоих со ссылкой и кнопкой для отписки
+   s.send_text(CATERER, "/Broadcast").await;
+   s.expect_text(CATERER.id, "подписчиков (2)").await;
+   s.send_text(CATERER, "Сегодня новое меню").await;
+   s.expect_text(CATERER.id, "Рассылка начата, подписчиков: 2").await;
+   let news = s.expect_text(EATER.id, "Сегодня новое меню").await;
+   assert!(news.text().contains(&format!("Новости «Блинная», отписаться /unsub{}", rest_num)), "{}", news.text());
+   assert!(news.inline_buttons().contains(&(String::from("🔕 Отписаться"), format!("uns{}_0_0", rest_num))));
+   s.expect_text(ADMIN.id, "Сегодня новое меню").await;
+   s.expect_text(CATERER.id, "завершена: доставлено 2, не доставлено 0").await;
+   s.expect_db("SELECT COUNT(*) FROM broadcasts WHERE finished IS NOT NULL AND delivered = 2", &[], 1i64).await;
+
+   // Следующая рассылка только через сутки
+   s.send_text(CATERER, "/Broadcast").await;
+   s.expect_text(CATERER.id, "Следующая рассылка возможна после").await;
+
+   // Администратор снимает ограничение
+   s.send_text(ADMIN, "/start").await;
+   s.expect_text(ADMIN.id, "выберите в основном меню").await;
+   s.send_text(ADMIN, "⚙").await;
+   s.expect_text(ADMIN.id, "Режим интерфейса").await;
+   s.send_text(ADMIN, &format!("/bcast{} 0", rest_num)).await;
+   s.expect_text(ADMIN.id, "без ограничения частоты").await;
+   s.expect_db("SELECT broadcast_hours FROM restaurants WHERE rest_num=$1", &[&(rest_num as i32)], Some(0i32)).await;
+
+   // Рассылка с фото
+   s.send_text(CATERER, "/Broadcast").await;
+   s.expect_text(CATERER.id, "подписчиков (2)").await;
+   s.send_photo(CATERER, "photo1", "Скидка на блины").await;
+   s.expect_text(CATERER.id, "Рассылка начата").await;
+   let news = s.expect(EATER.id, "sendPhoto", "Скидка на блины").await;
+   assert_eq!(news.params["photo"], "photo1");
+
+   // Отписка кнопкой и ссылкой
+   s.press(EATER, &news, &format!("uns{}_0_0", rest_num)).await;
+   s.expect_callback_answer("Вы отписались").await;
+   s.send_text(ADMIN, &format!("/unsub{}", rest_num)).await;
+   s.expect_text(ADMIN.id, "Вы отписались").await;
+   s.expect_db("SELECT COUNT(*) FROM subscriptions WHERE rest_num=$1", &[&(rest_num as i32)], 0i64).await;
+
+   // Статистика в информации о заведении
+   s.send_text(CATERER, "Главная").await;
+   let info = s.expect_text(CATERER.id, "Подписчиков: 0").await;
+   assert!(info.text().contains("Последняя рассылка"), "{}", info.text());
+}
@@ -0,0 +1,62 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Сценарий: повторная доставка нового заказа ресторатору. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+mod support;
+
+use support::{FakeApi, Scenario, TestUser};
+
+const CATERER: TestUser = TestUser {id: 20021, name: "Caterer"};
+const EATER: TestUser = TestUser {id: 30021, name: "Eater"};
+
+#[tokio::test]
+async fn caterer_outbox() {
+   let s = match Scenario::start_with(&[], &[("RESEND_INTERVAL", "1")]).await {
+      Some(s) => s,
+      None => return,
+   };
+
+   let rest_num = s.restaurant_with_dish(CATERER, "Очередь", "Обеды", "Борщ", 120).await;
+
+   // Едок собирает корзину с самовывозом
+   s.send_text(EATER, "/start").await;
+   s.expect_text(EATER.id, "выберите в основном меню").await;
+   s.send_text(EATER, "⚙").await;
+   s.expect_text(EATER.id, "Режим интерфейса").await;
+   s.send_text(EATER, &format!("/start {}_1_1", rest_num)).await;
+   let (message, data) = s.expect_button(EATER.id, "add").await;
+   s.press(EATER, &message, &data).await;
+   s.expect_db("SELECT amount FROM orders WHERE user_id=$1", &[&EATER.id], 1).await;
+   s.send_text(EATER, "🛒Корзина").await;
+   s.expect_text(EATER.id, "Борщ").await;
+   s.send_text(EATER, "/toggle").await;
+   s.expect_text(EATER.id, "самовывоз").await;
+
+   // Пересылка заказа ресторатору срывается, заказ всё равно оформлен и ждёт в очереди
+   s.api.respond_once("forwardMessage", FakeApi::broken_response());
+   let (message, data) = s.expect_button(EATER.id, "bas").await;
+   s.press(EATER, &message, &data).await;
+   s.expect_text(CATERER.id, "Заказ от Eater").await;
+   s.expect_button(EATER.id, "bca").await;
+   s.expect_db("SELECT COUNT(*) FROM outbox WHERE text = '' AND forward_from = $1", &[&i64::from(EATER.id)], 1i64).await;
+   s.expect_db("SELECT caterer_msg_id = 0 AND caterer_status_msg_id IS NULL FROM tickets WHERE eater_id=$1", &[&EATER.id], true).await;
+   let ticket_id: i32 = s.query("SELECT ticket_id FROM tickets WHERE eater_id=$1", &[&EATER.id]).await[0].get(0);
+
+   // Очередь берёт сообщения старше двух минут, состарим запись
+   s.query("UPDATE outbox SET created = created - interval '5 minutes'", &[]).await;
+   let forwards = |calls: &[support::Call]| calls.iter().filter(|c| c.method == "forwardMessage" && c.chat_id() == Some(i64::from(CATERER.id))).count();
+   let calls = s.wait_for(|calls| forwards(calls) == 2).await.expect("Order was not forwarded again");
+
+   // Ресторатор получил заказ и статус с кнопками, контакты едока не повторились
+   s.expect_db("SELECT COUNT(*) FROM outbox", &[], 0i64).await;
+   s.expect_db("SELECT caterer_msg_id <> 0 AND caterer_status_msg_id IS NOT NULL FROM tickets WHERE ticket_id=$1", &[&ticket_id], true).await;
+   let contacts = calls.iter().filter(|c| c.method == "sendMessage" && c.chat_id() == Some(i64::from(CATERER.id)) && c.text().contains("Заказ от Eater")).count();
+   assert_eq!(contacts, 1, "{}", s.dump(0));
+   let (_, data) = s.expect_button(CATERER.id, &format!("bne{}", ticket_id)).await;
+   assert_eq!(data, format!("bne{}_0_0", ticket_id));
+}
@@ -0,0 +1,56 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Сценарий: справка по командам и ошибки в аргументах. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+mod support;
+
+use support::{Scenario, TestUser};
+
+const ADMIN: TestUser = TestUser {id: 10003, name: "Admin"};
+const EATER: TestUser = TestUser {id: 30003, name: "Eater"};
+
+#[tokio::test]
+async fn help_and_wrong_arguments() {
+   let s = match Scenario::start(&[ADMIN.id]).await {
+      Some(s) => s,
+      None => return,
+   };
+
+   // Справка главного меню вместе с общими командами, без команд администратора
+   s.send_text(EATER, "/start").await;
+   s.expect_text(EATER.id, "выберите в основном меню").await;
+   s.send_text(EATER, "/help").await;
+   let reply = s.expect_text(EATER.id, "Команды в этом меню").await;
+   assert!(reply.text().contains("/chat — идентификатор чата"));
+   assert!(reply.text().contains("/goto<N_N_N>"));
+   assert!(!reply.text().contains("/rvh"), "Admin commands must be hidden");
+   assert!(reply.keyboard().contains(&String::from("⚙")), "Main menu keyboard expected");
+
+   // Нечисловой аргумент больше не означает номер 0
+   s.send_text(EATER, "/gotoabc").await;
+   s.expect_text(EATER.id, "Неверные аргументы команды /goto, ожидается /goto<N_N_N>").await;
+
+   // Справка зависит от меню и роли
+   s.send_text(EATER, "⚙").await;
+   s.expect_text(EATER.id, "Режим интерфейса").await;
+   s.send_text(EATER, "/help").await;
+   let reply = s.expect_text(EATER.id, "/lang_<xx> — выбрать язык").await;
+   assert!(!reply.text().contains("/regi"), "Admin commands must be hidden");
+   s.send_text(EATER, "/lang_xx").await;
+   s.expect_text(EATER.id, "ожидается /lang_<xx>").await;
+
+   s.send_text(ADMIN, "/start").await;
+   s.expect_text(ADMIN.id, "выберите в основном меню").await;
+   s.send_text(ADMIN, "⚙").await;
+   s.expect_text(ADMIN.id, "Режим интерфейса").await;
+   s.send_text(ADMIN, "/help").await;
+   s.expect_text(ADMIN.id, "/regi<N> — зарегистрировать ресторатора").await;
+   s.send_text(ADMIN, "/regiabc").await;
+   s.expect_text(ADMIN.id, "ожидается /regi<N>").await;
+   s.expect_db("SELECT COUNT(*) FROM restaurants", &[], 0i64).await;
+}
@@ -0,0 +1,81 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Сценарий: избранные заведения и блюда. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+mod support;
+
+use serde_json::json;
+use support::{Scenario, TestUser};
+
+const CATERER: TestUser = TestUser {id: 20006, name: "Caterer"};
+const EATER: TestUser = TestUser {id: 30006, name: "Eater"};
+
+#[tokio::test]
+async fn favorites() {
+   let s = match Scenario::start(&[]).await {
+      Some(s) => s,
+      None => return,
+   };
+
+   // Заведение с одним блюдом, работающее весь день
+   let rest_num = s.restaurant_with_dish(CATERER, "Пельменная", "Горячее", "Пельмени", 100).await;
+
+   // Пустое избранное
+   s.send_text(EATER, "/start").await;
+   s.expect_text(EATER.id, "выберите в основном меню").await;
+   s.send_text(EATER, "⭐Избранное").await;
+   s.expect_text(EATER.id, "В избранном пока пусто").await;
+
+   // Звёздочка у заведения в режиме с кнопками
+   s.send_text(EATER, &format!("/goto{}_0_0", rest_num)).await;
+   let (message, data) = s.expect_button(EATER.id, "fav").await;
+   assert_eq!(data, format!("fav{}_0_0", rest_num));
+   s.press(EATER, &message, &data).await;
+   s.expect_callback_answer("Добавлено в избранное").await;
+   let edit = s.expect(EATER.id, "editMessageReplyMarkup", "").await;
+   assert!(edit.inline_buttons().contains(&(String::from("⭐"), data.clone())));
+
+   // И у блюда
+   s.send_text(EATER, &format!("/goto{}_1_1", rest_num)).await;
+   let (message, data) = s.expect_button(EATER.id, "fav").await;
+   assert_eq!(data, format!("fav{}_1_1", rest_num));
+   s.press(EATER, &message, &data).await;
+   s.expect_callback_answer("Добавлено в избранное").await;
+   s.expect_db("SELECT COUNT(*) FROM favorites WHERE user_id=$1", &[&EATER.id], 2i64).await;
+
+   // Список с доступностью прямо сейчас
+   s.send_text(EATER, "⭐Избранное").await;
+   let list = s.expect(EATER.id, "sendPhoto", "Избранное").await;
+   let buttons = list.inline_buttons();
+   assert!(buttons.contains(&(String::from("✅ Пельменная"), format!("rng{}_0_0", rest_num))), "{:?}", buttons);
+   assert!(buttons.iter().any(|(text, data)| text.starts_with("✅ Пельмени 100") && *data == format!("dis{}_1_1", rest_num)), "{:?}", buttons);
+
+   // Скрытое блюдо остаётся в избранном, но недоступно
+   s.api("PATCH", &format!("restaurants/{}/groups/1/dishes/1", rest_num), Some(json!({"active": false}))).await;
+   s.send_text(EATER, "⭐Избранное").await;
+   let list = s.expect(EATER.id, "sendPhoto", "Избранное").await;
+   assert!(list.inline_buttons().iter().any(|(text, _)| text.starts_with("⛔ Пельмени")));
+
+   // В режиме со ссылками заведение убирается командой
+   s.send_text(EATER, "⚙").await;
+   s.expect_text(EATER.id, "Режим интерфейса").await;
+   s.send_text(EATER, "/toggle").await;
+   s.expect_text(EATER.id, "со ссылками").await;
+   s.send_text(EATER, &format!("/goto{}_0_0", rest_num)).await;
+   s.expect_text(EATER.id, "⭐ в избранном, убрать /fav").await;
+   s.send_text(EATER, "/fav").await;
+   s.expect_text(EATER.id, "Убрано из избранного").await;
+
+   s.send_text(EATER, "/start").await;
+   s.expect_text(EATER.id, "выберите в основном меню").await;
+   s.send_text(EATER, "⭐Избранное").await;
+   let reply = s.expect_text(EATER.id, "Избранное").await;
+   assert!(reply.text().contains("⛔ Пельмени 100"), "{}", reply.text());
+   assert!(reply.text().contains(&format!("/goto{}_1_1", rest_num)));
+   assert!(!reply.text().contains("Пельменная"));
+}
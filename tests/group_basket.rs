@@ -0,0 +1,113 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Сценарий: общий заказ в групповом чате. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+mod support;
+
+use serde_json::json;
+use support::{Scenario, TestUser};
+
+const CATERER: TestUser = TestUser {id: 20014, name: "Caterer"};
+const IVAN: TestUser = TestUser {id: 30012, name: "Ivan"};
+const OLGA: TestUser = TestUser {id: 30013, name: "Olga"};
+const GROUP: i64 = -100047;
+
+#[tokio::test]
+async fn group_basket() {
+   let s = match Scenario::start(&[]).await {
+      Some(s) => s,
+      None => return,
+   };
+
+   // Заведение с двумя блюдами, работающее весь день
+   let rest_num = s.restaurant_with_dish(CATERER, "Столовая", "Обеды", "Борщ", 150).await;
+   s.send_text(CATERER, "/EdGr1").await;
+   s.send_text(CATERER, "/AddDish").await;
+   s.send_text(CATERER, "Котлета").await;
+   s.expect_text(CATERER.id, "/EdDi2").await;
+   s.api("PATCH", &format!("restaurants/{}/groups/1/dishes/2", rest_num), Some(json!({"price": 200}))).await;
+
+   // Инициатор знаком с ботом и заберёт заказ сам
+   s.send_text(IVAN, "/start").await;
+   s.expect_text(IVAN.id, "выберите в основном меню").await;
+   s.send_text(IVAN, "⚙").await;
+   s.expect_text(IVAN.id, "Режим интерфейса").await;
+   s.send_text(IVAN, "/start").await;
+   s.expect_text(IVAN.id, "выберите в основном меню").await;
+   s.send_text(IVAN, "🛒Корзина").await;
+   s.expect_text(IVAN.id, "Корзина пуста").await;
+   s.send_text(IVAN, "/toggle").await;
+   s.expect_db("SELECT pickup FROM users WHERE user_id=$1", &[&IVAN.id], true).await;
+
+   // Бота добавили в группу, он подсказывает, с чего начать
+   s.add_bot_to_group(GROUP, IVAN).await;
+   s.expect_text(GROUP as i32, "отправьте /lunch").await;
+
+   // Список работающих заведений и начало общего заказа
+   s.send_group_text(GROUP, IVAN, "/lunch").await;
+   s.expect_text(GROUP as i32, &format!("Столовая /lunch{}", rest_num)).await;
+   s.send_group_text(GROUP, IVAN, &format!("/lunch{}@vzmuinebot", rest_num)).await;
+   let (message, _) = s.expect_button(GROUP as i32, "gad").await;
+   assert!(message.text().contains("Общий заказ в «Столовая», собирает Ivan"), "{}", message.text());
+   let buttons = message.inline_buttons();
+   assert!(buttons.contains(&(String::from("Борщ 150₫"), format!("gad{}_1_1", rest_num))), "{:?}", buttons);
+   assert!(buttons.iter().any(|(_, data)| *data == format!("gsn{}_0_0", rest_num)));
+
+   // Участники выбирают блюда себе
+   s.press_in_group(GROUP, OLGA, &message, &format!("gad{}_1_2", rest_num)).await;
+   s.expect_callback_answer("Добавлено: Котлета").await;
+   s.expect(GROUP as i32, "editMessageText", "Olga, итого 200₫").await;
+   s.press_in_group(GROUP, IVAN, &message, &format!("gad{}_1_1", rest_num)).await;
+   s.expect_callback_answer("Добавлено: Борщ").await;
+   s.press_in_group(GROUP, IVAN, &message, &format!("gad{}_1_1", rest_num)).await;
+   let edit = s.expect(GROUP as i32, "editMessageText", "Ivan, итого 300₫").await;
+   assert!(edit.text().contains("Всего: 500₫"), "{}", edit.text());
+
+   // Убрать можно только своё
+   s.press_in_group(GROUP, OLGA, &message, &format!("gcl{}_0_0", rest_num)).await;
+   s.expect_callback_answer("Ваши блюда убраны").await;
+   let edit = s.expect(GROUP as i32, "editMessageText", "Всего: 300₫").await;
+   assert!(!edit.text().contains("Olga"), "{}", edit.text());
+   s.press_in_group(GROUP, OLGA, &message, &format!("gad{}_1_2", rest_num)).await;
+   s.expect_callback_answer("Добавлено: Котлета").await;
+
+   // Пока группа закрыта, блюда из неё не добавить и заказ не отправить
+   s.api("PATCH", &format!("restaurants/{}/groups/1", rest_num), Some(json!({"active": false}))).await;
+   s.press_in_group(GROUP, OLGA, &message, &format!("gad{}_1_1", rest_num)).await;
+   s.expect_callback_answer("Это блюдо сейчас нельзя заказать").await;
+   s.press_in_group(GROUP, IVAN, &message, &format!("gsn{}_0_0", rest_num)).await;
+   s.expect_callback_answer("Сейчас нельзя заказать: Борщ, Котлета").await;
+   s.expect_db("SELECT COUNT(*) FROM tickets", &[], 0i64).await;
+   s.api("PATCH", &format!("restaurants/{}/groups/1", rest_num), Some(json!({"active": true}))).await;
+
+   // Отправить может только инициатор
+   s.press_in_group(GROUP, OLGA, &message, &format!("gsn{}_0_0", rest_num)).await;
+   s.expect_callback_answer("Это может сделать только Ivan").await;
+
+   // Один тикет на всех, даже если кнопку нажали дважды подряд
+   let before = s.api.len();
+   s.press_in_group(GROUP, IVAN, &message, &format!("gsn{}_0_0", rest_num)).await;
+   s.press_in_group(GROUP, IVAN, &message, &format!("gsn{}_0_0", rest_num)).await;
+   let answered = |calls: &[support::Call], fragment: &str| calls[before..].iter().any(|c| c.method == "answerCallbackQuery" && c.text().contains(fragment));
+   assert!(s.wait_for(|calls| answered(calls, "Отправка") && answered(calls, "уже закрыт")).await.is_some(), "{}", s.dump(before));
+   let order = s.expect_text(CATERER.id, "Общий заказ из группового чата, собрал Ivan").await;
+   assert!(order.text().contains("Olga, итого 200₫"), "{}", order.text());
+   s.expect_text(IVAN.id, "Общий заказ из группового чата").await;
+   s.expect(GROUP as i32, "editMessageText", "Заказ отправлен в «Столовая»").await;
+   s.expect_db("SELECT COUNT(*) FROM tickets WHERE eater_id=$1 AND caterer_id=$2", &[&IVAN.id, &CATERER.id], 1i64).await;
+   s.expect_db("SELECT SUM(amount) FROM ticket_items", &[], 3i64).await;
+   s.expect_db("SELECT COUNT(*) FROM group_baskets", &[], 0i64).await;
+   let orders = s.api.calls_since(before).into_iter()
+   .filter(|c| c.method == "sendMessage" && c.text().contains("Общий заказ из группового чата"))
+   .count();
+   assert_eq!(orders, 2, "одно сообщение ресторатору и одно инициатору");
+
+   // Старое сообщение больше не работает
+   s.press_in_group(GROUP, OLGA, &message, &format!("gad{}_1_1", rest_num)).await;
+   s.expect_callback_answer("уже закрыт").await;
+}
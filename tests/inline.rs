@@ -0,0 +1,54 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Сценарий: поиск блюд и заведений из любого чата. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+mod support;
+
+use serde_json::json;
+use support::{fake_api::BOT_USERNAME, Scenario, TestUser};
+
+const CATERER: TestUser = TestUser {id: 20005, name: "Caterer"};
+const EATER: TestUser = TestUser {id: 30005, name: "Eater"};
+
+#[tokio::test]
+async fn inline_search() {
+   let s = match Scenario::start(&[]).await {
+      Some(s) => s,
+      None => return,
+   };
+
+   // Заведение с одним блюдом
+   let rest_num = s.restaurant_with_dish(CATERER, "Пиццерия", "Горячее", "Пицца Маргарита", 150).await;
+
+   // Слишком короткий запрос получает пустой ответ
+   s.inline_query(EATER, "п").await;
+   let answer = s.expect_inline_answer().await;
+   assert_eq!(answer.params["results"], json!([]));
+
+   // Находятся и заведение, и блюдо, каждое со ссылкой в бота
+   s.inline_query(EATER, "пицц").await;
+   let answer = s.expect_inline_answer().await;
+   let results = answer.params["results"].as_array().cloned().unwrap_or_default();
+   assert_eq!(results.len(), 2, "{}", answer.params);
+
+   let link = format!("http://t.me/{}?start=", BOT_USERNAME);
+   assert_eq!(results[0]["id"], json!(format!("r{}", rest_num)));
+   assert!(results[0]["title"].as_str().unwrap().contains("Пиццерия"));
+   assert_eq!(results[0]["reply_markup"]["inline_keyboard"][0][0]["url"], json!(format!("{}{}_0_0", link, rest_num)));
+
+   assert_eq!(results[1]["id"], json!(format!("d{}_1_1", rest_num)));
+   assert!(results[1]["title"].as_str().unwrap().contains("Пицца Маргарита"));
+   assert!(results[1]["input_message_content"]["message_text"].as_str().unwrap().contains("150"));
+   assert_eq!(results[1]["reply_markup"]["inline_keyboard"][0][0]["url"], json!(format!("{}{}_1_1", link, rest_num)));
+
+   // Скрытое заведение не ищется
+   s.api("PATCH", &format!("restaurants/{}", rest_num), Some(json!({"active": false}))).await;
+   s.inline_query(EATER, "пицц").await;
+   let answer = s.expect_inline_answer().await;
+   assert_eq!(answer.params["results"], json!([]));
+}
@@ -0,0 +1,48 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Сценарий: выбор языка интерфейса. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+mod support;
+
+use support::{Scenario, TestUser};
+
+const EATER: TestUser = TestUser {id: 30002, name: "Eater"};
+
+#[tokio::test]
+async fn switch_language() {
+   let s = match Scenario::start(&[]).await {
+      Some(s) => s,
+      None => return,
+   };
+
+   // Язык определяется по настройкам Telegram и запоминается
+   s.send_text(EATER, "/start").await;
+   s.expect_text(EATER.id, "выберите в основном меню").await;
+   s.send_text(EATER, "⚙").await;
+   s.expect_text(EATER.id, "Язык: Русский /lang").await;
+   s.expect_db("SELECT language FROM users WHERE user_id=$1", &[&EATER.id], String::from("ru")).await;
+
+   s.send_text(EATER, "/lang").await;
+   s.expect_text(EATER.id, "/lang_vi Tiếng Việt").await;
+
+   // После выбора ответ и кнопки уже на новом языке
+   s.send_text(EATER, "/lang_en").await;
+   let reply = s.expect_text(EATER.id, "Language changed: English").await;
+   assert_eq!(reply.keyboard(), vec!["Home", "Add menu"]);
+   s.expect_db("SELECT language FROM users WHERE user_id=$1", &[&EATER.id], String::from("en")).await;
+
+   // Кнопки распознаются на любом языке
+   s.send_text(EATER, "В начало").await;
+   let reply = s.expect_text(EATER.id, "choose in the main menu").await;
+   assert!(reply.keyboard().contains(&String::from("Food")), "English main menu expected");
+
+   // Новое заведение получает заготовку названия на языке владельца
+   let (status, rest) = s.api("POST", &format!("caterers/{}", EATER.id), None).await;
+   assert_eq!(status, 200, "{}", rest);
+   assert_eq!(rest["title"], "Meow");
+}
@@ -0,0 +1,81 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Сценарий: заведения рядом по геопозиции. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+mod support;
+
+use serde_json::json;
+use support::{Scenario, TestUser};
+
+const NEAR: TestUser = TestUser {id: 20007, name: "Near"};
+const FAR: TestUser = TestUser {id: 20008, name: "Far"};
+const EATER: TestUser = TestUser {id: 30007, name: "Eater"};
+
+// Ресторатор заходит в режим ввода меню
+async fn caterer_mode(s: &Scenario, user: TestUser) {
+   s.send_text(user, "/start").await;
+   s.expect_text(user.id, "выберите в основном меню").await;
+   s.send_text(user, "⚙").await;
+   s.expect_text(user.id, "Режим интерфейса").await;
+   s.send_text(user, "Добавить меню").await;
+   s.expect_text(user.id, "Геопозиция не задана").await;
+}
+
+#[tokio::test]
+async fn near_me() {
+   let s = match Scenario::start(&[]).await {
+      Some(s) => s,
+      None => return,
+   };
+
+   // Два заведения, ближнее работает весь день
+   let near = s.restaurant_with_dish(NEAR, "Ближнее", "Супы", "Борщ", 100).await;
+   s.send_location(NEAR, 12.2400, 109.1900).await;
+   s.expect_text(NEAR.id, "Геопозиция: 12.24000, 109.19000").await;
+
+   let (_, rest) = s.api("POST", &format!("caterers/{}", FAR.id), None).await;
+   let far = rest["num"].as_i64().unwrap();
+   s.api("PATCH", &format!("restaurants/{}", far), Some(json!({"title": "Дальнее", "active": true}))).await;
+   let nums = [near, far];
+
+   caterer_mode(&s, FAR).await;
+   s.send_location(FAR, 12.2600, 109.1900).await;
+   s.expect_text(FAR.id, "Геопозиция: 12.26000, 109.19000").await;
+
+   let (_, rest) = s.api("GET", &format!("restaurants/{}", nums[1]), None).await;
+   assert_eq!(rest["location"], json!({"latitude": 12.26, "longitude": 109.19}));
+
+   // Кнопка главного меню просит геопозицию
+   s.send_text(EATER, "/start").await;
+   let reply = s.expect_text(EATER.id, "выберите в основном меню").await;
+   assert!(reply.keyboard().contains(&String::from("📍Рядом")));
+
+   // Список по возрастанию расстояния с состоянием прямо сейчас
+   s.send_location(EATER, 12.2405, 109.1900).await;
+   let list = s.expect(EATER.id, "sendPhoto", "Заведения рядом с вами").await;
+   let buttons = list.inline_buttons();
+   assert_eq!(buttons.len(), 2, "{:?}", buttons);
+   assert_eq!(buttons[0], (String::from("✅ Ближнее · 60 м"), format!("rng{}_0_0", nums[0])));
+   assert_eq!(buttons[1], (String::from("⛔ Дальнее · 2.2 км"), format!("rng{}_0_0", nums[1])));
+
+   // В режиме со ссылками выбор как в списке открытых сейчас
+   s.send_text(EATER, "⚙").await;
+   s.expect_text(EATER.id, "Режим интерфейса").await;
+   s.send_text(EATER, "/toggle").await;
+   s.expect_text(EATER.id, "со ссылками").await;
+   s.send_text(EATER, "/start").await;
+   s.expect_text(EATER.id, "выберите в основном меню").await;
+   s.send_location(EATER, 12.2605, 109.1900).await;
+   let reply = s.expect_text(EATER.id, "Заведения рядом с вами").await;
+   let text = reply.text();
+   let far = text.find(&format!("/rest{}", nums[1])).unwrap();
+   let near = text.find(&format!("/rest{}", nums[0])).unwrap();
+   assert!(far < near, "{}", text);
+   s.send_text(EATER, &format!("/rest{}", nums[0])).await;
+   s.expect_text(EATER.id, "Ближнее").await;
+}
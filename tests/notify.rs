@@ -0,0 +1,61 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Сценарий: разовые уведомления об открытии заведения и появлении блюда. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+mod support;
+
+use serde_json::json;
+use support::{Scenario, TestUser};
+
+const CATERER: TestUser = TestUser {id: 20013, name: "Caterer"};
+const EATER: TestUser = TestUser {id: 30011, name: "Eater"};
+
+#[tokio::test]
+async fn notify() {
+   let s = match Scenario::start(&[]).await {
+      Some(s) => s,
+      None => return,
+   };
+
+   // Заведение с одним скрытым блюдом, работающее весь день
+   let rest_num = s.restaurant_with_dish(CATERER, "Сырники", "Завтраки", "Сырник", 100).await;
+   s.api("PATCH", &format!("restaurants/{}/groups/1/dishes/1", rest_num), Some(json!({"active": false}))).await;
+
+   // Скрытое блюдо предлагает уведомление
+   s.send_text(EATER, "/start").await;
+   s.expect_text(EATER.id, "выберите в основном меню").await;
+   s.send_text(EATER, &format!("/goto{}_1_1", rest_num)).await;
+   s.expect_text(EATER.id, &format!("Сообщить, когда блюдо снова появится /ntf{}_1_1", rest_num)).await;
+   s.send_text(EATER, &format!("/ntf{}_1_1", rest_num)).await;
+   s.expect_text(EATER.id, "Пришлю сообщение").await;
+   s.expect_db("SELECT COUNT(*) FROM notifications WHERE user_id=$1", &[&EATER.id], 1i64).await;
+
+   // Ресторатор возвращает блюдо, уведомление приходит один раз
+   s.api("PATCH", &format!("restaurants/{}/groups/1/dishes/1", rest_num), Some(json!({"active": true}))).await;
+   s.expect_text(EATER.id, &format!("Теперь можно заказать: Сырники, Сырник /goto{}_1_1", rest_num)).await;
+   s.expect_db("SELECT COUNT(*) FROM notifications WHERE user_id=$1", &[&EATER.id], 0i64).await;
+
+   // Ждать уже нечего
+   s.send_text(EATER, &format!("/ntf{}_1_1", rest_num)).await;
+   s.expect_text(EATER.id, "Это можно заказать уже сейчас").await;
+   s.expect_db("SELECT COUNT(*) FROM notifications WHERE user_id=$1", &[&EATER.id], 0i64).await;
+
+   // Без работающих групп заведение предлагает кнопку уведомления
+   s.api("PATCH", &format!("restaurants/{}/groups/1", rest_num), Some(json!({"active": false}))).await;
+   s.send_text(EATER, &format!("/goto{}_0_0", rest_num)).await;
+   let (message, data) = s.expect_button(EATER.id, "ntf").await;
+   assert_eq!(data, format!("ntf{}_0_0", rest_num));
+   s.press(EATER, &message, &data).await;
+   s.expect_callback_answer("Пришлю сообщение").await;
+   s.expect_db("SELECT COUNT(*) FROM notifications WHERE user_id=$1", &[&EATER.id], 1i64).await;
+
+   // Открытие группы открывает и заведение
+   s.api("PATCH", &format!("restaurants/{}/groups/1", rest_num), Some(json!({"active": true}))).await;
+   s.expect_text(EATER.id, &format!("Теперь можно заказать: Сырники /goto{}_0_0", rest_num)).await;
+   s.expect_db("SELECT COUNT(*) FROM notifications WHERE user_id=$1", &[&EATER.id], 0i64).await;
+}
@@ -0,0 +1,75 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Сценарий: состояние работы в списках едока. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+mod support;
+
+use chrono::{Duration, Utc};
+use serde_json::json;
+use support::{Scenario, TestUser};
+
+const OPEN: TestUser = TestUser {id: 20017, name: "Open"};
+const CLOSED: TestUser = TestUser {id: 20018, name: "Closed"};
+const EATER: TestUser = TestUser {id: 30016, name: "Eater"};
+
+#[tokio::test]
+async fn opening_status() {
+   let s = match Scenario::start(&[]).await {
+      Some(s) => s,
+      None => return,
+   };
+
+   // Время по поясу бота (+7)
+   let local = (Utc::now() + Duration::hours(7)).time();
+   let later = |hours: i64| (local + Duration::hours(hours)).format("%H:%M").to_string();
+
+   // Целиком закрытое заведение и заведение с группой, которая откроется позже, и круглосуточной
+   let closed = s.restaurant_with_dish(CLOSED, "Ночное", "Ужины", "Блюдо", 100).await;
+   s.api("PATCH", &format!("restaurants/{}/groups/1", closed), Some(json!({"opening_time": later(3), "closing_time": later(5)}))).await;
+
+   let open = s.restaurant_with_dish(OPEN, "Дневное", "Завтра", "Блюдо", 100).await;
+   s.api("PATCH", &format!("restaurants/{}/groups/1", open), Some(json!({"opening_time": later(3), "closing_time": later(5)}))).await;
+   s.send_text(OPEN, "/AddGroup").await;
+   s.send_text(OPEN, "Всегда").await;
+   s.expect_text(OPEN.id, "/EdGr2").await;
+   s.api("PATCH", &format!("restaurants/{}/groups/2", open), Some(json!({"opening_time": "00:00", "closing_time": "23:59"}))).await;
+
+   // Закрытое заведение в списке после открытого
+   s.send_text(EATER, "/start").await;
+   s.expect_text(EATER.id, "выберите в основном меню").await;
+   s.send_text(EATER, "⚙").await;
+   s.expect_text(EATER.id, "Режим интерфейса").await;
+   s.send_text(EATER, "/toggle").await;
+   s.expect_text(EATER.id, "со ссылками").await;
+   s.send_text(EATER, "/start").await;
+   s.expect_text(EATER.id, "выберите в основном меню").await;
+   s.send_text(EATER, "Сейчас").await;
+   let list = s.expect_text(EATER.id, "Рестораны сейчас").await.text().to_string();
+   let open_line = format!("Дневное · круглосуточно /rest{}", open);
+   let closed_line = format!("Ночное · откроется в {} /rest{}", later(3), closed);
+   assert!(list.contains(&open_line) && list.contains(&closed_line), "{}", list);
+   assert!(list.find(&open_line) < list.find(&closed_line), "{}", list);
+
+   // Группы заведения, открытые в начале
+   s.send_text(EATER, &format!("/rest{}", open)).await;
+   let groups = s.expect_text(EATER.id, "Всегда · круглосуточно /grou2").await.text().to_string();
+   let tomorrow = format!("Завтра · откроется в {} /grou1", later(3));
+   assert!(groups.find("/grou2") < groups.find(&tomorrow), "{}", groups);
+   assert!(!groups.contains("сообщить об открытии"), "{}", groups);
+
+   // Меню закрытого заведения можно посмотреть заранее
+   s.send_text(EATER, "/start").await;
+   s.expect_text(EATER.id, "выберите в основном меню").await;
+   s.send_text(EATER, "Сейчас").await;
+   s.expect_text(EATER.id, "Рестораны сейчас").await;
+   s.send_text(EATER, &format!("/rest{}", closed)).await;
+   s.expect_text(EATER.id, &format!("Ужины · откроется в {} /grou1", later(3))).await;
+   s.expect_text(EATER.id, &format!("сообщить об открытии /ntf{}", closed)).await;
+   s.send_text(EATER, "/grou1").await;
+   s.expect_text(EATER.id, "Блюдо").await;
+}
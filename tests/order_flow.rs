@@ -0,0 +1,151 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Сценарий: регистрация ресторатора, меню, заказ и его выполнение. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+mod support;
+
+use support::{Scenario, TestUser};
+
+const ADMIN: TestUser = TestUser {id: 10001, name: "Admin"};
+const CATERER: TestUser = TestUser {id: 20001, name: "Caterer"};
+const EATER: TestUser = TestUser {id: 30001, name: "Eater"};
+
+// Администратор регистрирует ресторатора
+async fn register_caterer(s: &Scenario) {
+   s.send_text(ADMIN, "/start").await;
+   let reply = s.expect_text(ADMIN.id, "выберите в основном меню").await;
+   assert!(reply.keyboard().contains(&String::from("⚙")), "Main menu keyboard expected");
+
+   s.send_text(ADMIN, "⚙").await;
+   s.expect_text(ADMIN.id, "Режим интерфейса").await;
+
+   s.send_text(ADMIN, &format!("/regi{}", CATERER.id)).await;
+   s.expect_text(ADMIN.id, &format!("ресторатора {}: успешно", CATERER.id)).await;
+   s.expect_db("SELECT enabled FROM restaurants WHERE user_id=$1", &[&CATERER.id], true).await;
+}
+
+// Ресторатор заполняет меню из одной круглосуточной группы с одним блюдом
+async fn build_menu(s: &Scenario) -> i32 {
+   s.send_text(CATERER, "/start").await;
+   s.expect_text(CATERER.id, "выберите в основном меню").await;
+   s.send_text(CATERER, "⚙").await;
+   s.expect_text(CATERER.id, "Режим интерфейса").await;
+   s.send_text(CATERER, "Добавить меню").await;
+   let reply = s.expect_text(CATERER.id, "Добро пожаловать в режим ввода меню").await;
+   assert_eq!(reply.keyboard(), vec!["Главная", "Выход"]);
+
+   let rest_num: i32 = s.query("SELECT rest_num FROM restaurants WHERE user_id=$1", &[&CATERER.id]).await[0].get(0);
+
+   s.send_text(CATERER, "/Toggle").await;
+   s.expect_db("SELECT active FROM restaurants WHERE rest_num=$1", &[&rest_num], true).await;
+
+   s.send_text(CATERER, "/AddGroup").await;
+   s.send_text(CATERER, "Супы").await;
+   s.expect_text(CATERER.id, "/EdGr1").await;
+
+   s.send_text(CATERER, "/EdGr1").await;
+   s.send_text(CATERER, "/EditTime").await;
+   s.send_text(CATERER, "00:00-23:59").await;
+   s.expect_text(CATERER.id, "/AddDish").await;
+
+   s.send_text(CATERER, "/AddDish").await;
+   s.send_text(CATERER, "Борщ").await;
+   s.expect_text(CATERER.id, "/EdDi1").await;
+
+   s.send_text(CATERER, "/EdDi1").await;
+   s.send_text(CATERER, "/EditPrice").await;
+   s.send_text(CATERER, "150").await;
+   s.expect_db("SELECT price FROM dishes WHERE rest_num=$1 AND group_num=1 AND dish_num=1", &[&rest_num], 150).await;
+
+   rest_num
+}
+
+// Едок находит блюдо через инлайн-кнопки и отправляет заказ
+async fn make_order(s: &Scenario, rest_num: i32) -> i32 {
+   s.send_text(EATER, "/start").await;
+   s.expect_text(EATER.id, "выберите в основном меню").await;
+
+   s.send_text(EATER, "Еда").await;
+   let (message, data) = s.expect_button(EATER.id, "grc").await;
+   assert_eq!(message.method, "sendPhoto");
+
+   s.press(EATER, &message, &data).await;
+   let (message, data) = s.expect_button(EATER.id, "drg").await;
+   s.press(EATER, &message, &data).await;
+   let (message, data) = s.expect_button(EATER.id, "dis").await;
+   s.press(EATER, &message, &data).await;
+   let (message, data) = s.expect_button(EATER.id, "add").await;
+   assert_eq!(data, format!("add{}_1_1", rest_num));
+
+   s.press(EATER, &message, &data).await;
+   let (_, data) = s.expect_button(EATER.id, "del").await;
+   assert_eq!(data, format!("del{}_1_1", rest_num));
+   s.expect_db("SELECT amount FROM orders WHERE user_id=$1", &[&EATER.id], 1).await;
+
+   // Без адреса заказ возможен только самовывозом
+   s.send_text(EATER, "🛒Корзина").await;
+   s.expect_text(EATER.id, "Борщ").await;
+   s.send_text(EATER, "/edit_time").await;
+   s.expect_text(EATER.id, "Укажите, к какому времени нужен заказ").await;
+   s.send_text(EATER, "23:58").await;
+   s.expect_db("SELECT order_time IS NOT NULL FROM users WHERE user_id=$1", &[&EATER.id], true).await;
+   s.send_text(EATER, "/toggle").await;
+   s.expect_text(EATER.id, "самовывоз").await;
+   let (message, data) = s.expect_button(EATER.id, "bas").await;
+   assert_eq!(data, format!("bas{}_0_0", CATERER.id));
+
+   s.press(EATER, &message, &data).await;
+   s.expect(CATERER.id, "forwardMessage", "").await;
+   s.expect_db("SELECT stage FROM tickets WHERE eater_id=$1", &[&EATER.id], 1).await;
+   s.expect_db("SELECT COUNT(*) FROM orders WHERE user_id=$1", &[&EATER.id], 0i64).await;
+
+   // Время перешло в заказ, следующий заказ снова как можно скорее
+   s.expect_db("SELECT order_time IS NOT NULL FROM tickets WHERE eater_id=$1", &[&EATER.id], true).await;
+   s.expect_db("SELECT order_time IS NULL FROM users WHERE user_id=$1", &[&EATER.id], true).await;
+
+   s.query("SELECT ticket_id FROM tickets WHERE eater_id=$1", &[&EATER.id]).await[0].get(0)
+}
+
+// Ресторатор проводит заказ по стадиям, едок подтверждает получение и ставит оценку
+async fn complete_order(s: &Scenario, ticket_id: i32) {
+   let next = format!("bne{}_0_0", ticket_id);
+   let (caterer_status, data) = s.expect_button(CATERER.id, &next).await;
+   assert_eq!(data, next);
+
+   for stage in 2..=4 {
+      s.press(CATERER, &caterer_status, &next).await;
+      s.expect_db("SELECT stage FROM tickets WHERE ticket_id=$1", &[&ticket_id], stage).await;
+   }
+
+   // На последней стадии подтвердить может только едок
+   let (eater_status, _) = s.expect_button(EATER.id, &next).await;
+   s.press(CATERER, &caterer_status, &next).await;
+   s.expect_callback_answer("ошибка").await;
+   s.expect_db("SELECT stage FROM tickets WHERE ticket_id=$1", &[&ticket_id], 4).await;
+
+   s.press(EATER, &eater_status, &next).await;
+   s.expect_db("SELECT stage FROM tickets WHERE ticket_id=$1", &[&ticket_id], 5).await;
+
+   let (message, _) = s.expect_button(EATER.id, "rat").await;
+   s.press(EATER, &message, &format!("rat{}_5_0", ticket_id)).await;
+   s.expect_db("SELECT rating FROM reviews WHERE ticket_id=$1", &[&ticket_id], 5).await;
+   s.expect_text(CATERER.id, "⭐").await;
+}
+
+#[tokio::test]
+async fn order_from_registration_to_review() {
+   let s = match Scenario::start(&[ADMIN.id]).await {
+      Some(s) => s,
+      None => return,
+   };
+
+   register_caterer(&s).await;
+   let rest_num = build_menu(&s).await;
+   let ticket_id = make_order(&s, rest_num).await;
+   complete_order(&s, ticket_id).await;
+}
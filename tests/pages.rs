@@ -0,0 +1,115 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Сценарий: длинные списки по страницам. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+mod support;
+
+use serde_json::json;
+use support::{Scenario, TestUser};
+
+const ADMIN: TestUser = TestUser {id: 10009, name: "Admin"};
+const CATERERS: [TestUser; 3] = [
+   TestUser {id: 20009, name: "First"},
+   TestUser {id: 20010, name: "Second"},
+   TestUser {id: 20011, name: "Third"},
+];
+const EATER: TestUser = TestUser {id: 30009, name: "Eater"};
+
+#[tokio::test]
+async fn pages() {
+   let s = match Scenario::start_with(&[ADMIN.id], &[("PAGE_SIZE", "2"), ("PAGE_BUTTONS", "2")]).await {
+      Some(s) => s,
+      None => return,
+   };
+
+   // Три заведения, в первом группа из трёх блюд
+   let mut nums = vec![s.restaurant_with_dish(CATERERS[0], "Кафе 1", "Супы", "Борщ", 100).await];
+   s.send_text(CATERERS[0], "/EdGr1").await;
+   for (i, title) in ["Солянка", "Уха"].iter().enumerate() {
+      s.send_text(CATERERS[0], "/AddDish").await;
+      s.send_text(CATERERS[0], title).await;
+      s.expect_text(CATERERS[0].id, &format!("/EdDi{}", i + 2)).await;
+   }
+   for (i, caterer) in CATERERS.iter().enumerate().skip(1) {
+      let (_, rest) = s.api("POST", &format!("caterers/{}", caterer.id), None).await;
+      let num = rest["num"].as_i64().unwrap();
+      s.api("PATCH", &format!("restaurants/{}", num), Some(json!({"title": format!("Кафе {}", i + 1), "active": true}))).await;
+      nums.push(num);
+   }
+
+   // Список администратора по две строки
+   s.send_text(ADMIN, "/start").await;
+   s.expect_text(ADMIN.id, "выберите в основном меню").await;
+   s.send_text(ADMIN, "⚙").await;
+   s.expect_text(ADMIN.id, "Режим интерфейса").await;
+   s.send_text(ADMIN, "/list").await;
+   let reply = s.expect_text(ADMIN.id, "Страница 1 из 2: /list2 ▶").await;
+   assert!(reply.text().contains("Кафе 2") && !reply.text().contains("Кафе 3"), "{}", reply.text());
+   s.send_text(ADMIN, "/list2").await;
+   let reply = s.expect_text(ADMIN.id, "Страница 2 из 2: ◀ /list1").await;
+   assert!(reply.text().contains("Кафе 3") && !reply.text().contains("Кафе 1"), "{}", reply.text());
+
+   // Номер за пределами списка даёт последнюю страницу
+   s.send_text(ADMIN, "/rests9").await;
+   let reply = s.expect_text(ADMIN.id, "Выберите ресторан для входа").await;
+   assert!(reply.text().contains(&format!("/sudo{}", nums[2])), "{}", reply.text());
+   assert!(reply.text().contains("◀ /rests1"), "{}", reply.text());
+   s.send_text(ADMIN, "Добавить меню").await;
+   s.expect_text(ADMIN.id, "/rests2 ▶").await;
+
+   // Инлайн-кнопки по две со стрелками на соседние страницы
+   let rest = nums[0];
+   s.send_text(EATER, "/start").await;
+   s.expect_text(EATER.id, "выберите в основном меню").await;
+   s.send_text(EATER, &format!("/goto{}_1_0", rest)).await;
+   let (message, data) = s.expect_button(EATER.id, "dpg").await;
+   assert_eq!(data, format!("dpg{}_1_2", rest));
+   let buttons = message.inline_buttons();
+   assert!(buttons.contains(&(String::from("2/2 ▶"), data.clone())), "{:?}", buttons);
+   assert!(!buttons.iter().any(|(text, _)| text.starts_with("Уха")), "{:?}", buttons);
+   s.press(EATER, &message, &data).await;
+   s.expect_callback_answer("").await;
+   let edit = s.expect(EATER.id, "editMessageMedia", "Супы").await;
+   let buttons = edit.inline_buttons();
+   assert!(buttons.iter().any(|(text, _)| text.starts_with("Уха")), "{:?}", buttons);
+   assert!(buttons.contains(&(String::from("◀ 1/2"), format!("dpg{}_1_1", rest))), "{:?}", buttons);
+
+   // Избранное тоже по страницам
+   for num in &nums {
+      s.query("INSERT INTO favorites (user_id, rest_num, group_num, dish_num) VALUES ($1, $2, 0, 0)", &[&EATER.id, &(*num as i32)]).await;
+   }
+   s.send_text(EATER, "⭐Избранное").await;
+   let list = s.expect(EATER.id, "sendPhoto", "Избранное").await;
+   let buttons = list.inline_buttons();
+   assert!(buttons.contains(&(String::from("2/2 ▶"), String::from("fvp2_0_0"))), "{:?}", buttons);
+   assert!(!buttons.iter().any(|(text, _)| text.contains("Кафе 3")), "{:?}", buttons);
+   s.press(EATER, &list, "fvp2_0_0").await;
+   s.expect_callback_answer("").await;
+   let edit = s.expect(EATER.id, "editMessageReplyMarkup", "").await;
+   let buttons = edit.inline_buttons();
+   assert!(buttons.iter().any(|(text, _)| text.contains("Кафе 3")), "{:?}", buttons);
+   assert!(buttons.contains(&(String::from("◀ 1/2"), String::from("fvp1_0_0"))), "{:?}", buttons);
+
+   // В режиме со ссылками страницы листаются командой
+   s.send_text(EATER, "⚙").await;
+   s.expect_text(EATER.id, "Режим интерфейса").await;
+   s.send_text(EATER, "/toggle").await;
+   s.expect_text(EATER.id, "со ссылками").await;
+   s.send_text(EATER, &format!("/goto{}_1_0", rest)).await;
+   let reply = s.expect_text(EATER.id, "Страница 1 из 2: /page2 ▶").await;
+   assert!(reply.text().contains("/dish2") && !reply.text().contains("/dish3"), "{}", reply.text());
+   s.send_text(EATER, "/page2").await;
+   let reply = s.expect_text(EATER.id, "Страница 2 из 2: ◀ /page1").await;
+   assert!(reply.text().contains("Уха 0₫ /dish3"), "{}", reply.text());
+
+   s.send_text(EATER, "/start").await;
+   s.expect_text(EATER.id, "выберите в основном меню").await;
+   s.send_text(EATER, "/favs2").await;
+   let reply = s.expect_text(EATER.id, "Страница 2 из 2: ◀ /favs1").await;
+   assert!(reply.text().contains(&format!("/goto{}_0_0", nums[2])) && !reply.text().contains("Кафе 1"), "{}", reply.text());
+}
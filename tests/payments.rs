@@ -0,0 +1,106 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Сценарий: оплата заказа через бота. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+mod support;
+
+use serde_json::json;
+use support::{Scenario, TestUser};
+
+const ADMIN: TestUser = TestUser {id: 10012, name: "Admin"};
+const CATERER: TestUser = TestUser {id: 20019, name: "Caterer"};
+const EATER: TestUser = TestUser {id: 30017, name: "Eater"};
+const LATE: TestUser = TestUser {id: 30018, name: "Late"};
+
+// Едок кладёт блюдо в корзину и оформляет самовывоз, возвращает номер заказа
+async fn order(s: &Scenario, eater: TestUser, rest_num: i64) -> i32 {
+   s.send_text(eater, "/start").await;
+   s.expect_text(eater.id, "выберите в основном меню").await;
+   s.send_text(eater, "⚙").await;
+   s.expect_text(eater.id, "Режим интерфейса").await;
+   s.send_text(eater, &format!("/start {}_1_1", rest_num)).await;
+   let (message, data) = s.expect_button(eater.id, "add").await;
+   s.press(eater, &message, &data).await;
+   s.expect_db("SELECT amount FROM orders WHERE user_id=$1", &[&eater.id], 1).await;
+
+   s.send_text(eater, "🛒Корзина").await;
+   s.expect_text(eater.id, "Борщ").await;
+   s.send_text(eater, "/toggle").await;
+   s.expect_text(eater.id, "самовывоз").await;
+   let (message, data) = s.expect_button(eater.id, "bas").await;
+   s.press(eater, &message, &data).await;
+   s.expect_db("SELECT COUNT(*) FROM tickets WHERE eater_id=$1", &[&eater.id], 1i64).await;
+   s.query("SELECT ticket_id FROM tickets WHERE eater_id=$1", &[&eater.id]).await[0].get(0)
+}
+
+#[tokio::test]
+async fn payment_via_bot() {
+   let s = match Scenario::start(&[ADMIN.id]).await {
+      Some(s) => s,
+      None => return,
+   };
+
+   // Заведение принимает оплату через бота
+   let rest_num = s.restaurant_with_dish(CATERER, "Касса", "Обеды", "Борщ", 150).await;
+   s.send_text(ADMIN, "/start").await;
+   s.expect_text(ADMIN.id, "выберите в основном меню").await;
+   s.send_text(ADMIN, "⚙").await;
+   s.expect_text(ADMIN.id, "Режим интерфейса").await;
+   s.send_text(ADMIN, &format!("/paym{} 123:TEST:provider", rest_num)).await;
+   s.expect_text(ADMIN.id, &format!("Подключение оплаты через бота для ресторана {}: успешно", rest_num)).await;
+
+   // Счёт не доходит, заказ ждёт оплаты
+   s.api.respond_once("sendInvoice", json!({"ok": false, "error_code": 400, "description": "Bad Request: PAYMENT_PROVIDER_INVALID"}));
+   let ticket_id = order(&s, EATER, rest_num).await;
+   let payload = format!("ticket{}", ticket_id);
+   s.expect_db("SELECT payment_required AND NOT paid FROM tickets WHERE ticket_id=$1", &[&ticket_id], true).await;
+   let (eater_status, pay) = s.expect_button(EATER.id, "bpa").await;
+   assert_eq!(pay, format!("bpa{}_0_0", ticket_id));
+
+   // До оплаты ресторатор не может начать выполнение
+   let (caterer_status, _) = s.expect_button(CATERER.id, &format!("bca{}", ticket_id)).await;
+   assert!(caterer_status.inline_buttons().iter().all(|(_, data)| !data.starts_with("bne")));
+   s.press(CATERER, &caterer_status, &format!("bne{}_0_0", ticket_id)).await;
+   s.expect_callback_answer("ошибка").await;
+   s.expect_db("SELECT stage FROM tickets WHERE ticket_id=$1", &[&ticket_id], 1).await;
+
+   // Едок запрашивает счёт повторно
+   s.press(EATER, &eater_status, &pay).await;
+   let invoice = s.expect(EATER.id, "sendInvoice", "").await;
+   assert_eq!(invoice.params["payload"], json!(payload));
+   assert_eq!(invoice.params["currency"], json!("VND"));
+
+   // Неверная сумма отклоняется, верная принимается
+   s.pre_checkout(EATER, &payload, "VND", 100).await;
+   let answer = s.expect_pre_checkout_answer().await;
+   assert_eq!(answer.params["ok"], json!(false));
+   assert_eq!(answer.params["error_message"], json!("Сумма заказа изменилась"));
+   s.pre_checkout(LATE, &payload, "VND", 150).await;
+   let answer = s.expect_pre_checkout_answer().await;
+   assert_eq!(answer.params["error_message"], json!("Это не ваш заказ"));
+   s.pre_checkout(EATER, &payload, "VND", 150).await;
+   let answer = s.expect_pre_checkout_answer().await;
+   assert_eq!(answer.params["ok"], json!(true));
+
+   // После оплаты ресторатор получает уведомление и может приступать
+   s.successful_payment(EATER, &payload, "VND", 150, "charge1").await;
+   s.expect_text(CATERER.id, &format!("Заказ №{} оплачен", ticket_id)).await;
+   s.expect_db("SELECT charge_id FROM tickets WHERE ticket_id=$1 AND paid", &[&ticket_id], String::from("charge1")).await;
+   s.press(CATERER, &caterer_status, &format!("bne{}_0_0", ticket_id)).await;
+   s.expect_db("SELECT stage FROM tickets WHERE ticket_id=$1", &[&ticket_id], 2).await;
+
+   // Оплата, пришедшая после отмены, сохраняется, едоку обещают возврат
+   let late_id = order(&s, LATE, rest_num).await;
+   s.expect(LATE.id, "sendInvoice", "").await;
+   let (late_status, _) = s.expect_button(LATE.id, "bpa").await;
+   s.press(LATE, &late_status, &format!("bca{}_0_0", late_id)).await;
+   s.expect_db("SELECT stage FROM tickets WHERE ticket_id=$1", &[&late_id], 6).await;
+   s.successful_payment(LATE, &format!("ticket{}", late_id), "VND", 150, "charge2").await;
+   s.expect_text(LATE.id, &format!("Заказ №{} оплачен уже после отмены", late_id)).await;
+   s.expect_db("SELECT charge_id FROM tickets WHERE ticket_id=$1 AND paid AND stage = 6", &[&late_id], String::from("charge2")).await;
+}
@@ -0,0 +1,101 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Сценарий: скидки по промокодам и их лимиты. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+mod support;
+
+use support::{Scenario, TestUser};
+
+const ADMIN: TestUser = TestUser {id: 10013, name: "Admin"};
+const CATERER: TestUser = TestUser {id: 20020, name: "Caterer"};
+const FIRST: TestUser = TestUser {id: 30019, name: "First"};
+const SECOND: TestUser = TestUser {id: 30020, name: "Second"};
+
+// Едок кладёт блюдо в корзину и выбирает самовывоз
+async fn fill_basket(s: &Scenario, eater: TestUser, rest_num: i64) {
+   s.send_text(eater, "/start").await;
+   s.expect_text(eater.id, "выберите в основном меню").await;
+   s.send_text(eater, "⚙").await;
+   s.expect_text(eater.id, "Режим интерфейса").await;
+   s.send_text(eater, &format!("/start {}_1_1", rest_num)).await;
+   let (message, data) = s.expect_button(eater.id, "add").await;
+   s.press(eater, &message, &data).await;
+   s.expect_db("SELECT amount FROM orders WHERE user_id=$1", &[&eater.id], 1).await;
+
+   s.send_text(eater, "🛒Корзина").await;
+   s.expect_text(eater.id, "Борщ").await;
+   s.send_text(eater, "/toggle").await;
+   s.expect_text(eater.id, "самовывоз").await;
+}
+
+// Едок вводит промокод в корзине
+async fn enter_promo(s: &Scenario, eater: TestUser, code: &str) {
+   s.send_text(eater, "/edit_promo").await;
+   s.expect_text(eater.id, "Введите промокод").await;
+   s.send_text(eater, code).await;
+}
+
+// Едок оформляет заказ из корзины, показанной последней
+async fn send_basket(s: &Scenario, eater: TestUser) -> i32 {
+   let (message, data) = s.expect_button(eater.id, "bas").await;
+   s.press(eater, &message, &data).await;
+   s.expect_db("SELECT COUNT(*) FROM tickets WHERE eater_id=$1", &[&eater.id], 1i64).await;
+   s.query("SELECT ticket_id FROM tickets WHERE eater_id=$1", &[&eater.id]).await[0].get(0)
+}
+
+#[tokio::test]
+async fn promo_codes() {
+   let s = match Scenario::start(&[ADMIN.id]).await {
+      Some(s) => s,
+      None => return,
+   };
+
+   let rest_num = s.restaurant_with_dish(CATERER, "Скидки", "Обеды", "Борщ", 200).await;
+
+   // Процентный промокод без ограничений и фиксированный на одно использование
+   s.send_text(ADMIN, "/start").await;
+   s.expect_text(ADMIN.id, "выберите в основном меню").await;
+   s.send_text(ADMIN, "⚙").await;
+   s.expect_text(ADMIN.id, "Режим интерфейса").await;
+   s.send_text(ADMIN, "/promo PCT10 10%").await;
+   s.expect_text(ADMIN.id, "Промокод PCT10").await;
+   s.send_text(ADMIN, "/promo FIX50 50 uses=1").await;
+   s.expect_text(ADMIN.id, "Промокод FIX50").await;
+
+   // Неизвестный код не сохраняется
+   fill_basket(&s, FIRST, rest_num).await;
+   enter_promo(&s, FIRST, "nope").await;
+   s.expect_text(FIRST.id, "Промокод NOPE не найден").await;
+   s.expect_db("SELECT promo_code IS NULL FROM users WHERE user_id=$1", &[&FIRST.id], true).await;
+
+   // Процентная скидка, затем фиксированная
+   enter_promo(&s, FIRST, "pct10").await;
+   s.expect_text(FIRST.id, "Скидка по промокоду PCT10: -20₫\nК оплате: 180₫").await;
+   enter_promo(&s, FIRST, "fix50").await;
+   s.expect_text(FIRST.id, "Скидка по промокоду FIX50: -50₫\nК оплате: 150₫").await;
+   let first_ticket = send_basket(&s, FIRST).await;
+   let (status, _) = s.expect_button(CATERER.id, &format!("bca{}", first_ticket)).await;
+   s.expect_db("SELECT discount FROM tickets WHERE ticket_id=$1", &[&first_ticket], 50).await;
+   s.expect_db("SELECT COUNT(*) FROM promo_uses WHERE code='FIX50'", &[], 1i64).await;
+
+   // Лимит исчерпан для другого едока
+   fill_basket(&s, SECOND, rest_num).await;
+   enter_promo(&s, SECOND, "fix50").await;
+   s.expect_text(SECOND.id, "Промокод FIX50 не применён: исчерпан").await;
+
+   // Отмена заказа возвращает использование
+   s.press(CATERER, &status, &format!("bca{}_0_0", first_ticket)).await;
+   s.expect_db("SELECT stage FROM tickets WHERE ticket_id=$1", &[&first_ticket], 6).await;
+   s.expect_db("SELECT COUNT(*) FROM promo_uses WHERE code='FIX50'", &[], 0i64).await;
+
+   s.send_text(SECOND, "⟳ Обновить").await;
+   s.expect_text(SECOND.id, "Скидка по промокоду FIX50: -50₫").await;
+   let second_ticket = send_basket(&s, SECOND).await;
+   s.expect_db("SELECT discount FROM tickets WHERE ticket_id=$1", &[&second_ticket], 50).await;
+   s.expect_db("SELECT ticket_id FROM promo_uses WHERE code='FIX50'", &[], second_ticket).await;
+}
@@ -0,0 +1,309 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Локальная замена Bot API для тестов. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+// Бот обращается к https://api.telegram.org через прокси из TELEGRAM_API_PROXY. Замена
+// принимает CONNECT, сама завершает TLS самоподписанным сертификатом (его файл бот получает
+// в TELEGRAM_API_PROXY_CA), записывает вызовы методов и возвращает заготовленные ответы.
+
+use std::{collections::{HashMap, VecDeque}, env, fs, net::SocketAddr, path::PathBuf, sync::{Arc, Mutex}};
+use serde_json::{json, Value};
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::{TcpListener, TcpStream}, sync::mpsc};
+use warp::{Filter, hyper::body::Bytes};
+use openssl::{asn1::Asn1Time, bn::BigNum, hash::MessageDigest, pkey::PKey, rsa::Rsa, x509::{X509, X509NameBuilder, extension::SubjectAlternativeName}};
+
+// Идентификатор и имя бота, которые вернёт getMe
+pub const BOT_ID: i32 = 999_000;
+pub const BOT_USERNAME: &str = "vzmuine_test_bot";
+
+// Вызов метода Bot API
+#[derive(Clone, Debug)]
+pub struct Call {
+   pub method: String,
+   pub params: Value,
+   pub message_id: Option<i32>, // номер сообщения, выданный в ответе
+}
+
+impl Call {
+   // Чат, в который адресован вызов
+   pub fn chat_id(&self) -> Option<i64> {
+      match &self.params["chat_id"] {
+         Value::Number(n) => n.as_i64(),
+         Value::String(s) => s.parse().ok(),
+         _ => None,
+      }
+   }
+
+   // Текст сообщения, подпись к картинке или подпись в заменяемом медиа
+   pub fn text(&self) -> String {
+      ["text", "caption"].iter()
+      .filter_map(|key| self.params[*key].as_str())
+      .chain(self.params["media"]["caption"].as_str())
+      .collect::<Vec<&str>>()
+      .join("\n")
+   }
+
+   // Надписи кнопок обычной клавиатуры
+   pub fn keyboard(&self) -> Vec<String> {
+      self.params["reply_markup"]["keyboard"].as_array()
+      .map(|rows| rows.iter()
+         .filter_map(Value::as_array)
+         .flatten()
+         .filter_map(|button| button.as_str().or_else(|| button["text"].as_str()))
+         .map(String::from)
+         .collect())
+      .unwrap_or_default()
+   }
+
+   // Инлайн-кнопки в виде пар (надпись, данные колбека)
+   pub fn inline_buttons(&self) -> Vec<(String, String)> {
+      self.params["reply_markup"]["inline_keyboard"].as_array()
+      .map(|rows| rows.iter()
+         .filter_map(Value::as_array)
+         .flatten()
+         .map(|button| (
+            button["text"].as_str().unwrap_or_default().to_string(),
+            button["callback_data"].as_str().unwrap_or_default().to_string(),
+         ))
+         .collect())
+      .unwrap_or_default()
+   }
+}
+
+// Общее состояние замены
+#[derive(Default)]
+struct State {
+   calls: Vec<Call>,
+   last_message_id: i32,
+   queued: HashMap<String, VecDeque<Value>>, // заготовленные ответы на ближайшие вызовы вместо стандартных
+}
+
+#[derive(Clone)]
+pub struct FakeApi {
+   pub addr: SocketAddr,
+   pub ca_path: PathBuf, // сертификат замены для TELEGRAM_API_PROXY_CA
+   state: Arc<Mutex<State>>,
+}
+
+impl FakeApi {
+   // Запускает замену на свободном порту
+   pub async fn start() -> FakeApi {
+      let mut listener = TcpListener::bind("127.0.0.1:0").await.expect("Cannot bind fake api");
+      let addr = listener.local_addr().unwrap();
+      let state = Arc::new(Mutex::new(State::default()));
+      let (identity, cert) = identity();
+      let ca_path = env::temp_dir().join(format!("vzmuinebot-fake-api-{}.pem", addr.port()));
+      fs::write(&ca_path, cert).expect("Cannot write fake api certificate");
+      let acceptor = tokio_tls::TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap());
+
+      // Каждое соединение начинается с CONNECT, после него идёт TLS
+      let (tx, rx) = mpsc::unbounded_channel();
+      tokio::spawn(async move {
+         loop {
+            let (stream, _) = match listener.accept().await {
+               Ok(res) => res,
+               Err(_) => continue,
+            };
+            let acceptor = acceptor.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+               if let Some(stream) = connect_tunnel(stream).await {
+                  if let Ok(tls) = acceptor.accept(stream).await {
+                     let _ = tx.send(Ok::<_, std::io::Error>(tls));
+                  }
+               }
+            });
+         }
+      });
+
+      let s = Arc::clone(&state);
+      let routes = warp::post()
+      .and(warp::path!(String / String))
+      .and(warp::header::optional::<String>("content-type"))
+      .and(warp::body::bytes())
+      .map(move |_token: String, method: String, content_type: Option<String>, body: Bytes| {
+         let params = parse_params(content_type.as_deref().unwrap_or_default(), &body);
+         warp::reply::json(&respond(&s, method, params))
+      });
+      tokio::spawn(warp::serve(routes).run_incoming(rx));
+
+      FakeApi {addr, ca_path, state}
+   }
+
+   // Адрес для TELEGRAM_API_PROXY
+   pub fn proxy_url(&self) -> String {
+      format!("http://{}", self.addr)
+   }
+
+   // Все вызовы по порядку
+   pub fn calls(&self) -> Vec<Call> {
+      self.state.lock().unwrap().calls.clone()
+   }
+
+   // Количество вызовов, для отсчёта новых
+   pub fn len(&self) -> usize {
+      self.state.lock().unwrap().calls.len()
+   }
+
+   // Вызовы, начиная с указанного номера
+   pub fn calls_since(&self, from: usize) -> Vec<Call> {
+      self.state.lock().unwrap().calls.get(from..).unwrap_or_default().to_vec()
+   }
+
+   // На ближайший вызов метода вернуть указанный ответ, например ошибку, затем снова стандартные
+   pub fn respond_once(&self, method: &str, response: Value) {
+      self.state.lock().unwrap().queued.entry(method.to_string()).or_default().push_back(response);
+   }
+
+   // Испорченный ответ, как при обрыве связи: teloxide не может его разобрать, и сообщение ждёт повтора
+   pub fn broken_response() -> Value {
+      json!({"ok": false})
+   }
+
+   // Ответ Telegram о превышении частоты запросов, в том же виде, что и настоящий
+   pub fn too_many_requests(retry_after: i32) -> Value {
+      json!({
+         "ok": false,
+         "error_code": 429,
+         "description": format!("Too Many Requests: retry after {}", retry_after),
+         "parameters": {"retry_after": retry_after},
+      })
+   }
+}
+
+// Отвечает на CONNECT и возвращает поток для туннеля
+async fn connect_tunnel(mut stream: TcpStream) -> Option<TcpStream> {
+   let mut head = Vec::new();
+   let mut buf = [0u8; 1024];
+   while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+      let n = stream.read(&mut buf).await.ok()?;
+      if n == 0 || head.len() > 16 * 1024 {
+         return None;
+      }
+      head.extend_from_slice(&buf[..n]);
+   }
+   if !head.starts_with(b"CONNECT ") {
+      return None;
+   }
+   stream.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n").await.ok()?;
+   Some(stream)
+}
+
+// Самоподписанный сертификат для api.telegram.org и он же в PEM
+fn identity() -> (native_tls::Identity, Vec<u8>) {
+   let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+
+   let mut name = X509NameBuilder::new().unwrap();
+   name.append_entry_by_text("CN", "api.telegram.org").unwrap();
+   let name = name.build();
+
+   let mut cert = X509::builder().unwrap();
+   cert.set_version(2).unwrap();
+   cert.set_serial_number(&BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap()).unwrap();
+   cert.set_subject_name(&name).unwrap();
+   cert.set_issuer_name(&name).unwrap();
+   cert.set_pubkey(&key).unwrap();
+   cert.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+   cert.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+   let san = SubjectAlternativeName::new().dns("api.telegram.org").build(&cert.x509v3_context(None, None)).unwrap();
+   cert.append_extension(san).unwrap();
+   cert.sign(&key, MessageDigest::sha256()).unwrap();
+   let cert = cert.build();
+
+   let pem = cert.to_pem().unwrap();
+   let identity = native_tls::Identity::from_pkcs8(&pem, &key.private_key_to_pem_pkcs8().unwrap()).unwrap();
+   (identity, pem)
+}
+
+// Параметры запроса из JSON или multipart/form-data, вложенный JSON в полях формы разбирается
+fn parse_params(content_type: &str, body: &[u8]) -> Value {
+   if content_type.starts_with("application/json") {
+      return serde_json::from_slice(body).unwrap_or(Value::Null);
+   }
+
+   let boundary = match content_type.split("boundary=").nth(1) {
+      Some(b) => format!("--{}", b.trim_matches('"')),
+      None => return Value::Null,
+   };
+
+   let body = String::from_utf8_lossy(body);
+   let mut params = serde_json::Map::new();
+   for part in body.split(boundary.as_str()) {
+      let mut sections = part.splitn(2, "\r\n\r\n");
+      let headers = sections.next().unwrap_or_default();
+      let value = sections.next().unwrap_or_default().trim_end_matches("\r\n");
+      let name = headers.split("name=\"").nth(1).and_then(|s| s.split('"').next());
+      if let Some(name) = name {
+         let value = serde_json::from_str::<Value>(value)
+         .ok()
+         .filter(|v| v.is_object() || v.is_array())
+         .unwrap_or_else(|| Value::String(value.to_string()));
+         params.insert(name.to_string(), value);
+      }
+   }
+   Value::Object(params)
+}
+
+// Записывает вызов и формирует ответ
+fn respond(state: &Mutex<State>, method: String, params: Value) -> Value {
+   let mut state = state.lock().unwrap();
+
+   if let Some(response) = state.queued.get_mut(&method).and_then(VecDeque::pop_front) {
+      state.calls.push(Call {method, params, message_id: None});
+      return response;
+   }
+
+   // Методы, возвращающие сообщение, получают новый номер, правка сохраняет прежний
+   let (result, message_id) = match method.as_str() {
+      "getMe" => (json!({
+         "id": BOT_ID,
+         "is_bot": true,
+         "first_name": "Test bot",
+         "username": BOT_USERNAME,
+         "can_join_groups": true,
+         "can_read_all_group_messages": false,
+         "supports_inline_queries": true,
+      }), None),
+      "sendMessage" | "sendPhoto" | "forwardMessage" | "sendInvoice" | "sendLocation" => {
+         state.last_message_id += 1;
+         let id = state.last_message_id;
+         (message(id, &params), Some(id))
+      }
+      "editMessageText" | "editMessageCaption" | "editMessageMedia" | "editMessageReplyMarkup" => {
+         match params["message_id"].as_i64().or_else(|| params["message_id"].as_str().and_then(|s| s.parse().ok())) {
+            Some(id) => (message(id as i32, &params), Some(id as i32)),
+            None => (json!(true), None), // инлайн-сообщение
+         }
+      }
+      _ => (json!(true), None),
+   };
+
+   state.calls.push(Call {method, params, message_id});
+   json!({"ok": true, "result": result})
+}
+
+// Сообщение от бота в чат из параметров запроса
+fn message(message_id: i32, params: &Value) -> Value {
+   let chat_id = match &params["chat_id"] {
+      Value::Number(n) => n.as_i64().unwrap_or_default(),
+      Value::String(s) => s.parse().unwrap_or_default(),
+      _ => 0,
+   };
+   let chat = if chat_id < 0 {
+      json!({"id": chat_id, "type": "group", "title": "Test group"})
+   } else {
+      json!({"id": chat_id, "type": "private", "first_name": "Test"})
+   };
+   json!({
+      "message_id": message_id,
+      "date": 0,
+      "chat": chat,
+      "from": {"id": BOT_ID, "is_bot": true, "first_name": "Test bot", "username": BOT_USERNAME},
+      "text": params["text"].as_str().or_else(|| params["caption"].as_str()).unwrap_or("-"),
+   })
+}
@@ -0,0 +1,520 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Сценарные тесты: запуск бота на замене Bot API и проверка ответов. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+// Бот запускается отдельным процессом в режиме вебхука, обновления передаются ему
+// POST-запросами с секретом, а ответы записывает замена Bot API. Для тестов нужна
+// отдельная БД в TEST_DATABASE_URL, её схема public очищается перед каждым сценарием.
+
+#![allow(dead_code)]
+
+pub mod fake_api;
+
+use std::{env, net::TcpListener, process::{Child, Command, Stdio}, sync::atomic::{AtomicI32, Ordering}, time::Duration};
+use serde_json::{json, Value};
+use tokio_postgres::{types::ToSql, NoTls, Row};
+
+pub use fake_api::{Call, FakeApi};
+
+// Сколько ждать ответа бота, с учётом ограничения частоты отправки в чат
+const REPLY_TIMEOUT: Duration = Duration::from_secs(20);
+
+const WEBHOOK_PATH: &str = "test_hook";
+const WEBHOOK_SECRET: &str = "test_secret";
+pub const ADMIN_API_TOKEN: &str = "test_admin_api_token";
+
+// Пользователь Telegram, от имени которого приходят обновления
+#[derive(Copy, Clone)]
+pub struct TestUser {
+   pub id: i32,
+   pub name: &'static str,
+}
+
+impl TestUser {
+   fn to_json(self) -> Value {
+      json!({"id": self.id, "is_bot": false, "first_name": self.name, "language_code": "ru"})
+   }
+}
+
+pub struct Scenario {
+   pub api: FakeApi,
+   pub db: tokio_postgres::Client,
+   pub port: u16,
+   bot: Child,
+   client: reqwest::Client,
+   update_id: AtomicI32,
+   incoming_message_id: AtomicI32,
+   mark: std::sync::atomic::AtomicUsize, // номер вызова, с которого ищутся ответы на последнее обновление
+}
+
+impl Drop for Scenario {
+   fn drop(&mut self) {
+      let _ = self.bot.kill();
+      let _ = self.bot.wait();
+      let _ = std::fs::remove_file(&self.api.ca_path);
+   }
+}
+
+// Свободный порт для вебхука
+fn free_port() -> u16 {
+   TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+impl Scenario {
+   // Запускает бота с чистой БД, без TEST_DATABASE_URL возвращает None и тест пропускается
+   pub async fn start(admins: &[i32]) -> Option<Scenario> {
+      Self::start_with(admins, &[]).await
+   }
+
+   // То же с дополнительными переменными окружения бота
+   pub async fn start_with(admins: &[i32], vars: &[(&str, &str)]) -> Option<Scenario> {
+      let database_url = match env::var("TEST_DATABASE_URL") {
+         Ok(url) => url,
+         Err(_) => {
+            eprintln!("TEST_DATABASE_URL is not set, scenario skipped");
+            return None;
+         }
+      };
+
+      // Чистая БД, таблицы создаст сам бот
+      let (db, connection) = tokio_postgres::connect(&database_url, NoTls).await.expect("Cannot connect to TEST_DATABASE_URL");
+      tokio::spawn(connection);
+      db.batch_execute("DROP SCHEMA public CASCADE; CREATE SCHEMA public;").await.expect("Cannot clean test database");
+
+      let api = FakeApi::start().await;
+      let port = free_port();
+      let admins: Vec<String> = admins.iter().map(|id| id.to_string()).collect();
+
+      // Окружение бота задаётся полностью, чтобы не зависеть от переменных разработчика
+      let mut command = Command::new(env!("CARGO_BIN_EXE_vzmuinebot"));
+      command.env_clear()
+      .current_dir(env::temp_dir())
+      .env("TELOXIDE_TOKEN", "123456:TEST")
+      .env("DATABASE_URL", &database_url)
+      .env("TELEGRAM_ADMINS", admins.join(","))
+      .env("TIME_ZONE", "7")
+      .env("PRICE_UNIT", "₫")
+      .env("CONTACT_INFO", "@admin")
+      .env("UPDATE_MODE", "webhook")
+      .env("HOST", "localhost")
+      .env("PORT", port.to_string())
+      .env("WEBHOOK_PATH", WEBHOOK_PATH)
+      .env("WEBHOOK_SECRET", WEBHOOK_SECRET)
+      .env("ADMIN_API_TOKEN", ADMIN_API_TOKEN)
+      .env("TELEGRAM_API_PROXY", api.proxy_url())
+      .env("TELEGRAM_API_PROXY_CA", &api.ca_path)
+      .envs(vars.iter().cloned());
+
+      // Журнал бота виден только при заданном RUST_LOG
+      match env::var("RUST_LOG") {
+         Ok(filter) => {command.env("RUST_LOG", filter);}
+         Err(_) => {command.stdout(Stdio::null()).stderr(Stdio::null());}
+      }
+      let bot = command.spawn().expect("Cannot start the bot");
+
+      let client = reqwest::Client::builder().no_proxy().build().unwrap();
+      let scenario = Scenario {
+         api,
+         db,
+         port,
+         bot,
+         client,
+         update_id: AtomicI32::new(1),
+         incoming_message_id: AtomicI32::new(1_000_000),
+         mark: Default::default(),
+      };
+
+      // Бот готов, когда установил вебхук и отвечает на проверку готовности
+      scenario.wait_for(|calls| calls.iter().any(|c| c.method == "setWebhook")).await
+      .expect("The bot did not set a webhook");
+      let ready_url = format!("http://127.0.0.1:{}/readyz", port);
+      for _ in 0..100 {
+         if let Ok(res) = scenario.client.get(&ready_url).send().await {
+            if res.status().is_success() {
+               return Some(scenario);
+            }
+         }
+         tokio::time::delay_for(Duration::from_millis(100)).await;
+      }
+      panic!("The bot is not ready");
+   }
+
+   // Адрес вебхука
+   pub fn webhook_url(&self) -> String {
+      format!("http://127.0.0.1:{}/{}", self.port, WEBHOOK_PATH)
+   }
+
+   // Отправляет боту произвольное тело запроса с указанным секретом, возвращает код ответа
+   pub async fn post_raw(&self, body: &str, secret: Option<&str>) -> u16 {
+      let request = self.client.post(&self.webhook_url())
+      .header("content-type", "application/json")
+      .body(body.to_string());
+      let request = match secret {
+         Some(secret) => request.header("x-telegram-bot-api-secret-token", secret),
+         None => request,
+      };
+      request.send().await.expect("Webhook request failed").status().as_u16()
+   }
+
+   // Отправляет только заголовки запроса с длиной тела length, само тело не передаётся.
+   // Возвращает код ответа, если сервер ответил, не дожидаясь тела
+   pub async fn head_only(&self, method: &str, path: &str, headers: &[(&str, &str)], length: usize) -> u16 {
+      use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+      let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", self.port)).await.expect("Cannot connect to the bot");
+      let mut head = format!("{} /{} HTTP/1.1\r\nhost: 127.0.0.1\r\ncontent-type: application/json\r\ncontent-length: {}\r\n", method, path, length);
+      for (name, value) in headers {
+         head.push_str(&format!("{}: {}\r\n", name, value));
+      }
+      head.push_str("\r\n");
+      stream.write_all(head.as_bytes()).await.unwrap();
+
+      let mut buf = [0u8; 64];
+      let n = tokio::time::timeout(REPLY_TIMEOUT, stream.read(&mut buf)).await
+      .expect("No response before the body was sent")
+      .unwrap();
+      // HTTP/1.1 413 Payload Too Large
+      String::from_utf8_lossy(&buf[..n]).split(' ').nth(1).and_then(|code| code.parse().ok()).unwrap_or_default()
+   }
+
+   // Запрос к API администратора с указанным токеном, возвращает код ответа и JSON
+   pub async fn api_with_token(&self, method: &str, path: &str, body: Option<Value>, token: Option<&str>) -> (u16, Value) {
+      let url = format!("http://127.0.0.1:{}/api/{}", self.port, path);
+      let method = reqwest::Method::from_bytes(method.as_bytes()).unwrap();
+      let mut request = self.client.request(method, &url);
+      if let Some(token) = token {
+         request = request.header("authorization", format!("Bearer {}", token));
+      }
+      if let Some(body) = body {
+         request = request.header("content-type", "application/json").body(body.to_string());
+      }
+      let res = request.send().await.expect("Admin API request failed");
+      let status = res.status().as_u16();
+      let text = res.text().await.unwrap_or_default();
+      (status, serde_json::from_str(&text).unwrap_or(Value::Null))
+   }
+
+   // Запрос к API администратора с правильным токеном
+   pub async fn api(&self, method: &str, path: &str, body: Option<Value>) -> (u16, Value) {
+      self.api_with_token(method, path, body, Some(ADMIN_API_TOKEN)).await
+   }
+
+   // Регистрирует ресторатора с заведением из одной круглосуточной группы с одним блюдом.
+   // Ресторатор остаётся в меню заведения, возвращается номер заведения
+   pub async fn restaurant_with_dish(&self, caterer: TestUser, title: &str, group: &str, dish: &str, price: i32) -> i64 {
+      let (status, rest) = self.api("POST", &format!("caterers/{}", caterer.id), None).await;
+      assert_eq!(status, 200, "{}", rest);
+      let rest_num = rest["num"].as_i64().unwrap();
+      self.api("PATCH", &format!("restaurants/{}", rest_num), Some(json!({"title": title, "active": true}))).await;
+
+      self.send_text(caterer, "/start").await;
+      self.expect_text(caterer.id, "выберите в основном меню").await;
+      self.send_text(caterer, "⚙").await;
+      self.expect_text(caterer.id, "Режим интерфейса").await;
+      self.send_text(caterer, "Добавить меню").await;
+      self.expect_text(caterer.id, "Добро пожаловать в режим ввода меню").await;
+      self.send_text(caterer, "/AddGroup").await;
+      self.send_text(caterer, group).await;
+      self.expect_text(caterer.id, "/EdGr1").await;
+      self.send_text(caterer, "/EdGr1").await;
+      self.send_text(caterer, "/AddDish").await;
+      self.send_text(caterer, dish).await;
+      self.expect_text(caterer.id, "/EdDi1").await;
+      self.send_text(caterer, "Главная").await;
+      self.expect_text(caterer.id, "/EdGr1").await;
+
+      self.api("PATCH", &format!("restaurants/{}/groups/1", rest_num), Some(json!({"opening_time": "00:00", "closing_time": "23:59"}))).await;
+      self.api("PATCH", &format!("restaurants/{}/groups/1/dishes/1", rest_num), Some(json!({"price": price}))).await;
+      rest_num
+   }
+
+   // Передаёт обновление боту и запоминает, с какого вызова ждать ответы на него
+   pub async fn push(&self, mut update: Value) {
+      update["update_id"] = json!(self.update_id.fetch_add(1, Ordering::SeqCst));
+      self.mark.store(self.api.len(), Ordering::SeqCst);
+      let status = self.post_raw(&update.to_string(), Some(WEBHOOK_SECRET)).await;
+      assert_eq!(status, 200, "Webhook rejected the update {}", update);
+   }
+
+   // Пользователь пишет текст в личку боту
+   pub async fn send_text(&self, user: TestUser, text: &str) {
+      let message_id = self.incoming_message_id.fetch_add(1, Ordering::SeqCst);
+      self.push(json!({
+         "message": {
+            "message_id": message_id,
+            "date": 0,
+            "chat": {"id": user.id, "type": "private", "first_name": user.name},
+            "from": user.to_json(),
+            "text": text,
+         }
+      })).await;
+   }
+
+   // Участник пишет текст в групповой чат
+   pub async fn send_group_text(&self, chat_id: i64, user: TestUser, text: &str) {
+      let message_id = self.incoming_message_id.fetch_add(1, Ordering::SeqCst);
+      self.push(json!({
+         "message": {
+            "message_id": message_id,
+            "date": 0,
+            "chat": {"id": chat_id, "type": "group", "title": "Group"},
+            "from": user.to_json(),
+            "text": text,
+         }
+      })).await;
+   }
+
+   // Участник добавляет бота в групповой чат
+   pub async fn add_bot_to_group(&self, chat_id: i64, user: TestUser) {
+      let message_id = self.incoming_message_id.fetch_add(1, Ordering::SeqCst);
+      self.push(json!({
+         "message": {
+            "message_id": message_id,
+            "date": 0,
+            "chat": {"id": chat_id, "type": "group", "title": "Group"},
+            "from": user.to_json(),
+            "new_chat_members": [{"id": fake_api::BOT_ID, "is_bot": true, "first_name": "Test bot", "username": fake_api::BOT_USERNAME}],
+         }
+      })).await;
+   }
+
+   // Пользователь отправляет геопозицию
+   pub async fn send_location(&self, user: TestUser, latitude: f64, longitude: f64) {
+      let message_id = self.incoming_message_id.fetch_add(1, Ordering::SeqCst);
+      self.push(json!({
+         "message": {
+            "message_id": message_id,
+            "date": 0,
+            "chat": {"id": user.id, "type": "private", "first_name": user.name},
+            "from": user.to_json(),
+            "location": {"latitude": latitude, "longitude": longitude},
+         }
+      })).await;
+   }
+
+   // Пользователь отправляет фото с подписью
+   pub async fn send_photo(&self, user: TestUser, file_id: &str, caption: &str) {
+      let message_id = self.incoming_message_id.fetch_add(1, Ordering::SeqCst);
+      self.push(json!({
+         "message": {
+            "message_id": message_id,
+            "date": 0,
+            "chat": {"id": user.id, "type": "private", "first_name": user.name},
+            "from": user.to_json(),
+            "photo": [
+               {"file_id": format!("{}_small", file_id), "file_unique_id": "s", "width": 90, "height": 90},
+               {"file_id": file_id, "file_unique_id": "b", "width": 800, "height": 800},
+            ],
+            "caption": caption,
+         }
+      })).await;
+   }
+
+   // Пользователь нажимает инлайн-кнопку под сообщением бота
+   pub async fn press(&self, user: TestUser, message: &Call, data: &str) {
+      let chat = json!({"id": user.id, "type": "private", "first_name": user.name});
+      self.press_in(chat, user, message, data).await;
+   }
+
+   // Участник нажимает инлайн-кнопку под сообщением бота в групповом чате
+   pub async fn press_in_group(&self, chat_id: i64, user: TestUser, message: &Call, data: &str) {
+      let chat = json!({"id": chat_id, "type": "group", "title": "Group"});
+      self.press_in(chat, user, message, data).await;
+   }
+
+   async fn press_in(&self, chat: Value, user: TestUser, message: &Call, data: &str) {
+      let id = self.update_id.load(Ordering::SeqCst);
+      let mut query = json!({
+         "id": format!("cq{}", id),
+         "from": user.to_json(),
+         "message": {
+            "message_id": message.message_id.expect("The button is not under a message"),
+            "date": 0,
+            "chat": chat,
+            "text": message.text(),
+         },
+         "chat_instance": "1",
+         "data": data,
+      });
+
+      // Telegram присылает сообщение вместе с его кнопками
+      if message.params["reply_markup"]["inline_keyboard"].is_array() {
+         query["message"]["reply_markup"] = message.params["reply_markup"].clone();
+      }
+      self.push(json!({"callback_query": query})).await;
+   }
+
+   // Пользователь набирает в любом чате имя бота и запрос
+   pub async fn inline_query(&self, user: TestUser, query: &str) {
+      let id = self.update_id.load(Ordering::SeqCst);
+      self.push(json!({
+         "inline_query": {
+            "id": format!("iq{}", id),
+            "from": user.to_json(),
+            "query": query,
+            "offset": "",
+         }
+      })).await;
+   }
+
+   // Telegram спрашивает, можно ли принять оплату счёта
+   pub async fn pre_checkout(&self, user: TestUser, payload: &str, currency: &str, total_amount: i32) {
+      let id = self.update_id.load(Ordering::SeqCst);
+      self.push(json!({
+         "pre_checkout_query": {
+            "id": format!("pq{}", id),
+            "from": user.to_json(),
+            "currency": currency,
+            "total_amount": total_amount,
+            "invoice_payload": payload,
+         }
+      })).await;
+   }
+
+   // Telegram сообщает об успешной оплате счёта
+   pub async fn successful_payment(&self, user: TestUser, payload: &str, currency: &str, total_amount: i32, charge_id: &str) {
+      let message_id = self.incoming_message_id.fetch_add(1, Ordering::SeqCst);
+      self.push(json!({
+         "message": {
+            "message_id": message_id,
+            "date": 0,
+            "chat": {"id": user.id, "type": "private", "first_name": user.name},
+            "from": user.to_json(),
+            "successful_payment": {
+               "currency": currency,
+               "total_amount": total_amount,
+               "invoice_payload": payload,
+               "telegram_payment_charge_id": charge_id,
+               "provider_payment_charge_id": format!("provider_{}", charge_id),
+            },
+         }
+      })).await;
+   }
+
+   // Ждёт, пока записанные вызовы не удовлетворят условию
+   pub async fn wait_for<F>(&self, check: F) -> Option<Vec<Call>>
+   where
+      F: Fn(&[Call]) -> bool,
+   {
+      let deadline = tokio::time::Instant::now() + REPLY_TIMEOUT;
+      loop {
+         let calls = self.api.calls();
+         if check(&calls) {
+            return Some(calls);
+         }
+         if tokio::time::Instant::now() > deadline {
+            return None;
+         }
+         tokio::time::delay_for(Duration::from_millis(50)).await;
+      }
+   }
+
+   // Ждёт ответа в чат с указанным фрагментом текста после последнего обновления
+   pub async fn expect(&self, chat_id: i32, method: &str, fragment: &str) -> Call {
+      let mark = self.mark.load(Ordering::SeqCst);
+      let found = |calls: &[Call]| calls.get(mark..).unwrap_or_default().iter()
+      .find(|c| c.method == method && c.chat_id() == Some(i64::from(chat_id)) && c.text().contains(fragment))
+      .cloned();
+
+      match self.wait_for(|calls| found(calls).is_some()).await {
+         Some(calls) => found(&calls).unwrap(),
+         None => panic!("No {} to {} containing '{}', calls since update:\n{}", method, chat_id, fragment, self.dump(mark)),
+      }
+   }
+
+   // Ждёт текстового ответа в чат
+   pub async fn expect_text(&self, chat_id: i32, fragment: &str) -> Call {
+      self.expect(chat_id, "sendMessage", fragment).await
+   }
+
+   // Ждёт всплывающего ответа на нажатие кнопки
+   pub async fn expect_callback_answer(&self, fragment: &str) -> Call {
+      let mark = self.mark.load(Ordering::SeqCst);
+      let found = |calls: &[Call]| calls.get(mark..).unwrap_or_default().iter()
+      .find(|c| c.method == "answerCallbackQuery" && c.text().contains(fragment))
+      .cloned();
+
+      match self.wait_for(|calls| found(calls).is_some()).await {
+         Some(calls) => found(&calls).unwrap(),
+         None => panic!("No callback answer containing '{}', calls since update:\n{}", fragment, self.dump(mark)),
+      }
+   }
+
+   // Ждёт ответа на инлайн-запрос
+   pub async fn expect_inline_answer(&self) -> Call {
+      let mark = self.mark.load(Ordering::SeqCst);
+      let found = |calls: &[Call]| calls.get(mark..).unwrap_or_default().iter()
+      .find(|c| c.method == "answerInlineQuery")
+      .cloned();
+
+      match self.wait_for(|calls| found(calls).is_some()).await {
+         Some(calls) => found(&calls).unwrap(),
+         None => panic!("No inline answer, calls since update:\n{}", self.dump(mark)),
+      }
+   }
+
+   // Ждёт ответа на запрос подтверждения оплаты
+   pub async fn expect_pre_checkout_answer(&self) -> Call {
+      let mark = self.mark.load(Ordering::SeqCst);
+      let found = |calls: &[Call]| calls.get(mark..).unwrap_or_default().iter()
+      .find(|c| c.method == "answerPreCheckoutQuery")
+      .cloned();
+
+      match self.wait_for(|calls| found(calls).is_some()).await {
+         Some(calls) => found(&calls).unwrap(),
+         None => panic!("No pre-checkout answer, calls since update:\n{}", self.dump(mark)),
+      }
+   }
+
+   // Ждёт сообщения в чат с инлайн-кнопкой, данные которой начинаются с префикса, возвращает сообщение и данные
+   pub async fn expect_button(&self, chat_id: i32, prefix: &str) -> (Call, String) {
+      let mark = self.mark.load(Ordering::SeqCst);
+      let found = |calls: &[Call]| calls.get(mark..).unwrap_or_default().iter()
+      .filter(|c| c.chat_id() == Some(i64::from(chat_id)))
+      .find_map(|c| c.inline_buttons().into_iter()
+         .find(|(_, data)| data.starts_with(prefix))
+         .map(|(_, data)| (c.clone(), data)));
+
+      match self.wait_for(|calls| found(calls).is_some()).await {
+         Some(calls) => found(&calls).unwrap(),
+         None => panic!("No button '{}' for {}, calls since update:\n{}", prefix, chat_id, self.dump(mark)),
+      }
+   }
+
+   // Вызовы после указанного номера для сообщения об ошибке
+   pub fn dump(&self, from: usize) -> String {
+      self.api.calls_since(from).iter()
+      .map(|c| format!("   {} {:?}: {}", c.method, c.chat_id(), c.params))
+      .collect::<Vec<String>>()
+      .join("\n")
+   }
+
+   // Запрос к БД бота
+   pub async fn query(&self, sql: &str, params: &[&(dyn ToSql + Sync)]) -> Vec<Row> {
+      self.db.query(sql, params).await.expect("Test query failed")
+   }
+
+   // Ждёт, пока запрос к БД не вернёт значение первого столбца первой строки
+   pub async fn expect_db<T>(&self, sql: &str, params: &[&(dyn ToSql + Sync)], expected: T)
+   where
+      T: for<'a> tokio_postgres::types::FromSql<'a> + PartialEq + std::fmt::Debug,
+   {
+      let deadline = tokio::time::Instant::now() + REPLY_TIMEOUT;
+      loop {
+         let rows = self.query(sql, params).await;
+         let value = rows.first().map(|row| row.get::<usize, T>(0));
+         if value.as_ref() == Some(&expected) {
+            return;
+         }
+         if tokio::time::Instant::now() > deadline {
+            panic!("{} returned {:?}, expected {:?}", sql, value, expected);
+         }
+         tokio::time::delay_for(Duration::from_millis(100)).await;
+      }
+   }
+}
@@ -0,0 +1,103 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Сценарий: собственный часовой пояс заведения. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+mod support;
+
+use chrono::{Duration, Utc};
+use serde_json::json;
+use support::{Scenario, TestUser};
+
+const ADMIN: TestUser = TestUser {id: 10011, name: "Admin"};
+const CATERER: TestUser = TestUser {id: 20016, name: "Caterer"};
+const EATER: TestUser = TestUser {id: 30015, name: "Eater"};
+
+// Едок запрашивает список "Сейчас"
+async fn open_now(s: &Scenario) -> String {
+   s.send_text(EATER, "/start").await;
+   s.expect_text(EATER.id, "выберите в основном меню").await;
+   s.send_text(EATER, "Сейчас").await;
+   s.expect_text(EATER.id, "Рестораны сейчас").await.text().to_string()
+}
+
+#[tokio::test]
+async fn time_zones() {
+   let s = match Scenario::start(&[ADMIN.id]).await {
+      Some(s) => s,
+      None => return,
+   };
+
+   // Группа работает два часа вокруг текущего времени UTC+12, по поясу бота (+7) она закрыта
+   let rest_num = s.restaurant_with_dish(CATERER, "Остров", "Завтраки", "Омлет", 100).await;
+   s.send_text(CATERER, "Главная").await;
+   s.expect_text(CATERER.id, "Часовой пояс: Etc/GMT-7").await;
+
+   let local = (Utc::now() + Duration::hours(12)).time();
+   let opening = (local - Duration::hours(1)).format("%H:%M").to_string();
+   let closing = (local + Duration::hours(1)).format("%H:%M").to_string();
+   s.api("PATCH", &format!("restaurants/{}/groups/1", rest_num), Some(json!({"opening_time": opening, "closing_time": closing}))).await;
+
+   // Едок со ссылками видит заведение закрытым
+   s.send_text(EATER, "/start").await;
+   s.expect_text(EATER.id, "выберите в основном меню").await;
+   s.send_text(EATER, "⚙").await;
+   s.expect_text(EATER.id, "Режим интерфейса").await;
+   s.send_text(EATER, "/toggle").await;
+   s.expect_text(EATER.id, "со ссылками").await;
+   let closed = format!("Остров · откроется в {} /rest{}", opening, rest_num);
+   let list = open_now(&s).await;
+   assert!(list.contains(&closed), "{}", list);
+
+   // Администратор задаёт пояс заведения, неизвестное название отклоняется
+   s.send_text(ADMIN, "/start").await;
+   s.expect_text(ADMIN.id, "выберите в основном меню").await;
+   s.send_text(ADMIN, "⚙").await;
+   s.expect_text(ADMIN.id, "Режим интерфейса").await;
+   s.send_text(ADMIN, &format!("/tz{} Mars/Olympus", rest_num)).await;
+   s.expect_text(ADMIN.id, "Неизвестный часовой пояс Mars/Olympus").await;
+   s.send_text(ADMIN, &format!("/tz{} +12", rest_num)).await;
+   s.expect_text(ADMIN.id, &format!("Часовой пояс заведения {}: Etc/GMT-12", rest_num)).await;
+   s.expect_db("SELECT time_zone FROM restaurants WHERE rest_num=$1", &[&(rest_num as i32)], String::from("Etc/GMT-12")).await;
+
+   // Теперь группа открыта по времени заведения
+   let list = open_now(&s).await;
+   assert!(list.contains(&format!("Остров · открыто до {} /rest{}", closing, rest_num)), "{}", list);
+   s.send_text(EATER, &format!("/rest{}", rest_num)).await;
+   s.expect_text(EATER.id, &format!("Завтраки · открыто до {} /grou1", closing)).await;
+
+   // Желаемое время заказа тоже по времени заведения: два часа назад по его часам значит завтра
+   s.query("INSERT INTO orders (user_id, rest_num, group_num, dish_num, amount) VALUES ($1, $2, 1, 1, 1)", &[&EATER.id, &(rest_num as i32)]).await;
+   s.send_text(EATER, "/start").await;
+   s.expect_text(EATER.id, "выберите в основном меню").await;
+   s.send_text(EATER, "🛒Корзина").await;
+   s.expect_text(EATER.id, "Омлет").await;
+   s.send_text(EATER, "/edit_time").await;
+   s.expect_text(EATER.id, "Укажите, к какому времени нужен заказ").await;
+   s.send_text(EATER, &(local - Duration::hours(2)).format("%H:%M").to_string()).await;
+   s.expect_db("SELECT COALESCE(order_time > (now() AT TIME ZONE 'Etc/GMT-12') + interval '12 hours', FALSE) FROM users WHERE user_id=$1", &[&EATER.id], true).await;
+   s.query("DELETE FROM orders WHERE user_id=$1", &[&EATER.id]).await;
+   s.query("UPDATE users SET order_time = NULL WHERE user_id=$1", &[&EATER.id]).await;
+
+   // Через API пояс виден и сбрасывается
+   let (_, rest) = s.api("GET", &format!("restaurants/{}", rest_num), None).await;
+   assert_eq!(rest["time_zone"], json!("Etc/GMT-12"));
+   let (status, _) = s.api("PATCH", &format!("restaurants/{}", rest_num), Some(json!({"time_zone": "Mars/Olympus"}))).await;
+   assert_eq!(status, 400);
+   let (status, rest) = s.api("PATCH", &format!("restaurants/{}", rest_num), Some(json!({"time_zone": null}))).await;
+   assert_eq!(status, 200);
+   assert_eq!(rest["time_zone"], json!(null));
+   let list = open_now(&s).await;
+   assert!(list.contains(&closed), "{}", list);
+
+   // Пояс по названию IANA
+   s.send_text(ADMIN, &format!("/tz{} Asia/Tokyo", rest_num)).await;
+   s.expect_text(ADMIN.id, &format!("Часовой пояс заведения {}: Asia/Tokyo", rest_num)).await;
+   s.send_text(ADMIN, &format!("/tz{}", rest_num)).await;
+   s.expect_text(ADMIN.id, &format!("Часовой пояс заведения {} по-умолчанию", rest_num)).await;
+   s.expect_db("SELECT time_zone FROM restaurants WHERE rest_num=$1", &[&(rest_num as i32)], None::<String>).await;
+}
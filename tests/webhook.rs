@@ -0,0 +1,65 @@
+/* ===============================================================================
+Бот для сбора меню у рестораторов и выдача их желающим покушать.
+Сценарий: защита вебхука, служебные маршруты и повтор после отказа Telegram. 19 October 2026.
+----------------------------------------------------------------------------
+Licensed under the terms of the GPL version 3.
+http://www.gnu.org/licenses/gpl-3.0.html
+Copyright (c) 2020 by Artem Khomenko _mag12@yahoo.com.
+=============================================================================== */
+
+mod support;
+
+use support::{FakeApi, Scenario, TestUser};
+
+const EATER: TestUser = TestUser {id: 30001, name: "Eater"};
+
+#[tokio::test]
+async fn webhook_security_and_retry() {
+   let s = match Scenario::start(&[]).await {
+      Some(s) => s,
+      None => return,
+   };
+
+   // Вебхук установлен с секретом
+   let set_webhook = s.api.calls().into_iter().find(|c| c.method == "setWebhook").unwrap();
+   assert_eq!(set_webhook.params["url"], "https://localhost/test_hook");
+   assert_eq!(set_webhook.params["secret_token"], "test_secret");
+   assert_eq!(set_webhook.params["allowed_updates"], serde_json::json!(["message", "callback_query", "inline_query", "pre_checkout_query"]));
+
+   // Без секрета или с чужим секретом обновления отклоняются и не обрабатываются
+   let update = r#"{"update_id":1,"message":{"message_id":1,"date":0,"chat":{"id":30001,"type":"private","first_name":"Eater"},"from":{"id":30001,"is_bot":false,"first_name":"Eater"},"text":"/start"}}"#;
+   let before = s.api.len();
+   assert_eq!(s.post_raw(update, None).await, 403);
+   assert_eq!(s.post_raw(update, Some("wrong")).await, 403);
+   assert_eq!(s.post_raw("{not json", Some("test_secret")).await, 400);
+   assert_eq!(s.head_only("POST", "test_hook", &[("x-telegram-bot-api-secret-token", "test_secret")], 2 * 1024 * 1024).await, 413);
+
+   // Неизвестный боту вид обновления пропускается без повторов со стороны Telegram
+   let member = r#"{"update_id":2,"my_chat_member":{"chat":{"id":-100,"type":"group","title":"Group"},"from":{"id":30001,"is_bot":false,"first_name":"Eater"},"date":0,
+      "old_chat_member":{"user":{"id":999000,"is_bot":true,"first_name":"Test bot"},"status":"left"},"new_chat_member":{"user":{"id":999000,"is_bot":true,"first_name":"Test bot"},"status":"member"}}}"#;
+   assert_eq!(s.post_raw(member, Some("test_secret")).await, 200);
+
+   // Служебные маршруты
+   let client = reqwest::Client::builder().no_proxy().build().unwrap();
+   let get = |path: &str| client.get(&format!("http://127.0.0.1:{}/{}", s.port, path)).send();
+   assert_eq!(get("healthz").await.unwrap().status().as_u16(), 200);
+   assert_eq!(get("readyz").await.unwrap().status().as_u16(), 200);
+   assert_eq!(get("unknown").await.unwrap().status().as_u16(), 404);
+
+   // Ни одного ответа на отклонённые запросы
+   tokio::time::delay_for(std::time::Duration::from_millis(500)).await;
+   assert!(s.api.calls_since(before).iter().all(|c| c.method != "sendMessage"), "Rejected update was processed:\n{}", s.dump(before));
+
+   // При перегрузке Telegram сообщение отправляется повторно после паузы
+   s.api.respond_once("sendMessage", FakeApi::too_many_requests(1));
+   s.send_text(EATER, "/start").await;
+   let sent = |calls: &[support::Call]| calls.iter().filter(|c| c.method == "sendMessage" && c.chat_id() == Some(i64::from(EATER.id))).count();
+   let calls = s.wait_for(|calls| sent(calls) >= 2).await.expect("The message was not sent again");
+   tokio::time::delay_for(std::time::Duration::from_millis(500)).await;
+   assert_eq!(sent(&s.api.calls()), 2);
+   assert!(calls.iter().rev().find(|c| c.method == "sendMessage").unwrap().text().contains("выберите в основном меню"));
+
+   let metrics = get("metrics").await.unwrap().text().await.unwrap();
+   assert!(metrics.contains("vzmuinebot_updates_total{kind=\"message\"} 1"), "{}", metrics);
+   assert!(metrics.contains("vzmuinebot_telegram_api_errors_total{context=\"retry_after\"} 1"), "{}", metrics);
+}